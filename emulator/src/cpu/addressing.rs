@@ -3,22 +3,27 @@
 //! Refer to http://www.scarpaz.com/Attic/Didattica/Scarpazza-2005-68k-1-addressing.pdf
 //! and http://faculty.cs.niu.edu/~winans/CS463/notes/amodes.pdf for reference on how these work.
 
+use std::fmt::Display;
+
 use m68kdecode::{Indexer, MemoryIndirection};
 
 use crate::ram::Memory;
-use crate::{EmulationError, M68kInteger, OperandSize};
+use crate::{Address, EmulationError, M68kInteger, OperandSize};
 
-use super::{registers::*, CPU};
+use super::{registers::*, symbols::SymbolTable, CpuModel, CPU};
 
-/// Index register scaling - the ONLY legal values for this are 1, 2, and 4.
+/// Index register scaling - the ONLY legal values for this are 1, 2, 4, and 8.
+///
+/// The 68000 itself only supports 1; scaling was added in the 68020.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum IndexScale {
     One = 1,
     Two = 2,
     Four = 4,
+    Eight = 8,
 }
 
-// TODO: is this correct? consult the docs!
+/// Scaling by the size of an operand is how an array of that size is indexed
 impl From<OperandSize> for IndexScale {
     fn from(size: OperandSize) -> Self {
         match size {
@@ -55,6 +60,8 @@ pub enum AddressMode {
         displacement: u16,
         address_register: AddressRegister,
         index_register: Register,
+        index_size: OperandSize,
+        index_scale: IndexScale,
     },
 
     // Memory-based addressing
@@ -63,12 +70,16 @@ pub enum AddressMode {
         outer_displacement: u16,
         address_register: AddressRegister,
         index_register: Register,
+        index_size: OperandSize,
+        index_scale: IndexScale,
     },
     MemoryPreIndexed {
         base_displacement: u16,
         outer_displacement: u16,
         address_register: AddressRegister,
         index_register: Register,
+        index_size: OperandSize,
+        index_scale: IndexScale,
     },
 
     // Program counter-based addressing
@@ -78,16 +89,22 @@ pub enum AddressMode {
     ProgramCounterIndirectIndexed {
         displacement: u16,
         index_register: Register,
+        index_size: OperandSize,
+        index_scale: IndexScale,
     },
     ProgramCounterMemoryIndirectPreIndexed {
         base_displacement: u16,
         outer_displacement: u16,
         index_register: Register,
+        index_size: OperandSize,
+        index_scale: IndexScale,
     },
     ProgramCounterMemoryIndirectPostIndexed {
         base_displacement: u16,
         outer_displacement: u16,
         index_register: Register,
+        index_size: OperandSize,
+        index_scale: IndexScale,
     },
 
     // Absolute addressing
@@ -102,7 +119,9 @@ pub enum AddressMode {
 }
 
 /// Gets the increment for a given register + get_value size
-fn get_increment(register: AddressRegister, size: OperandSize) -> u32 {
+///
+/// A7 always moves by at least 2, so the stack pointer stays word-aligned even when bytes are pushed or popped
+pub(crate) fn get_increment(register: AddressRegister, size: OperandSize) -> u32 {
     let minimum = match register {
         AddressRegister::A7 => 2,
         _ => 1,
@@ -116,40 +135,108 @@ fn get_increment(register: AddressRegister, size: OperandSize) -> u32 {
     }
 }
 
+/// Gets the address that `address` reaches on the model's address bus (see [`CpuModel::address_mask`])
+pub(crate) fn bus_address(cpu: &CPU<impl Memory>, address: u32) -> Address {
+    Address(address & cpu.model.address_mask())
+}
+
+/// Computes `base + displacement`, wrapping around the model's address space (see [`CpuModel::address_mask`])
+/// like the hardware does
+///
+/// Displacements are signed, so they should be sign-extended (`as i16 as u32`) before being passed in.
+/// With [`CPU::strict_addressing`] set, an address that would wrap is an [`EmulationError::AddressWrapped`] instead.
+fn offset_address(
+    cpu: &CPU<impl Memory>,
+    base: u32,
+    displacement: u32,
+) -> Result<Address, EmulationError> {
+    let mask = cpu.model.address_mask();
+    let base = base & mask;
+    let unwrapped = base as i64 + displacement as i32 as i64;
+    if cpu.strict_addressing && !(0..=mask as i64).contains(&unwrapped) {
+        return Err(EmulationError::AddressWrapped {
+            base: Address(base),
+            displacement: displacement as i32,
+        });
+    }
+    Ok(Address(base.wrapping_add(displacement) & mask))
+}
+
+/// Gets the factor an index is multiplied by.
+///
+/// The 68000 ignores the scale bits of the extension word, so it always scales by 1.
+fn scale_factor(cpu: &CPU<impl Memory>, scale: IndexScale) -> u32 {
+    if cpu.model < CpuModel::M68020 {
+        1
+    } else {
+        scale as u32
+    }
+}
+
+/// Computes `base + displacement + (index * scale)`, wrapping around the address space like the hardware does
+fn indexed_address(
+    cpu: &CPU<impl Memory>,
+    base: u32,
+    displacement: u32,
+    index: u32,
+    scale: u32,
+) -> Result<Address, EmulationError> {
+    offset_address(
+        cpu,
+        base,
+        displacement.wrapping_add(index.wrapping_mul(scale)),
+    )
+}
+
+/// Gets the base address for PC-relative addressing modes.
+///
+/// PC-relative displacements are relative to the first extension word, which directly follows the opcode
+/// of the instruction being run (see [`CPU::instruction_address`]).
+fn pc_relative_base(cpu: &CPU<impl crate::ram::Memory>) -> u32 {
+    cpu.instruction_address().wrapping_add(2)
+}
+
+/// Gets the value of an index register; word-sized indexes use the sign-extended low word of the register
+fn index_value(cpu: &CPU<impl crate::ram::Memory>, register: Register, size: OperandSize) -> u32 {
+    let value = cpu.registers.get(register);
+    match size {
+        OperandSize::Word => value as u16 as i16 as u32,
+        OperandSize::Byte | OperandSize::Long => value,
+    }
+}
+
 /// Gets a value at the RAM address in a register with displacement
 fn get_address_register_indirect_with_displacement(
     cpu: &mut CPU<impl crate::ram::Memory>,
-    register: Register,
+    base_address: u32,
     displacement: u32,
     size: OperandSize,
 ) -> Result<M68kInteger, EmulationError> {
     cpu.memory
-        .read(cpu.registers.get(register) + displacement, size)
+        .read(offset_address(cpu, base_address, displacement)?, size)
 }
 
 /// Sets a value at the RAM address in a register with displacement
 fn set_address_register_indirect_with_displacement(
     cpu: &mut CPU<impl crate::ram::Memory>,
-    register: Register,
+    base_address: u32,
     displacement: u32,
     value: M68kInteger,
 ) -> Result<(), EmulationError> {
     cpu.memory
-        .write(cpu.registers.get(register) + displacement, value)
+        .write(offset_address(cpu, base_address, displacement)?, value)
 }
 
 /// Gets a value at the RAM address in a register with indexing
 fn get_address_register_indirect_indexed(
     cpu: &mut CPU<impl crate::ram::Memory>,
-    address_register: Register,
-    index_register: Register,
+    base_address: u32,
+    index: u32,
     index_scale: u32,
     displacement: u32,
     size: OperandSize,
 ) -> Result<M68kInteger, EmulationError> {
-    let base_address = cpu.registers.get(address_register);
-    let index_value = cpu.registers.get(index_register) * index_scale;
-    let operand_address = base_address + displacement + index_value;
+    let operand_address = indexed_address(cpu, base_address, displacement, index, index_scale)?;
 
     cpu.memory.read(operand_address, size)
 }
@@ -157,15 +244,13 @@ fn get_address_register_indirect_indexed(
 /// Sets a value at the RAM address in a register with indexing
 fn set_address_register_indirect_indexed(
     cpu: &mut CPU<impl crate::ram::Memory>,
-    address_register: Register,
-    index_register: Register,
+    base_address: u32,
+    index: u32,
     index_scale: u32,
     displacement: u32,
     value: M68kInteger,
 ) -> Result<(), EmulationError> {
-    let base_address = cpu.registers.get(address_register);
-    let index_value = cpu.registers.get(index_register) * index_scale;
-    let operand_address = base_address + displacement + index_value;
+    let operand_address = indexed_address(cpu, base_address, displacement, index, index_scale)?;
 
     cpu.memory.write(operand_address, value)
 }
@@ -174,85 +259,119 @@ fn set_address_register_indirect_indexed(
 fn get_address_ram_post_indexed(
     cpu: &mut CPU<impl crate::ram::Memory>,
     base_address: u32,
-    index_register: Register,
+    index: u32,
     index_scale: u32,
     base_displacement: u32,
     outer_displacement: u32,
     size: OperandSize,
 ) -> Result<M68kInteger, EmulationError> {
-    let index_value = cpu.registers.get(index_register) * index_scale;
-    let intermediate_address = base_address + base_displacement;
+    let intermediate_address = offset_address(cpu, base_address, base_displacement)?;
     let intermediate_address_value = cpu.memory.read_long(intermediate_address)?;
-    cpu.memory.read(
-        intermediate_address_value + index_value + outer_displacement,
-        size,
-    )
+    let operand_address = indexed_address(
+        cpu,
+        intermediate_address_value,
+        outer_displacement,
+        index,
+        index_scale,
+    )?;
+    cpu.memory.read(operand_address, size)
 }
 /// Gets a value at a given address with a postindex register
 fn set_address_ram_post_indexed(
     cpu: &mut CPU<impl crate::ram::Memory>,
     base_address: u32,
-    index_register: Register,
+    index: u32,
     index_scale: u32,
     base_displacement: u32,
     outer_displacement: u32,
     value: M68kInteger,
 ) -> Result<(), EmulationError> {
-    let index_value = cpu.registers.get(index_register) * index_scale;
-    let intermediate_address = base_address + base_displacement;
+    let intermediate_address = offset_address(cpu, base_address, base_displacement)?;
     let intermediate_address_value = cpu.memory.read_long(intermediate_address)?;
+    let operand_address = indexed_address(
+        cpu,
+        intermediate_address_value,
+        outer_displacement,
+        index,
+        index_scale,
+    )?;
 
-    cpu.memory.write(
-        intermediate_address_value + index_value + outer_displacement,
-        value,
-    )
+    cpu.memory.write(operand_address, value)
 }
 
 /// Gets a value at a given address with a preindex register
 fn get_address_ram_pre_indexed(
     cpu: &mut CPU<impl crate::ram::Memory>,
     base_address: u32,
-    index_register: Register,
+    index: u32,
     index_scale: u32,
     base_displacement: u32,
     outer_displacement: u32,
     size: OperandSize,
 ) -> Result<M68kInteger, EmulationError> {
-    let index_value = cpu.registers.get(index_register) * index_scale;
-    let intermediate_address = base_address + base_displacement + index_value;
+    let intermediate_address =
+        indexed_address(cpu, base_address, base_displacement, index, index_scale)?;
     let intermediate_address_value = cpu.memory.read_long(intermediate_address)?;
+    let operand_address = offset_address(cpu, intermediate_address_value, outer_displacement)?;
 
-    cpu.memory
-        .read(intermediate_address_value + outer_displacement, size)
+    cpu.memory.read(operand_address, size)
 }
 
 /// Sets a value at a given address with a preindex register
 fn set_address_ram_pre_indexed(
     cpu: &mut CPU<impl crate::ram::Memory>,
     base_address: u32,
-    index_register: Register,
+    index: u32,
     index_scale: u32,
     base_displacement: u32,
     outer_displacement: u32,
     value: M68kInteger,
 ) -> Result<(), EmulationError> {
-    let index_value = cpu.registers.get(index_register) * index_scale;
-    let intermediate_address = base_address + base_displacement + index_value;
+    let intermediate_address =
+        indexed_address(cpu, base_address, base_displacement, index, index_scale)?;
     let intermediate_address_value = cpu.memory.read_long(intermediate_address)?;
+    let operand_address = offset_address(cpu, intermediate_address_value, outer_displacement)?;
 
-    cpu.memory
-        .write(intermediate_address_value + outer_displacement, value)
+    cpu.memory.write(operand_address, value)
 }
 
 impl AddressMode {
+    /// Data register direct (`dN`)
+    pub fn data(register: DataRegister) -> Self {
+        AddressMode::RegisterDirect {
+            register: Register::Data(register),
+        }
+    }
+
+    /// Address register direct (`aN`)
+    pub fn addr(register: AddressRegister) -> Self {
+        AddressMode::RegisterDirect {
+            register: Register::Address(register),
+        }
+    }
+
+    /// Address register indirect (`(aN)`)
+    pub fn indirect(register: AddressRegister) -> Self {
+        AddressMode::RegisterIndirect { register }
+    }
+
+    /// Immediate (`#value`)
+    pub fn imm(value: u32) -> Self {
+        AddressMode::Immediate { value }
+    }
+
     /// Converts an m68kdecode instruction to a (src, dest) pair of AddressModes
     ///
     /// TODO: refactor m68kdecode to use my types natively, or use its types in this program.
     ///
+    /// m68kdecode doesn't say whether an index register is a word or a long,
+    /// so the size of each operand's index (if it has one) is passed in `index_sizes`.
+    ///
     /// the third tuple element is an optional size override
     pub fn from_m68kdecode(
         source: m68kdecode::Operand,
         destination: m68kdecode::Operand,
+        index_sizes: [OperandSize; 2],
     ) -> Result<
         (
             Option<AddressMode>,
@@ -261,8 +380,9 @@ impl AddressMode {
         ),
         EmulationError,
     > {
-        let (src, src_size_override) = AddressMode::from_m68kdecode_operand(source);
-        let (dest, dest_size_override) = AddressMode::from_m68kdecode_operand(destination);
+        let (src, src_size_override) = AddressMode::from_m68kdecode_operand(source, index_sizes[0]);
+        let (dest, dest_size_override) =
+            AddressMode::from_m68kdecode_operand(destination, index_sizes[1]);
 
         let size_override = match src_size_override {
             Some(size) => {
@@ -283,9 +403,9 @@ impl AddressMode {
 
     fn from_m68kdecode_operand(
         op: m68kdecode::Operand,
+        index_size: OperandSize,
     ) -> (Option<AddressMode>, Option<OperandSize>) {
-        let mut size_override = None;
-        let op = match op {
+        let mode = match op {
             m68kdecode::Operand::IMM8(value) => Some(AddressMode::Immediate {
                 value: value.into(),
             }),
@@ -322,6 +442,7 @@ impl AddressMode {
                             disp.indirection,
                             Register::Address(reg.into()),
                             Register::Address(index_reg.into()),
+                            index_size,
                             offset,
                             disp.base_displacement as u16,
                             disp.outer_displacement as u16,
@@ -332,6 +453,7 @@ impl AddressMode {
                             disp.indirection,
                             Register::Address(reg.into()),
                             Register::Data(index_reg.into()),
+                            index_size,
                             offset,
                             disp.base_displacement as u16,
                             disp.outer_displacement as u16,
@@ -352,17 +474,17 @@ impl AddressMode {
                     },
                 }
             }
-            m68kdecode::Operand::PCDISP(size, disp) => {
-                // This is gross! TODO: refactor either us or m68kdecode to be better
-                size_override =
-                    Some(OperandSize::from_size_in_bytes(size.into()).expect("bad size override"));
+            m68kdecode::Operand::PCDISP(extension_offset, mut disp) => {
+                // m68kdecode gives the offset of the extension word from the start of the instruction,
+                // but our PC-relative displacements are always relative to the word after the opcode.
+                disp.base_displacement += extension_offset as i32 - 2;
                 match disp.indexer {
                     Indexer::AR(index_reg, offset) => {
-                        // TODO: set `size` to be size
                         Some(Self::from_m68kdecode_with_register_indexing(
                             disp.indirection,
                             Register::ProgramCounter,
                             Register::Address(index_reg.into()),
+                            index_size,
                             offset,
                             disp.base_displacement as u16,
                             disp.outer_displacement as u16,
@@ -373,6 +495,7 @@ impl AddressMode {
                             disp.indirection,
                             Register::ProgramCounter,
                             Register::Data(index_reg.into()),
+                            index_size,
                             offset,
                             disp.base_displacement as u16,
                             disp.outer_displacement as u16,
@@ -400,7 +523,7 @@ impl AddressMode {
             _ => unimplemented!("converting m68kdecode operand {:?} to AddressMode", op),
         };
 
-        (op, size_override)
+        (mode, None)
     }
 
     fn from_m68kdecode_register_bitmask(mask: u16) -> Vec<Register> {
@@ -457,30 +580,43 @@ impl AddressMode {
         indirection: MemoryIndirection,
         address_register: Register,
         index_register: Register,
-        _offset: u8,
+        index_size: OperandSize,
+        scale: u8,
         base_displacement: u16,
         outer_displacement: u16,
     ) -> Self {
+        // m68kdecode gives the scale as a shift count
+        let index_scale = match scale {
+            0 => IndexScale::One,
+            1 => IndexScale::Two,
+            2 => IndexScale::Four,
+            _ => IndexScale::Eight,
+        };
         match indirection {
             MemoryIndirection::Indirect => {
                 match address_register {
                     Register::ProgramCounter => AddressMode::ProgramCounterIndirectIndexed {
                         displacement: base_displacement,
                         index_register,
+                        index_size,
+                        index_scale,
                     },
                     _ => panic!("Shouldn't have memory indirect indexing without pre/postindexing specified except for PC")
                 }
             }
-            // TODO: handle offset
             MemoryIndirection::IndirectPostIndexed => match address_register {
                 Register::Address(ar) => AddressMode::MemoryPostIndexed {
                     address_register: ar,
                     index_register,
+                    index_size,
+                    index_scale,
                     base_displacement,
                     outer_displacement,
                 },
                 Register::ProgramCounter => AddressMode::ProgramCounterMemoryIndirectPostIndexed {
                     index_register,
+                    index_size,
+                    index_scale,
                     base_displacement,
                     outer_displacement,
                 },
@@ -490,40 +626,332 @@ impl AddressMode {
                 Register::Address(ar) => AddressMode::MemoryPreIndexed {
                     address_register: ar,
                     index_register,
+                    index_size,
+                    index_scale,
                     base_displacement,
                     outer_displacement,
                 },
                 Register::ProgramCounter => AddressMode::ProgramCounterMemoryIndirectPreIndexed {
                     index_register,
+                    index_size,
+                    index_scale,
                     base_displacement,
                     outer_displacement,
                 },
                 Register::Data(_) => unimplemented!("data register with preindexed addressing"),
             }
             MemoryIndirection::NoIndirection => match address_register {
-                Register::Address(ar) => AddressMode::RegisterIndirectWithDisplacement {
-                    register: ar,
+                Register::Address(ar) => AddressMode::RegisterIndirectIndexed {
                     displacement: base_displacement,
+                    address_register: ar,
+                    index_register,
+                    index_size,
+                    index_scale,
                 },
-                Register::ProgramCounter => AddressMode::ProgramCounterIndirectWithDisplacement {
+                Register::ProgramCounter => AddressMode::ProgramCounterIndirectIndexed {
                     displacement: base_displacement,
+                    index_register,
+                    index_size,
+                    index_scale,
                 },
                 Register::Data(_) => unimplemented!("data register where address/PC expected"),
             }
         }
     }
 
+    /// Gets the registers that are read to find this operand, or to read its value
+    pub fn registers_read(&self) -> Vec<Register> {
+        match self {
+            AddressMode::RegisterDirect { register } => vec![*register],
+            AddressMode::RegisterDirectList { registers } => registers.clone(),
+            AddressMode::RegisterIndirect { register }
+            | AddressMode::RegisterIndirectPostIncrement { register }
+            | AddressMode::RegisterIndirectPreDecrement { register }
+            | AddressMode::RegisterIndirectWithDisplacement { register, .. } => {
+                vec![Register::Address(*register)]
+            }
+            AddressMode::RegisterIndirectIndexed {
+                address_register,
+                index_register,
+                ..
+            }
+            | AddressMode::MemoryPostIndexed {
+                address_register,
+                index_register,
+                ..
+            }
+            | AddressMode::MemoryPreIndexed {
+                address_register,
+                index_register,
+                ..
+            } => vec![Register::Address(*address_register), *index_register],
+            AddressMode::ProgramCounterIndirectWithDisplacement { .. } => {
+                vec![Register::ProgramCounter]
+            }
+            AddressMode::ProgramCounterIndirectIndexed { index_register, .. }
+            | AddressMode::ProgramCounterMemoryIndirectPostIndexed { index_register, .. }
+            | AddressMode::ProgramCounterMemoryIndirectPreIndexed { index_register, .. } => {
+                vec![Register::ProgramCounter, *index_register]
+            }
+            AddressMode::Absolute { .. } | AddressMode::Immediate { .. } => vec![],
+        }
+    }
+
+    /// Gets the registers that using this operand writes to.
+    ///
+    /// Postincrement and predecrement always write their address register;
+    /// register-direct operands are only written when they're the destination.
+    pub fn registers_written(&self, is_destination: bool) -> Vec<Register> {
+        match self {
+            AddressMode::RegisterIndirectPostIncrement { register }
+            | AddressMode::RegisterIndirectPreDecrement { register } => {
+                vec![Register::Address(*register)]
+            }
+            AddressMode::RegisterDirect { register } if is_destination => vec![*register],
+            AddressMode::RegisterDirectList { registers } if is_destination => registers.clone(),
+            _ => vec![],
+        }
+    }
+
+    /// Gets the number of bytes of extension words this addressing mode adds to an instruction
+    ///
+    /// Absolute addresses always use the long form.
+    pub fn extension_size(&self, size: OperandSize) -> u32 {
+        match self {
+            AddressMode::RegisterDirect { .. }
+            | AddressMode::RegisterIndirect { .. }
+            | AddressMode::RegisterIndirectPostIncrement { .. }
+            | AddressMode::RegisterIndirectPreDecrement { .. } => 0,
+            // register list mask
+            AddressMode::RegisterDirectList { .. } => 2,
+            AddressMode::RegisterIndirectWithDisplacement { .. }
+            | AddressMode::RegisterIndirectIndexed { .. }
+            | AddressMode::ProgramCounterIndirectWithDisplacement { .. }
+            | AddressMode::ProgramCounterIndirectIndexed { .. } => 2,
+            // full extension word, followed by the base and outer displacements (if present)
+            AddressMode::MemoryPostIndexed {
+                base_displacement,
+                outer_displacement,
+                ..
+            }
+            | AddressMode::MemoryPreIndexed {
+                base_displacement,
+                outer_displacement,
+                ..
+            }
+            | AddressMode::ProgramCounterMemoryIndirectPostIndexed {
+                base_displacement,
+                outer_displacement,
+                ..
+            }
+            | AddressMode::ProgramCounterMemoryIndirectPreIndexed {
+                base_displacement,
+                outer_displacement,
+                ..
+            } => {
+                2 + if *base_displacement != 0 { 2 } else { 0 }
+                    + if *outer_displacement != 0 { 2 } else { 0 }
+            }
+            AddressMode::Absolute { .. } => 4,
+            AddressMode::Immediate { .. } => match size {
+                OperandSize::Byte | OperandSize::Word => 2,
+                OperandSize::Long => 4,
+            },
+        }
+    }
+
+    /// Gets the number of clock cycles the 68000 takes to calculate this effective address
+    /// and read a `size` operand from it
+    ///
+    /// The 68020's memory indirect modes have no 68000 timing, so they're counted as an indexed access
+    /// plus fetching the intermediate address.
+    pub fn calculation_cycles(&self, size: OperandSize) -> u32 {
+        let (byte_or_word, long) = match self {
+            AddressMode::RegisterDirect { .. } | AddressMode::RegisterDirectList { .. } => (0, 0),
+            AddressMode::RegisterIndirect { .. }
+            | AddressMode::RegisterIndirectPostIncrement { .. }
+            | AddressMode::Immediate { .. } => (4, 8),
+            AddressMode::RegisterIndirectPreDecrement { .. } => (6, 10),
+            AddressMode::RegisterIndirectWithDisplacement { .. }
+            | AddressMode::ProgramCounterIndirectWithDisplacement { .. } => (8, 12),
+            AddressMode::RegisterIndirectIndexed { .. }
+            | AddressMode::ProgramCounterIndirectIndexed { .. } => (10, 14),
+            // always the long form
+            AddressMode::Absolute { .. } => (12, 16),
+            AddressMode::MemoryPostIndexed { .. }
+            | AddressMode::MemoryPreIndexed { .. }
+            | AddressMode::ProgramCounterMemoryIndirectPostIndexed { .. }
+            | AddressMode::ProgramCounterMemoryIndirectPreIndexed { .. } => (18, 22),
+        };
+        match size {
+            OperandSize::Byte | OperandSize::Word => byte_or_word,
+            OperandSize::Long => long,
+        }
+    }
+
+    /// Computes the address in memory that this addressing mode refers to, without reading or writing it.
+    ///
+    /// Only the control addressing modes (the ones `jmp`, `lea`, etc. accept) have an effective address
+    /// that can be computed without side effects; others produce an error.
+    pub fn effective_address(&self, cpu: &CPU<impl Memory>) -> Result<Address, EmulationError> {
+        match *self {
+            AddressMode::Absolute { address } => Ok(bus_address(cpu, address)),
+            AddressMode::RegisterIndirect { register } => Ok(bus_address(
+                cpu,
+                cpu.registers.get_address_register(register),
+            )),
+            AddressMode::RegisterIndirectWithDisplacement {
+                register,
+                displacement,
+            } => offset_address(
+                cpu,
+                cpu.registers.get_address_register(register),
+                displacement as i16 as u32,
+            ),
+            AddressMode::RegisterIndirectIndexed {
+                displacement,
+                address_register,
+                index_register,
+                index_size,
+                index_scale,
+            } => indexed_address(
+                cpu,
+                cpu.registers.get_address_register(address_register),
+                displacement as i16 as u32,
+                index_value(cpu, index_register, index_size),
+                scale_factor(cpu, index_scale),
+            ),
+            AddressMode::ProgramCounterIndirectWithDisplacement { displacement } => {
+                offset_address(cpu, pc_relative_base(cpu), displacement as i16 as u32)
+            }
+            AddressMode::ProgramCounterIndirectIndexed {
+                displacement,
+                index_register,
+                index_size,
+                index_scale,
+            } => indexed_address(
+                cpu,
+                pc_relative_base(cpu),
+                displacement as i16 as u32,
+                index_value(cpu, index_register, index_size),
+                scale_factor(cpu, index_scale),
+            ),
+            AddressMode::MemoryPostIndexed {
+                base_displacement,
+                outer_displacement,
+                address_register,
+                index_register,
+                index_size,
+                index_scale,
+            } => indexed_address(
+                cpu,
+                cpu.memory.read_long(offset_address(
+                    cpu,
+                    cpu.registers.get_address_register(address_register),
+                    base_displacement as i16 as u32,
+                )?)?,
+                outer_displacement as i16 as u32,
+                index_value(cpu, index_register, index_size),
+                scale_factor(cpu, index_scale),
+            ),
+            AddressMode::MemoryPreIndexed {
+                base_displacement,
+                outer_displacement,
+                address_register,
+                index_register,
+                index_size,
+                index_scale,
+            } => offset_address(
+                cpu,
+                cpu.memory.read_long(indexed_address(
+                    cpu,
+                    cpu.registers.get_address_register(address_register),
+                    base_displacement as i16 as u32,
+                    index_value(cpu, index_register, index_size),
+                    scale_factor(cpu, index_scale),
+                )?)?,
+                outer_displacement as i16 as u32,
+            ),
+            AddressMode::ProgramCounterMemoryIndirectPostIndexed {
+                base_displacement,
+                outer_displacement,
+                index_register,
+                index_size,
+                index_scale,
+            } => indexed_address(
+                cpu,
+                cpu.memory.read_long(offset_address(
+                    cpu,
+                    pc_relative_base(cpu),
+                    base_displacement as i16 as u32,
+                )?)?,
+                outer_displacement as i16 as u32,
+                index_value(cpu, index_register, index_size),
+                scale_factor(cpu, index_scale),
+            ),
+            AddressMode::ProgramCounterMemoryIndirectPreIndexed {
+                base_displacement,
+                outer_displacement,
+                index_register,
+                index_size,
+                index_scale,
+            } => offset_address(
+                cpu,
+                cpu.memory.read_long(indexed_address(
+                    cpu,
+                    pc_relative_base(cpu),
+                    base_displacement as i16 as u32,
+                    index_value(cpu, index_register, index_size),
+                    scale_factor(cpu, index_scale),
+                )?)?,
+                outer_displacement as i16 as u32,
+            ),
+            AddressMode::RegisterDirect { .. }
+            | AddressMode::RegisterDirectList { .. }
+            | AddressMode::RegisterIndirectPostIncrement { .. }
+            | AddressMode::RegisterIndirectPreDecrement { .. }
+            | AddressMode::Immediate { .. } => {
+                Err(EmulationError::NoEffectiveAddress(self.clone()))
+            }
+        }
+    }
+
+    /// Gets the address in memory that accessing this operand at `size` would use, without changing any registers.
+    ///
+    /// Unlike [`AddressMode::effective_address`], this includes `(An)+` and `-(An)`;
+    /// it returns `None` for operands that aren't in memory (registers and immediates).
+    pub fn memory_address(
+        &self,
+        cpu: &CPU<impl Memory>,
+        size: OperandSize,
+    ) -> Result<Option<Address>, EmulationError> {
+        match *self {
+            AddressMode::RegisterDirect { .. }
+            | AddressMode::RegisterDirectList { .. }
+            | AddressMode::Immediate { .. } => Ok(None),
+            AddressMode::RegisterIndirectPostIncrement { register } => Ok(Some(bus_address(
+                cpu,
+                cpu.registers.get_address_register(register),
+            ))),
+            AddressMode::RegisterIndirectPreDecrement { register } => Ok(Some(bus_address(
+                cpu,
+                cpu.registers.get_address_register(register) - get_increment(register, size),
+            ))),
+            _ => Ok(Some(self.effective_address(cpu)?)),
+        }
+    }
+
     /// Gets the value referenced by this address
     ///
-    /// Should return the same size `M68kInteger` as the `OperandSize` given in the enum
+    /// Returns the same size `M68kInteger` as the `OperandSize` given, which is checked in debug builds
     pub fn get_value(
         &self,
         cpu: &mut CPU<impl crate::ram::Memory>,
         size: OperandSize,
     ) -> Result<M68kInteger, EmulationError> {
-        match *self {
+        let result = match *self {
             // Absolute
-            AddressMode::Absolute { address } => cpu.memory.read(address, size),
+            AddressMode::Absolute { address } => cpu.memory.read(bus_address(cpu, address), size),
 
             // Immediate
             AddressMode::Immediate { value } => match size {
@@ -539,24 +967,27 @@ impl AddressMode {
                 OperandSize::Long => Ok(M68kInteger::Long(cpu.registers.get(register))),
             },
             AddressMode::RegisterDirectList { .. } => Err(EmulationError::ReadMultipleRegisters),
-            AddressMode::RegisterIndirect { register } => cpu
-                .memory
-                .read(cpu.registers.get_address_register(register), size),
+            AddressMode::RegisterIndirect { register } => cpu.memory.read(
+                bus_address(cpu, cpu.registers.get_address_register(register)),
+                size,
+            ),
             AddressMode::RegisterIndirectIndexed {
                 displacement,
                 address_register,
                 index_register,
+                index_size,
+                index_scale,
             } => get_address_register_indirect_indexed(
                 cpu,
-                Register::Address(address_register),
-                index_register,
-                size.size_in_bytes(),
-                displacement as u32,
+                cpu.registers.get_address_register(address_register),
+                index_value(cpu, index_register, index_size),
+                scale_factor(cpu, index_scale),
+                displacement as i16 as u32,
                 size,
             ),
             AddressMode::RegisterIndirectPostIncrement { register } => {
                 let address = cpu.registers.get_address_register(register);
-                let value = cpu.memory.read(address, size)?;
+                let value = cpu.memory.read(bus_address(cpu, address), size)?;
                 cpu.registers
                     .set_address_register(register, address + get_increment(register, size));
                 Ok(value)
@@ -565,15 +996,15 @@ impl AddressMode {
                 let address =
                     cpu.registers.get_address_register(register) - get_increment(register, size);
                 cpu.registers.set_address_register(register, address);
-                cpu.memory.read(address, size)
+                cpu.memory.read(bus_address(cpu, address), size)
             }
             AddressMode::RegisterIndirectWithDisplacement {
                 register,
                 displacement,
             } => get_address_register_indirect_with_displacement(
                 cpu,
-                Register::Address(register),
-                displacement as u32,
+                cpu.registers.get_address_register(register),
+                displacement as i16 as u32,
                 size,
             ),
 
@@ -581,46 +1012,52 @@ impl AddressMode {
             AddressMode::ProgramCounterIndirectWithDisplacement { displacement } => {
                 get_address_register_indirect_with_displacement(
                     cpu,
-                    Register::ProgramCounter,
-                    displacement as u32,
+                    pc_relative_base(cpu),
+                    displacement as i16 as u32,
                     size,
                 )
             }
             AddressMode::ProgramCounterIndirectIndexed {
                 displacement,
                 index_register,
+                index_size,
+                index_scale,
             } => get_address_register_indirect_indexed(
                 cpu,
-                Register::ProgramCounter,
-                index_register,
-                size.size_in_bytes(),
-                displacement as u32,
+                pc_relative_base(cpu),
+                index_value(cpu, index_register, index_size),
+                scale_factor(cpu, index_scale),
+                displacement as i16 as u32,
                 size,
             ),
             AddressMode::ProgramCounterMemoryIndirectPostIndexed {
                 base_displacement,
                 outer_displacement,
                 index_register,
+                index_size,
+                index_scale,
             } => get_address_ram_post_indexed(
                 cpu,
-                cpu.registers.get(Register::ProgramCounter),
-                index_register,
-                size.size_in_bytes(),
-                base_displacement as u32,
-                outer_displacement as u32,
+                pc_relative_base(cpu),
+                index_value(cpu, index_register, index_size),
+                scale_factor(cpu, index_scale),
+                base_displacement as i16 as u32,
+                outer_displacement as i16 as u32,
                 size,
             ),
             AddressMode::ProgramCounterMemoryIndirectPreIndexed {
                 base_displacement,
                 outer_displacement,
                 index_register,
+                index_size,
+                index_scale,
             } => get_address_ram_pre_indexed(
                 cpu,
-                cpu.registers.get(Register::ProgramCounter),
-                index_register,
-                size.size_in_bytes(),
-                base_displacement as u32,
-                outer_displacement as u32,
+                pc_relative_base(cpu),
+                index_value(cpu, index_register, index_size),
+                scale_factor(cpu, index_scale),
+                base_displacement as i16 as u32,
+                outer_displacement as i16 as u32,
                 size,
             ),
 
@@ -630,13 +1067,15 @@ impl AddressMode {
                 outer_displacement,
                 address_register,
                 index_register,
+                index_size,
+                index_scale,
             } => get_address_ram_post_indexed(
                 cpu,
                 cpu.registers.get_address_register(address_register),
-                index_register,
-                size.size_in_bytes(),
-                base_displacement as u32,
-                outer_displacement as u32,
+                index_value(cpu, index_register, index_size),
+                scale_factor(cpu, index_scale),
+                base_displacement as i16 as u32,
+                outer_displacement as i16 as u32,
                 size,
             ),
             AddressMode::MemoryPreIndexed {
@@ -644,16 +1083,30 @@ impl AddressMode {
                 outer_displacement,
                 address_register,
                 index_register,
+                index_size,
+                index_scale,
             } => get_address_ram_pre_indexed(
                 cpu,
                 cpu.registers.get_address_register(address_register),
-                index_register,
-                size.size_in_bytes(),
-                base_displacement as u32,
-                outer_displacement as u32,
+                index_value(cpu, index_register, index_size),
+                scale_factor(cpu, index_scale),
+                base_displacement as i16 as u32,
+                outer_displacement as i16 as u32,
                 size,
             ),
+        };
+
+        // A mode that reads the wrong size would silently truncate or widen values, so catch it in debug builds
+        if let Ok(value) = &result {
+            debug_assert!(
+                value.is_size(size),
+                "{:?} read {:?} when asked for a {:?}",
+                self,
+                value,
+                size
+            );
         }
+        result
     }
 
     /// Sets the RAM or register referenced by this address to the given value
@@ -671,7 +1124,9 @@ impl AddressMode {
     ) -> Result<(), EmulationError> {
         match *self {
             // Absolute
-            AddressMode::Absolute { address } => cpu.memory.write(address, new_value),
+            AddressMode::Absolute { address } => {
+                cpu.memory.write(bus_address(cpu, address), new_value)
+            }
 
             // Immediate
             AddressMode::Immediate { .. } => Err(EmulationError::WriteToReadOnly(
@@ -691,24 +1146,27 @@ impl AddressMode {
                 }
                 Ok(())
             }
-            AddressMode::RegisterIndirect { register } => cpu
-                .memory
-                .write(cpu.registers.get_address_register(register), new_value),
+            AddressMode::RegisterIndirect { register } => cpu.memory.write(
+                bus_address(cpu, cpu.registers.get_address_register(register)),
+                new_value,
+            ),
             AddressMode::RegisterIndirectIndexed {
                 displacement,
                 address_register,
                 index_register,
+                index_size,
+                index_scale,
             } => set_address_register_indirect_indexed(
                 cpu,
-                Register::Address(address_register),
-                index_register,
-                new_value.size().size_in_bytes(),
-                displacement as u32,
+                cpu.registers.get_address_register(address_register),
+                index_value(cpu, index_register, index_size),
+                scale_factor(cpu, index_scale),
+                displacement as i16 as u32,
                 new_value,
             ),
             AddressMode::RegisterIndirectPostIncrement { register } => {
                 let address = cpu.registers.get_address_register(register);
-                cpu.memory.write(address, new_value)?;
+                cpu.memory.write(bus_address(cpu, address), new_value)?;
                 cpu.registers.set_address_register(
                     register,
                     address + get_increment(register, new_value.size()),
@@ -720,94 +1178,210 @@ impl AddressMode {
                 let address = cpu.registers.get_address_register(register)
                     - get_increment(register, new_value.size());
                 cpu.registers.set_address_register(register, address);
-                cpu.memory.write(address, new_value)
+                cpu.memory.write(bus_address(cpu, address), new_value)
             }
             AddressMode::RegisterIndirectWithDisplacement {
                 register,
                 displacement,
             } => set_address_register_indirect_with_displacement(
                 cpu,
-                Register::Address(register),
-                displacement as u32,
+                cpu.registers.get_address_register(register),
+                displacement as i16 as u32,
                 new_value,
             ),
 
             // Program Counter
-            AddressMode::ProgramCounterIndirectWithDisplacement { displacement } => {
-                set_address_register_indirect_with_displacement(
-                    cpu,
-                    Register::ProgramCounter,
-                    displacement as u32,
-                    new_value,
-                )
-            }
-            AddressMode::ProgramCounterIndirectIndexed {
-                displacement,
-                index_register,
-            } => set_address_register_indirect_indexed(
-                cpu,
-                Register::ProgramCounter,
-                index_register,
-                new_value.size().size_in_bytes(),
-                displacement as u32,
-                new_value,
+            // The 68000 can't write through the program counter
+            AddressMode::ProgramCounterIndirectWithDisplacement { .. }
+            | AddressMode::ProgramCounterIndirectIndexed { .. }
+            | AddressMode::ProgramCounterMemoryIndirectPostIndexed { .. }
+            | AddressMode::ProgramCounterMemoryIndirectPreIndexed { .. } => Err(
+                EmulationError::WriteToReadOnly("can't write to PC-relative address".to_string()),
             ),
-            AddressMode::ProgramCounterMemoryIndirectPostIndexed {
+
+            // Memory
+            AddressMode::MemoryPostIndexed {
                 base_displacement,
                 outer_displacement,
+                address_register,
                 index_register,
+                index_size,
+                index_scale,
             } => set_address_ram_post_indexed(
                 cpu,
-                cpu.registers.get(Register::ProgramCounter),
-                index_register,
-                new_value.size().size_in_bytes(),
-                base_displacement as u32,
-                outer_displacement as u32,
+                cpu.registers.get_address_register(address_register),
+                index_value(cpu, index_register, index_size),
+                scale_factor(cpu, index_scale),
+                base_displacement as i16 as u32,
+                outer_displacement as i16 as u32,
                 new_value,
             ),
-            AddressMode::ProgramCounterMemoryIndirectPreIndexed {
+            AddressMode::MemoryPreIndexed {
                 base_displacement,
                 outer_displacement,
+                address_register,
                 index_register,
+                index_size,
+                index_scale,
             } => set_address_ram_pre_indexed(
                 cpu,
-                cpu.registers.get(Register::ProgramCounter),
-                index_register,
-                new_value.size().size_in_bytes(),
-                base_displacement as u32,
-                outer_displacement as u32,
+                cpu.registers.get_address_register(address_register),
+                index_value(cpu, index_register, index_size),
+                scale_factor(cpu, index_scale),
+                base_displacement as i16 as u32,
+                outer_displacement as i16 as u32,
                 new_value,
             ),
+        }
+    }
+}
 
-            // Memory
+/// Formats an index register the way it's written in assembly, like `d1.w*4`
+fn format_index(register: Register, size: OperandSize, scale: IndexScale) -> String {
+    match scale {
+        IndexScale::One => format!("{}{}", register, size.suffix()),
+        _ => format!("{}{}*{}", register, size.suffix(), scale as u8),
+    }
+}
+
+impl Display for AddressMode {
+    /// Formats an operand in the syntax the assembler accepts, such as `(8, a0)` or `([4, a0], d1.w*2, 0)`.
+    ///
+    /// Displacements are signed decimal; addresses and immediates are hex.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressMode::RegisterDirect { register } => write!(f, "{}", register),
+            AddressMode::RegisterDirectList { registers } => {
+                let names: Vec<String> = registers.iter().map(Register::to_string).collect();
+                write!(f, "{}", names.join("/"))
+            }
+            AddressMode::RegisterIndirect { register } => {
+                write!(f, "({})", Register::Address(*register))
+            }
+            AddressMode::RegisterIndirectPostIncrement { register } => {
+                write!(f, "({})+", Register::Address(*register))
+            }
+            AddressMode::RegisterIndirectPreDecrement { register } => {
+                write!(f, "-({})", Register::Address(*register))
+            }
+            AddressMode::RegisterIndirectWithDisplacement {
+                register,
+                displacement,
+            } => write!(
+                f,
+                "({}, {})",
+                *displacement as i16,
+                Register::Address(*register)
+            ),
+            AddressMode::RegisterIndirectIndexed {
+                displacement,
+                address_register,
+                index_register,
+                index_size,
+                index_scale,
+            } => write!(
+                f,
+                "({}, {}, {})",
+                *displacement as i16,
+                Register::Address(*address_register),
+                format_index(*index_register, *index_size, *index_scale)
+            ),
             AddressMode::MemoryPostIndexed {
                 base_displacement,
                 outer_displacement,
                 address_register,
                 index_register,
-            } => set_address_ram_post_indexed(
-                cpu,
-                cpu.registers.get_address_register(address_register),
-                index_register,
-                new_value.size().size_in_bytes(),
-                base_displacement as u32,
-                outer_displacement as u32,
-                new_value,
+                index_size,
+                index_scale,
+            } => write!(
+                f,
+                "([{}, {}], {}, {})",
+                *base_displacement as i16,
+                Register::Address(*address_register),
+                format_index(*index_register, *index_size, *index_scale),
+                *outer_displacement as i16
             ),
             AddressMode::MemoryPreIndexed {
                 base_displacement,
                 outer_displacement,
                 address_register,
                 index_register,
-            } => set_address_ram_pre_indexed(
-                cpu,
-                cpu.registers.get_address_register(address_register),
+                index_size,
+                index_scale,
+            } => write!(
+                f,
+                "([{}, {}, {}], {})",
+                *base_displacement as i16,
+                Register::Address(*address_register),
+                format_index(*index_register, *index_size, *index_scale),
+                *outer_displacement as i16
+            ),
+            AddressMode::ProgramCounterIndirectWithDisplacement { displacement } => {
+                write!(f, "({}, pc)", *displacement as i16)
+            }
+            AddressMode::ProgramCounterIndirectIndexed {
+                displacement,
                 index_register,
-                new_value.size().size_in_bytes(),
-                base_displacement as u32,
-                outer_displacement as u32,
-                new_value,
+                index_size,
+                index_scale,
+            } => write!(
+                f,
+                "({}, pc, {})",
+                *displacement as i16,
+                format_index(*index_register, *index_size, *index_scale)
+            ),
+            AddressMode::ProgramCounterMemoryIndirectPreIndexed {
+                base_displacement,
+                outer_displacement,
+                index_register,
+                index_size,
+                index_scale,
+            } => write!(
+                f,
+                "([{}, pc, {}], {})",
+                *base_displacement as i16,
+                format_index(*index_register, *index_size, *index_scale),
+                *outer_displacement as i16
+            ),
+            AddressMode::ProgramCounterMemoryIndirectPostIndexed {
+                base_displacement,
+                outer_displacement,
+                index_register,
+                index_size,
+                index_scale,
+            } => write!(
+                f,
+                "([{}, pc], {}, {})",
+                *base_displacement as i16,
+                format_index(*index_register, *index_size, *index_scale),
+                *outer_displacement as i16
             ),
+            AddressMode::Absolute { address } => write!(f, "(${:X})", address),
+            AddressMode::Immediate { value } => write!(f, "#${:X}", value),
+        }
+    }
+}
+
+impl AddressMode {
+    /// Formats an operand like [`Display`] does, but with addresses that have a name in `symbols` written as that name:
+    /// `(strcpy)` instead of `($1234)`, and `(table, pc)` instead of `(18, pc)`.
+    ///
+    /// `pc` is the address of the instruction the operand belongs to.
+    pub fn to_assembly(&self, pc: u32, symbols: &SymbolTable) -> String {
+        let name = match self {
+            AddressMode::Absolute { address } => symbols.name(*address),
+            // Only the source can be PC-relative, so its extension word always directly follows the opcode
+            AddressMode::ProgramCounterIndirectWithDisplacement { displacement } => {
+                symbols.name(pc.wrapping_add(2).wrapping_add(*displacement as i16 as u32))
+            }
+            _ => None,
+        };
+        match (self, name) {
+            (AddressMode::Absolute { .. }, Some(name)) => format!("({})", name),
+            (AddressMode::ProgramCounterIndirectWithDisplacement { .. }, Some(name)) => {
+                format!("({}, pc)", name)
+            }
+            _ => self.to_string(),
         }
     }
 }
@@ -827,6 +1401,13 @@ mod tests {
     static ADDRESS_REGISTER: AddressRegister = AddressRegister::A0;
     static DATA_REGISTER: DataRegister = DataRegister::D0;
 
+    /// Index scaling (and the memory indirect modes) need a 68020
+    fn cpu_68020() -> CPU<VecBackedMemory> {
+        let mut cpu = CPU::<VecBackedMemory>::new(1_024);
+        cpu.model = CpuModel::M68020;
+        cpu
+    }
+
     /// Runs test closure (|size: OperandSize, get_value: M68kInteger, set_value: M68kInteger| { ... })
     fn all_sizes(
         closure: impl Fn(
@@ -836,9 +1417,9 @@ mod tests {
             M68kInteger,
         ) -> Result<(), EmulationError>,
     ) {
-        let cpu1 = CPU::<VecBackedMemory>::new(1_024);
-        let cpu2 = CPU::<VecBackedMemory>::new(1_024);
-        let cpu3 = CPU::<VecBackedMemory>::new(1_024);
+        let cpu1 = cpu_68020();
+        let cpu2 = cpu_68020();
+        let cpu3 = cpu_68020();
 
         closure(
             cpu1,
@@ -863,6 +1444,87 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn every_mode_reads_requested_size() {
+        all_sizes(|mut cpu, size, _, _| {
+            cpu.registers
+                .set_address_register(ADDRESS_REGISTER, ADDRESS);
+            cpu.registers.set_data_register(DATA_REGISTER, INDEX);
+            let index_register = Register::Data(DATA_REGISTER);
+            let modes = [
+                AddressMode::Absolute { address: ADDRESS },
+                AddressMode::Immediate { value: 0xFFFF_FFFF },
+                AddressMode::RegisterDirect {
+                    register: Register::Address(ADDRESS_REGISTER),
+                },
+                AddressMode::RegisterIndirect {
+                    register: ADDRESS_REGISTER,
+                },
+                AddressMode::RegisterIndirectPostIncrement {
+                    register: ADDRESS_REGISTER,
+                },
+                AddressMode::RegisterIndirectPreDecrement {
+                    register: ADDRESS_REGISTER,
+                },
+                AddressMode::RegisterIndirectWithDisplacement {
+                    register: ADDRESS_REGISTER,
+                    displacement: DISPLACEMENT,
+                },
+                AddressMode::RegisterIndirectIndexed {
+                    displacement: DISPLACEMENT,
+                    address_register: ADDRESS_REGISTER,
+                    index_register,
+                    index_size: OperandSize::Long,
+                    index_scale: IndexScale::Two,
+                },
+                AddressMode::MemoryPostIndexed {
+                    base_displacement: DISPLACEMENT,
+                    outer_displacement: OUTER_DISPLACEMENT,
+                    address_register: ADDRESS_REGISTER,
+                    index_register,
+                    index_size: OperandSize::Long,
+                    index_scale: IndexScale::Four,
+                },
+                AddressMode::MemoryPreIndexed {
+                    base_displacement: DISPLACEMENT,
+                    outer_displacement: OUTER_DISPLACEMENT,
+                    address_register: ADDRESS_REGISTER,
+                    index_register,
+                    index_size: OperandSize::Word,
+                    index_scale: IndexScale::Eight,
+                },
+                AddressMode::ProgramCounterIndirectWithDisplacement {
+                    displacement: DISPLACEMENT,
+                },
+                AddressMode::ProgramCounterIndirectIndexed {
+                    displacement: DISPLACEMENT,
+                    index_register,
+                    index_size: OperandSize::Long,
+                    index_scale: IndexScale::One,
+                },
+                AddressMode::ProgramCounterMemoryIndirectPostIndexed {
+                    base_displacement: DISPLACEMENT,
+                    outer_displacement: OUTER_DISPLACEMENT,
+                    index_register,
+                    index_size: OperandSize::Long,
+                    index_scale: IndexScale::Two,
+                },
+                AddressMode::ProgramCounterMemoryIndirectPreIndexed {
+                    base_displacement: DISPLACEMENT,
+                    outer_displacement: OUTER_DISPLACEMENT,
+                    index_register,
+                    index_size: OperandSize::Long,
+                    index_scale: IndexScale::Four,
+                },
+            ];
+
+            for mode in modes.iter() {
+                assert_eq!(mode.get_value(&mut cpu, size)?.size(), size, "{:?}", mode);
+            }
+            Ok(())
+        });
+    }
+
     #[test]
     fn register_direct() {
         all_sizes(|mut cpu, size, get_value, set_value| {
@@ -891,13 +1553,13 @@ mod tests {
                 register: ADDRESS_REGISTER,
             };
 
-            cpu.memory.write(ADDRESS, get_value)?;
+            cpu.memory.write(Address(ADDRESS), get_value)?;
             cpu.registers
                 .set_address_register(ADDRESS_REGISTER, ADDRESS);
             assert_eq!(mode.get_value(&mut cpu, size)?, get_value);
 
             mode.set_value(&mut cpu, set_value)?;
-            assert_eq!(cpu.memory.read(ADDRESS, size)?, set_value);
+            assert_eq!(cpu.memory.read(Address(ADDRESS), size)?, set_value);
             Ok(())
         });
     }
@@ -911,7 +1573,7 @@ mod tests {
             };
 
             // get
-            cpu.memory.write(ADDRESS, get_value)?;
+            cpu.memory.write(Address(ADDRESS), get_value)?;
             cpu.registers
                 .set_address_register(ADDRESS_REGISTER, ADDRESS);
             assert_eq!(mode.get_value(&mut cpu, size)?, get_value);
@@ -922,7 +1584,10 @@ mod tests {
 
             // set
             mode.set_value(&mut cpu, set_value)?;
-            assert_eq!(cpu.memory.read(ADDRESS + byte_offset, size)?, set_value);
+            assert_eq!(
+                cpu.memory.read(Address(ADDRESS + byte_offset), size)?,
+                set_value
+            );
             assert_eq!(
                 cpu.registers.get_address_register(ADDRESS_REGISTER),
                 ADDRESS + (byte_offset * 2)
@@ -940,7 +1605,9 @@ mod tests {
             };
 
             // get
-            cpu.memory.write(ADDRESS - byte_offset, get_value).unwrap();
+            cpu.memory
+                .write(Address(ADDRESS - byte_offset), get_value)
+                .unwrap();
             cpu.registers
                 .set_address_register(ADDRESS_REGISTER, ADDRESS);
             assert_eq!(mode.get_value(&mut cpu, size).unwrap(), get_value);
@@ -952,7 +1619,8 @@ mod tests {
             // set
             mode.set_value(&mut cpu, set_value)?;
             assert_eq!(
-                cpu.memory.read(ADDRESS - (byte_offset * 2), size)?,
+                cpu.memory
+                    .read(Address(ADDRESS - (byte_offset * 2)), size)?,
                 set_value
             );
             assert_eq!(
@@ -1021,12 +1689,12 @@ mod tests {
             cpu.registers
                 .set_address_register(ADDRESS_REGISTER, ADDRESS);
             let addr = ADDRESS + DISPLACEMENT as u32;
-            cpu.memory.write(addr, get_value).unwrap();
+            cpu.memory.write(Address(addr), get_value).unwrap();
             assert_eq!(mode.get_value(&mut cpu, size).unwrap(), get_value);
 
             // set
             mode.set_value(&mut cpu, set_value)?;
-            assert_eq!(cpu.memory.read(addr, size)?, set_value);
+            assert_eq!(cpu.memory.read(Address(addr), size)?, set_value);
             Ok(())
         });
     }
@@ -1037,6 +1705,8 @@ mod tests {
             let mode = AddressMode::RegisterIndirectIndexed {
                 address_register: ADDRESS_REGISTER,
                 index_register: Register::Data(DATA_REGISTER),
+                index_size: OperandSize::Long,
+                index_scale: size.into(),
                 displacement: DISPLACEMENT,
             };
 
@@ -1046,16 +1716,187 @@ mod tests {
             cpu.registers
                 .set_address_register(ADDRESS_REGISTER, ADDRESS);
             cpu.registers.set_data_register(DATA_REGISTER, INDEX);
-            cpu.memory.write(addr, get_value)?;
+            cpu.memory.write(Address(addr), get_value)?;
             assert_eq!(mode.get_value(&mut cpu, size)?, get_value);
 
             // set
             mode.set_value(&mut cpu, set_value)?;
-            assert_eq!(cpu.memory.read(addr, size)?, set_value);
+            assert_eq!(cpu.memory.read(Address(addr), size)?, set_value);
             Ok(())
         });
     }
 
+    #[test]
+    fn register_indirect_indexed_wraps_around_address_space() {
+        let mut cpu = cpu_68020();
+        let mode = AddressMode::RegisterIndirectIndexed {
+            displacement: DISPLACEMENT,
+            address_register: ADDRESS_REGISTER,
+            index_register: Register::Data(DATA_REGISTER),
+            index_size: OperandSize::Long,
+            index_scale: IndexScale::Four,
+        };
+
+        // 0x40000001 * 4 overflows a u32, and should wrap around to 4
+        cpu.registers
+            .set_address_register(ADDRESS_REGISTER, ADDRESS);
+        cpu.registers
+            .set_data_register(DATA_REGISTER, 0x4000_0001u32);
+        let addr = ADDRESS + DISPLACEMENT as u32 + 4;
+
+        cpu.memory
+            .write(Address(addr), M68kInteger::Long(0xFACEBEEF))
+            .unwrap();
+        assert_eq!(
+            mode.get_value(&mut cpu, OperandSize::Long).unwrap(),
+            M68kInteger::Long(0xFACEBEEF)
+        );
+
+        mode.set_value(&mut cpu, M68kInteger::Long(0xAF7B3AD))
+            .unwrap();
+        assert_eq!(
+            cpu.memory.read(Address(addr), OperandSize::Long).unwrap(),
+            M68kInteger::Long(0xAF7B3AD)
+        );
+    }
+
+    #[test]
+    fn strict_addressing() {
+        let mut cpu = CPU::<VecBackedMemory>::new(1_024);
+        let mode = AddressMode::RegisterIndirectWithDisplacement {
+            register: ADDRESS_REGISTER,
+            displacement: 0x20,
+        };
+        cpu.registers
+            .set_address_register(ADDRESS_REGISTER, 0x00FF_FFF0u32);
+        cpu.memory.write_long(Address(0x10), 0xFACEBEEF).unwrap();
+
+        // by default, addresses wrap around like they do on the hardware
+        assert_eq!(mode.effective_address(&cpu).unwrap(), Address(0x10));
+        assert_eq!(
+            mode.get_value(&mut cpu, OperandSize::Long).unwrap(),
+            M68kInteger::Long(0xFACEBEEF)
+        );
+
+        cpu.strict_addressing = true;
+        match mode.get_value(&mut cpu, OperandSize::Long) {
+            Err(EmulationError::AddressWrapped { base, displacement }) => {
+                assert_eq!(base, Address(0x00FF_FFF0));
+                assert_eq!(displacement, 0x20);
+            }
+            other => panic!("expected AddressWrapped, got {:?}", other),
+        }
+
+        // negative displacements can wrap past the bottom of the address space too
+        cpu.registers
+            .set_address_register(ADDRESS_REGISTER, 0x10u32);
+        let mode = AddressMode::RegisterIndirectWithDisplacement {
+            register: ADDRESS_REGISTER,
+            displacement: -0x20i16 as u16,
+        };
+        assert!(matches!(
+            mode.effective_address(&cpu),
+            Err(EmulationError::AddressWrapped {
+                displacement: -0x20,
+                ..
+            })
+        ));
+
+        // addresses that stay in range are unaffected
+        let mode = AddressMode::RegisterIndirectWithDisplacement {
+            register: ADDRESS_REGISTER,
+            displacement: -0x10i16 as u16,
+        };
+        assert_eq!(mode.effective_address(&cpu).unwrap(), Address(0));
+
+        // the 68020 has a 32-bit address space, so going past 24 bits doesn't wrap there
+        cpu.model = CpuModel::M68020;
+        cpu.registers
+            .set_address_register(ADDRESS_REGISTER, 0x00FF_FFF0u32);
+        let mode = AddressMode::RegisterIndirectWithDisplacement {
+            register: ADDRESS_REGISTER,
+            displacement: 0x20,
+        };
+        assert_eq!(mode.effective_address(&cpu).unwrap(), Address(0x0100_0010));
+    }
+
+    #[test]
+    fn address_mask_applies_to_every_mode() {
+        let mut cpu = CPU::<VecBackedMemory>::new(0x2000);
+        cpu.registers
+            .set_address_register(ADDRESS_REGISTER, 0xFF00_1000u32);
+        cpu.memory.write_long(Address(0x1000), 0xFACEBEEF).unwrap();
+
+        // The 68000 ignores the top byte, so these all reach $001000
+        for mode in [
+            AddressMode::RegisterIndirect {
+                register: ADDRESS_REGISTER,
+            },
+            AddressMode::Absolute {
+                address: 0xFF00_1000,
+            },
+            AddressMode::RegisterIndirectWithDisplacement {
+                register: ADDRESS_REGISTER,
+                displacement: 0,
+            },
+        ] {
+            assert_eq!(mode.effective_address(&cpu).unwrap(), Address(0x1000));
+            assert_eq!(
+                mode.get_value(&mut cpu, OperandSize::Long).unwrap(),
+                M68kInteger::Long(0xFACEBEEF),
+                "{:?}",
+                mode
+            );
+            mode.set_value(&mut cpu, M68kInteger::Long(0x12345678))
+                .unwrap();
+            assert_eq!(cpu.memory.read_long(Address(0x1000)).unwrap(), 0x12345678);
+            cpu.memory.write_long(Address(0x1000), 0xFACEBEEF).unwrap();
+        }
+
+        // (An)+ and -(An) use the masked address, but keep all 32 bits in the register
+        let postincrement = AddressMode::RegisterIndirectPostIncrement {
+            register: ADDRESS_REGISTER,
+        };
+        assert_eq!(
+            postincrement
+                .memory_address(&cpu, OperandSize::Long)
+                .unwrap(),
+            Some(Address(0x1000))
+        );
+        assert_eq!(
+            postincrement
+                .get_value(&mut cpu, OperandSize::Long)
+                .unwrap(),
+            M68kInteger::Long(0xFACEBEEF)
+        );
+        assert_eq!(
+            cpu.registers.get_address_register(ADDRESS_REGISTER),
+            0xFF00_1004
+        );
+        let predecrement = AddressMode::RegisterIndirectPreDecrement {
+            register: ADDRESS_REGISTER,
+        };
+        predecrement
+            .set_value(&mut cpu, M68kInteger::Long(0xCAFEF00D))
+            .unwrap();
+        assert_eq!(cpu.memory.read_long(Address(0x1000)).unwrap(), 0xCAFEF00D);
+        assert_eq!(
+            cpu.registers.get_address_register(ADDRESS_REGISTER),
+            0xFF00_1000
+        );
+
+        // The 68020 uses all 32 bits, which are past the end of memory
+        cpu.model = CpuModel::M68020;
+        let mode = AddressMode::RegisterIndirect {
+            register: ADDRESS_REGISTER,
+        };
+        assert_eq!(mode.effective_address(&cpu).unwrap(), Address(0xFF00_1000));
+        assert!(matches!(
+            mode.get_value(&mut cpu, OperandSize::Long),
+            Err(EmulationError::MemoryOutOfBoundsAccess(_))
+        ));
+    }
+
     #[test]
     fn memory_post_indexed() {
         all_sizes(|mut cpu, size, get_value, set_value| {
@@ -1065,6 +1906,8 @@ mod tests {
                 outer_displacement: OUTER_DISPLACEMENT,
                 address_register: ADDRESS_REGISTER,
                 index_register: Register::Data(DATA_REGISTER),
+                index_size: OperandSize::Long,
+                index_scale: size.into(),
             };
 
             cpu.registers
@@ -1075,16 +1918,16 @@ mod tests {
             let operand_address =
                 ADDRESS + (INDEX * size.size_in_bytes()) + OUTER_DISPLACEMENT as u32;
             cpu.memory
-                .write_long(intermediate_address, ADDRESS)
+                .write_long(Address(intermediate_address), ADDRESS)
                 .unwrap();
 
             // get
-            cpu.memory.write(operand_address, get_value)?;
+            cpu.memory.write(Address(operand_address), get_value)?;
             assert_eq!(mode.get_value(&mut cpu, size)?, get_value);
 
             // set
             mode.set_value(&mut cpu, set_value)?;
-            assert_eq!(cpu.memory.read(operand_address, size)?, set_value);
+            assert_eq!(cpu.memory.read(Address(operand_address), size)?, set_value);
             Ok(())
         });
     }
@@ -1098,6 +1941,8 @@ mod tests {
                 outer_displacement: OUTER_DISPLACEMENT,
                 address_register: ADDRESS_REGISTER,
                 index_register: Register::Data(DATA_REGISTER),
+                index_size: OperandSize::Long,
+                index_scale: size.into(),
             };
 
             cpu.registers
@@ -1106,16 +1951,17 @@ mod tests {
 
             let intermediate_address =
                 initial_address + DISPLACEMENT as u32 + (INDEX * size.size_in_bytes());
-            cpu.memory.write_long(intermediate_address, ADDRESS)?;
+            cpu.memory
+                .write_long(Address(intermediate_address), ADDRESS)?;
             let operand_address = ADDRESS + OUTER_DISPLACEMENT as u32;
 
             // get
-            cpu.memory.write(operand_address, get_value)?;
+            cpu.memory.write(Address(operand_address), get_value)?;
             assert_eq!(mode.get_value(&mut cpu, size)?, get_value);
 
             // set
             mode.set_value(&mut cpu, set_value)?;
-            assert_eq!(cpu.memory.read(operand_address, size)?, set_value);
+            assert_eq!(cpu.memory.read(Address(operand_address), size)?, set_value);
             Ok(())
         });
     }
@@ -1128,15 +1974,19 @@ mod tests {
             };
 
             cpu.registers.set(Register::ProgramCounter, ADDRESS);
-            let address = ADDRESS + DISPLACEMENT as u32;
+            // displacements are relative to the extension word
+            let address = ADDRESS + 2 + DISPLACEMENT as u32;
 
             // get
-            cpu.memory.write(address, get_value)?;
+            cpu.memory.write(Address(address), get_value)?;
             assert_eq!(mode.get_value(&mut cpu, size)?, get_value);
 
-            // set
-            mode.set_value(&mut cpu, set_value)?;
-            assert_eq!(cpu.memory.read(address, size)?, set_value);
+            // set: writing through the program counter isn't allowed
+            assert!(matches!(
+                mode.set_value(&mut cpu, set_value),
+                Err(EmulationError::WriteToReadOnly(_))
+            ));
+            assert_eq!(cpu.memory.read(Address(address), size)?, get_value);
 
             Ok(())
         });
@@ -1148,21 +1998,26 @@ mod tests {
             let mode = AddressMode::ProgramCounterIndirectIndexed {
                 displacement: DISPLACEMENT,
                 index_register: Register::Data(DATA_REGISTER),
+                index_size: OperandSize::Long,
+                index_scale: size.into(),
             };
 
             cpu.registers.set(Register::ProgramCounter, ADDRESS);
             cpu.registers.set_data_register(DATA_REGISTER, INDEX);
 
-            let address = ADDRESS + DISPLACEMENT as u32 + (INDEX * size.size_in_bytes());
-            cpu.memory.write(address, get_value)?;
+            let address = ADDRESS + 2 + DISPLACEMENT as u32 + (INDEX * size.size_in_bytes());
+            cpu.memory.write(Address(address), get_value)?;
 
             // get
-            cpu.memory.write(address, get_value)?;
+            cpu.memory.write(Address(address), get_value)?;
             assert_eq!(mode.get_value(&mut cpu, size)?, get_value);
 
-            // set
-            mode.set_value(&mut cpu, set_value)?;
-            assert_eq!(cpu.memory.read(address, size)?, set_value);
+            // set: writing through the program counter isn't allowed
+            assert!(matches!(
+                mode.set_value(&mut cpu, set_value),
+                Err(EmulationError::WriteToReadOnly(_))
+            ));
+            assert_eq!(cpu.memory.read(Address(address), size)?, get_value);
 
             Ok(())
         });
@@ -1176,25 +2031,30 @@ mod tests {
                 base_displacement: DISPLACEMENT,
                 outer_displacement: OUTER_DISPLACEMENT,
                 index_register: Register::Data(DATA_REGISTER),
+                index_size: OperandSize::Long,
+                index_scale: size.into(),
             };
 
             cpu.registers.set(Register::ProgramCounter, initial_address);
             cpu.registers.set_data_register(DATA_REGISTER, INDEX);
 
-            let intermediate_address = initial_address + DISPLACEMENT as u32;
+            let intermediate_address = initial_address + 2 + DISPLACEMENT as u32;
             cpu.memory
-                .write_long(intermediate_address, ADDRESS)
+                .write_long(Address(intermediate_address), ADDRESS)
                 .unwrap();
             let final_address =
                 ADDRESS + (INDEX * size.size_in_bytes()) + OUTER_DISPLACEMENT as u32;
 
             // get
-            cpu.memory.write(final_address, get_value)?;
+            cpu.memory.write(Address(final_address), get_value)?;
             assert_eq!(mode.get_value(&mut cpu, size)?, get_value);
 
-            // set
-            mode.set_value(&mut cpu, set_value)?;
-            assert_eq!(cpu.memory.read(final_address, size)?, set_value);
+            // set: writing through the program counter isn't allowed
+            assert!(matches!(
+                mode.set_value(&mut cpu, set_value),
+                Err(EmulationError::WriteToReadOnly(_))
+            ));
+            assert_eq!(cpu.memory.read(Address(final_address), size)?, get_value);
 
             Ok(())
         });
@@ -1208,40 +2068,333 @@ mod tests {
                 base_displacement: DISPLACEMENT,
                 outer_displacement: OUTER_DISPLACEMENT,
                 index_register: Register::Data(DATA_REGISTER),
+                index_size: OperandSize::Long,
+                index_scale: size.into(),
             };
 
             cpu.registers.set(Register::ProgramCounter, initial_address);
             cpu.registers.set_data_register(DATA_REGISTER, INDEX);
 
             let intermediate_address =
-                initial_address + DISPLACEMENT as u32 + (INDEX * size.size_in_bytes());
+                initial_address + 2 + DISPLACEMENT as u32 + (INDEX * size.size_in_bytes());
             let final_address = ADDRESS + OUTER_DISPLACEMENT as u32;
-            cpu.memory.write_long(intermediate_address, ADDRESS)?;
+            cpu.memory
+                .write_long(Address(intermediate_address), ADDRESS)?;
 
             // get
-            cpu.memory.write(final_address, get_value)?;
+            cpu.memory.write(Address(final_address), get_value)?;
             assert_eq!(mode.get_value(&mut cpu, size)?, get_value);
 
-            // set
-            mode.set_value(&mut cpu, set_value)?;
-            assert_eq!(cpu.memory.read(final_address, size)?, set_value);
+            // set: writing through the program counter isn't allowed
+            assert!(matches!(
+                mode.set_value(&mut cpu, set_value),
+                Err(EmulationError::WriteToReadOnly(_))
+            ));
+            assert_eq!(cpu.memory.read(Address(final_address), size)?, get_value);
 
             Ok(())
         });
     }
 
+    #[test]
+    fn program_counter_effective_address() {
+        let mut cpu = CPU::<VecBackedMemory>::new(1_024);
+        cpu.registers.set(Register::ProgramCounter, ADDRESS);
+        cpu.registers.set_data_register(DATA_REGISTER, INDEX);
+
+        // relative to the extension word at PC + 2
+        assert_eq!(
+            AddressMode::ProgramCounterIndirectWithDisplacement {
+                displacement: DISPLACEMENT
+            }
+            .effective_address(&cpu)
+            .unwrap(),
+            Address(ADDRESS + 2 + DISPLACEMENT as u32)
+        );
+        assert_eq!(
+            AddressMode::ProgramCounterIndirectWithDisplacement {
+                displacement: -4_i16 as u16
+            }
+            .effective_address(&cpu)
+            .unwrap(),
+            Address(ADDRESS - 2)
+        );
+        assert_eq!(
+            AddressMode::ProgramCounterIndirectIndexed {
+                displacement: DISPLACEMENT,
+                index_register: Register::Data(DATA_REGISTER),
+                index_size: OperandSize::Long,
+                index_scale: IndexScale::One,
+            }
+            .effective_address(&cpu)
+            .unwrap(),
+            Address(ADDRESS + 2 + DISPLACEMENT as u32 + INDEX)
+        );
+    }
+
+    #[test]
+    fn constructors() {
+        assert_eq!(
+            AddressMode::data(D3),
+            AddressMode::RegisterDirect {
+                register: Register::Data(D3)
+            }
+        );
+        assert_eq!(
+            AddressMode::addr(A2),
+            AddressMode::RegisterDirect {
+                register: Register::Address(A2)
+            }
+        );
+        assert_eq!(
+            AddressMode::indirect(A0),
+            AddressMode::RegisterIndirect { register: A0 }
+        );
+        assert_eq!(AddressMode::imm(42), AddressMode::Immediate { value: 42 });
+    }
+
+    #[test]
+    fn constructed_modes_read_and_write() {
+        let mut cpu = CPU::<VecBackedMemory>::new(1_024);
+        cpu.registers.set_address_register(A0, ADDRESS);
+
+        AddressMode::data(D1)
+            .set_value(&mut cpu, M68kInteger::Long(0xDEADBEEF))
+            .unwrap();
+        let value = AddressMode::data(D1)
+            .get_value(&mut cpu, OperandSize::Long)
+            .unwrap();
+        AddressMode::indirect(A0)
+            .set_value(&mut cpu, value)
+            .unwrap();
+        assert_eq!(cpu.memory.read_long(Address(ADDRESS)).unwrap(), 0xDEADBEEF);
+        assert_eq!(
+            AddressMode::addr(A0)
+                .get_value(&mut cpu, OperandSize::Long)
+                .unwrap(),
+            M68kInteger::Long(ADDRESS)
+        );
+        assert_eq!(
+            AddressMode::imm(7)
+                .get_value(&mut cpu, OperandSize::Byte)
+                .unwrap(),
+            M68kInteger::Byte(7)
+        );
+    }
+
+    #[test]
+    fn scaled_word_index() {
+        let mut cpu = cpu_68020();
+        cpu.registers
+            .set_address_register(ADDRESS_REGISTER, ADDRESS);
+        // only the low word is used, and it's sign-extended: -2 * 4 = -8
+        cpu.registers
+            .set_data_register(DATA_REGISTER, 0x1234_FFFEu32);
+        let mode = AddressMode::RegisterIndirectIndexed {
+            displacement: DISPLACEMENT,
+            address_register: ADDRESS_REGISTER,
+            index_register: Register::Data(DATA_REGISTER),
+            index_size: OperandSize::Word,
+            index_scale: IndexScale::Four,
+        };
+        assert_eq!(
+            mode.effective_address(&cpu).unwrap(),
+            Address(ADDRESS + DISPLACEMENT as u32 - 8)
+        );
+    }
+
+    #[test]
+    fn index_scale_factors() {
+        let mut cpu = cpu_68020();
+        cpu.registers
+            .set_address_register(ADDRESS_REGISTER, ADDRESS);
+        cpu.registers.set_data_register(DATA_REGISTER, 3u32);
+        for (scale, factor) in [
+            (IndexScale::One, 1),
+            (IndexScale::Two, 2),
+            (IndexScale::Four, 4),
+            (IndexScale::Eight, 8),
+        ] {
+            let mode = AddressMode::RegisterIndirectIndexed {
+                displacement: DISPLACEMENT,
+                address_register: ADDRESS_REGISTER,
+                index_register: Register::Data(DATA_REGISTER),
+                index_size: OperandSize::Long,
+                index_scale: scale,
+            };
+            let address = Address(ADDRESS + DISPLACEMENT as u32 + 3 * factor);
+            cpu.model = CpuModel::M68020;
+            assert_eq!(
+                mode.effective_address(&cpu).unwrap(),
+                address,
+                "{:?}",
+                scale
+            );
+
+            // The 68000 doesn't scale indexes
+            cpu.model = CpuModel::M68000;
+            assert_eq!(
+                mode.effective_address(&cpu).unwrap(),
+                Address(ADDRESS + DISPLACEMENT as u32 + 3),
+                "{:?}",
+                scale
+            );
+        }
+    }
+
+    #[test]
+    fn indexed_get_and_set_use_same_address() {
+        // The index scale comes from the mode, not from the size of the value being accessed,
+        // so a get and a set through the same mode must always target the same address.
+        let pointer = 0x200;
+        for scale in [
+            IndexScale::One,
+            IndexScale::Two,
+            IndexScale::Four,
+            IndexScale::Eight,
+        ] {
+            for mode in [
+                AddressMode::RegisterIndirectIndexed {
+                    displacement: DISPLACEMENT,
+                    address_register: ADDRESS_REGISTER,
+                    index_register: Register::Data(DATA_REGISTER),
+                    index_size: OperandSize::Long,
+                    index_scale: scale,
+                },
+                AddressMode::MemoryPostIndexed {
+                    base_displacement: DISPLACEMENT,
+                    outer_displacement: OUTER_DISPLACEMENT,
+                    address_register: ADDRESS_REGISTER,
+                    index_register: Register::Data(DATA_REGISTER),
+                    index_size: OperandSize::Long,
+                    index_scale: scale,
+                },
+                AddressMode::MemoryPreIndexed {
+                    base_displacement: DISPLACEMENT,
+                    outer_displacement: OUTER_DISPLACEMENT,
+                    address_register: ADDRESS_REGISTER,
+                    index_register: Register::Data(DATA_REGISTER),
+                    index_size: OperandSize::Long,
+                    index_scale: scale,
+                },
+            ] {
+                for value in [
+                    M68kInteger::Byte(0xAB),
+                    M68kInteger::Word(0xDEAD),
+                    M68kInteger::Long(0xFACEBEEF),
+                ] {
+                    let mut cpu = cpu_68020();
+                    cpu.registers
+                        .set_address_register(ADDRESS_REGISTER, ADDRESS);
+                    cpu.registers.set_data_register(DATA_REGISTER, INDEX);
+                    let pointer_address = match mode {
+                        AddressMode::MemoryPreIndexed { .. } => {
+                            ADDRESS + DISPLACEMENT as u32 + INDEX * scale_factor(&cpu, scale)
+                        }
+                        _ => ADDRESS + DISPLACEMENT as u32,
+                    };
+                    cpu.memory
+                        .write(Address(pointer_address), M68kInteger::Long(pointer))
+                        .unwrap();
+
+                    let address = mode.memory_address(&cpu, value.size()).unwrap().unwrap();
+                    mode.set_value(&mut cpu, value).unwrap();
+                    assert_eq!(
+                        cpu.memory.read(address, value.size()).unwrap(),
+                        value,
+                        "{:?} wrote {:?} somewhere other than {:?}",
+                        mode,
+                        value,
+                        address
+                    );
+                    assert_eq!(
+                        mode.get_value(&mut cpu, value.size()).unwrap(),
+                        value,
+                        "{:?} read {:?} from a different address than it wrote to",
+                        mode,
+                        value.size()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn decoded_index_scale() {
+        for (shift, scale) in [
+            (0, IndexScale::One),
+            (1, IndexScale::Two),
+            (2, IndexScale::Four),
+            (3, IndexScale::Eight),
+        ] {
+            assert_eq!(
+                AddressMode::from_m68kdecode_with_register_indexing(
+                    MemoryIndirection::NoIndirection,
+                    Register::Address(ADDRESS_REGISTER),
+                    Register::Data(DATA_REGISTER),
+                    OperandSize::Long,
+                    shift,
+                    DISPLACEMENT,
+                    0,
+                ),
+                AddressMode::RegisterIndirectIndexed {
+                    displacement: DISPLACEMENT,
+                    address_register: ADDRESS_REGISTER,
+                    index_register: Register::Data(DATA_REGISTER),
+                    index_size: OperandSize::Long,
+                    index_scale: scale,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn registers_read_and_written() {
+        let postincrement = AddressMode::RegisterIndirectPostIncrement { register: A0 };
+        for is_destination in [false, true] {
+            assert_eq!(postincrement.registers_read(), vec![Register::Address(A0)]);
+            assert_eq!(
+                postincrement.registers_written(is_destination),
+                vec![Register::Address(A0)]
+            );
+        }
+
+        let d0 = AddressMode::RegisterDirect {
+            register: Register::Data(D0),
+        };
+        assert_eq!(d0.registers_read(), vec![Register::Data(D0)]);
+        assert_eq!(d0.registers_written(false), vec![]);
+        assert_eq!(d0.registers_written(true), vec![Register::Data(D0)]);
+
+        let indexed = AddressMode::ProgramCounterIndirectIndexed {
+            displacement: DISPLACEMENT,
+            index_register: Register::Address(A3),
+            index_size: OperandSize::Word,
+            index_scale: IndexScale::Two,
+        };
+        assert_eq!(
+            indexed.registers_read(),
+            vec![Register::ProgramCounter, Register::Address(A3)]
+        );
+        assert_eq!(indexed.registers_written(true), vec![]);
+
+        let absolute = AddressMode::Absolute { address: ADDRESS };
+        assert_eq!(absolute.registers_read(), vec![]);
+        assert_eq!(absolute.registers_written(true), vec![]);
+    }
+
     #[test]
     fn absolute() {
         all_sizes(|mut cpu, size, get_value, set_value| {
             let mode = AddressMode::Absolute { address: ADDRESS };
 
             // get
-            cpu.memory.write(ADDRESS, get_value)?;
+            cpu.memory.write(Address(ADDRESS), get_value)?;
             assert_eq!(mode.get_value(&mut cpu, size)?, get_value);
 
             // set
             mode.set_value(&mut cpu, set_value)?;
-            assert_eq!(cpu.memory.read(ADDRESS, size)?, set_value);
+            assert_eq!(cpu.memory.read(Address(ADDRESS), size)?, set_value);
 
             Ok(())
         });
@@ -1329,4 +2482,90 @@ mod tests {
             assert_eq!(AddressMode::from_m68kdecode_register_bitmask(mask), regs);
         }
     }
+
+    #[test]
+    fn display() {
+        for (mode, expected) in [
+            (AddressMode::data(D3), "d3"),
+            (
+                AddressMode::RegisterDirectList {
+                    registers: vec![Register::Data(D0), Register::Address(A6)],
+                },
+                "d0/a6",
+            ),
+            (AddressMode::indirect(A1), "(a1)"),
+            (
+                AddressMode::RegisterIndirectPostIncrement { register: A1 },
+                "(a1)+",
+            ),
+            (
+                AddressMode::RegisterIndirectPreDecrement { register: A7 },
+                "-(a7)",
+            ),
+            (
+                AddressMode::RegisterIndirectWithDisplacement {
+                    register: A2,
+                    displacement: -8i16 as u16,
+                },
+                "(-8, a2)",
+            ),
+            (
+                AddressMode::RegisterIndirectIndexed {
+                    displacement: 4,
+                    address_register: A0,
+                    index_register: Register::Data(D1),
+                    index_size: OperandSize::Long,
+                    index_scale: IndexScale::Four,
+                },
+                "(4, a0, d1.l*4)",
+            ),
+            (
+                AddressMode::MemoryPostIndexed {
+                    base_displacement: 4,
+                    outer_displacement: 2,
+                    address_register: A0,
+                    index_register: Register::Data(D1),
+                    index_size: OperandSize::Word,
+                    index_scale: IndexScale::One,
+                },
+                "([4, a0], d1.w, 2)",
+            ),
+            (
+                AddressMode::ProgramCounterMemoryIndirectPreIndexed {
+                    base_displacement: 4,
+                    outer_displacement: 0,
+                    index_register: Register::Address(A3),
+                    index_size: OperandSize::Word,
+                    index_scale: IndexScale::Two,
+                },
+                "([4, pc, a3.w*2], 0)",
+            ),
+            (
+                AddressMode::ProgramCounterIndirectWithDisplacement { displacement: 18 },
+                "(18, pc)",
+            ),
+            (AddressMode::Absolute { address: 0x1234 }, "($1234)"),
+            (AddressMode::imm(0xFF), "#$FF"),
+        ] {
+            assert_eq!(mode.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn to_assembly_uses_symbols() {
+        let mut symbols = SymbolTable::new();
+        symbols.insert(0x1234, "buffer");
+        symbols.insert(0x114, "table");
+
+        let absolute = AddressMode::Absolute { address: 0x1234 };
+        assert_eq!(absolute.to_assembly(0x100, &symbols), "(buffer)");
+        // The displacement is relative to the extension word after the opcode at 0x100
+        let pc_relative = AddressMode::ProgramCounterIndirectWithDisplacement { displacement: 18 };
+        assert_eq!(pc_relative.to_assembly(0x100, &symbols), "(table, pc)");
+        assert_eq!(pc_relative.to_assembly(0x102, &symbols), "(18, pc)");
+        assert_eq!(
+            AddressMode::Absolute { address: 0x1238 }.to_assembly(0x100, &symbols),
+            "($1238)"
+        );
+    }
 }
@@ -0,0 +1,159 @@
+//! Golden-trace tests: small programs are run one instruction at a time,
+//! and the registers after every instruction are compared against a checked-in trace of what a 68000 should do.
+//!
+//! Each program is an assembly file in `golden/` with a matching `.trace` file.
+//! The traces here were worked out by hand from the PRM, not captured from a reference core,
+//! so they're only as good as that reading of it. Traces captured from a reference emulator such as Musashi
+//! should replace them; they can be dropped in as-is, as long as they use the same format.
+//! A trace has one line per state, with 18 hexadecimal columns: `pc sr d0-d7 a0-a7`.
+//! The first line is the state before the program runs, and `#` starts a comment.
+//! Programs end by jumping to themselves, which [`CPU::run_collecting`] treats as halting.
+
+use super::{registers::RegisterFile, CPU};
+use crate::{
+    parsers::{assembly::AssemblyInterpreter, encoder::encode},
+    ram::{Memory, VecBackedMemory},
+    Address,
+};
+
+/// Where programs are loaded
+const ORIGIN: u32 = 0x1000;
+
+/// Parses a trace into a list of register states
+fn parse_trace(trace: &str) -> Vec<RegisterFile> {
+    trace
+        .lines()
+        .map(|line| line.split('#').next().unwrap().trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let columns = line
+                .split_whitespace()
+                .map(|column| u32::from_str_radix(column, 16).unwrap())
+                .collect::<Vec<u32>>();
+            assert_eq!(columns.len(), 18, "wrong number of columns in '{}'", line);
+
+            let mut state = RegisterFile {
+                pc: columns[0],
+                status: columns[1] as u16,
+                ..RegisterFile::default()
+            };
+            state.data.copy_from_slice(&columns[2..10]);
+            state.address.copy_from_slice(&columns[10..18]);
+            state
+        })
+        .collect()
+}
+
+/// Lists each register that differs between two states
+fn describe_differences(expected: &RegisterFile, actual: &RegisterFile) -> String {
+    let mut differences = vec![];
+    let mut compare = |name: String, expected: u32, actual: u32| {
+        if expected != actual {
+            differences.push(format!(
+                "{}: expected {:#X}, got {:#X}",
+                name, expected, actual
+            ));
+        }
+    };
+
+    compare("PC".to_string(), expected.pc, actual.pc);
+    compare(
+        "SR".to_string(),
+        expected.status as u32,
+        actual.status as u32,
+    );
+    for i in 0..8 {
+        compare(format!("D{}", i), expected.data[i], actual.data[i]);
+    }
+    for i in 0..8 {
+        compare(format!("A{}", i), expected.address[i], actual.address[i]);
+    }
+    differences.join("\n")
+}
+
+/// Assembles and runs a program, panicking at the first instruction after which the registers don't match the trace.
+///
+/// `setup` can prepare memory before the program runs. Returns the CPU so memory can be checked afterwards.
+fn run_golden(
+    name: &str,
+    source: &str,
+    trace: &str,
+    setup: impl FnOnce(&mut CPU<VecBackedMemory>),
+) -> CPU<VecBackedMemory> {
+    let mut cpu = CPU::<VecBackedMemory>::new(0x2000);
+    for assembled in AssemblyInterpreter::new().assemble(source, ORIGIN).unwrap() {
+        let binary = encode(&assembled.instruction, assembled.size).unwrap();
+        cpu.load_binary(assembled.address, binary).unwrap();
+    }
+
+    let mut expected = parse_trace(trace);
+    let initial = expected.remove(0);
+    cpu.registers.restore(&initial);
+    setup(&mut cpu);
+
+    // Run one extra instruction, so running past the end of the trace is caught
+    let actual = cpu.run_collecting(expected.len() + 1);
+    let mut previous = initial;
+    for (step, (expected, actual)) in expected.iter().zip(&actual).enumerate() {
+        if expected != actual {
+            panic!(
+                "{}: diverged at step {} (the instruction at {:#X}):\n{}",
+                name,
+                step + 1,
+                previous.pc,
+                describe_differences(expected, actual)
+            );
+        }
+        previous = *actual;
+    }
+    assert_eq!(
+        actual.len(),
+        expected.len(),
+        "{}: ran {} instructions, but the trace has {}",
+        name,
+        actual.len(),
+        expected.len()
+    );
+    cpu
+}
+
+#[test]
+fn add() {
+    run_golden(
+        "add",
+        include_str!("golden/add.s"),
+        include_str!("golden/add.trace"),
+        |_| {},
+    );
+}
+
+#[test]
+fn memory_copy() {
+    let cpu = run_golden(
+        "memory_copy",
+        include_str!("golden/memory_copy.s"),
+        include_str!("golden/memory_copy.trace"),
+        |cpu| {
+            cpu.memory
+                .write_bytes(Address(0x1800), b"68k!".to_vec())
+                .unwrap()
+        },
+    );
+    assert_eq!(cpu.memory.read_bytes(Address(0x1900), 4).unwrap(), b"68k!");
+}
+
+#[test]
+fn divergence_is_reported() {
+    let trace = include_str!("golden/add.trace").replacen("0000000A", "0000000B", 1);
+    let result = std::panic::catch_unwind(|| {
+        run_golden("add", include_str!("golden/add.s"), &trace, |_| {})
+    });
+    let message = match result {
+        Ok(_) => panic!("the trace should have diverged"),
+        Err(panic) => *panic.downcast::<String>().unwrap(),
+    };
+    assert_eq!(
+        message,
+        "add: diverged at step 3 (the instruction at 0x1000):\nD0: expected 0xB, got 0xA"
+    );
+}
@@ -0,0 +1,8 @@
+; Adds d1 to d0 in a DBRA loop, then the edge cases for each flag
+loop:   add.l d1, d0
+        dbra d6, loop   ; d6 starts at 2, so the loop runs three times
+        add.b d2, d3    ; $7F + 1 overflows
+        add.b d2, d4    ; $FF + 1 carries and is zero
+        sub.w d2, d5    ; 0 - 1 borrows and is negative
+        add.l d1, d0    ; clears every flag, including X
+halt:   jmp halt
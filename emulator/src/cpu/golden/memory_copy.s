@@ -0,0 +1,6 @@
+; Copies 4 bytes from (a0) to (a1), one move at a time
+        move.b (a0)+, (a1)+
+        move.b (a0)+, (a1)+
+        move.b (a0)+, (a1)+
+        move.b (a0)+, (a1)+
+halt:   jmp halt
@@ -86,16 +86,538 @@
 //! TRAPV (Trap on Overflow),
 //! TST (Test Operand),
 //! UNLK (Unlink)
+//!
+//! A few instructions from later models are here too; they can only be executed if the CPU is set to that model.
+//! See [`Instruction::required_model`].
+
+use std::{fmt::Display, str::FromStr};
 
 use crate::{
-    cpu::{addressing::AddressMode, registers::Register, CPU},
+    cpu::{
+        addressing::{bus_address, get_increment, AddressMode},
+        registers::{DataRegister, FlagMask, Register},
+        symbols::SymbolTable,
+        CpuModel, CPU,
+    },
+    parsers::ParseError,
     ram::Memory,
-    EmulationError, OperandSize,
+    Address, EmulationError, Flags, M68kInteger, OperandSize,
 };
 
 use super::registers::AddressRegister;
 
-#[derive(Debug, PartialEq)]
+/// Writes the flags from an addition or subtraction into the CCR, with the extend flag set like the carry flag.
+///
+/// Arithmetic on address registers (ADDA/SUBA) doesn't affect the CCR.
+fn set_arithmetic_flags(cpu: &mut CPU<impl Memory>, dest: &AddressMode, flags: Flags) {
+    if let AddressMode::RegisterDirect {
+        register: Register::Address(_),
+    } = dest
+    {
+        return;
+    }
+    cpu.registers.set_flags(flags);
+    cpu.registers.set_ccr_flag(FlagMask::EXTEND, flags.carry);
+}
+
+/// Writes the flags from ADDX, SUBX, or NEGX, which work on one part of a multiple-precision number at a time.
+///
+/// The zero flag is "sticky": a nonzero result clears it, but a zero result leaves it as it was,
+/// so after the last part it's only set if every part was zero.
+fn set_extended_flags(cpu: &mut CPU<impl Memory>, dest: &AddressMode, flags: Flags) {
+    let flags = Flags {
+        zero: flags.zero && cpu.registers.get_flags().zero,
+        ..flags
+    };
+    set_arithmetic_flags(cpu, dest, flags);
+}
+
+/// Works out whether `dest + src` (or `dest - src`, if `subtract` is set) overflowed to give `result`,
+/// from the signs of the operands and the result.
+///
+/// ADDX, SUBX, and NEGX also add or subtract the extend bit, which can overflow separately from the operands;
+/// only the signs of the original operands and the final result matter, not either step on its own.
+fn extended_overflow(
+    subtract: bool,
+    src: M68kInteger,
+    dest: M68kInteger,
+    result: M68kInteger,
+) -> bool {
+    let (src, dest, result) = (src.is_negative(), dest.is_negative(), result.is_negative());
+    if subtract {
+        src != dest && result != dest
+    } else {
+        src == dest && result != dest
+    }
+}
+
+/// Runs ADDX or SUBX: `dest` and `src` are added or subtracted, and then the extend flag
+fn add_or_subtract_with_extend(
+    cpu: &mut CPU<impl Memory>,
+    src: &AddressMode,
+    dest: &AddressMode,
+    size: OperandSize,
+    subtract: bool,
+) -> Result<(), EmulationError> {
+    let operation = if subtract {
+        M68kInteger::sub_with_flags
+    } else {
+        M68kInteger::add_with_flags
+    };
+    // In the memory form, both address registers are decremented before anything is added
+    let src_val = src.get_value(cpu, size)?;
+    let (dest_val, dest) = read_for_write_back(cpu, dest, size)?;
+    let extend = M68kInteger::from_u32(cpu.registers.get_extend_flag() as u32, size);
+    let (partial, operand_flags) = operation(&dest_val, src_val);
+    let (val, extend_flags) = operation(&partial, extend);
+    let flags = Flags {
+        // At most one of the two steps can carry
+        carry: operand_flags.carry || extend_flags.carry,
+        overflow: extended_overflow(subtract, src_val, dest_val, val),
+        zero: val.is_zero(),
+        negative: val.is_negative(),
+    };
+
+    dest.set_value(cpu, val)?;
+    set_extended_flags(cpu, &dest, flags);
+    Ok(())
+}
+
+/// Reads `dest` for an instruction that writes its result back there, returning the value and where to write the result.
+///
+/// `(An)+` and `-(An)` move An when they're read, so the result is written to the address that was read
+/// without moving An again.
+fn read_for_write_back(
+    cpu: &mut CPU<impl Memory>,
+    dest: &AddressMode,
+    size: OperandSize,
+) -> Result<(M68kInteger, AddressMode), EmulationError> {
+    let target = match *dest {
+        AddressMode::RegisterIndirectPostIncrement { register } => {
+            AddressMode::RegisterIndirectWithDisplacement {
+                register,
+                displacement: (get_increment(register, size) as u16).wrapping_neg(),
+            }
+        }
+        AddressMode::RegisterIndirectPreDecrement { register } => {
+            AddressMode::RegisterIndirect { register }
+        }
+        ref dest => dest.clone(),
+    };
+    Ok((dest.get_value(cpu, size)?, target))
+}
+
+/// Adds two packed binary-coded decimal bytes (where each nibble is a decimal digit, so 0x42 is 42)
+/// and the extend bit, like ABCD. Returns the result and whether there was a decimal carry out.
+fn add_decimal(src: u8, dest: u8, extend: bool) -> (u8, bool) {
+    decimal_operation(src, dest, extend, u16::wrapping_add)
+}
+
+/// Subtracts `src` and the extend bit from `dest`, both packed BCD, like SBCD.
+/// Returns the result and whether there was a decimal borrow.
+fn subtract_decimal(src: u8, dest: u8, extend: bool) -> (u8, bool) {
+    decimal_operation(src, dest, extend, u16::wrapping_sub)
+}
+
+/// Adds or subtracts packed BCD bytes, depending on `operation`.
+///
+/// The bytes are combined in binary, then adjusted one digit at a time:
+///
+/// 1. The low digits and the extend bit are combined. If that's out of the range 0-9, it's adjusted by 6,
+///    which skips over the hex digits A-F and carries (or borrows) 1 into the high digit.
+/// 2. The high digits are combined with that. If the total is out of the range 0-0x99, the high digit
+///    is adjusted by 6 the same way (by 0x60), and there's a decimal carry or borrow.
+///
+/// A borrow wraps around, so it's out of range too. This is how the hardware behaves even when the inputs aren't valid BCD.
+fn decimal_operation(
+    src: u8,
+    dest: u8,
+    extend: bool,
+    operation: fn(u16, u16) -> u16,
+) -> (u8, bool) {
+    let mut result = operation(
+        operation((dest & 0x0F) as u16, (src & 0x0F) as u16),
+        extend as u16,
+    );
+    if result > 9 {
+        result = operation(result, 6);
+    }
+    result = operation(
+        result.wrapping_add((dest & 0xF0) as u16),
+        (src & 0xF0) as u16,
+    );
+
+    let carry = result > 0x99;
+    if carry {
+        result = operation(result, 0x60);
+    }
+    (result as u8, carry)
+}
+
+/// Sets the flags after a decimal operation: C and X to the carry, and Z cleared if the result wasn't zero.
+///
+/// Like ADDX, Z is only ever cleared, so it's set after a multiple-byte operation only if every byte was zero.
+/// N and V are undefined, so they're left alone.
+fn set_decimal_flags(cpu: &mut CPU<impl Memory>, result: u8, carry: bool) {
+    cpu.registers.set_ccr_flag(FlagMask::CARRY, carry);
+    cpu.registers.set_ccr_flag(FlagMask::EXTEND, carry);
+    if result != 0 {
+        cpu.registers.set_ccr_flag(FlagMask::ZERO, false);
+    }
+}
+
+/// Gets the flags for a logical operation (or a test) that produced `value`: the carry and overflow flags are cleared
+fn logical_flags(value: M68kInteger) -> Flags {
+    Flags {
+        zero: value.is_zero(),
+        negative: value.is_negative(),
+        ..Flags::default()
+    }
+}
+
+/// Gets the stack pointer (A7), checking that it can be used to push or pop a value of the given size.
+///
+/// Words and longs can't be accessed at odd addresses, so an odd stack pointer is only usable for bytes.
+fn stack_pointer(cpu: &CPU<impl Memory>, size: OperandSize) -> Result<u32, EmulationError> {
+    let stack_pointer = cpu.registers.get_address_register(AddressRegister::A7);
+    if stack_pointer & 1 != 0 && size != OperandSize::Byte {
+        return Err(EmulationError::OddStackPointer(Address(stack_pointer)));
+    }
+    Ok(stack_pointer)
+}
+
+/// Pushes a value onto the stack (the one A7 points to), like `move value, -(a7)`
+///
+/// A byte still takes up a whole word on the stack; it's written to the upper (even) byte of that word.
+fn push(cpu: &mut CPU<impl Memory>, value: M68kInteger) -> Result<(), EmulationError> {
+    let stack_pointer = stack_pointer(cpu, value.size())?
+        .wrapping_sub(get_increment(AddressRegister::A7, value.size()));
+    cpu.memory.write(bus_address(cpu, stack_pointer), value)?;
+    cpu.registers
+        .set_address_register(AddressRegister::A7, stack_pointer);
+    Ok(())
+}
+
+/// Pops a value off the stack, like `move (a7)+, value`
+fn pop(cpu: &mut CPU<impl Memory>, size: OperandSize) -> Result<M68kInteger, EmulationError> {
+    let stack_pointer = stack_pointer(cpu, size)?;
+    let value = cpu.memory.read(bus_address(cpu, stack_pointer), size)?;
+    cpu.registers.set_address_register(
+        AddressRegister::A7,
+        stack_pointer.wrapping_add(get_increment(AddressRegister::A7, size)),
+    );
+    Ok(value)
+}
+
+/// Gets the number of bits a shift instruction shifts by.
+///
+/// Immediate counts are used as-is (the assembler only allows 1 to 8),
+/// while counts in a data register are taken modulo 64.
+fn shift_count(cpu: &mut CPU<impl Memory>, amount: &AddressMode) -> Result<u32, EmulationError> {
+    match amount {
+        AddressMode::Immediate { value } => Ok(*value),
+        _ => Ok(u32::from(amount.get_value(cpu, OperandSize::Long)?) % 64),
+    }
+}
+
+/// Sets the flags after a shift by `count` bits, where `carry` is the last bit shifted out
+fn set_shift_flags(
+    cpu: &mut CPU<impl Memory>,
+    count: u32,
+    result: M68kInteger,
+    carry: bool,
+    overflow: bool,
+) {
+    cpu.registers.set_flags(Flags {
+        carry,
+        overflow,
+        zero: result.is_zero(),
+        negative: result.is_negative(),
+    });
+    // A shift by zero clears the carry flag, but leaves the extend flag alone
+    if count != 0 {
+        cpu.registers.set_extend_flag(carry);
+    }
+}
+
+/// The offset or width of a bit field, which is either part of the instruction or held in a data register
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BitFieldParameter {
+    Immediate(u8),
+    Register(DataRegister),
+}
+
+impl From<m68kdecode::BitfieldData> for BitFieldParameter {
+    fn from(data: m68kdecode::BitfieldData) -> Self {
+        match data {
+            m68kdecode::BitfieldData::STATIC(value) => BitFieldParameter::Immediate(value),
+            m68kdecode::BitfieldData::DYNAMIC(register) => {
+                BitFieldParameter::Register(match register & 7 {
+                    0 => DataRegister::D0,
+                    1 => DataRegister::D1,
+                    2 => DataRegister::D2,
+                    3 => DataRegister::D3,
+                    4 => DataRegister::D4,
+                    5 => DataRegister::D5,
+                    6 => DataRegister::D6,
+                    _ => DataRegister::D7,
+                })
+            }
+        }
+    }
+}
+
+impl Display for BitFieldParameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BitFieldParameter::Immediate(value) => write!(f, "{}", value),
+            BitFieldParameter::Register(register) => write!(f, "{}", Register::Data(*register)),
+        }
+    }
+}
+
+impl BitFieldParameter {
+    fn get_value(&self, cpu: &CPU<impl Memory>) -> u32 {
+        match *self {
+            BitFieldParameter::Immediate(value) => value as u32,
+            BitFieldParameter::Register(register) => cpu.registers.get_data_register(register),
+        }
+    }
+}
+
+/// A condition tested by Bcc, DBcc, and Scc, numbered by its encoding in the opcode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    True,
+    False,
+    Higher,
+    LowerOrSame,
+    CarryClear,
+    CarrySet,
+    NotEqual,
+    Equal,
+    OverflowClear,
+    OverflowSet,
+    Plus,
+    Minus,
+    GreaterOrEqual,
+    LessThan,
+    GreaterThan,
+    LessOrEqual,
+}
+
+impl Condition {
+    /// Every condition, in encoding order
+    pub const ALL: [Condition; 16] = [
+        Condition::True,
+        Condition::False,
+        Condition::Higher,
+        Condition::LowerOrSame,
+        Condition::CarryClear,
+        Condition::CarrySet,
+        Condition::NotEqual,
+        Condition::Equal,
+        Condition::OverflowClear,
+        Condition::OverflowSet,
+        Condition::Plus,
+        Condition::Minus,
+        Condition::GreaterOrEqual,
+        Condition::LessThan,
+        Condition::GreaterThan,
+        Condition::LessOrEqual,
+    ];
+
+    /// Tests the condition against the CCR's flags
+    pub fn evaluate(&self, flags: Flags) -> bool {
+        let Flags {
+            carry,
+            overflow,
+            zero,
+            negative,
+        } = flags;
+        match self {
+            Condition::True => true,
+            Condition::False => false,
+            Condition::Higher => !carry && !zero,
+            Condition::LowerOrSame => carry || zero,
+            Condition::CarryClear => !carry,
+            Condition::CarrySet => carry,
+            Condition::NotEqual => !zero,
+            Condition::Equal => zero,
+            Condition::OverflowClear => !overflow,
+            Condition::OverflowSet => overflow,
+            Condition::Plus => !negative,
+            Condition::Minus => negative,
+            Condition::GreaterOrEqual => negative == overflow,
+            Condition::LessThan => negative != overflow,
+            Condition::GreaterThan => !zero && negative == overflow,
+            Condition::LessOrEqual => zero || negative != overflow,
+        }
+    }
+}
+
+impl Display for Condition {
+    /// Formats a condition as the two-letter code that follows `b`, `db`, or `s` in a mnemonic
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let code = match self {
+            Condition::True => "t",
+            Condition::False => "f",
+            Condition::Higher => "hi",
+            Condition::LowerOrSame => "ls",
+            Condition::CarryClear => "cc",
+            Condition::CarrySet => "cs",
+            Condition::NotEqual => "ne",
+            Condition::Equal => "eq",
+            Condition::OverflowClear => "vc",
+            Condition::OverflowSet => "vs",
+            Condition::Plus => "pl",
+            Condition::Minus => "mi",
+            Condition::GreaterOrEqual => "ge",
+            Condition::LessThan => "lt",
+            Condition::GreaterThan => "gt",
+            Condition::LessOrEqual => "le",
+        };
+        write!(f, "{}", code)
+    }
+}
+
+impl FromStr for Condition {
+    type Err = ParseError;
+
+    /// Parses a (lowercase) condition code, such as `eq` or `t`.
+    ///
+    /// `hs` and `lo` are accepted as the other assemblers' names for `cc` and `cs`.
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        match code {
+            "t" => Ok(Condition::True),
+            "f" => Ok(Condition::False),
+            "hi" => Ok(Condition::Higher),
+            "ls" => Ok(Condition::LowerOrSame),
+            "cc" | "hs" => Ok(Condition::CarryClear),
+            "cs" | "lo" => Ok(Condition::CarrySet),
+            "ne" => Ok(Condition::NotEqual),
+            "eq" => Ok(Condition::Equal),
+            "vc" => Ok(Condition::OverflowClear),
+            "vs" => Ok(Condition::OverflowSet),
+            "pl" => Ok(Condition::Plus),
+            "mi" => Ok(Condition::Minus),
+            "ge" => Ok(Condition::GreaterOrEqual),
+            "lt" => Ok(Condition::LessThan),
+            "gt" => Ok(Condition::GreaterThan),
+            "le" => Ok(Condition::LessOrEqual),
+            _ => Err(ParseError::UnknownCondition(code.to_string())),
+        }
+    }
+}
+
+/// Gets the offset (in bits, which may be negative for fields in memory) and width of a bit field.
+///
+/// Widths are taken modulo 32, with 0 meaning 32.
+fn bit_field_bounds(
+    offset: BitFieldParameter,
+    width: BitFieldParameter,
+    cpu: &CPU<impl Memory>,
+) -> (i32, u32) {
+    let width = match width.get_value(cpu) % 32 {
+        0 => 32,
+        width => width,
+    };
+    (offset.get_value(cpu) as i32, width)
+}
+
+/// Locates a bit field in memory, returning the address of its first byte,
+/// its offset from the most significant bit of that byte, and how many bytes it spans.
+fn bit_field_bytes(base_address: Address, offset: i32, width: u32) -> (Address, u32, u32) {
+    let address = base_address + offset.div_euclid(8) as u32;
+    let bit_offset = offset.rem_euclid(8) as u32;
+    (address, bit_offset, (bit_offset + width).div_ceil(8))
+}
+
+/// Sets the flags after a bit field instruction from the `width`-bit field it read or wrote:
+/// N is its most significant bit, Z is set if it's all zeroes, and V and C are cleared
+fn set_bit_field_flags(cpu: &mut CPU<impl Memory>, field: u32, width: u32) {
+    cpu.registers.set_flags(Flags {
+        negative: (field >> (width - 1)) & 1 != 0,
+        zero: field == 0,
+        overflow: false,
+        carry: false,
+    });
+}
+
+/// Reads a bit field, which is counted from the most significant bit of `base`.
+///
+/// If `base` is a data register, the field wraps around from bit 0 to bit 31.
+fn read_bit_field(
+    cpu: &CPU<impl Memory>,
+    base: &AddressMode,
+    offset: i32,
+    width: u32,
+) -> Result<u32, EmulationError> {
+    match base {
+        AddressMode::RegisterDirect {
+            register: Register::Data(register),
+        } => {
+            let offset = offset.rem_euclid(32) as u32;
+            Ok(cpu
+                .registers
+                .get_data_register(*register)
+                .rotate_left(offset)
+                >> (32 - width))
+        }
+        _ => {
+            let (address, bit_offset, len) =
+                bit_field_bytes(base.effective_address(cpu)?, offset, width);
+            let data = cpu
+                .memory
+                .read_bytes(address, len)?
+                .iter()
+                .fold(0u64, |data, byte| (data << 8) | *byte as u64);
+            let shift = 8 * len - bit_offset - width;
+            Ok(((data >> shift) & ((1 << width) - 1)) as u32)
+        }
+    }
+}
+
+/// Replaces a bit field with the low `width` bits of `value`, leaving all other bits untouched
+fn write_bit_field(
+    cpu: &mut CPU<impl Memory>,
+    base: &AddressMode,
+    offset: i32,
+    width: u32,
+    value: u32,
+) -> Result<(), EmulationError> {
+    match base {
+        AddressMode::RegisterDirect {
+            register: Register::Data(register),
+        } => {
+            let offset = offset.rem_euclid(32) as u32;
+            let mask = (u32::MAX << (32 - width)).rotate_right(offset);
+            let field = (value << (32 - width)).rotate_right(offset);
+            let old = cpu.registers.get_data_register(*register);
+            cpu.registers
+                .set_data_register(*register, (old & !mask) | (field & mask));
+            Ok(())
+        }
+        _ => {
+            let (address, bit_offset, len) =
+                bit_field_bytes(base.effective_address(cpu)?, offset, width);
+            let data = cpu
+                .memory
+                .read_bytes(address, len)?
+                .iter()
+                .fold(0u64, |data, byte| (data << 8) | *byte as u64);
+            let shift = 8 * len - bit_offset - width;
+            let mask = ((1u64 << width) - 1) << shift;
+            let data = (data & !mask) | (((value as u64) << shift) & mask);
+            cpu.memory.write_bytes(
+                address,
+                (0..len).rev().map(|i| (data >> (8 * i)) as u8).collect(),
+            )
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Instruction {
     Add {
         src: AddressMode,
@@ -121,133 +643,1377 @@ pub enum Instruction {
         src: AddressMode,
         dest: AddressMode,
     },
+    /// MOVEQ: `src` is an 8-bit immediate, which is sign-extended to a long
+    MoveQuick {
+        src: AddressMode,
+        dest: AddressMode,
+    },
+    /// MULU: multiplies the low words of `src` and `dest` (a data register), treating both as unsigned,
+    /// and puts the long product in `dest`.
+    ///
+    /// At [`OperandSize::Long`] it's the 68020's MULU.L, which keeps the low long of a 32x32-bit product
+    /// and sets the overflow flag if the product didn't fit.
     MultiplyUnsigned {
         src: AddressMode,
         dest: AddressMode,
     },
+    /// DIVU: divides the long in `dest` (a data register) by the word `src`, treating both as unsigned.
+    ///
+    /// The quotient goes in the low word of `dest` and the remainder in the high word.
+    /// If the quotient doesn't fit in a word, the overflow flag is set and `dest` is left alone.
+    DivideUnsigned {
+        src: AddressMode,
+        dest: AddressMode,
+    },
+    /// DIVS: like DIVU, but signed; the remainder has the same sign as the dividend
+    DivideSigned {
+        src: AddressMode,
+        dest: AddressMode,
+    },
     AddBCD {
         src: AddressMode,
         dest: AddressMode,
     },
+    /// SBCD: subtracts `src` and the extend flag from `dest`, treating both bytes as binary-coded decimal.
+    ///
+    /// Like ABCD, the operands are either both data registers or both predecremented address registers.
+    SubtractBCD {
+        src: AddressMode,
+        dest: AddressMode,
+    },
+    /// NBCD: subtracts `dest` and the extend flag from zero, treating it as binary-coded decimal
+    NegateBCD {
+        dest: AddressMode,
+    },
+    /// ADDX: adds `src` and the extend flag to `dest`, for multiple-precision arithmetic.
+    ///
+    /// The operands are either both data registers or both predecremented address registers (`addx -(a0), -(a1)`),
+    /// in which case the source is decremented and read before the destination.
+    AddWithExtend {
+        src: AddressMode,
+        dest: AddressMode,
+    },
+    /// SUBX: subtracts `src` and the extend flag from `dest`; takes the same operands as ADDX
+    SubtractWithExtend {
+        src: AddressMode,
+        dest: AddressMode,
+    },
+    /// ROL: rotates `to_rotate` left by `rotate_amount` bits
     RotateLeft {
         to_rotate: AddressMode,
         rotate_amount: AddressMode,
     },
+    /// ROR: rotates `to_rotate` right by `rotate_amount` bits
+    RotateRight {
+        to_rotate: AddressMode,
+        rotate_amount: AddressMode,
+    },
+    /// ROXL: rotates `to_rotate` left by `rotate_amount` bits through the extend flag,
+    /// as if it were one bit above the most significant bit
+    RotateLeftExtended {
+        to_rotate: AddressMode,
+        rotate_amount: AddressMode,
+    },
+    /// ROXR: rotates `to_rotate` right by `rotate_amount` bits through the extend flag
+    RotateRightExtended {
+        to_rotate: AddressMode,
+        rotate_amount: AddressMode,
+    },
+    /// MOVEP: moves a word or long between a data register and alternate bytes of memory.
+    ///
+    /// Peripherals with an 8-bit bus are only wired to even or odd addresses, so the bytes go to every other address,
+    /// starting with the register's high-order byte at the lowest address: `movep.l d0, (0, a0)`
+    /// writes bits 31-24 of D0 to a0, 23-16 to a0 + 2, 15-8 to a0 + 4, and 7-0 to a0 + 6.
+    /// One operand is a data register and the other is address register indirect with displacement.
+    MovePeripheral {
+        src: AddressMode,
+        dest: AddressMode,
+    },
+    /// MOVES (68010+): moves between a register and memory in another address space,
+    /// picked by the source or destination function code register.
+    ///
+    /// Function codes aren't emulated, so every address space is ordinary memory. Only the supervisor can run it.
+    MoveAlternateSpace {
+        src: AddressMode,
+        dest: AddressMode,
+    },
+    /// LSL: shifts `to_shift` left by `shift_amount` bits, filling with zeroes
+    LogicalShiftLeft {
+        to_shift: AddressMode,
+        shift_amount: AddressMode,
+    },
+    /// ASL: shifts `dest` left by `count` bits, filling with zeroes.
+    ///
+    /// Unlike LSL, the overflow flag is set if the sign bit changes at any point during the shift.
+    ArithmeticShiftLeft {
+        count: AddressMode,
+        dest: AddressMode,
+    },
+    /// ASR: shifts `dest` right by `count` bits, filling with copies of the sign bit
+    ArithmeticShiftRight {
+        count: AddressMode,
+        dest: AddressMode,
+    },
     JumpTo {
         address: AddressMode,
     },
+    /// JSR: pushes the address of the next instruction onto the stack, then jumps to `address`
+    JumpToSubroutine {
+        address: AddressMode,
+    },
+    /// Bcc: branches if `condition` holds.
+    ///
+    /// Like a PC-relative operand, `displacement` is relative to the word after the opcode.
+    /// The instruction's size is the size of the displacement: a byte is stored in the opcode, and a word follows it.
+    BranchConditional {
+        condition: Condition,
+        displacement: i32,
+    },
+    /// BRA: always branches, with a displacement like [`Instruction::BranchConditional`]'s
+    Branch {
+        displacement: i32,
+    },
+    /// BSR: pushes the address of the next instruction onto the stack, then branches like BRA
+    BranchToSubroutine {
+        displacement: i32,
+    },
+    /// DBcc: the loop primitive. Does nothing if `condition` holds; otherwise decrements the low word of `register`,
+    /// and branches (with a word displacement like [`Instruction::BranchConditional`]'s) unless it became -1.
+    DecrementBranch {
+        condition: Condition,
+        register: DataRegister,
+        displacement: i32,
+    },
+    /// LEA: loads the address `src` refers to (rather than the value there) into `dest`
+    LoadEffectiveAddress {
+        src: AddressMode,
+        dest: AddressMode,
+    },
+    /// PEA: pushes the address `src` refers to onto the stack
+    PushEffectiveAddress {
+        src: AddressMode,
+    },
+    /// CHK: checks that `value` is between 0 and `bound` (inclusive)
     BoundsCheck {
         bound: AddressMode,
         value: AddressMode,
     },
+    /// BFEXTU (68020+): zero-extends the bit field `offset` bits into `base` and stores it in `dest`
+    BitFieldExtractUnsigned {
+        base: AddressMode,
+        offset: BitFieldParameter,
+        width: BitFieldParameter,
+        dest: AddressMode,
+    },
+    /// BFEXTS (68020+): like BFEXTU, but the field is sign-extended
+    BitFieldExtractSigned {
+        base: AddressMode,
+        offset: BitFieldParameter,
+        width: BitFieldParameter,
+        dest: AddressMode,
+    },
+    /// BFINS (68020+): replaces the bit field `offset` bits into `base` with the low bits of `src`
+    BitFieldInsert {
+        src: AddressMode,
+        base: AddressMode,
+        offset: BitFieldParameter,
+        width: BitFieldParameter,
+    },
+    /// CMP, CMPA, and CMPI: sets the flags as if `src` were subtracted from `dest`, without changing either.
+    ///
+    /// CMPA (an address register `dest`) compares the whole register, so a word `src` is sign-extended first.
+    Compare {
+        src: AddressMode,
+        dest: AddressMode,
+    },
+    /// CMPM: compares `(dest)+` with `(src)+`, setting the flags as if `src` were subtracted from `dest`
+    ///
+    /// This isn't a [`Instruction::Compare`], because CMP's destination has to be a data register:
+    /// CMPM has its own opcode, and only takes two postincremented address registers,
+    /// so its operands are just the registers, which can't be given a mode CMPM can't encode.
+    CompareMemory {
+        src: AddressRegister,
+        dest: AddressRegister,
+    },
+    /// NEG: subtracts `dest` from zero
+    Negate {
+        dest: AddressMode,
+    },
+    /// NEGX: subtracts `dest` and the extend flag from zero
+    NegateWithExtend {
+        dest: AddressMode,
+    },
+    /// CLR: sets `dest` to zero
+    Clear {
+        dest: AddressMode,
+    },
+    /// NOT: inverts every bit of `dest`
+    Not {
+        dest: AddressMode,
+    },
+    /// TST: sets the flags from `src`, without changing it
+    Test {
+        src: AddressMode,
+    },
+    /// BTST: sets the zero flag if bit `bit_number` of `dest` is clear, and clears it otherwise.
+    ///
+    /// `bit_number` is an immediate or a data register. A data register `dest` is a long, so the bit number is taken modulo 32;
+    /// any other `dest` is a byte in memory, so it's taken modulo 8.
+    TestBit {
+        bit_number: AddressMode,
+        dest: AddressMode,
+    },
+    /// BSET: tests a bit like BTST, then sets it
+    SetBit {
+        bit_number: AddressMode,
+        dest: AddressMode,
+    },
+    /// BCLR: tests a bit like BTST, then clears it
+    ClearBit {
+        bit_number: AddressMode,
+        dest: AddressMode,
+    },
+    /// BCHG: tests a bit like BTST, then inverts it
+    ChangeBit {
+        bit_number: AddressMode,
+        dest: AddressMode,
+    },
     ReturnFromSubroutine,
+    /// TRAP: raises trap exception `vector` (0-15), which is how programs make system calls.
+    ///
+    /// Exceptions aren't emulated yet, so this can be decoded but not executed.
+    Trap {
+        vector: u8,
+    },
     NoOp,
 }
 
 impl Instruction {
-    pub fn execute(
-        &self,
-        cpu: &mut CPU<impl Memory>,
-        size: OperandSize,
-    ) -> Result<(), EmulationError> {
+    /// Gets the operands of this instruction, in assembly order
+    pub fn operands(&self) -> Vec<AddressMode> {
         match self {
-            Instruction::Add { src, dest } => {
-                let val = src
-                    .get_value(cpu, size)?
-                    .wrapping_add(dest.get_value(cpu, size)?);
-                dest.set_value(cpu, val)
+            Instruction::Add { src, dest }
+            | Instruction::Subtract { src, dest }
+            | Instruction::ExclusiveOr { src, dest }
+            | Instruction::InclusiveOr { src, dest }
+            | Instruction::And { src, dest }
+            | Instruction::Move { src, dest }
+            | Instruction::MoveQuick { src, dest }
+            | Instruction::MovePeripheral { src, dest }
+            | Instruction::MoveAlternateSpace { src, dest }
+            | Instruction::LoadEffectiveAddress { src, dest }
+            | Instruction::MultiplyUnsigned { src, dest }
+            | Instruction::DivideUnsigned { src, dest }
+            | Instruction::DivideSigned { src, dest }
+            | Instruction::AddBCD { src, dest }
+            | Instruction::SubtractBCD { src, dest }
+            | Instruction::AddWithExtend { src, dest }
+            | Instruction::SubtractWithExtend { src, dest }
+            | Instruction::Compare { src, dest } => vec![src.clone(), dest.clone()],
+            Instruction::RotateLeft {
+                to_rotate,
+                rotate_amount,
             }
-            Instruction::Subtract { src, dest } => {
-                let val = src
-                    .get_value(cpu, size)?
-                    .wrapping_sub(dest.get_value(cpu, size)?);
-                dest.set_value(cpu, val)
+            | Instruction::RotateRight {
+                to_rotate,
+                rotate_amount,
             }
-            Instruction::MultiplyUnsigned { src, dest } => {
-                let val = src
-                    .get_value(cpu, size)?
-                    .wrapping_mul(dest.get_value(cpu, size)?);
-                dest.set_value(cpu, val)
+            | Instruction::RotateLeftExtended {
+                to_rotate,
+                rotate_amount,
             }
-            Instruction::Move { src, dest } => {
-                let val = src.get_value(cpu, size)?;
-                dest.set_value(cpu, val)
+            | Instruction::RotateRightExtended {
+                to_rotate,
+                rotate_amount,
+            } => vec![rotate_amount.clone(), to_rotate.clone()],
+            Instruction::LogicalShiftLeft {
+                to_shift,
+                shift_amount,
+            } => vec![shift_amount.clone(), to_shift.clone()],
+            Instruction::ArithmeticShiftLeft { count, dest }
+            | Instruction::ArithmeticShiftRight { count, dest } => {
+                vec![count.clone(), dest.clone()]
             }
-            Instruction::ExclusiveOr { src, dest } => {
-                let val = src.get_value(cpu, size)?.xor(dest.get_value(cpu, size)?);
-                dest.set_value(cpu, val)
+            Instruction::JumpTo { address } | Instruction::JumpToSubroutine { address } => {
+                vec![address.clone()]
             }
-            Instruction::InclusiveOr { src, dest } => {
-                let val = src.get_value(cpu, size)?.or(dest.get_value(cpu, size)?);
-                dest.set_value(cpu, val)
+            Instruction::PushEffectiveAddress { src } | Instruction::Test { src } => {
+                vec![src.clone()]
             }
-            // Could use a cleaner API like `src.modify(cpu, size, |val| val.and(dest.get_value(cpu, size)?))`
-            Instruction::And { src, dest } => {
-                let val = src.get_value(cpu, size)?.and(dest.get_value(cpu, size)?);
-                dest.set_value(cpu, val)
+            Instruction::Negate { dest }
+            | Instruction::NegateWithExtend { dest }
+            | Instruction::NegateBCD { dest }
+            | Instruction::Clear { dest }
+            | Instruction::Not { dest } => vec![dest.clone()],
+            Instruction::BoundsCheck { bound, value } => vec![bound.clone(), value.clone()],
+            Instruction::BitFieldExtractUnsigned { base, dest, .. }
+            | Instruction::BitFieldExtractSigned { base, dest, .. } => {
+                vec![base.clone(), dest.clone()]
             }
-            Instruction::RotateLeft {
-                to_rotate,
-                rotate_amount,
-            } => {
-                let val = to_rotate
-                    .get_value(cpu, size)?
-                    .rotate_left(rotate_amount.get_value(cpu, size)?);
-                to_rotate.set_value(cpu, val)
-            }
-            Instruction::JumpTo { address } => {
-                let val = address.get_value(cpu, OperandSize::Long)?;
-                let int: u32 = val.into();
-                eprintln!(
-                    "Jumping to {:X} (current PC value: {:?})",
-                    int,
-                    cpu.registers.get(Register::ProgramCounter)
-                );
-                cpu.registers.set(Register::ProgramCounter, val);
-                Ok(())
+            Instruction::BitFieldInsert { src, base, .. } => vec![src.clone(), base.clone()],
+            Instruction::CompareMemory { src, dest } => vec![
+                AddressMode::RegisterIndirectPostIncrement { register: *src },
+                AddressMode::RegisterIndirectPostIncrement { register: *dest },
+            ],
+            Instruction::DecrementBranch { register, .. } => vec![AddressMode::data(*register)],
+            Instruction::TestBit { bit_number, dest }
+            | Instruction::SetBit { bit_number, dest }
+            | Instruction::ClearBit { bit_number, dest }
+            | Instruction::ChangeBit { bit_number, dest } => {
+                vec![bit_number.clone(), dest.clone()]
             }
-            Instruction::BoundsCheck { bound, value } => {
-                let val: u32 = value.get_value(cpu, size)?.into();
-                let val = val as i32;
-                let bound: u32 = bound.get_value(cpu, size)?.into();
+            Instruction::BranchConditional { .. }
+            | Instruction::Branch { .. }
+            | Instruction::BranchToSubroutine { .. }
+            | Instruction::ReturnFromSubroutine
+            | Instruction::Trap { .. }
+            | Instruction::NoOp => vec![],
+        }
+    }
 
-                if val > bound as i32 || val < 0 {
-                    todo!("exception handling")
-                } else {
-                    Ok(())
-                }
+    /// Gets the operand size this instruction always uses, regardless of the size it was decoded with.
+    ///
+    /// `execute` uses this instead of the size it's given, if there is one.
+    pub fn intrinsic_size(&self) -> Option<OperandSize> {
+        match self {
+            Instruction::MoveQuick { .. }
+            | Instruction::JumpTo { .. }
+            | Instruction::JumpToSubroutine { .. }
+            | Instruction::LoadEffectiveAddress { .. }
+            | Instruction::PushEffectiveAddress { .. }
+            | Instruction::BitFieldExtractUnsigned { .. }
+            | Instruction::BitFieldExtractSigned { .. }
+            | Instruction::BitFieldInsert { .. } => Some(OperandSize::Long),
+            Instruction::AddBCD { .. }
+            | Instruction::SubtractBCD { .. }
+            | Instruction::NegateBCD { .. } => Some(OperandSize::Byte),
+            // The divisor is a word; the dividend is always a long
+            Instruction::DivideUnsigned { .. } | Instruction::DivideSigned { .. } => {
+                Some(OperandSize::Word)
             }
-            Instruction::ReturnFromSubroutine => {
-                let stack_value = AddressMode::RegisterIndirectPostIncrement {
-                    register: AddressRegister::A7, // stack pointer
+            // Bit instructions work on a whole data register, or a single byte of memory
+            Instruction::TestBit { dest, .. }
+            | Instruction::SetBit { dest, .. }
+            | Instruction::ClearBit { dest, .. }
+            | Instruction::ChangeBit { dest, .. } => match dest {
+                AddressMode::RegisterDirect {
+                    register: Register::Data(_),
+                } => Some(OperandSize::Long),
+                _ => Some(OperandSize::Byte),
+            },
+            _ => None,
+        }
+    }
+
+    /// Gets the earliest CPU model that can execute this instruction
+    pub fn required_model(&self) -> CpuModel {
+        match self {
+            Instruction::BitFieldExtractUnsigned { .. }
+            | Instruction::BitFieldExtractSigned { .. }
+            | Instruction::BitFieldInsert { .. } => CpuModel::M68020,
+            Instruction::MoveAlternateSpace { .. } => CpuModel::M68010,
+            _ => CpuModel::M68000,
+        }
+    }
+
+    /// Gets the condition codes this instruction can change, including ones the 68000 leaves undefined
+    pub fn affected_flags(&self) -> FlagMask {
+        let to_address_register = |dest: &AddressMode| {
+            matches!(
+                dest,
+                AddressMode::RegisterDirect {
+                    register: Register::Address(_)
                 }
-                .get_value(cpu, OperandSize::Long)?;
-                dbg!(stack_value);
-                cpu.registers.set(Register::ProgramCounter, stack_value);
-                Ok(())
-            }
-            Instruction::NoOp => Ok(()),
+            )
+        };
 
-            _ => todo!("implement {:?}", self),
+        match self {
+            // ADDA, SUBA, and MOVEA leave the flags alone
+            Instruction::Add { dest, .. }
+            | Instruction::Subtract { dest, .. }
+            | Instruction::Move { dest, .. }
+                if to_address_register(dest) =>
+            {
+                FlagMask::NONE
+            }
+            Instruction::Add { .. }
+            | Instruction::Subtract { .. }
+            | Instruction::AddBCD { .. }
+            | Instruction::SubtractBCD { .. }
+            | Instruction::AddWithExtend { .. }
+            | Instruction::SubtractWithExtend { .. }
+            | Instruction::LogicalShiftLeft { .. }
+            | Instruction::RotateLeftExtended { .. }
+            | Instruction::RotateRightExtended { .. }
+            | Instruction::ArithmeticShiftLeft { .. }
+            | Instruction::ArithmeticShiftRight { .. }
+            | Instruction::Negate { .. }
+            | Instruction::NegateWithExtend { .. }
+            | Instruction::NegateBCD { .. } => FlagMask::XNZVC,
+            Instruction::Move { .. }
+            | Instruction::MoveQuick { .. }
+            | Instruction::MultiplyUnsigned { .. }
+            | Instruction::DivideUnsigned { .. }
+            | Instruction::DivideSigned { .. }
+            | Instruction::ExclusiveOr { .. }
+            | Instruction::InclusiveOr { .. }
+            | Instruction::And { .. }
+            | Instruction::RotateLeft { .. }
+            | Instruction::RotateRight { .. }
+            | Instruction::BoundsCheck { .. }
+            | Instruction::BitFieldExtractUnsigned { .. }
+            | Instruction::BitFieldExtractSigned { .. }
+            | Instruction::BitFieldInsert { .. }
+            | Instruction::Compare { .. }
+            | Instruction::CompareMemory { .. }
+            | Instruction::Clear { .. }
+            | Instruction::Not { .. }
+            | Instruction::Test { .. } => FlagMask::NZVC,
+            Instruction::TestBit { .. }
+            | Instruction::SetBit { .. }
+            | Instruction::ClearBit { .. }
+            | Instruction::ChangeBit { .. } => FlagMask::ZERO,
+            Instruction::MovePeripheral { .. }
+            | Instruction::MoveAlternateSpace { .. }
+            | Instruction::JumpTo { .. }
+            | Instruction::JumpToSubroutine { .. }
+            | Instruction::BranchConditional { .. }
+            | Instruction::Branch { .. }
+            | Instruction::BranchToSubroutine { .. }
+            | Instruction::DecrementBranch { .. }
+            | Instruction::LoadEffectiveAddress { .. }
+            | Instruction::PushEffectiveAddress { .. }
+            | Instruction::ReturnFromSubroutine
+            | Instruction::Trap { .. }
+            | Instruction::NoOp => FlagMask::NONE,
         }
     }
-}
-#[cfg(test)]
-mod test {
-    use super::*;
-    use crate::{
-        cpu::{addressing::AddressMode, CPU},
-        ram::VecBackedMemory,
-        M68kInteger, OperandSize,
-    };
 
-    static ADDRESS: u32 = 0x40;
-    static VALUE: u32 = 0xDEADBEEF;
+    /// Estimates the number of clock cycles the 68000 takes to run this instruction, from the tables in the
+    /// programmer's reference manual.
+    ///
+    /// Timings that depend on the data (like MULU's and the shifts') use their best case.
+    pub fn cycles(&self, size: OperandSize) -> u32 {
+        let size = self.intrinsic_size().unwrap_or(size);
+        let long = size == OperandSize::Long;
+        let ea = |mode: &AddressMode| mode.calculation_cycles(size);
+        let in_register = |mode: &AddressMode| matches!(mode, AddressMode::RegisterDirect { .. });
+        // JMP, LEA, and PEA only calculate an address: (An), (d16, An), (d8, An, Xn), and absolute
+        let control = |mode: &AddressMode, timings: [u32; 4]| match mode {
+            AddressMode::RegisterIndirect { .. } => timings[0],
+            AddressMode::RegisterIndirectWithDisplacement { .. }
+            | AddressMode::ProgramCounterIndirectWithDisplacement { .. } => timings[1],
+            AddressMode::Absolute { .. } | AddressMode::Immediate { .. } => timings[3],
+            _ => timings[2],
+        };
 
-    /// Genernates a unit test with the given test cases for an instruction that uses the src/dest format
-    macro_rules! test_instruction {
-        ($( #[$meta:meta], )? $function_name:ident, $variant:ident, $op1:ident, $op2:ident, $size:ident, $( ($src:expr, $dest:expr) => $result:expr ),*) => {
-            #[test]
+        match self {
+            // ADDA/SUBA
+            Instruction::Add { src, dest } | Instruction::Subtract { src, dest }
+                if matches!(
+                    dest,
+                    AddressMode::RegisterDirect {
+                        register: Register::Address(_)
+                    }
+                ) =>
+            {
+                match size {
+                    OperandSize::Long
+                        if in_register(src) || matches!(src, AddressMode::Immediate { .. }) =>
+                    {
+                        8 + ea(src)
+                    }
+                    OperandSize::Long => 6 + ea(src),
+                    _ => 8 + ea(src),
+                }
+            }
+            Instruction::Add { src, dest }
+            | Instruction::Subtract { src, dest }
+            | Instruction::ExclusiveOr { src, dest }
+            | Instruction::InclusiveOr { src, dest }
+            | Instruction::And { src, dest } => match (src, dest) {
+                // ADDI and friends
+                (AddressMode::Immediate { .. }, dest) if in_register(dest) => {
+                    if long {
+                        16
+                    } else {
+                        8
+                    }
+                }
+                (AddressMode::Immediate { .. }, dest) => (if long { 20 } else { 12 }) + ea(dest),
+                (src, dest) if in_register(dest) => {
+                    (if long { 6 } else { 4 })
+                        + ea(src)
+                        + if long && in_register(src) { 2 } else { 0 }
+                }
+                (_, dest) => (if long { 12 } else { 8 }) + ea(dest),
+            },
+            Instruction::AddWithExtend { src, .. }
+            | Instruction::SubtractWithExtend { src, .. } => match (in_register(src), long) {
+                (true, false) => 4,
+                (true, true) => 8,
+                (false, false) => 18,
+                (false, true) => 30,
+            },
+            Instruction::AddBCD { src, .. } | Instruction::SubtractBCD { src, .. } => {
+                if in_register(src) {
+                    6
+                } else {
+                    18
+                }
+            }
+            Instruction::NegateBCD { dest } => {
+                if in_register(dest) {
+                    6
+                } else {
+                    8 + ea(dest)
+                }
+            }
+            Instruction::MultiplyUnsigned { src, .. } => 38 + ea(src),
+            // The manual only gives the worst case for division
+            Instruction::DivideUnsigned { src, .. } => 140 + ea(src),
+            Instruction::DivideSigned { src, .. } => 158 + ea(src),
+            Instruction::Move { src, dest } => 4 + ea(src) + ea(dest),
+            Instruction::MoveQuick { .. } => 4,
+            // MOVES isn't on the 68000, so this is MOVE's timing plus fetching the extension word
+            Instruction::MoveAlternateSpace { src, dest } => 8 + ea(src) + ea(dest),
+            Instruction::MovePeripheral { .. } => {
+                if long {
+                    24
+                } else {
+                    16
+                }
+            }
+            Instruction::RotateLeft {
+                to_rotate: dest, ..
+            }
+            | Instruction::RotateRight {
+                to_rotate: dest, ..
+            }
+            | Instruction::RotateLeftExtended {
+                to_rotate: dest, ..
+            }
+            | Instruction::RotateRightExtended {
+                to_rotate: dest, ..
+            }
+            | Instruction::LogicalShiftLeft { to_shift: dest, .. }
+            | Instruction::ArithmeticShiftLeft { dest, .. }
+            | Instruction::ArithmeticShiftRight { dest, .. } => {
+                if !in_register(dest) {
+                    8 + ea(dest)
+                } else if long {
+                    8
+                } else {
+                    6
+                }
+            }
+            Instruction::Negate { dest }
+            | Instruction::NegateWithExtend { dest }
+            | Instruction::Clear { dest }
+            | Instruction::Not { dest } => match (in_register(dest), long) {
+                (true, false) => 4,
+                (true, true) => 6,
+                (false, false) => 8 + ea(dest),
+                (false, true) => 12 + ea(dest),
+            },
+            Instruction::Test { src } => 4 + ea(src),
+            Instruction::TestBit { bit_number, dest }
+            | Instruction::SetBit { bit_number, dest }
+            | Instruction::ClearBit { bit_number, dest }
+            | Instruction::ChangeBit { bit_number, dest } => {
+                // Register timings are the worst case, where the bit number is 16 or more
+                let (register, memory) = match self {
+                    Instruction::TestBit { .. } => (6, 4),
+                    Instruction::ClearBit { .. } => (10, 8),
+                    _ => (8, 8),
+                };
+                // An immediate bit number has to be fetched from an extension word
+                let immediate = match bit_number {
+                    AddressMode::Immediate { .. } => 4,
+                    _ => 0,
+                };
+                if in_register(dest) {
+                    register + immediate
+                } else {
+                    memory + immediate + ea(dest)
+                }
+            }
+            Instruction::Compare { src, dest } => match (src, dest) {
+                // CMPA
+                (
+                    src,
+                    AddressMode::RegisterDirect {
+                        register: Register::Address(_),
+                    },
+                ) => 6 + ea(src),
+                // CMPI
+                (AddressMode::Immediate { .. }, dest) if in_register(dest) => {
+                    if long {
+                        14
+                    } else {
+                        8
+                    }
+                }
+                (AddressMode::Immediate { .. }, dest) => (if long { 12 } else { 8 }) + ea(dest),
+                (src, _) => (if long { 6 } else { 4 }) + ea(src),
+            },
+            Instruction::CompareMemory { .. } => {
+                if long {
+                    20
+                } else {
+                    12
+                }
+            }
+            Instruction::BoundsCheck { bound, .. } => 10 + ea(bound),
+            Instruction::BitFieldExtractUnsigned { base, .. }
+            | Instruction::BitFieldExtractSigned { base, .. }
+            | Instruction::BitFieldInsert { base, .. } => 8 + ea(base),
+            Instruction::JumpTo { address } => control(address, [8, 10, 14, 12]),
+            Instruction::JumpToSubroutine { address } => control(address, [16, 18, 22, 20]),
+            // Assumes the branch is taken; a byte branch that isn't takes 8, and a word branch 12
+            Instruction::BranchConditional { .. } | Instruction::Branch { .. } => 10,
+            Instruction::BranchToSubroutine { .. } => 18,
+            // Also assumes the branch is taken; otherwise it takes 12 if the condition holds, or 14 if the counter expired
+            Instruction::DecrementBranch { .. } => 10,
+            Instruction::LoadEffectiveAddress { src, .. } => control(src, [4, 8, 12, 12]),
+            Instruction::PushEffectiveAddress { src } => control(src, [12, 16, 20, 20]),
+            Instruction::ReturnFromSubroutine => 16,
+            Instruction::Trap { .. } => 34,
+            Instruction::NoOp => 4,
+        }
+    }
+
+    /// Gets the address this instruction will transfer control to, without executing it.
+    ///
+    /// Returns `None` if the instruction doesn't change the flow of control
+    /// (in which case execution continues with the next instruction).
+    pub fn branch_target(&self, cpu: &CPU<impl Memory>) -> Result<Option<u32>, EmulationError> {
+        match self {
+            Instruction::JumpTo { address } | Instruction::JumpToSubroutine { address } => {
+                match *address {
+                    // Jumping to a constant is treated like jumping to an absolute address
+                    AddressMode::Immediate { value } => Ok(Some(value)),
+                    _ => Ok(Some(address.effective_address(cpu)?.into())),
+                }
+            }
+            Instruction::BranchConditional {
+                condition,
+                displacement,
+            } if condition.evaluate(cpu.registers.get_flags()) => {
+                let base = Address(cpu.instruction_address()) + 2;
+                Ok(Some((base + *displacement as u32).into()))
+            }
+            Instruction::Branch { displacement }
+            | Instruction::BranchToSubroutine { displacement } => {
+                let base = Address(cpu.instruction_address()) + 2;
+                Ok(Some((base + *displacement as u32).into()))
+            }
+            Instruction::DecrementBranch {
+                condition,
+                register,
+                displacement,
+            } if !condition.evaluate(cpu.registers.get_flags())
+                // The counter will be decremented to -1, ending the loop
+                && cpu.registers.get_data_register(*register) as u16 != 0 =>
+            {
+                let base = Address(cpu.instruction_address()) + 2;
+                Ok(Some((base + *displacement as u32).into()))
+            }
+            Instruction::ReturnFromSubroutine => Ok(Some(cpu.memory.read_long(Address(
+                cpu.registers.get_address_register(AddressRegister::A7),
+            ))?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Gets the mnemonic for this instruction, without a size suffix
+    fn mnemonic(&self) -> String {
+        match self {
+            Instruction::Add { .. } => "add",
+            Instruction::Subtract { .. } => "sub",
+            Instruction::ExclusiveOr { .. } => "eor",
+            Instruction::InclusiveOr { .. } => "or",
+            Instruction::And { .. } => "and",
+            Instruction::Move { .. } => "move",
+            Instruction::MoveQuick { .. } => "moveq",
+            Instruction::MultiplyUnsigned { .. } => "mulu",
+            Instruction::DivideUnsigned { .. } => "divu",
+            Instruction::DivideSigned { .. } => "divs",
+            Instruction::AddBCD { .. } => "abcd",
+            Instruction::SubtractBCD { .. } => "sbcd",
+            Instruction::NegateBCD { .. } => "nbcd",
+            Instruction::AddWithExtend { .. } => "addx",
+            Instruction::SubtractWithExtend { .. } => "subx",
+            Instruction::RotateLeft { .. } => "rol",
+            Instruction::RotateRight { .. } => "ror",
+            Instruction::RotateLeftExtended { .. } => "roxl",
+            Instruction::RotateRightExtended { .. } => "roxr",
+            Instruction::MovePeripheral { .. } => "movep",
+            Instruction::MoveAlternateSpace { .. } => "moves",
+            Instruction::LogicalShiftLeft { .. } => "lsl",
+            Instruction::ArithmeticShiftLeft { .. } => "asl",
+            Instruction::ArithmeticShiftRight { .. } => "asr",
+            Instruction::JumpTo { .. } => "jmp",
+            Instruction::JumpToSubroutine { .. } => "jsr",
+            Instruction::BranchConditional { condition, .. } => return format!("b{}", condition),
+            Instruction::Branch { .. } => "bra",
+            Instruction::BranchToSubroutine { .. } => "bsr",
+            Instruction::DecrementBranch { condition, .. } => return format!("db{}", condition),
+            Instruction::LoadEffectiveAddress { .. } => "lea",
+            Instruction::PushEffectiveAddress { .. } => "pea",
+            Instruction::BoundsCheck { .. } => "chk",
+            Instruction::BitFieldExtractUnsigned { .. } => "bfextu",
+            Instruction::BitFieldExtractSigned { .. } => "bfexts",
+            Instruction::BitFieldInsert { .. } => "bfins",
+            Instruction::Compare { .. } => "cmp",
+            Instruction::CompareMemory { .. } => "cmpm",
+            Instruction::Negate { .. } => "neg",
+            Instruction::NegateWithExtend { .. } => "negx",
+            Instruction::Clear { .. } => "clr",
+            Instruction::Not { .. } => "not",
+            Instruction::Test { .. } => "tst",
+            Instruction::TestBit { .. } => "btst",
+            Instruction::SetBit { .. } => "bset",
+            Instruction::ClearBit { .. } => "bclr",
+            Instruction::ChangeBit { .. } => "bchg",
+            Instruction::ReturnFromSubroutine => "rts",
+            Instruction::Trap { .. } => "trap",
+            Instruction::NoOp => "nop",
+        }
+        .to_string()
+    }
+
+    /// Formats this instruction as assembly, such as `bsr strcpy` or `move.w (8, a0), d0`.
+    ///
+    /// `pc` is the address of the instruction, which branch targets and PC-relative operands are relative to,
+    /// and `size` is the size it was decoded with. Targets and addresses that have a name in `symbols` are written as that name.
+    pub fn disassemble(&self, pc: u32, size: OperandSize, symbols: &SymbolTable) -> String {
+        let address = |address: u32| match symbols.name(address) {
+            Some(name) => name.to_string(),
+            None => format!("${:X}", address),
+        };
+        let operand = |mode: &AddressMode| mode.to_assembly(pc, symbols);
+        let bit_field =
+            |base: &AddressMode, offset: &BitFieldParameter, width: &BitFieldParameter| {
+                format!("{}{{{}:{}}}", operand(base), offset, width)
+            };
+
+        let operands = match self {
+            Instruction::BranchConditional { displacement, .. }
+            | Instruction::Branch { displacement }
+            | Instruction::BranchToSubroutine { displacement } => {
+                vec![address(
+                    pc.wrapping_add(2).wrapping_add(*displacement as u32),
+                )]
+            }
+            Instruction::DecrementBranch {
+                register,
+                displacement,
+                ..
+            } => vec![
+                operand(&AddressMode::data(*register)),
+                address(pc.wrapping_add(2).wrapping_add(*displacement as u32)),
+            ],
+            // Jump targets are written bare, like the assembler accepts them
+            Instruction::JumpTo { address: target }
+            | Instruction::JumpToSubroutine { address: target } => match *target {
+                AddressMode::Absolute { address: target }
+                | AddressMode::Immediate { value: target } => {
+                    vec![address(target)]
+                }
+                _ => vec![operand(target)],
+            },
+            Instruction::MoveQuick { src, dest } => match src {
+                AddressMode::Immediate { value } => {
+                    vec![format!("#{}", *value as u8 as i8), operand(dest)]
+                }
+                _ => vec![operand(src), operand(dest)],
+            },
+            Instruction::BitFieldExtractUnsigned {
+                base,
+                offset,
+                width,
+                dest,
+            }
+            | Instruction::BitFieldExtractSigned {
+                base,
+                offset,
+                width,
+                dest,
+            } => vec![bit_field(base, offset, width), operand(dest)],
+            Instruction::BitFieldInsert {
+                src,
+                base,
+                offset,
+                width,
+            } => vec![operand(src), bit_field(base, offset, width)],
+            Instruction::Trap { vector } => vec![format!("#{}", vector)],
+            _ => self.operands().iter().map(operand).collect(),
+        };
+
+        let is_sized = !matches!(
+            self,
+            Instruction::BranchConditional { .. }
+                | Instruction::Branch { .. }
+                | Instruction::BranchToSubroutine { .. }
+                | Instruction::DecrementBranch { .. }
+                | Instruction::Trap { .. }
+        ) && self.intrinsic_size().is_none()
+            && !operands.is_empty();
+        let mnemonic = if is_sized {
+            format!("{}{}", self.mnemonic(), size.suffix())
+        } else {
+            self.mnemonic()
+        };
+
+        match operands.is_empty() {
+            true => mnemonic,
+            false => format!("{} {}", mnemonic, operands.join(", ")),
+        }
+    }
+
+    /// Runs the instruction.
+    ///
+    /// The PC should already point to the next instruction, as it does under [`CPU::run_one_cycle`];
+    /// that's where subroutine calls return to.
+    pub fn execute(
+        &self,
+        cpu: &mut CPU<impl Memory>,
+        size: OperandSize,
+    ) -> Result<(), EmulationError> {
+        if self.required_model() > cpu.model {
+            return Err(EmulationError::UnsupportedOnModel {
+                instruction: format!("{:?}", self),
+                model: cpu.model,
+            });
+        }
+
+        let size = self.intrinsic_size().unwrap_or(size);
+        match self {
+            Instruction::Add { src, dest } => {
+                let src_val = src.get_value(cpu, size)?;
+                let (dest_val, target) = read_for_write_back(cpu, dest, size)?;
+                let (val, flags) = dest_val.add_with_flags(src_val);
+                target.set_value(cpu, val)?;
+                set_arithmetic_flags(cpu, dest, flags);
+                Ok(())
+            }
+            Instruction::Subtract { src, dest } => {
+                // dest - src, not src - dest: `sub #5, d0` subtracts 5 from d0
+                let src_val = src.get_value(cpu, size)?;
+                let (dest_val, target) = read_for_write_back(cpu, dest, size)?;
+                let (val, flags) = dest_val.sub_with_flags(src_val);
+                target.set_value(cpu, val)?;
+                set_arithmetic_flags(cpu, dest, flags);
+                Ok(())
+            }
+            Instruction::AddWithExtend { src, dest } => {
+                add_or_subtract_with_extend(cpu, src, dest, size, false)
+            }
+            Instruction::SubtractWithExtend { src, dest } => {
+                add_or_subtract_with_extend(cpu, src, dest, size, true)
+            }
+            Instruction::MultiplyUnsigned { src, dest } => {
+                let operand_size = match size {
+                    OperandSize::Long => OperandSize::Long,
+                    OperandSize::Byte | OperandSize::Word => OperandSize::Word,
+                };
+                let src_val: u32 = src.get_value(cpu, operand_size)?.into();
+                let dest_val: u32 = dest.get_value(cpu, operand_size)?.into();
+                let product = src_val as u64 * dest_val as u64;
+                let val = M68kInteger::Long(product as u32);
+                dest.set_value(cpu, val)?;
+                cpu.registers.set_flags(Flags {
+                    overflow: product > u32::MAX as u64,
+                    ..logical_flags(val)
+                });
+                Ok(())
+            }
+            Instruction::AddBCD { src, dest } | Instruction::SubtractBCD { src, dest } => {
+                let operation = match self {
+                    Instruction::AddBCD { .. } => add_decimal,
+                    _ => subtract_decimal,
+                };
+                // In the memory form, both address registers are decremented before either byte is used
+                let src_val: u32 = src.get_value(cpu, OperandSize::Byte)?.into();
+                let (dest_val, target) = read_for_write_back(cpu, dest, OperandSize::Byte)?;
+                let (val, carry) = operation(
+                    src_val as u8,
+                    u32::from(dest_val) as u8,
+                    cpu.registers.get_extend_flag(),
+                );
+                target.set_value(cpu, M68kInteger::Byte(val))?;
+                set_decimal_flags(cpu, val, carry);
+                Ok(())
+            }
+            Instruction::NegateBCD { dest } => {
+                let (dest_val, target) = read_for_write_back(cpu, dest, OperandSize::Byte)?;
+                let (val, carry) = subtract_decimal(
+                    u32::from(dest_val) as u8,
+                    0,
+                    cpu.registers.get_extend_flag(),
+                );
+                target.set_value(cpu, M68kInteger::Byte(val))?;
+                set_decimal_flags(cpu, val, carry);
+                Ok(())
+            }
+            Instruction::DivideUnsigned { src, dest } | Instruction::DivideSigned { src, dest } => {
+                let divisor: u32 = src.get_value(cpu, OperandSize::Word)?.into();
+                if divisor == 0 {
+                    return Err(EmulationError::DivideByZero(format!("{:?}", self)));
+                }
+                let dividend: u32 = dest.get_value(cpu, OperandSize::Long)?.into();
+
+                let (quotient, remainder) = match self {
+                    Instruction::DivideSigned { .. } => {
+                        // Widened so that dividing the most negative long by -1 can't overflow
+                        let dividend = dividend as i32 as i64;
+                        let divisor = divisor as u16 as i16 as i64;
+                        (
+                            i16::try_from(dividend / divisor).ok().map(|q| q as u16),
+                            (dividend % divisor) as u16,
+                        )
+                    }
+                    _ => (
+                        u16::try_from(dividend / divisor).ok(),
+                        (dividend % divisor) as u16,
+                    ),
+                };
+                match quotient {
+                    Some(quotient) => {
+                        dest.set_value(
+                            cpu,
+                            M68kInteger::Long(((remainder as u32) << 16) | quotient as u32),
+                        )?;
+                        cpu.registers
+                            .set_flags(logical_flags(M68kInteger::Word(quotient)));
+                    }
+                    // N and Z are undefined on overflow, so they're left alone
+                    None => {
+                        cpu.registers.set_ccr_flag(FlagMask::OVERFLOW, true);
+                        cpu.registers.set_ccr_flag(FlagMask::CARRY, false);
+                    }
+                }
+                Ok(())
+            }
+            Instruction::Move { src, dest } => {
+                let val = src.get_value(cpu, size)?;
+                dest.set_value(cpu, val)?;
+                // MOVEA leaves the flags alone
+                if !matches!(
+                    dest,
+                    AddressMode::RegisterDirect {
+                        register: Register::Address(_)
+                    }
+                ) {
+                    cpu.registers.set_flags(logical_flags(val));
+                }
+                Ok(())
+            }
+            Instruction::MoveAlternateSpace { src, dest } => {
+                if !cpu.registers.is_supervisor() {
+                    return Err(EmulationError::PrivilegeViolation(format!("{:?}", self)));
+                }
+                let val = src.get_value(cpu, size)?;
+                dest.set_value(cpu, val)
+            }
+            Instruction::MoveQuick { src, dest } => {
+                let val: u32 = src.get_value(cpu, OperandSize::Byte)?.into();
+                let val = M68kInteger::Long(val as u8 as i8 as i32 as u32);
+                dest.set_value(cpu, val)?;
+                cpu.registers.set_flags(logical_flags(val));
+                Ok(())
+            }
+            Instruction::ExclusiveOr { src, dest } => {
+                let src_val = src.get_value(cpu, size)?;
+                let (dest_val, target) = read_for_write_back(cpu, dest, size)?;
+                let val = src_val.xor(dest_val);
+                target.set_value(cpu, val)?;
+                cpu.registers.set_flags(logical_flags(val));
+                Ok(())
+            }
+            Instruction::InclusiveOr { src, dest } => {
+                let src_val = src.get_value(cpu, size)?;
+                let (dest_val, target) = read_for_write_back(cpu, dest, size)?;
+                let val = src_val.or(dest_val);
+                target.set_value(cpu, val)?;
+                cpu.registers.set_flags(logical_flags(val));
+                Ok(())
+            }
+            // Could use a cleaner API like `src.modify(cpu, size, |val| val.and(dest.get_value(cpu, size)?))`
+            Instruction::And { src, dest } => {
+                let src_val = src.get_value(cpu, size)?;
+                let (dest_val, target) = read_for_write_back(cpu, dest, size)?;
+                let val = src_val.and(dest_val);
+                target.set_value(cpu, val)?;
+                cpu.registers.set_flags(logical_flags(val));
+                Ok(())
+            }
+            Instruction::RotateLeft {
+                to_rotate,
+                rotate_amount,
+            }
+            | Instruction::RotateRight {
+                to_rotate,
+                rotate_amount,
+            } => {
+                let count = shift_count(cpu, rotate_amount)?;
+                let (val, target) = read_for_write_back(cpu, to_rotate, size)?;
+                let count_value = M68kInteger::from_u32(count, size);
+                // The last bit rotated out is also the one rotated in at the other end
+                let (result, carry) = match self {
+                    Instruction::RotateLeft { .. } => {
+                        let result = val.rotate_left(count_value);
+                        (result, u32::from(result) & 1 == 1)
+                    }
+                    _ => {
+                        let result = val.rotate_right(count_value);
+                        (result, result.is_negative())
+                    }
+                };
+                target.set_value(cpu, result)?;
+                // The extend flag isn't used, so unlike the shifts, it's left alone
+                cpu.registers.set_flags(Flags {
+                    carry: count != 0 && carry,
+                    overflow: false,
+                    zero: result.is_zero(),
+                    negative: result.is_negative(),
+                });
+                Ok(())
+            }
+            Instruction::RotateLeftExtended {
+                to_rotate,
+                rotate_amount,
+            }
+            | Instruction::RotateRightExtended {
+                to_rotate,
+                rotate_amount,
+            } => {
+                let count = shift_count(cpu, rotate_amount)?;
+                let (val, target) = read_for_write_back(cpu, to_rotate, size)?;
+                let extend = cpu.registers.get_extend_flag();
+                let (result, extend) = match self {
+                    Instruction::RotateLeftExtended { .. } => {
+                        val.rotate_left_extended(count, extend)
+                    }
+                    _ => val.rotate_right_extended(count, extend),
+                };
+                target.set_value(cpu, result)?;
+                // A rotation by zero copies the extend flag to the carry flag
+                set_shift_flags(cpu, count, result, extend, false);
+                Ok(())
+            }
+            Instruction::LogicalShiftLeft {
+                to_shift,
+                shift_amount,
+            } => {
+                let count = shift_count(cpu, shift_amount)?;
+                let (val, target) = read_for_write_back(cpu, to_shift, size)?;
+                let (result, carry) = val.shift_left(count);
+                target.set_value(cpu, result)?;
+                set_shift_flags(cpu, count, result, carry, false);
+                Ok(())
+            }
+            Instruction::ArithmeticShiftLeft { count, dest } => {
+                let count = shift_count(cpu, count)?;
+                let (val, target) = read_for_write_back(cpu, dest, size)?;
+                let (result, carry) = val.shift_left(count);
+                target.set_value(cpu, result)?;
+                set_shift_flags(cpu, count, result, carry, val.shift_left_overflows(count));
+                Ok(())
+            }
+            Instruction::ArithmeticShiftRight { count, dest } => {
+                let count = shift_count(cpu, count)?;
+                let (val, target) = read_for_write_back(cpu, dest, size)?;
+                let (result, carry) = val.shift_right_arithmetic(count);
+                target.set_value(cpu, result)?;
+                set_shift_flags(cpu, count, result, carry, false);
+                Ok(())
+            }
+            Instruction::BranchToSubroutine { .. } => {
+                // The PC has already moved past the displacement, wherever it was
+                let return_address = cpu.registers.get(Register::ProgramCounter);
+                push(cpu, M68kInteger::Long(return_address))?;
+
+                if let Some(target) = self.branch_target(cpu)? {
+                    cpu.registers.set(Register::ProgramCounter, target);
+                }
+                Ok(())
+            }
+            Instruction::JumpToSubroutine { .. } => {
+                // The target is worked out first, since it can depend on the stack pointer (`jsr (a7)`)
+                let target = self.branch_target(cpu)?;
+                // The PC has already moved past the whole instruction, however long its operand was
+                let return_address = cpu.registers.get(Register::ProgramCounter);
+                push(cpu, M68kInteger::Long(return_address))?;
+
+                if let Some(target) = target {
+                    cpu.registers.set(Register::ProgramCounter, target);
+                }
+                Ok(())
+            }
+            Instruction::DecrementBranch {
+                condition,
+                register,
+                ..
+            } => {
+                let target = self.branch_target(cpu)?;
+                if !condition.evaluate(cpu.registers.get_flags()) {
+                    let value = cpu.registers.get_data_register(*register);
+                    let counter = (value as u16).wrapping_sub(1);
+                    cpu.registers
+                        .set_data_register(*register, (value & 0xFFFF_0000) | counter as u32);
+                }
+                if let Some(target) = target {
+                    cpu.registers.set(Register::ProgramCounter, target);
+                }
+                Ok(())
+            }
+            Instruction::JumpTo { .. }
+            | Instruction::BranchConditional { .. }
+            | Instruction::Branch { .. } => {
+                if let Some(target) = self.branch_target(cpu)? {
+                    #[cfg(feature = "debug-trace")]
+                    eprintln!(
+                        "Jumping to {:X} (current PC value: {:?})",
+                        target,
+                        cpu.registers.get(Register::ProgramCounter)
+                    );
+                    cpu.registers.set(Register::ProgramCounter, target);
+                }
+                Ok(())
+            }
+            Instruction::LoadEffectiveAddress { src, dest } => {
+                let address = src.effective_address(cpu)?;
+                dest.set_value(cpu, M68kInteger::Long(address.into()))
+            }
+            Instruction::PushEffectiveAddress { src } => {
+                let address = src.effective_address(cpu)?;
+                push(cpu, M68kInteger::Long(address.into()))
+            }
+            Instruction::BoundsCheck { bound, value } => {
+                // Both are signed, so a negative value is below the lower bound of 0
+                let val = value.get_value(cpu, size)?.sign_extended();
+                let bound = bound.get_value(cpu, size)?.sign_extended();
+
+                if val > bound || val < 0 {
+                    Err(EmulationError::BoundsCheckFailed(format!("{:?}", self)))
+                } else {
+                    Ok(())
+                }
+            }
+            Instruction::BitFieldExtractUnsigned {
+                base,
+                offset,
+                width,
+                dest,
+            } => {
+                let (offset, width) = bit_field_bounds(*offset, *width, cpu);
+                let field = read_bit_field(cpu, base, offset, width)?;
+                dest.set_value(cpu, M68kInteger::Long(field))?;
+                set_bit_field_flags(cpu, field, width);
+                Ok(())
+            }
+            Instruction::BitFieldExtractSigned {
+                base,
+                offset,
+                width,
+                dest,
+            } => {
+                let (offset, width) = bit_field_bounds(*offset, *width, cpu);
+                let field = read_bit_field(cpu, base, offset, width)?;
+                set_bit_field_flags(cpu, field, width);
+                let field = ((field << (32 - width)) as i32 >> (32 - width)) as u32;
+                dest.set_value(cpu, M68kInteger::Long(field))
+            }
+            Instruction::BitFieldInsert {
+                src,
+                base,
+                offset,
+                width,
+            } => {
+                let (offset, width) = bit_field_bounds(*offset, *width, cpu);
+                let value: u32 = src.get_value(cpu, OperandSize::Long)?.into();
+                write_bit_field(cpu, base, offset, width, value)?;
+                set_bit_field_flags(cpu, value & (u32::MAX >> (32 - width)), width);
+                Ok(())
+            }
+            Instruction::Compare { src, dest } => {
+                let (src_val, dest_val) = match dest {
+                    AddressMode::RegisterDirect {
+                        register: Register::Address(_),
+                    } => {
+                        let src_val = match src.get_value(cpu, size)? {
+                            M68kInteger::Word(word) => word as i16 as i32 as u32,
+                            src_val => src_val.into(),
+                        };
+                        (
+                            M68kInteger::Long(src_val),
+                            dest.get_value(cpu, OperandSize::Long)?,
+                        )
+                    }
+                    _ => (src.get_value(cpu, size)?, dest.get_value(cpu, size)?),
+                };
+                // Comparisons don't affect the extend flag
+                let (_, flags) = dest_val.sub_with_flags(src_val);
+                cpu.registers.set_flags(flags);
+                Ok(())
+            }
+            Instruction::CompareMemory { src, dest } => {
+                let src_val = AddressMode::RegisterIndirectPostIncrement { register: *src }
+                    .get_value(cpu, size)?;
+                let dest_val = AddressMode::RegisterIndirectPostIncrement { register: *dest }
+                    .get_value(cpu, size)?;
+                // Comparisons don't affect the extend flag
+                let (_, flags) = dest_val.sub_with_flags(src_val);
+                cpu.registers.set_flags(flags);
+                Ok(())
+            }
+            Instruction::Negate { dest } => {
+                let (val, target) = read_for_write_back(cpu, dest, size)?;
+                let (val, flags) = M68kInteger::from_u32(0, size).sub_with_flags(val);
+                target.set_value(cpu, val)?;
+                set_arithmetic_flags(cpu, dest, flags);
+                Ok(())
+            }
+            Instruction::NegateWithExtend { dest } => {
+                let (dest_val, target) = read_for_write_back(cpu, dest, size)?;
+                let extend = M68kInteger::from_u32(cpu.registers.get_extend_flag() as u32, size);
+                let zero = M68kInteger::from_u32(0, size);
+                let (negated, negate_flags) = zero.sub_with_flags(dest_val);
+                let (val, extend_flags) = negated.sub_with_flags(extend);
+                let flags = Flags {
+                    carry: negate_flags.carry || extend_flags.carry,
+                    overflow: extended_overflow(true, dest_val, zero, val),
+                    zero: val.is_zero(),
+                    negative: val.is_negative(),
+                };
+                target.set_value(cpu, val)?;
+                set_extended_flags(cpu, dest, flags);
+                Ok(())
+            }
+            Instruction::Clear { dest } => {
+                // The 68000 reads a memory operand before clearing it, which matters for memory-mapped devices
+                if let Some(address) = dest.memory_address(cpu, size)? {
+                    cpu.memory.read(address, size)?;
+                }
+                let val = M68kInteger::from_u32(0, size);
+                dest.set_value(cpu, val)?;
+                cpu.registers.set_flags(logical_flags(val));
+                Ok(())
+            }
+            Instruction::Not { dest } => {
+                let (val, target) = read_for_write_back(cpu, dest, size)?;
+                let val = val.xor(M68kInteger::from_u32(u32::MAX, size));
+                target.set_value(cpu, val)?;
+                cpu.registers.set_flags(logical_flags(val));
+                Ok(())
+            }
+            Instruction::Test { src } => {
+                let val = src.get_value(cpu, size)?;
+                cpu.registers.set_flags(logical_flags(val));
+                Ok(())
+            }
+            Instruction::TestBit { bit_number, dest }
+            | Instruction::SetBit { bit_number, dest }
+            | Instruction::ClearBit { bit_number, dest }
+            | Instruction::ChangeBit { bit_number, dest } => {
+                // The bit number wraps around within the operand
+                let bit: u32 = bit_number.get_value(cpu, OperandSize::Long)?.into();
+                let mask = 1 << (bit % (size.size_in_bytes() * 8));
+                let (val, target) = read_for_write_back(cpu, dest, size)?;
+                let val = u32::from(val);
+                cpu.registers.set_ccr_flag(FlagMask::ZERO, val & mask == 0);
+
+                let result = match self {
+                    Instruction::SetBit { .. } => val | mask,
+                    Instruction::ClearBit { .. } => val & !mask,
+                    Instruction::ChangeBit { .. } => val ^ mask,
+                    _ => return Ok(()),
+                };
+                target.set_value(cpu, M68kInteger::from_u32(result, size))?;
+                Ok(())
+            }
+            Instruction::MovePeripheral { src, dest } => match (src, dest) {
+                (
+                    AddressMode::RegisterDirect {
+                        register: Register::Data(register),
+                    },
+                    memory,
+                ) => {
+                    let address = memory.effective_address(cpu)?;
+                    let value = cpu.registers.get_data_register(*register);
+                    let bytes = size.size_in_bytes();
+                    for i in 0..bytes {
+                        let byte = (value >> (8 * (bytes - 1 - i))) as u8;
+                        cpu.memory.write_byte(address + 2 * i, byte)?;
+                    }
+                    Ok(())
+                }
+                (
+                    memory,
+                    AddressMode::RegisterDirect {
+                        register: Register::Data(register),
+                    },
+                ) => {
+                    let address = memory.effective_address(cpu)?;
+                    let mut value = 0;
+                    for i in 0..size.size_in_bytes() {
+                        value = (value << 8) | cpu.memory.read_byte(address + 2 * i)? as u32;
+                    }
+                    // A word transfer leaves the high word of the register alone
+                    if size == OperandSize::Word {
+                        value |= cpu.registers.get_data_register(*register) & 0xFFFF_0000;
+                    }
+                    cpu.registers.set_data_register(*register, value);
+                    Ok(())
+                }
+                _ => Err(EmulationError::UnimplementedInstruction(format!(
+                    "{:?}",
+                    self
+                ))),
+            },
+            Instruction::ReturnFromSubroutine => {
+                let return_address = pop(cpu, OperandSize::Long)?;
+                cpu.registers.set(Register::ProgramCounter, return_address);
+                Ok(())
+            }
+            Instruction::NoOp => Ok(()),
+
+            _ => Err(EmulationError::UnimplementedInstruction(format!(
+                "{:?}",
+                self
+            ))),
+        }
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        cpu::{
+            addressing::{AddressMode, IndexScale},
+            registers::DataRegister,
+            CPU,
+        },
+        parsers::{assembly::AssemblyInterpreter, encoder::encode, Parser},
+        ram::VecBackedMemory,
+        M68kInteger, OperandSize,
+    };
+
+    static ADDRESS: u32 = 0x40;
+    static VALUE: u32 = 0xDEADBEEF;
+
+    /// Assembles a single instruction and executes it
+    fn run(cpu: &mut CPU<VecBackedMemory>, asm: &str) {
+        let (instruction, size, _) = AssemblyInterpreter::new().parse(asm.to_string()).unwrap();
+        instruction.execute(cpu, size).unwrap();
+    }
+
+    /// Genernates a unit test with the given test cases for an instruction that uses the src/dest format
+    macro_rules! test_instruction {
+        ($( #[$meta:meta], )? $function_name:ident, $variant:ident, $op1:ident, $op2:ident, $size:ident, $( ($src:expr, $dest:expr) => $result:expr ),*) => {
+            #[test]
             $( #[$meta] )?
             fn $function_name() {
                 for (a, b, result) in [$( ($src, $dest, $result) ),*].iter() {
@@ -279,26 +2045,33 @@ mod test {
 
     // TODO: figure out about setting the status register
     test_instruction!(add, Add, (1, 2) => 3, (0, 0) => 0, (0xFFFFFFFF, 1) => 0x00000000);
-    test_instruction!(subtract, Subtract, (1, 2) => 0xFFFFFFFF, (0, 0) => 0, (20, 10) => 10);
+    test_instruction!(subtract, Subtract, (1, 2) => 1, (0, 0) => 0, (20, 10) => 0xFFFFFFF6);
     test_instruction!(multiply_unsigned, MultiplyUnsigned, (1, 2) => 2, (0, 0) => 0, (20, 10) => 200, (0x80000000, 2) => 0);
     test_instruction!(xor, ExclusiveOr, (1, 2) => 3, (0, 0) => 0, (7, 3) => 4, (0xAAAA, 0x15555) => 0x1FFFF);
     test_instruction!(or, InclusiveOr, (1, 2) => 3, (0, 0) => 0, (7, 3) => 7);
     test_instruction!(and, And, (2, 4) => 0, (0, 0) => 0, (0xCD, 0xAB) => 0x89);
     test_instruction!(rotate_left, RotateLeft, rotate_amount, to_rotate, Byte, (2, 0b10101011) => 0b10101110, (0, 0) => 0, (2, 0b11101011) => 0b10101111);
-    // I don't really know how BCD works, and it seems like there are multiple ways to represent numbers :S
-    // Let's hope a real 68k uses the same representation as is documented on Wikipedia!
-    // https://en.wikipedia.org/wiki/Binary-coded_decimal#Background
-    // The Macintosh ROMs seem to use BCD instructions, too, so this actually will need to be correct eventually.
-    // Also, TODO: understand the "extend bit" mentioned at http://wpage.unina.it/rcanonic/didattica/ce1/docs/68000.pdf
+    // Packed BCD stores a decimal digit in each nibble, with the tens digit in the upper nibble
+    // https://en.wikipedia.org/wiki/Binary-coded_decimal#Packed_BCD
     test_instruction!(
-        #[ignore],
-        abcd, AddBCD,
+        abcd, AddBCD, src, dest, Byte,
         // 2 + 2 = 4
-        (0b0010, 0b0010) => 0b0100,
-        // 13 + 21 = 34
-        (0b0011_0001, 0b0001_0010) => 0b0011_0100,
-        // 13 + 9 = 22
-        (0b0011_0001, 0b1001) => 0b0010_0010
+        (0x02, 0x02) => 0x04,
+        // 31 + 12 = 43
+        (0x31, 0x12) => 0x43,
+        // 31 + 9 = 40
+        (0x31, 0x09) => 0x40,
+        // 99 + 1 = 100, which carries out
+        (0x99, 0x01) => 0x00
+    );
+    test_instruction!(
+        sbcd, SubtractBCD, src, dest, Byte,
+        // 34 - 13 = 21
+        (0x13, 0x34) => 0x21,
+        // 10 - 1 = 9
+        (0x01, 0x10) => 0x09,
+        // 0 - 1 = -1, which borrows and leaves 99
+        (0x01, 0x00) => 0x99
     );
 
     #[test]
@@ -321,16 +2094,2401 @@ mod test {
         let instruction = Instruction::Move { src, dest };
 
         instruction.execute(&mut cpu, OperandSize::Long).unwrap();
-        assert_eq!(cpu.memory.read_long(ADDRESS).unwrap(), VALUE);
+        assert_eq!(cpu.memory.read_long(Address(ADDRESS)).unwrap(), VALUE);
     }
 
     #[test]
-    fn no_op() {
+    fn immediate_source_memory_dest() {
+        for (asm, result) in [
+            ("add #5, ($1000).w", 0x1239),
+            ("sub #5, ($1000).w", 0x122F),
+            ("and #$FF, ($1000).w", 0x0034),
+            ("or #$F, ($1000).w", 0x123F),
+            ("eor #$FFFF, ($1000).w", 0xEDCB),
+        ] {
+            let cpu = &mut CPU::<VecBackedMemory>::new(0x2000);
+            cpu.memory.write_word(Address(0x1000), 0x1234).unwrap();
+            cpu.memory.write_word(Address(0x1002), 0xABCD).unwrap();
+
+            let (instruction, size, _) = AssemblyInterpreter::new().parse(asm.to_string()).unwrap();
+            assert_eq!(size, OperandSize::Word, "{}", asm);
+            instruction.execute(cpu, size).unwrap();
+
+            assert_eq!(
+                cpu.memory.read_word(Address(0x1000)).unwrap(),
+                result,
+                "{}",
+                asm
+            );
+            // only the word at the destination should be touched
+            assert_eq!(
+                cpu.memory.read_word(Address(0x1002)).unwrap(),
+                0xABCD,
+                "{}",
+                asm
+            );
+        }
+
+        // 0x1234 + 5 doesn't set any condition codes
+        let cpu = &mut CPU::<VecBackedMemory>::new(0x2000);
+        cpu.memory.write_word(Address(0x1000), 0x1234).unwrap();
+        let (instruction, size, _) = AssemblyInterpreter::new()
+            .parse("add #5, ($1000).w".to_string())
+            .unwrap();
+        instruction.execute(cpu, size).unwrap();
+        assert_eq!(cpu.registers.get_status_register() & 0x1F, 0);
+    }
+
+    #[test]
+    fn move_peripheral() {
         let cpu = &mut CPU::<VecBackedMemory>::new(1024);
-        let instruction = Instruction::NoOp;
+        cpu.registers
+            .set_address_register(AddressRegister::A0, 0x100_u32);
 
-        let initial_state = format!("{}", cpu);
-        instruction.execute(cpu, OperandSize::Long).unwrap();
-        assert_eq!(format!("{}", cpu), initial_state);
+        // Register to memory: high-order byte first, at every other address
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0x1234_5678_u32);
+        run(cpu, "movep.l d0, (0, a0)");
+        assert_eq!(
+            cpu.memory.read_bytes(Address(0x100), 8).unwrap(),
+            vec![0x12, 0, 0x34, 0, 0x56, 0, 0x78, 0]
+        );
+        run(cpu, "movep.w d0, (9, a0)");
+        assert_eq!(
+            cpu.memory.read_bytes(Address(0x108), 4).unwrap(),
+            vec![0, 0x56, 0, 0x78]
+        );
+
+        // Memory to register, starting at an odd address
+        cpu.memory
+            .write_bytes(
+                Address(0x201),
+                vec![0xAB, 0xFF, 0xCD, 0xFF, 0xEF, 0xFF, 0x01],
+            )
+            .unwrap();
+        run(cpu, "movep.l ($101, a0), d1");
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D1),
+            0xABCD_EF01
+        );
+        // A word only replaces the low word of the register
+        cpu.registers
+            .set_data_register(DataRegister::D2, 0x1111_2222_u32);
+        run(cpu, "movep.w ($105, a0), d2");
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D2),
+            0x1111_EF01
+        );
+    }
+
+    #[test]
+    fn logical_shift_left() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        // Register counts are modulo 64
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0x8000_0001_u32);
+        cpu.registers.set_data_register(DataRegister::D1, 65_u32);
+        run(cpu, "lsl d1, d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 2);
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                carry: true,
+                ..Flags::default()
+            }
+        );
+        assert!(cpu.registers.get_extend_flag());
+
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0xF000_0001_u32);
+        run(cpu, "lsl #3, d0");
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0),
+            0x8000_0008
+        );
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                carry: true,
+                negative: true,
+                ..Flags::default()
+            }
+        );
+
+        // Shifting by 64 is shifting by 0: the carry is cleared, but not the extend flag
+        cpu.registers.set_data_register(DataRegister::D1, 64_u32);
+        run(cpu, "lsl d1, d0");
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0),
+            0x8000_0008
+        );
+        assert!(!cpu.registers.get_flags().carry);
+        assert!(cpu.registers.get_extend_flag());
+
+        // Shifting out every bit
+        cpu.registers.set_data_register(DataRegister::D1, 40_u32);
+        run(cpu, "lsl d1, d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0);
+        assert!(cpu.registers.get_flags().zero);
+        assert!(!cpu.registers.get_extend_flag());
+    }
+
+    #[test]
+    fn rotate() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        // The carry flag gets the bit that wrapped around, and the extend flag isn't touched
+        cpu.registers.set_extend_flag(true);
+        cpu.registers.set_data_register(DataRegister::D0, 0x81_u32);
+        run(cpu, "rol.b #1, d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0x03);
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                carry: true,
+                ..Flags::default()
+            }
+        );
+        assert!(cpu.registers.get_extend_flag());
+
+        run(cpu, "ror.b #2, d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0xC0);
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                carry: true,
+                negative: true,
+                ..Flags::default()
+            }
+        );
+
+        // Rotating by a multiple of the width leaves the value alone, but the carry flag still gets the last bit out
+        cpu.registers.set_data_register(DataRegister::D1, 16_u32);
+        run(cpu, "ror.w d1, d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0xC0);
+        assert_eq!(cpu.registers.get_flags(), Flags::default());
+    }
+
+    #[test]
+    fn rotate_through_extend() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        // The extend flag is rotated into the bottom, and the top bit comes out into the carry and extend flags
+        cpu.registers.set_extend_flag(true);
+        cpu.registers.set_data_register(DataRegister::D0, 0x80_u32);
+        run(cpu, "roxl.b #1, d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0x01);
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                carry: true,
+                ..Flags::default()
+            }
+        );
+        assert!(cpu.registers.get_extend_flag());
+
+        run(cpu, "roxr.b #2, d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0xC0);
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                negative: true,
+                ..Flags::default()
+            }
+        );
+        assert!(!cpu.registers.get_extend_flag());
+
+        // The window is one bit wider than the operand: 9, 17, or 33 bits
+        for (asm, value, count) in [
+            ("roxl.b d1, d0", 0xA5_u32, 9_u32),
+            ("roxr.w d1, d0", 0x8001, 17),
+            ("roxl.l d1, d0", 0xDEAD_BEEF, 33),
+        ] {
+            cpu.registers.set_extend_flag(true);
+            cpu.registers.set_data_register(DataRegister::D0, value);
+            cpu.registers.set_data_register(DataRegister::D1, count);
+            run(cpu, asm);
+            assert_eq!(
+                cpu.registers.get_data_register(DataRegister::D0),
+                value,
+                "{}",
+                asm
+            );
+            assert!(cpu.registers.get_extend_flag(), "{}", asm);
+
+            // One bit short of the window leaves the old extend bit in the top bit
+            cpu.registers.set_extend_flag(true);
+            cpu.registers.set_data_register(DataRegister::D0, 0_u32);
+            cpu.registers.set_data_register(DataRegister::D1, count - 1);
+            run(cpu, &asm.replace("roxr", "roxl"));
+            assert!(cpu.registers.get_flags().negative, "{}", asm);
+            assert!(!cpu.registers.get_extend_flag(), "{}", asm);
+        }
+
+        // Rotating by zero copies the extend flag into the carry flag
+        cpu.registers.set_extend_flag(true);
+        cpu.registers.set_data_register(DataRegister::D1, 0_u32);
+        run(cpu, "roxr.l d1, d0");
+        assert!(cpu.registers.get_flags().carry);
+        assert!(cpu.registers.get_extend_flag());
+    }
+
+    #[test]
+    fn arithmetic_shift_left() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        // The sign bit changes on the only shift
+        cpu.registers.set_data_register(DataRegister::D0, 0x40_u32);
+        run(cpu, "asl.b #1, d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0x80);
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                overflow: true,
+                negative: true,
+                ..Flags::default()
+            }
+        );
+
+        // Ones are shifted out, but the sign bit stays set
+        cpu.registers.set_data_register(DataRegister::D0, 0xC0_u32);
+        run(cpu, "asl.b #1, d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0x80);
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                carry: true,
+                negative: true,
+                ..Flags::default()
+            }
+        );
+        assert!(cpu.registers.get_extend_flag());
+
+        // The sign bit changes and then changes back, which still overflows
+        cpu.registers.set_data_register(DataRegister::D0, 0x50_u32);
+        run(cpu, "asl.b #2, d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0x40);
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                carry: true,
+                overflow: true,
+                ..Flags::default()
+            }
+        );
+
+        // Only the operand's size counts: the word's sign bit doesn't change, even though the long's would
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0x0000_F000_u32);
+        run(cpu, "asl.w #3, d0");
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0),
+            0x0000_8000
+        );
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                carry: true,
+                negative: true,
+                ..Flags::default()
+            }
+        );
+
+        // Shifting every bit out of a negative number overflows
+        cpu.registers.set_data_register(DataRegister::D0, u32::MAX);
+        cpu.registers.set_data_register(DataRegister::D1, 32_u32);
+        run(cpu, "asl.l d1, d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0);
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                carry: true,
+                overflow: true,
+                zero: true,
+                negative: false,
+            }
+        );
+
+        // Shifting by zero clears the carry and overflow flags, but not the extend flag
+        cpu.registers.set_data_register(DataRegister::D1, 64_u32);
+        run(cpu, "asl.l d1, d0");
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                zero: true,
+                ..Flags::default()
+            }
+        );
+        assert!(cpu.registers.get_extend_flag());
+    }
+
+    #[test]
+    fn arithmetic_shift_right() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        cpu.registers.set_data_register(DataRegister::D0, 0x81_u32);
+        run(cpu, "asr.b #1, d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0xC0);
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                carry: true,
+                negative: true,
+                ..Flags::default()
+            }
+        );
+        assert!(cpu.registers.get_extend_flag());
+
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0x7FFF_FF00_u32);
+        run(cpu, "asr.l #8, d0");
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0),
+            0x007F_FFFF
+        );
+        assert_eq!(cpu.registers.get_flags(), Flags::default());
+        assert!(!cpu.registers.get_extend_flag());
+
+        // Shifting past the width leaves only copies of the sign bit
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0x8000_u32);
+        cpu.registers.set_data_register(DataRegister::D1, 20_u32);
+        run(cpu, "asr.w d1, d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0xFFFF);
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                carry: true,
+                negative: true,
+                ..Flags::default()
+            }
+        );
+    }
+
+    #[test]
+    fn unary_operations() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        cpu.registers.set_data_register(DataRegister::D0, 1_u32);
+        run(cpu, "neg.w d0");
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0) & 0xFFFF,
+            0xFFFF
+        );
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                carry: true,
+                negative: true,
+                ..Flags::default()
+            }
+        );
+        assert!(cpu.registers.get_extend_flag());
+
+        // Negating the smallest negative number overflows
+        cpu.registers.set_data_register(DataRegister::D0, 0x80_u32);
+        run(cpu, "neg.b d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0x80);
+        assert!(cpu.registers.get_flags().overflow);
+
+        // NEGX subtracts the extend flag too, and only ever clears the zero flag
+        cpu.registers.set_data_register(DataRegister::D0, 0_u32);
+        cpu.registers.set_extend_flag(true);
+        cpu.registers.set_flags(Flags {
+            zero: true,
+            ..Flags::default()
+        });
+        run(cpu, "negx.l d0");
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0),
+            0xFFFF_FFFF
+        );
+        assert!(!cpu.registers.get_flags().zero);
+        assert!(cpu.registers.get_extend_flag());
+        cpu.registers.set_data_register(DataRegister::D0, 0_u32);
+        cpu.registers.set_extend_flag(false);
+        run(cpu, "negx.l d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0);
+        assert!(!cpu.registers.get_flags().zero);
+        assert!(!cpu.registers.get_extend_flag());
+        // 0 - 0x80 overflows, but subtracting X brings it back in range
+        cpu.registers.set_data_register(DataRegister::D0, 0x80_u32);
+        cpu.registers.set_extend_flag(true);
+        run(cpu, "negx.b d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0x7F);
+        assert!(!cpu.registers.get_flags().overflow);
+        assert!(cpu.registers.get_flags().carry);
+        // Without X, it overflows like NEG
+        cpu.registers.set_data_register(DataRegister::D0, 0x80_u32);
+        cpu.registers.set_extend_flag(false);
+        run(cpu, "negx.b d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0x80);
+        assert!(cpu.registers.get_flags().overflow);
+
+        // CLR, NOT, and TST clear the carry and overflow flags, but leave the extend flag alone
+        cpu.registers.set_extend_flag(true);
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0xFFFF_FFFF_u32);
+        run(cpu, "clr.b d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0) & 0xFF, 0);
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                zero: true,
+                ..Flags::default()
+            }
+        );
+        run(cpu, "not.w d0");
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0) & 0xFFFF,
+            0xFFFF
+        );
+        assert!(cpu.registers.get_flags().negative);
+        cpu.memory
+            .write_long(Address(ADDRESS), 0x8000_0000)
+            .unwrap();
+        run(cpu, &format!("tst.l (${:X})", ADDRESS));
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                negative: true,
+                ..Flags::default()
+            }
+        );
+        assert_eq!(cpu.memory.read_long(Address(ADDRESS)).unwrap(), 0x8000_0000);
+        assert!(cpu.registers.get_extend_flag());
+    }
+
+    #[test]
+    fn test_operand() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        let negative = Flags {
+            negative: true,
+            ..Flags::default()
+        };
+        let zero = Flags {
+            zero: true,
+            ..Flags::default()
+        };
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0x0000_8000_u32);
+        cpu.memory.write_word(Address(ADDRESS), 0xFF00).unwrap();
+
+        // Only the low byte/word is tested
+        for (asm, flags) in [
+            ("tst.l d0", Flags::default()),
+            ("tst.w d0", negative),
+            ("tst.b d0", zero),
+            (&format!("tst.w (${:X})", ADDRESS), negative),
+            (&format!("tst.b (${:X})", ADDRESS + 1), zero),
+        ] {
+            // The carry and overflow flags are always cleared
+            cpu.registers.set_flags(Flags {
+                carry: true,
+                overflow: true,
+                ..Flags::default()
+            });
+            run(cpu, asm);
+            assert_eq!(cpu.registers.get_flags(), flags, "{}", asm);
+        }
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0),
+            0x0000_8000
+        );
+        assert_eq!(cpu.memory.read_word(Address(ADDRESS)).unwrap(), 0xFF00);
+    }
+
+    #[test]
+    fn clear_reads_memory_first() {
+        /// RAM that keeps a log of the addresses read from
+        struct ReadLoggingMemory {
+            ram: VecBackedMemory,
+            reads: std::cell::RefCell<Vec<Address>>,
+        }
+        impl std::fmt::Display for ReadLoggingMemory {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.ram)
+            }
+        }
+        impl Memory for ReadLoggingMemory {
+            fn new(size_in_bytes: usize) -> Self {
+                Self {
+                    ram: VecBackedMemory::new(size_in_bytes),
+                    reads: Default::default(),
+                }
+            }
+            fn clear(&mut self) {
+                self.ram.clear();
+            }
+            fn read_byte(&self, address: Address) -> Result<u8, EmulationError> {
+                self.reads.borrow_mut().push(address);
+                self.ram.read_byte(address)
+            }
+            fn write_byte(&mut self, address: Address, value: u8) -> Result<(), EmulationError> {
+                self.ram.write_byte(address, value)
+            }
+        }
+
+        let cpu = &mut CPU::<ReadLoggingMemory>::new(1024);
+        let clear = |cpu: &mut CPU<ReadLoggingMemory>, dest, size| {
+            cpu.memory.reads.borrow_mut().clear();
+            Instruction::Clear { dest }.execute(cpu, size).unwrap();
+            cpu.memory.reads.take()
+        };
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0xFFFF_FFFF_u32);
+        cpu.registers
+            .set_address_register(AddressRegister::A0, 0x100_u32);
+        cpu.memory.write_long(Address(0xFC), 0xFFFF_FFFF).unwrap();
+        cpu.memory.write_long(Address(0x100), 0xFFFF_FFFF).unwrap();
+
+        // Registers aren't in memory
+        assert_eq!(
+            clear(cpu, AddressMode::data(DataRegister::D0), OperandSize::Long),
+            vec![]
+        );
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0);
+
+        let post_increment = AddressMode::RegisterIndirectPostIncrement {
+            register: AddressRegister::A0,
+        };
+        assert_eq!(
+            clear(cpu, post_increment, OperandSize::Word),
+            vec![Address(0x100), Address(0x101)]
+        );
+        // The read doesn't increment the register a second time
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A0),
+            0x102
+        );
+        assert_eq!(
+            cpu.memory.ram.read_long(Address(0x100)).unwrap(),
+            0x0000_FFFF
+        );
+
+        let pre_decrement = AddressMode::RegisterIndirectPreDecrement {
+            register: AddressRegister::A0,
+        };
+        cpu.registers
+            .set_address_register(AddressRegister::A0, 0x100_u32);
+        assert_eq!(
+            clear(cpu, pre_decrement, OperandSize::Byte),
+            vec![Address(0xFF)]
+        );
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A0),
+            0xFF
+        );
+        assert_eq!(
+            cpu.memory.ram.read_long(Address(0xFC)).unwrap(),
+            0xFFFF_FF00
+        );
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                zero: true,
+                ..Flags::default()
+            }
+        );
+    }
+
+    #[test]
+    fn logical_immediate_to_memory() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(0x4000);
+        cpu.registers.set_flags(Flags {
+            carry: true,
+            overflow: true,
+            ..Flags::default()
+        });
+        cpu.memory.write_word(Address(0x2000), 0xFFF0).unwrap();
+
+        run(cpu, "andi.w #$0F0F, ($2000).w");
+        assert_eq!(cpu.memory.read_word(Address(0x2000)).unwrap(), 0x0F00);
+        assert_eq!(cpu.registers.get_flags(), Flags::default());
+
+        run(cpu, "ori.w #$8000, ($2000).w");
+        assert_eq!(cpu.memory.read_word(Address(0x2000)).unwrap(), 0x8F00);
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                negative: true,
+                ..Flags::default()
+            }
+        );
+
+        run(cpu, "eori.w #$8F00, ($2000).w");
+        assert_eq!(cpu.memory.read_word(Address(0x2000)).unwrap(), 0);
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                zero: true,
+                ..Flags::default()
+            }
+        );
+
+        // Only the immediate forms can be written with an `i`
+        assert!(matches!(
+            AssemblyInterpreter::new().parse("andi.w d0, ($2000).w".to_string()),
+            Err(ParseError::InvalidOperand { .. })
+        ));
+    }
+
+    #[test]
+    fn multiple_precision_add_with_extend() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(0x1000);
+        // 0x1_FFFFFFFF_FFFFFFFF + 0x2_00000000_00000001, most significant long first
+        for (i, (a, b)) in [(1, 2), (0xFFFF_FFFF, 0), (0xFFFF_FFFF, 1)]
+            .iter()
+            .enumerate()
+        {
+            cpu.memory
+                .write_long(Address(0x100 + 4 * i as u32), *a)
+                .unwrap();
+            cpu.memory
+                .write_long(Address(0x200 + 4 * i as u32), *b)
+                .unwrap();
+        }
+        let program = "addx.l -(a0), -(a1)\n".repeat(3);
+        for assembled in AssemblyInterpreter::new()
+            .assemble(&program, 0x400)
+            .unwrap()
+        {
+            let code = encode(&assembled.instruction, assembled.size).unwrap();
+            cpu.load_binary(assembled.address, code).unwrap();
+        }
+
+        // Start just past the end of each number, with X clear and Z set
+        cpu.registers
+            .set_address_register(AddressRegister::A0, 0x10Cu32);
+        cpu.registers
+            .set_address_register(AddressRegister::A1, 0x20Cu32);
+        cpu.registers.set(Register::ProgramCounter, 0x400u32);
+        cpu.registers.set_flags(Flags {
+            zero: true,
+            ..Flags::default()
+        });
+        cpu.registers.set_extend_flag(false);
+        for _ in 0..3 {
+            cpu.run_one_cycle().unwrap();
+        }
+
+        let sum: Vec<u32> = (0..3)
+            .map(|i| cpu.memory.read_long(Address(0x200 + 4 * i)).unwrap())
+            .collect();
+        assert_eq!(sum, vec![4, 0, 0]);
+        // Both registers are left pointing at the most significant long
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A0),
+            0x100
+        );
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A1),
+            0x200
+        );
+        assert!(!cpu.registers.get_extend_flag());
+        // Z was cleared by the nonzero high long, even though the last two longs were zero
+        assert!(!cpu.registers.get_flags().zero);
+    }
+
+    #[test]
+    fn sticky_zero_flag() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        // 0x0000_0001 + 0x0000_0000 as two words (d1:d0 + d3:d2), least significant first
+        cpu.registers.set_data_register(DataRegister::D0, 1_u32);
+        cpu.registers.set_flags(Flags {
+            zero: true,
+            ..Flags::default()
+        });
+        cpu.registers.set_extend_flag(false);
+
+        run(cpu, "addx.w d2, d0");
+        assert!(!cpu.registers.get_flags().zero);
+        // The high words add up to zero, but that doesn't set Z again
+        run(cpu, "addx.w d3, d1");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D1), 0);
+        assert!(!cpu.registers.get_flags().zero);
+
+        // If every part is zero, Z stays set
+        cpu.registers.set_data_register(DataRegister::D0, 0_u32);
+        cpu.registers.set_flags(Flags {
+            zero: true,
+            ..Flags::default()
+        });
+        run(cpu, "addx.w d2, d0");
+        run(cpu, "subx.w d3, d1");
+        run(cpu, "negx.w d1");
+        assert!(cpu.registers.get_flags().zero);
+    }
+
+    #[test]
+    fn add_with_extend_overflow() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        let (instruction, size, _) = AssemblyInterpreter::new()
+            .parse("addx.b d0, d1".to_string())
+            .unwrap();
+        for (src, dest, result, overflow, carry) in [
+            // Both steps overflow on their own (0x80 + 0xFF = 0x7F, then 0x7F + 1 = 0x80), but the sum doesn't
+            (0xFF, 0x80, 0x80, false, true),
+            // Only adding the extend bit overflows
+            (0x00, 0x7F, 0x80, true, false),
+            (0x3F, 0x40, 0x80, true, false),
+            (0x7F, 0x7F, 0xFF, true, false),
+            // Two negative numbers with a positive result
+            (0x80, 0x80, 0x01, true, true),
+        ] {
+            cpu.registers
+                .set_data_register(DataRegister::D0, src as u32);
+            cpu.registers
+                .set_data_register(DataRegister::D1, dest as u32);
+            cpu.registers.set_extend_flag(true);
+            instruction.execute(cpu, size).unwrap();
+            let flags = cpu.registers.get_flags();
+            assert_eq!(
+                cpu.registers.get_data_register(DataRegister::D1),
+                result,
+                "{:#X} + {:#X} + 1",
+                dest,
+                src
+            );
+            assert_eq!(flags.overflow, overflow, "{:#X} + {:#X} + 1", dest, src);
+            assert_eq!(flags.carry, carry, "{:#X} + {:#X} + 1", dest, src);
+        }
+    }
+
+    #[test]
+    fn subtract_with_extend() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        cpu.registers.set_data_register(DataRegister::D0, 5u32);
+        cpu.registers.set_data_register(DataRegister::D1, 5u32);
+        cpu.registers.set_flags(Flags {
+            zero: true,
+            ..Flags::default()
+        });
+        cpu.registers.set_extend_flag(true);
+
+        // 5 - 5 - X borrows
+        run(cpu, "subx.l d0, d1");
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D1),
+            0xFFFF_FFFF
+        );
+        assert!(cpu.registers.get_extend_flag());
+        assert!(cpu.registers.get_flags().negative);
+        assert!(!cpu.registers.get_flags().zero);
+
+        // Both steps overflow on their own (0x7F - 0xFF = 0x80, then 0x80 - 1 = 0x7F), but the difference doesn't
+        cpu.registers.set_data_register(DataRegister::D0, 0xFF_u32);
+        cpu.registers.set_data_register(DataRegister::D1, 0x7F_u32);
+        cpu.registers.set_extend_flag(true);
+        run(cpu, "subx.b d0, d1");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D1), 0x7F);
+        assert!(!cpu.registers.get_flags().overflow);
+        assert!(cpu.registers.get_flags().carry);
+        // Only subtracting the extend bit overflows
+        cpu.registers.set_data_register(DataRegister::D0, 0_u32);
+        cpu.registers.set_data_register(DataRegister::D1, 0x80_u32);
+        run(cpu, "subx.b d0, d1");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D1), 0x7F);
+        assert!(cpu.registers.get_flags().overflow);
+        assert!(!cpu.registers.get_flags().carry);
+
+        assert!(matches!(
+            AssemblyInterpreter::new().parse("addx (a0), d1".to_string()),
+            Err(ParseError::InvalidOperand { .. })
+        ));
+        assert!(matches!(
+            AssemblyInterpreter::new().parse("subx d0, -(a1)".to_string()),
+            Err(ParseError::InvalidOperand { .. })
+        ));
+    }
+
+    #[test]
+    fn arithmetic_sets_flags() {
+        let d0 = AddressMode::RegisterDirect {
+            register: Register::Data(DataRegister::D0),
+        };
+
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        cpu.registers.set_data_register(DataRegister::D0, 0xFF_u32);
+        Instruction::Add {
+            src: AddressMode::Immediate { value: 1 },
+            dest: d0.clone(),
+        }
+        .execute(cpu, OperandSize::Byte)
+        .unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0);
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                carry: true,
+                zero: true,
+                ..Flags::default()
+            }
+        );
+        assert!(cpu.registers.get_extend_flag());
+
+        cpu.registers.set_data_register(DataRegister::D0, 0x80_u32);
+        Instruction::Subtract {
+            src: AddressMode::Immediate { value: 1 },
+            dest: d0,
+        }
+        .execute(cpu, OperandSize::Byte)
+        .unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0x7F);
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                overflow: true,
+                ..Flags::default()
+            }
+        );
+        assert!(!cpu.registers.get_extend_flag());
+
+        // ADDA leaves the flags alone
+        Instruction::Add {
+            src: AddressMode::Immediate { value: 0xFFFFFFFF },
+            dest: AddressMode::RegisterDirect {
+                register: Register::Address(AddressRegister::A0),
+            },
+        }
+        .execute(cpu, OperandSize::Long)
+        .unwrap();
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                overflow: true,
+                ..Flags::default()
+            }
+        );
+    }
+
+    #[test]
+    fn long_arithmetic_flag_edge_cases() {
+        let d0 = AddressMode::RegisterDirect {
+            register: Register::Data(DataRegister::D0),
+        };
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        // (is_add, d0 before, operand, d0 after, XNZVC flags set after)
+        for (is_add, before, operand, after, set) in [
+            (true, 0x7FFF_FFFFu32, 1, 0x8000_0000u32, FlagMask(0b01010)),
+            (true, 0xFFFF_FFFF, 1, 0, FlagMask(0b10101)),
+            (true, 0x8000_0000, 0x8000_0000, 0, FlagMask(0b10111)),
+            (true, 0x7FFF_FFFF, 0, 0x7FFF_FFFF, FlagMask::NONE),
+            (false, 0x8000_0000, 1, 0x7FFF_FFFF, FlagMask(0b00010)),
+            (false, 0, 1, 0xFFFF_FFFF, FlagMask(0b11001)),
+            (false, 5, 5, 0, FlagMask(0b00100)),
+        ] {
+            cpu.registers.set_ccr_flag(FlagMask::XNZVC, false);
+            cpu.registers.set_data_register(DataRegister::D0, before);
+            let src = AddressMode::Immediate { value: operand };
+            let instruction = if is_add {
+                Instruction::Add {
+                    src,
+                    dest: d0.clone(),
+                }
+            } else {
+                Instruction::Subtract {
+                    src,
+                    dest: d0.clone(),
+                }
+            };
+            instruction.execute(cpu, OperandSize::Long).unwrap();
+
+            assert_eq!(cpu.registers.get_data_register(DataRegister::D0), after);
+            for flag in [
+                FlagMask::EXTEND,
+                FlagMask::NEGATIVE,
+                FlagMask::ZERO,
+                FlagMask::OVERFLOW,
+                FlagMask::CARRY,
+            ] {
+                assert_eq!(
+                    cpu.registers.get_ccr_flag(flag),
+                    set.0 & flag.0 != 0,
+                    "{:?} {:#X}, {:#X}: {:?}",
+                    instruction,
+                    before,
+                    operand,
+                    flag
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn compare() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0x1234_u32);
+        cpu.registers.set_extend_flag(true);
+        let compare = |src, dest| Instruction::Compare {
+            src: AddressMode::Immediate { value: src },
+            dest,
+        };
+        let d0 = AddressMode::RegisterDirect {
+            register: Register::Data(DataRegister::D0),
+        };
+
+        compare(0x1234, d0.clone())
+            .execute(cpu, OperandSize::Long)
+            .unwrap();
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                zero: true,
+                ..Flags::default()
+            }
+        );
+        // Neither operand changes, and neither does the extend flag
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0x1234);
+        assert!(cpu.registers.get_extend_flag());
+
+        // 0x1234 - 0x1235 borrows
+        compare(0x1235, d0.clone())
+            .execute(cpu, OperandSize::Word)
+            .unwrap();
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                carry: true,
+                negative: true,
+                ..Flags::default()
+            }
+        );
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0x1234);
+
+        // Only the low byte is compared
+        compare(0x34, d0).execute(cpu, OperandSize::Byte).unwrap();
+        assert!(cpu.registers.get_flags().zero);
+
+        // CMPA sign-extends a word source and compares the whole register
+        cpu.registers
+            .set_address_register(AddressRegister::A0, 0xFFFF_FFFF_u32);
+        compare(
+            0xFFFF,
+            AddressMode::RegisterDirect {
+                register: Register::Address(AddressRegister::A0),
+            },
+        )
+        .execute(cpu, OperandSize::Word)
+        .unwrap();
+        assert!(cpu.registers.get_flags().zero);
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A0),
+            0xFFFF_FFFF
+        );
+    }
+
+    #[test]
+    fn compare_memory() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        cpu.memory
+            .write_bytes(Address(0x100), b"hello".to_vec())
+            .unwrap();
+        cpu.memory
+            .write_bytes(Address(0x200), b"help!".to_vec())
+            .unwrap();
+        cpu.registers
+            .set_address_register(AddressRegister::A0, 0x100_u32);
+        cpu.registers
+            .set_address_register(AddressRegister::A1, 0x200_u32);
+        let instruction = Instruction::CompareMemory {
+            src: AddressRegister::A0,
+            dest: AddressRegister::A1,
+        };
+
+        for (expected_a0, zero) in [(0x101, true), (0x102, true), (0x103, true), (0x104, false)] {
+            instruction.execute(cpu, OperandSize::Byte).unwrap();
+            assert_eq!(cpu.registers.get_flags().zero, zero);
+            assert_eq!(
+                cpu.registers.get_address_register(AddressRegister::A0),
+                expected_a0
+            );
+            assert_eq!(
+                cpu.registers.get_address_register(AddressRegister::A1),
+                expected_a0 + 0x100
+            );
+        }
+        // 'p' - 'l' is positive
+        assert_eq!(cpu.registers.get_flags(), Flags::default());
+
+        // Both pointers advance by the operand size
+        instruction.execute(cpu, OperandSize::Word).unwrap();
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A0),
+            0x106
+        );
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A1),
+            0x206
+        );
+    }
+
+    #[test]
+    fn load_effective_address_pc_relative() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        cpu.registers.set(Register::ProgramCounter, ADDRESS);
+        let instruction = Instruction::LoadEffectiveAddress {
+            src: AddressMode::ProgramCounterIndirectWithDisplacement { displacement: 0x10 },
+            dest: AddressMode::RegisterDirect {
+                register: Register::Address(AddressRegister::A0),
+            },
+        };
+
+        instruction.execute(cpu, OperandSize::Long).unwrap();
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A0),
+            ADDRESS + 2 + 0x10
+        );
+    }
+
+    #[test]
+    fn load_effective_address() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        cpu.registers
+            .set_address_register(AddressRegister::A0, 0x200_u32);
+        cpu.registers
+            .set_data_register(DataRegister::D1, 0xFFFF_0003_u32);
+        let a1 = AddressMode::RegisterDirect {
+            register: Register::Address(AddressRegister::A1),
+        };
+
+        for (src, address) in [
+            (
+                AddressMode::RegisterIndirectWithDisplacement {
+                    register: AddressRegister::A0,
+                    displacement: 0x10,
+                },
+                0x210,
+            ),
+            (
+                AddressMode::RegisterIndirectWithDisplacement {
+                    register: AddressRegister::A0,
+                    displacement: -8_i16 as u16,
+                },
+                0x1F8,
+            ),
+            // A word index only uses the low word of the register
+            (
+                AddressMode::RegisterIndirectIndexed {
+                    displacement: 4,
+                    address_register: AddressRegister::A0,
+                    index_register: Register::Data(DataRegister::D1),
+                    index_size: OperandSize::Word,
+                    index_scale: IndexScale::One,
+                },
+                0x207,
+            ),
+            (
+                AddressMode::RegisterIndirectIndexed {
+                    displacement: 0,
+                    address_register: AddressRegister::A0,
+                    index_register: Register::Address(AddressRegister::A0),
+                    index_size: OperandSize::Long,
+                    index_scale: IndexScale::One,
+                },
+                0x400,
+            ),
+        ] {
+            Instruction::LoadEffectiveAddress {
+                src: src.clone(),
+                dest: a1.clone(),
+            }
+            .execute(cpu, OperandSize::Long)
+            .unwrap();
+            assert_eq!(
+                cpu.registers.get_address_register(AddressRegister::A1),
+                address,
+                "{:?}",
+                src
+            );
+        }
+
+        // Registers and immediates aren't in memory, so they don't have an address
+        for src in [
+            AddressMode::RegisterDirect {
+                register: Register::Data(DataRegister::D1),
+            },
+            AddressMode::Immediate { value: 0x100 },
+        ] {
+            let instruction = Instruction::LoadEffectiveAddress {
+                src,
+                dest: a1.clone(),
+            };
+            assert!(matches!(
+                instruction.execute(cpu, OperandSize::Long),
+                Err(EmulationError::NoEffectiveAddress(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn push_effective_address_pc_relative() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        cpu.registers.set(Register::ProgramCounter, ADDRESS);
+        cpu.registers
+            .set_address_register(AddressRegister::A7, 0x200_u32);
+        let instruction = Instruction::PushEffectiveAddress {
+            src: AddressMode::ProgramCounterIndirectWithDisplacement { displacement: 0x10 },
+        };
+
+        instruction.execute(cpu, OperandSize::Long).unwrap();
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A7),
+            0x1FC
+        );
+        assert_eq!(
+            cpu.memory.read_long(Address(0x1FC)).unwrap(),
+            ADDRESS + 2 + 0x10
+        );
+    }
+
+    #[test]
+    fn assembled_load_effective_address_finds_label() {
+        let program = AssemblyInterpreter::new()
+            .assemble("lea (msg, pc), a0\nmove d0, d1\nmsg: move d1, d2", 0x100)
+            .unwrap();
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        cpu.registers
+            .set(Register::ProgramCounter, program[0].address);
+
+        program[0]
+            .instruction
+            .execute(cpu, program[0].size)
+            .unwrap();
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A0),
+            program[2].address
+        );
+    }
+
+    #[test]
+    fn move_quick_sign_extends() {
+        for (value, result) in [
+            (0x7F, 0x0000007F),
+            (0x80, 0xFFFFFF80),
+            (0xFFFFFFFF, 0xFFFFFFFF),
+        ] {
+            let mut cpu = CPU::<VecBackedMemory>::new(1024);
+            let dest = AddressMode::RegisterDirect {
+                register: Register::Data(DataRegister::D0),
+            };
+            let instruction = Instruction::MoveQuick {
+                src: AddressMode::Immediate { value },
+                dest,
+            };
+
+            instruction.execute(&mut cpu, OperandSize::Long).unwrap();
+            assert_eq!(cpu.registers.get_data_register(DataRegister::D0), result);
+        }
+    }
+
+    #[test]
+    fn move_quick_is_always_long() {
+        let instruction = Instruction::MoveQuick {
+            src: AddressMode::Immediate { value: 0x80 },
+            dest: AddressMode::RegisterDirect {
+                register: Register::Data(DataRegister::D0),
+            },
+        };
+        assert_eq!(instruction.intrinsic_size(), Some(OperandSize::Long));
+
+        for size in [OperandSize::Byte, OperandSize::Word, OperandSize::Long] {
+            let mut cpu = CPU::<VecBackedMemory>::new(1024);
+            cpu.registers
+                .set_data_register(DataRegister::D0, 0x12345678_u32);
+
+            instruction.execute(&mut cpu, size).unwrap();
+            assert_eq!(
+                cpu.registers.get_data_register(DataRegister::D0),
+                0xFFFFFF80,
+                "{:?}",
+                size
+            );
+        }
+
+        // All of the upper bits are replaced, whether it's assembled or decoded from machine code
+        let (instruction, size, _) = AssemblyInterpreter::new()
+            .parse("moveq #-1, d0".to_string())
+            .unwrap();
+        let mut cpu = CPU::<VecBackedMemory>::new(1024);
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0x1234_0000_u32);
+        instruction.execute(&mut cpu, size).unwrap();
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0),
+            0xFFFF_FFFF
+        );
+
+        let mut cpu = CPU::<VecBackedMemory>::new(1024);
+        cpu.memory
+            .write_bytes(Address(0x100), vec![0x70, 0xFF]) // moveq #-1, d0
+            .unwrap();
+        cpu.registers.set(Register::ProgramCounter, 0x100_u32);
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0x1234_0000_u32);
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0),
+            0xFFFF_FFFF
+        );
+    }
+
+    #[test]
+    fn unimplemented_instruction_is_an_error() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        let instruction = Instruction::Trap { vector: 0 };
+
+        match instruction.execute(cpu, OperandSize::Word) {
+            Err(EmulationError::UnimplementedInstruction(name)) => assert!(name.contains("Trap")),
+            other => panic!("expected UnimplementedInstruction, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn no_op() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        let instruction = Instruction::NoOp;
+
+        let initial_state = format!("{}", cpu);
+        instruction.execute(cpu, OperandSize::Long).unwrap();
+        assert_eq!(format!("{}", cpu), initial_state);
+    }
+
+    /// Runs a bit-field instruction on a 68020 with `D0` as the field's base register
+    fn run_bit_field(instruction: Instruction, d0: u32, d1: u32) -> CPU<VecBackedMemory> {
+        let mut cpu = CPU::<VecBackedMemory>::new(1024);
+        cpu.model = CpuModel::M68020;
+        cpu.registers.set_data_register(DataRegister::D0, d0);
+        cpu.registers.set_data_register(DataRegister::D1, d1);
+        instruction.execute(&mut cpu, OperandSize::Long).unwrap();
+        cpu
+    }
+
+    #[test]
+    fn bit_field_extract() {
+        let d0 = AddressMode::RegisterDirect {
+            register: Register::Data(DataRegister::D0),
+        };
+        let d1 = AddressMode::RegisterDirect {
+            register: Register::Data(DataRegister::D1),
+        };
+
+        // Bits 3-7 of 0xB5 (0b101_10101) are 0b10101
+        let cpu = run_bit_field(
+            Instruction::BitFieldExtractUnsigned {
+                base: d0.clone(),
+                offset: BitFieldParameter::Immediate(3),
+                width: BitFieldParameter::Immediate(5),
+                dest: d1.clone(),
+            },
+            0xB5C3_1234,
+            0,
+        );
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D1), 0b10101);
+
+        let cpu = run_bit_field(
+            Instruction::BitFieldExtractSigned {
+                base: d0.clone(),
+                offset: BitFieldParameter::Immediate(3),
+                width: BitFieldParameter::Immediate(5),
+                dest: d1.clone(),
+            },
+            0xB5C3_1234,
+            0,
+        );
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D1),
+            -11_i32 as u32
+        );
+
+        // Fields in a register wrap around, and a width of 0 means 32
+        let cpu = run_bit_field(
+            Instruction::BitFieldExtractUnsigned {
+                base: d0,
+                offset: BitFieldParameter::Immediate(28),
+                width: BitFieldParameter::Immediate(0),
+                dest: d1,
+            },
+            0xB5C3_1234,
+            0,
+        );
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D1),
+            0x4B5C_3123
+        );
+    }
+
+    #[test]
+    fn bit_field_extract_from_memory() {
+        let mut cpu = CPU::<VecBackedMemory>::new(1024);
+        cpu.model = CpuModel::M68020;
+        cpu.memory
+            .write_long(Address(ADDRESS), 0xB5C3_1234)
+            .unwrap();
+        cpu.registers.set_data_register(DataRegister::D2, 11_u32);
+
+        // The field spans two bytes: bits 3-7 of 0xC3 and bits 0-5 of 0x12
+        Instruction::BitFieldExtractUnsigned {
+            base: AddressMode::Absolute {
+                address: ADDRESS + 1,
+            },
+            offset: BitFieldParameter::Immediate(3),
+            width: BitFieldParameter::Register(DataRegister::D2),
+            dest: AddressMode::RegisterDirect {
+                register: Register::Data(DataRegister::D1),
+            },
+        }
+        .execute(&mut cpu, OperandSize::Long)
+        .unwrap();
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D1),
+            0b000_1100_0100
+        );
+    }
+
+    #[test]
+    fn bit_field_insert() {
+        let instruction = |base| Instruction::BitFieldInsert {
+            src: AddressMode::RegisterDirect {
+                register: Register::Data(DataRegister::D1),
+            },
+            base,
+            offset: BitFieldParameter::Immediate(3),
+            width: BitFieldParameter::Immediate(5),
+        };
+
+        // Only the low 5 bits of D1 are inserted
+        let cpu = run_bit_field(
+            instruction(AddressMode::RegisterDirect {
+                register: Register::Data(DataRegister::D0),
+            }),
+            0xB5C3_1234,
+            0xFFFF_FF0A,
+        );
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0),
+            0xAAC3_1234
+        );
+
+        let mut cpu = CPU::<VecBackedMemory>::new(1024);
+        cpu.model = CpuModel::M68020;
+        cpu.memory
+            .write_long(Address(ADDRESS), 0xB5C3_1234)
+            .unwrap();
+        cpu.registers.set_data_register(DataRegister::D1, 0x0A_u32);
+        instruction(AddressMode::Absolute { address: ADDRESS })
+            .execute(&mut cpu, OperandSize::Long)
+            .unwrap();
+        assert_eq!(cpu.memory.read_long(Address(ADDRESS)).unwrap(), 0xAAC3_1234);
+    }
+
+    #[test]
+    fn bit_field_requires_68020() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        let instruction = Instruction::BitFieldExtractUnsigned {
+            base: AddressMode::Absolute { address: ADDRESS },
+            offset: BitFieldParameter::Immediate(0),
+            width: BitFieldParameter::Immediate(8),
+            dest: AddressMode::RegisterDirect {
+                register: Register::Data(DataRegister::D1),
+            },
+        };
+
+        match instruction.execute(cpu, OperandSize::Long) {
+            Err(EmulationError::UnsupportedOnModel { model, .. }) => {
+                assert_eq!(model, CpuModel::M68000)
+            }
+            other => panic!("expected UnsupportedOnModel, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn move_alternate_space() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        let (instruction, size, _) = AssemblyInterpreter::new()
+            .parse(format!("moves.l d0, (${:X})", ADDRESS))
+            .unwrap();
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0xCAFE_F00D_u32);
+
+        match instruction.execute(cpu, size) {
+            Err(EmulationError::UnsupportedOnModel { model, .. }) => {
+                assert_eq!(model, CpuModel::M68000)
+            }
+            other => panic!("expected UnsupportedOnModel, got {:?}", other),
+        }
+        assert_eq!(cpu.memory.read_long(Address(ADDRESS)).unwrap(), 0);
+
+        cpu.model = CpuModel::M68010;
+        instruction.execute(cpu, size).unwrap();
+        assert_eq!(cpu.memory.read_long(Address(ADDRESS)).unwrap(), 0xCAFE_F00D);
+
+        let (instruction, size, _) = AssemblyInterpreter::new()
+            .parse(format!("moves.w (${:X}), d1", ADDRESS + 2))
+            .unwrap();
+        instruction.execute(cpu, size).unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D1), 0xF00D);
+
+        // User mode can't use MOVES
+        cpu.registers.set_status_register(0x0000);
+        assert!(matches!(
+            instruction.execute(cpu, size),
+            Err(EmulationError::PrivilegeViolation(_))
+        ));
+    }
+
+    #[test]
+    fn condition_codes_round_trip() {
+        for condition in Condition::ALL {
+            assert_eq!(
+                condition.to_string().parse::<Condition>().unwrap(),
+                condition
+            );
+        }
+
+        assert_eq!(Condition::True.to_string(), "t");
+        assert_eq!(Condition::False.to_string(), "f");
+        assert_eq!(Condition::Equal.to_string(), "eq");
+        assert_eq!("hs".parse::<Condition>().unwrap(), Condition::CarryClear);
+        assert_eq!("lo".parse::<Condition>().unwrap(), Condition::CarrySet);
+        assert!(matches!(
+            "ra".parse::<Condition>(),
+            Err(ParseError::UnknownCondition(_))
+        ));
+    }
+
+    #[test]
+    fn condition_evaluation() {
+        // (N, Z, V, C) for each CCR state, then the conditions that hold in it, in encoding order from HI
+        let flags = |negative, zero, overflow, carry| Flags {
+            carry,
+            overflow,
+            zero,
+            negative,
+        };
+        for (ccr, expected) in [
+            // hi ls cc cs ne eq vc vs pl mi ge lt gt le
+            (
+                flags(false, false, false, false),
+                "1 0 1 0 1 0 1 0 1 0 1 0 1 0",
+            ),
+            (
+                flags(false, true, false, false),
+                "0 1 1 0 0 1 1 0 1 0 1 0 0 1",
+            ),
+            (
+                flags(false, false, false, true),
+                "0 1 0 1 1 0 1 0 1 0 1 0 1 0",
+            ),
+            (
+                flags(true, false, false, false),
+                "1 0 1 0 1 0 1 0 0 1 0 1 0 1",
+            ),
+            (
+                flags(false, false, true, false),
+                "1 0 1 0 1 0 0 1 1 0 0 1 0 1",
+            ),
+            (
+                flags(true, false, true, false),
+                "1 0 1 0 1 0 0 1 0 1 1 0 1 0",
+            ),
+        ] {
+            assert!(Condition::True.evaluate(ccr));
+            assert!(!Condition::False.evaluate(ccr));
+            for (condition, expected) in Condition::ALL[2..].iter().zip(expected.split(' ')) {
+                assert_eq!(
+                    condition.evaluate(ccr),
+                    expected == "1",
+                    "{} with {:?}",
+                    condition,
+                    ccr
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn branch_conditional() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        let beq = |displacement| Instruction::BranchConditional {
+            condition: Condition::Equal,
+            displacement,
+        };
+        cpu.registers.set(Register::ProgramCounter, 0x100_u32);
+
+        // Not taken: the PC is left for run_one_cycle to advance
+        beq(0x20).execute(cpu, OperandSize::Byte).unwrap();
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), 0x100);
+        assert_eq!(beq(0x20).branch_target(cpu).unwrap(), None);
+
+        // Taken: relative to the word after the opcode
+        cpu.registers.set_flags(Flags {
+            zero: true,
+            ..Flags::default()
+        });
+        beq(0x20).execute(cpu, OperandSize::Byte).unwrap();
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), 0x122);
+        beq(-0x22).execute(cpu, OperandSize::Word).unwrap();
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), 0x102);
+    }
+
+    #[test]
+    fn decrement_branch() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        let dbeq = Instruction::DecrementBranch {
+            condition: Condition::Equal,
+            register: DataRegister::D0,
+            displacement: -0x22,
+        };
+        cpu.registers.set(Register::ProgramCounter, 0x100_u32);
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0x1234_0001_u32);
+
+        // Condition false: only the low word counts down, and it branches until that reaches -1
+        assert_eq!(dbeq.branch_target(cpu).unwrap(), Some(0xE0));
+        dbeq.execute(cpu, OperandSize::Word).unwrap();
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), 0xE0);
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0),
+            0x1234_0000
+        );
+        assert_eq!(dbeq.branch_target(cpu).unwrap(), None);
+        dbeq.execute(cpu, OperandSize::Word).unwrap();
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), 0xE0);
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0),
+            0x1234_FFFF
+        );
+
+        // Condition true: falls through without touching the counter
+        cpu.registers.set_flags(Flags {
+            zero: true,
+            ..Flags::default()
+        });
+        assert_eq!(dbeq.branch_target(cpu).unwrap(), None);
+        dbeq.execute(cpu, OperandSize::Word).unwrap();
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), 0xE0);
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0),
+            0x1234_FFFF
+        );
+    }
+
+    #[test]
+    fn branch_always() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        cpu.registers.set(Register::ProgramCounter, 0x100_u32);
+
+        Instruction::Branch {
+            displacement: -0x42,
+        }
+        .execute(cpu, OperandSize::Word)
+        .unwrap();
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), 0xC0);
+        assert_eq!(
+            Instruction::Branch { displacement: 6 }
+                .branch_target(cpu)
+                .unwrap(),
+            Some(0xC8)
+        );
+    }
+
+    #[test]
+    fn branch_to_subroutine() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        cpu.memory
+            .write_bytes(
+                Address(0x100),
+                vec![
+                    0x61, 0x40, // bsr.s *+$42
+                ],
+            )
+            .unwrap();
+        cpu.memory
+            .write_bytes(
+                Address(0x142),
+                vec![
+                    0x61, 0x00, 0xFF, 0x80, // bsr.w *-$7E
+                ],
+            )
+            .unwrap();
+        cpu.registers.set(Register::ProgramCounter, 0x100_u32);
+        cpu.registers
+            .set_address_register(AddressRegister::A7, 0x200_u32);
+
+        // With a byte displacement, the next instruction is right after the opcode
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), 0x142);
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A7),
+            0x1FC
+        );
+        assert_eq!(cpu.memory.read_long(Address(0x1FC)).unwrap(), 0x102);
+
+        // With a word displacement, it's after the extension word
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), 0xC4);
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A7),
+            0x1F8
+        );
+        assert_eq!(cpu.memory.read_long(Address(0x1F8)).unwrap(), 0x146);
+        assert_eq!(cpu.memory.read_long(Address(0x1FC)).unwrap(), 0x102);
+    }
+
+    #[test]
+    fn stack_stays_word_aligned() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        cpu.registers
+            .set_address_register(AddressRegister::A7, 0x200_u32);
+
+        // A byte takes up a whole word, in its upper half
+        push(cpu, M68kInteger::Byte(0xAB)).unwrap();
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A7),
+            0x1FE
+        );
+        assert_eq!(cpu.memory.read_byte(Address(0x1FE)).unwrap(), 0xAB);
+        assert_eq!(cpu.memory.read_byte(Address(0x1FF)).unwrap(), 0);
+
+        push(cpu, M68kInteger::Word(0x1234)).unwrap();
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A7),
+            0x1FC
+        );
+        assert_eq!(
+            pop(cpu, OperandSize::Word).unwrap(),
+            M68kInteger::Word(0x1234)
+        );
+        assert_eq!(
+            pop(cpu, OperandSize::Byte).unwrap(),
+            M68kInteger::Byte(0xAB)
+        );
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A7),
+            0x200
+        );
+
+        // Words and longs can't go through an odd stack pointer
+        cpu.registers
+            .set_address_register(AddressRegister::A7, 0x201_u32);
+        cpu.registers.set(Register::ProgramCounter, 0x100_u32);
+        assert!(matches!(
+            Instruction::BranchToSubroutine { displacement: 0x40 }.execute(cpu, OperandSize::Byte),
+            Err(EmulationError::OddStackPointer(Address(0x201)))
+        ));
+        assert!(matches!(
+            Instruction::ReturnFromSubroutine.execute(cpu, OperandSize::Long),
+            Err(EmulationError::OddStackPointer(Address(0x201)))
+        ));
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A7),
+            0x201
+        );
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), 0x100);
+    }
+
+    #[test]
+    fn add_decimal_digits() {
+        for (src, dest, extend, expected) in [
+            (0x00, 0x00, false, (0x00, false)),
+            (0x00, 0x00, true, (0x01, false)),
+            (0x19, 0x01, false, (0x20, false)),
+            (0x45, 0x55, false, (0x00, true)),
+            (0x99, 0x99, true, (0x99, true)),
+            (0x50, 0x50, false, (0x00, true)),
+            // Invalid BCD digits are adjusted like on the hardware
+            (0x0A, 0x00, false, (0x10, false)),
+        ] {
+            assert_eq!(
+                add_decimal(src, dest, extend),
+                expected,
+                "{:02X} + {:02X} + {}",
+                src,
+                dest,
+                extend
+            );
+        }
+    }
+
+    #[test]
+    fn add_decimal_flags_and_memory_form() {
+        // 1999 + 0001 = 2000, one byte at a time from the least significant end
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        cpu.memory.write_word(Address(0x100), 0x1999).unwrap();
+        cpu.memory.write_word(Address(0x200), 0x0001).unwrap();
+        cpu.registers
+            .set_address_register(AddressRegister::A0, 0x202_u32);
+        cpu.registers
+            .set_address_register(AddressRegister::A1, 0x102_u32);
+        cpu.registers.set_ccr_flag(FlagMask::EXTEND, false);
+        cpu.registers.set_ccr_flag(FlagMask::ZERO, true);
+        let instruction = Instruction::AddBCD {
+            src: AddressMode::RegisterIndirectPreDecrement {
+                register: AddressRegister::A0,
+            },
+            dest: AddressMode::RegisterIndirectPreDecrement {
+                register: AddressRegister::A1,
+            },
+        };
+
+        instruction.execute(cpu, OperandSize::Byte).unwrap();
+        assert_eq!(cpu.memory.read_byte(Address(0x101)).unwrap(), 0x00);
+        assert!(cpu.registers.get_ccr_flag(FlagMask::CARRY));
+        assert!(cpu.registers.get_ccr_flag(FlagMask::EXTEND));
+        // A zero result doesn't set Z, but it was already set
+        assert!(cpu.registers.get_ccr_flag(FlagMask::ZERO));
+
+        instruction.execute(cpu, OperandSize::Byte).unwrap();
+        assert_eq!(cpu.memory.read_word(Address(0x100)).unwrap(), 0x2000);
+        assert!(!cpu.registers.get_ccr_flag(FlagMask::CARRY));
+        assert!(!cpu.registers.get_ccr_flag(FlagMask::EXTEND));
+        assert!(!cpu.registers.get_ccr_flag(FlagMask::ZERO));
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A0),
+            0x200
+        );
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A1),
+            0x100
+        );
+
+        // Z is never set, only cleared
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        cpu.registers.set_ccr_flag(FlagMask::ZERO, false);
+        Instruction::AddBCD {
+            src: AddressMode::data(DataRegister::D0),
+            dest: AddressMode::data(DataRegister::D1),
+        }
+        .execute(cpu, OperandSize::Byte)
+        .unwrap();
+        assert!(!cpu.registers.get_ccr_flag(FlagMask::ZERO));
+    }
+
+    #[test]
+    fn subtract_decimal_digits() {
+        for (src, dest, extend, expected) in [
+            (0x00, 0x00, false, (0x00, false)),
+            (0x13, 0x34, false, (0x21, false)),
+            (0x25, 0x50, true, (0x24, false)),
+            (0x00, 0x00, true, (0x99, true)),
+            (0x99, 0x00, false, (0x01, true)),
+            (0x40, 0x20, false, (0x80, true)),
+        ] {
+            assert_eq!(
+                subtract_decimal(src, dest, extend),
+                expected,
+                "{:02X} - {:02X} - {}",
+                dest,
+                src,
+                extend
+            );
+        }
+    }
+
+    #[test]
+    fn subtract_and_negate_decimal() {
+        // 2000 - 0001 = 1999, one byte at a time from the least significant end
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        cpu.memory.write_word(Address(0x100), 0x2000).unwrap();
+        cpu.memory.write_word(Address(0x200), 0x0001).unwrap();
+        cpu.registers
+            .set_address_register(AddressRegister::A0, 0x202_u32);
+        cpu.registers
+            .set_address_register(AddressRegister::A1, 0x102_u32);
+        cpu.registers.set_ccr_flag(FlagMask::EXTEND, false);
+        cpu.registers.set_ccr_flag(FlagMask::ZERO, true);
+        let instruction = Instruction::SubtractBCD {
+            src: AddressMode::RegisterIndirectPreDecrement {
+                register: AddressRegister::A0,
+            },
+            dest: AddressMode::RegisterIndirectPreDecrement {
+                register: AddressRegister::A1,
+            },
+        };
+        instruction.execute(cpu, OperandSize::Byte).unwrap();
+        assert_eq!(cpu.memory.read_byte(Address(0x101)).unwrap(), 0x99);
+        assert!(cpu.registers.get_ccr_flag(FlagMask::CARRY));
+        assert!(cpu.registers.get_ccr_flag(FlagMask::EXTEND));
+        assert!(!cpu.registers.get_ccr_flag(FlagMask::ZERO));
+        instruction.execute(cpu, OperandSize::Byte).unwrap();
+        assert_eq!(cpu.memory.read_word(Address(0x100)).unwrap(), 0x1999);
+        assert!(!cpu.registers.get_ccr_flag(FlagMask::CARRY));
+        assert!(!cpu.registers.get_ccr_flag(FlagMask::EXTEND));
+
+        // NBCD subtracts from zero: 100 - 21 = 79, with a borrow
+        let d0 = AddressMode::data(DataRegister::D0);
+        cpu.registers.set_data_register(DataRegister::D0, 0x21_u32);
+        let negate = Instruction::NegateBCD { dest: d0 };
+        negate.execute(cpu, OperandSize::Byte).unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0x79);
+        assert!(cpu.registers.get_ccr_flag(FlagMask::CARRY));
+        assert!(cpu.registers.get_ccr_flag(FlagMask::EXTEND));
+
+        // With X set, negating zero borrows too, giving 99
+        cpu.registers.set_data_register(DataRegister::D0, 0_u32);
+        negate.execute(cpu, OperandSize::Byte).unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0x99);
+        assert!(cpu.registers.get_ccr_flag(FlagMask::CARRY));
+
+        // Without X, zero stays zero with no borrow, and Z is left alone
+        cpu.registers.set_data_register(DataRegister::D0, 0_u32);
+        cpu.registers.set_ccr_flag(FlagMask::EXTEND, false);
+        cpu.registers.set_ccr_flag(FlagMask::ZERO, true);
+        negate.execute(cpu, OperandSize::Byte).unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0);
+        assert!(!cpu.registers.get_ccr_flag(FlagMask::CARRY));
+        assert!(cpu.registers.get_ccr_flag(FlagMask::ZERO));
+
+        // The memory form writes back to the byte it read
+        cpu.memory.write_byte(Address(0x2FF), 0x01).unwrap();
+        cpu.registers
+            .set_address_register(AddressRegister::A2, 0x300_u32);
+        Instruction::NegateBCD {
+            dest: AddressMode::RegisterIndirectPreDecrement {
+                register: AddressRegister::A2,
+            },
+        }
+        .execute(cpu, OperandSize::Byte)
+        .unwrap();
+        assert_eq!(cpu.memory.read_byte(Address(0x2FF)).unwrap(), 0x99);
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A2),
+            0x2FF
+        );
+    }
+
+    #[test]
+    fn bit_instructions_on_registers() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        let d0 = AddressMode::data(DataRegister::D0);
+        let bit = |number| AddressMode::Immediate { value: number };
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0x8000_0001_u32);
+
+        // Z is the inverse of the bit being tested
+        Instruction::TestBit {
+            bit_number: bit(31),
+            dest: d0.clone(),
+        }
+        .execute(cpu, OperandSize::Long)
+        .unwrap();
+        assert!(!cpu.registers.get_ccr_flag(FlagMask::ZERO));
+        // Bit numbers wrap around modulo 32, so bit 33 is bit 1
+        Instruction::SetBit {
+            bit_number: bit(33),
+            dest: d0.clone(),
+        }
+        .execute(cpu, OperandSize::Long)
+        .unwrap();
+        assert!(cpu.registers.get_ccr_flag(FlagMask::ZERO));
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0),
+            0x8000_0003
+        );
+
+        // A data register can hold the bit number
+        cpu.registers.set_data_register(DataRegister::D1, 31_u32);
+        Instruction::ClearBit {
+            bit_number: AddressMode::data(DataRegister::D1),
+            dest: d0.clone(),
+        }
+        .execute(cpu, OperandSize::Long)
+        .unwrap();
+        assert!(!cpu.registers.get_ccr_flag(FlagMask::ZERO));
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 3);
+
+        let change = Instruction::ChangeBit {
+            bit_number: bit(16),
+            dest: d0,
+        };
+        change.execute(cpu, OperandSize::Long).unwrap();
+        assert!(cpu.registers.get_ccr_flag(FlagMask::ZERO));
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0x1_0003);
+        change.execute(cpu, OperandSize::Long).unwrap();
+        assert!(!cpu.registers.get_ccr_flag(FlagMask::ZERO));
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 3);
+        // Only Z is affected
+        assert!(!cpu.registers.get_ccr_flag(FlagMask::NEGATIVE));
+        assert_eq!(change.affected_flags(), FlagMask::ZERO);
+    }
+
+    #[test]
+    fn bit_instructions_on_memory() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        let a0 = AddressMode::RegisterIndirect {
+            register: AddressRegister::A0,
+        };
+        cpu.memory.write_word(Address(0x100), 0x0080).unwrap();
+        cpu.registers
+            .set_address_register(AddressRegister::A0, 0x100_u32);
+
+        // Only a byte is accessed, so bit numbers wrap around modulo 8: bit 15 is bit 7 of the byte at $100
+        let test = Instruction::TestBit {
+            bit_number: AddressMode::Immediate { value: 15 },
+            dest: a0.clone(),
+        };
+        assert_eq!(test.intrinsic_size(), Some(OperandSize::Byte));
+        test.execute(cpu, OperandSize::Byte).unwrap();
+        assert!(cpu.registers.get_ccr_flag(FlagMask::ZERO));
+
+        Instruction::SetBit {
+            bit_number: AddressMode::Immediate { value: 15 },
+            dest: a0.clone(),
+        }
+        .execute(cpu, OperandSize::Byte)
+        .unwrap();
+        assert_eq!(cpu.memory.read_word(Address(0x100)).unwrap(), 0x8080);
+
+        Instruction::ClearBit {
+            bit_number: AddressMode::Immediate { value: 7 },
+            dest: AddressMode::Absolute { address: 0x101 },
+        }
+        .execute(cpu, OperandSize::Byte)
+        .unwrap();
+        assert!(!cpu.registers.get_ccr_flag(FlagMask::ZERO));
+        assert_eq!(cpu.memory.read_word(Address(0x100)).unwrap(), 0x8000);
+
+        // The write goes back to the byte that was read
+        cpu.registers
+            .set_address_register(AddressRegister::A1, 0x102_u32);
+        Instruction::ChangeBit {
+            bit_number: AddressMode::Immediate { value: 0 },
+            dest: AddressMode::RegisterIndirectPreDecrement {
+                register: AddressRegister::A1,
+            },
+        }
+        .execute(cpu, OperandSize::Byte)
+        .unwrap();
+        assert!(cpu.registers.get_ccr_flag(FlagMask::ZERO));
+        assert_eq!(cpu.memory.read_word(Address(0x100)).unwrap(), 0x8001);
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A1),
+            0x101
+        );
+
+        // So does postincrement, which only moves An once
+        cpu.registers
+            .set_address_register(AddressRegister::A0, 0x200_u32);
+        Instruction::SetBit {
+            bit_number: AddressMode::Immediate { value: 0 },
+            dest: AddressMode::RegisterIndirectPostIncrement {
+                register: AddressRegister::A0,
+            },
+        }
+        .execute(cpu, OperandSize::Byte)
+        .unwrap();
+        assert_eq!(cpu.memory.read_word(Address(0x200)).unwrap(), 0x0100);
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A0),
+            0x201
+        );
+    }
+
+    #[test]
+    fn read_modify_write_moves_register_once() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        cpu.memory.write_long(Address(0x200), 0x0102_0304).unwrap();
+        let a0_postincrement = AddressMode::RegisterIndirectPostIncrement {
+            register: AddressRegister::A0,
+        };
+        cpu.registers
+            .set_address_register(AddressRegister::A0, 0x200_u32);
+        Instruction::Not {
+            dest: a0_postincrement.clone(),
+        }
+        .execute(cpu, OperandSize::Word)
+        .unwrap();
+        assert_eq!(cpu.memory.read_long(Address(0x200)).unwrap(), 0xFEFD_0304);
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A0),
+            0x202
+        );
+
+        Instruction::Add {
+            src: AddressMode::Immediate { value: 1 },
+            dest: a0_postincrement,
+        }
+        .execute(cpu, OperandSize::Byte)
+        .unwrap();
+        assert_eq!(cpu.memory.read_long(Address(0x200)).unwrap(), 0xFEFD_0404);
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A0),
+            0x203
+        );
+
+        // A7 moves by 2 even for bytes, and the write still goes to the byte that was read
+        cpu.registers
+            .set_address_register(AddressRegister::A7, 0x204_u32);
+        Instruction::NegateWithExtend {
+            dest: AddressMode::RegisterIndirectPreDecrement {
+                register: AddressRegister::A7,
+            },
+        }
+        .execute(cpu, OperandSize::Byte)
+        .unwrap();
+        assert_eq!(cpu.memory.read_long(Address(0x200)).unwrap(), 0xFEFD_FC04);
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A7),
+            0x202
+        );
+    }
+
+    #[test]
+    fn divide() {
+        let d0 = AddressMode::data(DataRegister::D0);
+        let d1 = AddressMode::data(DataRegister::D1);
+        for (signed, dividend, divisor, expected, (negative, zero)) in [
+            // 100 / 7 = 14 remainder 2
+            (false, 100_u32, 7_u32, 0x0002_000E, (false, false)),
+            (true, 100, 7, 0x0002_000E, (false, false)),
+            // -100 / 7 = -14 remainder -2
+            (true, -100i32 as u32, 7, 0xFFFE_FFF2, (true, false)),
+            // 100 / -7 = -14 remainder 2
+            (true, 100, 0xFFF9, 0x0002_FFF2, (true, false)),
+            // Unsigned, the same divisor is 65529
+            (false, 100, 0xFFF9, 0x0064_0000, (false, true)),
+            (false, 0x1_0000, 0xFFFF, 0x0001_0001, (false, false)),
+        ] {
+            let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+            cpu.registers.set_data_register(DataRegister::D0, divisor);
+            cpu.registers.set_data_register(DataRegister::D1, dividend);
+            cpu.registers.set_ccr_flag(FlagMask::CARRY, true);
+            let instruction = if signed {
+                Instruction::DivideSigned {
+                    src: d0.clone(),
+                    dest: d1.clone(),
+                }
+            } else {
+                Instruction::DivideUnsigned {
+                    src: d0.clone(),
+                    dest: d1.clone(),
+                }
+            };
+            instruction.execute(cpu, OperandSize::Word).unwrap();
+            assert_eq!(
+                cpu.registers.get_data_register(DataRegister::D1),
+                expected,
+                "{:?}",
+                instruction
+            );
+            assert_eq!(
+                cpu.registers.get_ccr_flag(FlagMask::NEGATIVE),
+                negative,
+                "{:?}",
+                instruction
+            );
+            assert_eq!(
+                cpu.registers.get_ccr_flag(FlagMask::ZERO),
+                zero,
+                "{:?}",
+                instruction
+            );
+            assert!(!cpu.registers.get_ccr_flag(FlagMask::OVERFLOW));
+            assert!(!cpu.registers.get_ccr_flag(FlagMask::CARRY));
+        }
+    }
+
+    #[test]
+    fn divide_overflow() {
+        for (instruction, dividend, divisor) in [
+            // 0x10000 doesn't fit in a word
+            (
+                Instruction::DivideUnsigned {
+                    src: AddressMode::data(DataRegister::D0),
+                    dest: AddressMode::data(DataRegister::D1),
+                },
+                0x2_0000_u32,
+                2_u32,
+            ),
+            // 32768 is too big for a signed word
+            (
+                Instruction::DivideSigned {
+                    src: AddressMode::data(DataRegister::D0),
+                    dest: AddressMode::data(DataRegister::D1),
+                },
+                0x1_0000,
+                2,
+            ),
+            // The most negative long divided by -1
+            (
+                Instruction::DivideSigned {
+                    src: AddressMode::data(DataRegister::D0),
+                    dest: AddressMode::data(DataRegister::D1),
+                },
+                0x8000_0000,
+                0xFFFF,
+            ),
+        ] {
+            let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+            cpu.registers.set_data_register(DataRegister::D0, divisor);
+            cpu.registers.set_data_register(DataRegister::D1, dividend);
+            instruction.execute(cpu, OperandSize::Word).unwrap();
+            assert!(
+                cpu.registers.get_ccr_flag(FlagMask::OVERFLOW),
+                "{:?}",
+                instruction
+            );
+            assert!(
+                !cpu.registers.get_ccr_flag(FlagMask::CARRY),
+                "{:?}",
+                instruction
+            );
+            // The destination is left alone
+            assert_eq!(
+                cpu.registers.get_data_register(DataRegister::D1),
+                dividend,
+                "{:?}",
+                instruction
+            );
+        }
+    }
+
+    #[test]
+    fn divide_by_zero() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        cpu.registers.set_data_register(DataRegister::D1, 100_u32);
+        // Only the low word of the divisor is used
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0x1234_0000_u32);
+        for instruction in [
+            Instruction::DivideUnsigned {
+                src: AddressMode::data(DataRegister::D0),
+                dest: AddressMode::data(DataRegister::D1),
+            },
+            Instruction::DivideSigned {
+                src: AddressMode::Immediate { value: 0 },
+                dest: AddressMode::data(DataRegister::D1),
+            },
+        ] {
+            assert!(matches!(
+                instruction.execute(cpu, OperandSize::Word),
+                Err(EmulationError::DivideByZero(_))
+            ));
+            assert_eq!(cpu.registers.get_data_register(DataRegister::D1), 100);
+        }
+    }
+
+    #[test]
+    fn bounds_check() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+        let chk = Instruction::BoundsCheck {
+            bound: AddressMode::Immediate { value: 10 },
+            value: AddressMode::data(DataRegister::D0),
+        };
+        for value in [0_u32, 5, 10] {
+            cpu.registers.set_data_register(DataRegister::D0, value);
+            chk.execute(cpu, OperandSize::Word).unwrap();
+        }
+        // $FFFF is -1 as a word
+        for value in [11_u32, 0xFFFF] {
+            cpu.registers.set_data_register(DataRegister::D0, value);
+            assert!(matches!(
+                chk.execute(cpu, OperandSize::Word),
+                Err(EmulationError::BoundsCheckFailed(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn multiply_unsigned_word() {
+        let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+
+        // The product of two words is a long, and the flags come from the whole long
+        cpu.registers.set_data_register(DataRegister::D0, 0x100_u32);
+        run(cpu, "mulu.w #$100, d0");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0x10000);
+        assert_eq!(cpu.registers.get_flags(), Flags::default());
+
+        // Only the low words are multiplied, and they're unsigned
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0x1234_FFFF_u32);
+        cpu.registers
+            .set_data_register(DataRegister::D1, 0xABCD_FFFF_u32);
+        run(cpu, "mulu.w d0, d1");
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D1),
+            0xFFFE_0001
+        );
+        assert_eq!(
+            cpu.registers.get_flags(),
+            Flags {
+                negative: true,
+                ..Flags::default()
+            }
+        );
+
+        cpu.registers
+            .set_data_register(DataRegister::D1, 0x1234_0000_u32);
+        run(cpu, "mulu.w d0, d1");
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D1), 0);
+        assert!(cpu.registers.get_flags().zero);
+    }
+
+    #[test]
+    fn only_affected_flags_change() {
+        let mut instructions: Vec<Instruction> = [
+            "add d0, d1",
+            "add.w d0, a1",
+            "sub #1, d2",
+            "mulu d0, d1",
+            "move d0, d1",
+            "move.l d2, a1",
+            "moveq #-1, d0",
+            "eor d0, d1",
+            "or d0, d1",
+            "and d0, d1",
+            "rol #1, d0",
+            "ror #1, d0",
+            "roxl #1, d0",
+            "roxr #1, d0",
+            "lsl #1, d0",
+            "asl #1, d0",
+            "asr #1, d0",
+            "lea (4, a0), a1",
+            "movep.l d0, (0, a0)",
+            "neg d0",
+            "negx d0",
+            "clr d0",
+            "not d0",
+            "tst d0",
+            "cmp d0, d1",
+            "cmpa.w d0, a1",
+            "nop",
+        ]
+        .iter()
+        .map(|asm| AssemblyInterpreter::new().parse(asm.to_string()).unwrap().0)
+        .collect();
+        instructions.push(Instruction::PushEffectiveAddress {
+            src: AddressMode::RegisterIndirect {
+                register: AddressRegister::A0,
+            },
+        });
+        instructions.push(Instruction::CompareMemory {
+            src: AddressRegister::A0,
+            dest: AddressRegister::A1,
+        });
+        instructions.push(Instruction::BitFieldExtractUnsigned {
+            base: AddressMode::RegisterDirect {
+                register: Register::Data(DataRegister::D0),
+            },
+            offset: BitFieldParameter::Immediate(4),
+            width: BitFieldParameter::Immediate(8),
+            dest: AddressMode::RegisterDirect {
+                register: Register::Data(DataRegister::D1),
+            },
+        });
+
+        for instruction in instructions {
+            let mask = instruction.affected_flags();
+            // Starting with every flag clear and then every flag set means each flag that's written changes at least once,
+            // unless it depends on X (like N after ROXR), so X is also flipped on its own
+            let mut written = 0;
+            for (d0, ccr) in [0x8000_0001u32, 0, 0x7FFF_FFFF]
+                .into_iter()
+                .flat_map(|d0| [(d0, 0x00), (d0, 0x1F), (d0, 0x10), (d0, 0x0F)])
+            {
+                let cpu = &mut CPU::<VecBackedMemory>::new(1024);
+                cpu.model = CpuModel::M68020;
+                cpu.registers.set_data_register(DataRegister::D0, d0);
+                cpu.registers.set_data_register(DataRegister::D1, VALUE);
+                cpu.registers
+                    .set_address_register(AddressRegister::A0, ADDRESS);
+                cpu.registers
+                    .set_address_register(AddressRegister::A1, ADDRESS + 0x40);
+                cpu.registers
+                    .set_address_register(AddressRegister::A7, 0x200u32);
+                cpu.registers.set_status_register(0x2700 | ccr);
+
+                instruction.execute(cpu, OperandSize::Long).unwrap();
+
+                let changed = (cpu.registers.get_status_register() ^ (0x2700 | ccr)) as u8;
+                written |= changed;
+                assert_eq!(
+                    changed & !mask.0,
+                    0,
+                    "{:?} changed flags {:05b}, but only declared {:05b}",
+                    instruction,
+                    changed,
+                    mask.0
+                );
+            }
+            assert_eq!(
+                written, mask.0,
+                "{:?} declared flags {:05b}, but only wrote {:05b}",
+                instruction, mask.0, written
+            );
+        }
+    }
+
+    #[test]
+    fn disassemble_with_symbols() {
+        let source = "\
+main:
+    lea (table, pc), a0
+    bsr strcpy
+    jsr strcpy
+    jmp $2000
+    beq main
+strcpy:
+    move.b (a0)+, (a1)+
+    bne.s strcpy
+    rts
+table:
+    nop";
+        let mut interpreter = AssemblyInterpreter::new();
+        let program = interpreter.assemble(source, 0x100).unwrap();
+        let symbols = SymbolTable::from(interpreter.symbols());
+        let disassemble = |symbols: &SymbolTable| -> Vec<String> {
+            program
+                .iter()
+                .map(|line| {
+                    line.instruction
+                        .disassemble(line.address, line.size, symbols)
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            disassemble(&symbols),
+            vec![
+                "lea (table, pc), a0",
+                "bsr strcpy",
+                "jsr strcpy",
+                "jmp $2000",
+                "beq main",
+                "move.b (a0)+, (a1)+",
+                "bne strcpy",
+                "rts",
+                "nop",
+            ]
+        );
+
+        // Without symbols, targets are written as addresses
+        let strcpy = interpreter.symbols()["strcpy"];
+        let unnamed = disassemble(&SymbolTable::new());
+        assert_eq!(unnamed[1], format!("bsr ${:X}", strcpy));
+        assert_eq!(unnamed[2], format!("jsr ${:X}", strcpy));
+        assert!(unnamed[0].ends_with(", pc), a0"));
+    }
+
+    #[test]
+    fn disassemble_sizes_and_immediates() {
+        let symbols = SymbolTable::new();
+        for (asm, expected) in [
+            ("moveq #-1, d0", "moveq #-1, d0"),
+            ("add.w #$10, (8, a0)", "add.w #$10, (8, a0)"),
+            ("clr.l -(sp)", "clr.l -(a7)"),
+            ("divu d1, d0", "divu d1, d0"),
+            ("lsl #3, d0", "lsl.l #$3, d0"),
+        ] {
+            let (instruction, size, _) = AssemblyInterpreter::new().parse(asm.to_string()).unwrap();
+            assert_eq!(
+                instruction.disassemble(0x100, size, &symbols),
+                expected,
+                "{}",
+                asm
+            );
+        }
+        assert_eq!(
+            Instruction::Trap { vector: 15 }.disassemble(0x100, OperandSize::Word, &symbols),
+            "trap #15"
+        );
     }
 }
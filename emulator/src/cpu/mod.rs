@@ -1,26 +1,124 @@
 //! CPU emulation
 //!
-//! In the future, CPU features (e.g. 68000 vs 68030) may be configurable via Cargo features.
-//!
-//! However, we don't support non-68000s yet, so it's not terribly relevant.
+//! The CPU emulates a 68000 by default. Instructions added by later models (such as the 68020's bit-field instructions)
+//! are only executed if [`CPU::model`] is set to a model that supports them.
 
+#[cfg(feature = "debug-trace")]
 use colored::*;
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display};
 
 use crate::{
-    parsers::{binary::MachineCodeParser, Parser},
+    parsers::{binary::MachineCodeParser, MachineCodeSource, ParseError},
     ram::Memory,
-    EmulationError,
+    Address, EmulationError, OperandSize,
 };
 pub mod addressing;
+#[cfg(test)]
+mod golden;
 pub mod isa_68000;
 pub mod registers;
+pub mod symbols;
+use isa_68000::Instruction;
 use registers::*;
+use symbols::SymbolTable;
+
+/// How many bytes are read from memory to decode an instruction, which is enough for any instruction the emulator runs
+const MAX_FETCH: u32 = 8;
+
+/// A member of the 68k family.
+///
+/// Later models are backwards-compatible with earlier ones, so they compare greater.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CpuModel {
+    M68000,
+    M68010,
+    M68020,
+}
 
+impl CpuModel {
+    /// Gets the bits of an address that reach the address bus.
+    ///
+    /// The 68000 and 68010 only have 24 address lines, so the top byte of a computed address is ignored;
+    /// the 68020 uses all 32 bits.
+    pub fn address_mask(self) -> u32 {
+        match self {
+            CpuModel::M68000 | CpuModel::M68010 => 0x00FF_FFFF,
+            CpuModel::M68020 => 0xFFFF_FFFF,
+        }
+    }
+}
+
+/// Something that's told about each instruction after it runs, such as a profiler or a device that counts clock cycles.
+///
+/// Unlike the `debug-trace` output, which comes before an instruction is executed,
+/// observers only hear about instructions that completed successfully.
+pub trait RetirementObserver {
+    /// Called with the address of the instruction that just ran, the instruction,
+    /// and the number of clock cycles it took (see [`Instruction::cycles`])
+    fn on_retire(&mut self, pc: u32, instruction: &Instruction, cycles: u32);
+}
+
+/// A 68k CPU, with its registers and memory.
+///
+/// Memory only ever gets `&mut M`, never `&mut CPU`, so memory-mapped devices and write callbacks
+/// can't re-enter the CPU while an instruction is reading or writing an operand.
+/// Code that needs the registers while accessing memory (like a device handler) should use [`CPU::split_mut`].
+///
+/// A CPU can be cloned if its memory can, to snapshot it or try running ahead without losing the original state.
 pub struct CPU<M: Memory> {
     pub registers: Registers,
     pub memory: M,
-    pub parser: MachineCodeParser,
+    /// Decodes instructions from memory; a [`MachineCodeParser`] unless another was given to [`CPU::with_parser`]
+    pub parser: Box<dyn MachineCodeSource>,
+    /// Which model is being emulated; determines which instructions can be executed
+    pub model: CpuModel,
+    /// When set, a displacement that takes an address past either end of the address space is an error
+    /// ([`EmulationError::AddressWrapped`]) instead of wrapping around like the hardware does.
+    ///
+    /// Off by default; it's useful for catching bad pointer arithmetic in guest code.
+    pub strict_addressing: bool,
+    /// When set, running more than this many `0x0000` opcodes in a row is an [`EmulationError::RanIntoZeros`].
+    ///
+    /// `0x0000` is `ori.b #0, d0`, which does nothing, so a program that has crashed into zeroed memory
+    /// would otherwise run on until it falls off the end of memory.
+    pub zero_opcode_limit: Option<usize>,
+    /// Names for addresses in the program, used to label disassembly and profiles
+    pub symbols: SymbolTable,
+    /// Where the current run of `0x0000` opcodes started, and how many have been run
+    zero_run: Option<(u32, usize)>,
+    /// Where the instruction being run by [`CPU::run_one_cycle`] starts, since the PC has already moved past it
+    instruction_address: Option<u32>,
+    instructions_run: u64,
+    cycles_run: u64,
+    /// Cycles spent on instructions at each address, if profiling is enabled
+    profile: Option<HashMap<u32, u64>>,
+    retirement_observers: Vec<Box<dyn RetirementObserver>>,
+}
+
+/// Copies the CPU's state.
+///
+/// Retirement observers can't be cloned, so the copy has none; add them again with [`CPU::observe_retirement`].
+impl<M> Clone for CPU<M>
+where
+    M: Memory + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            registers: self.registers.clone(),
+            memory: self.memory.clone(),
+            parser: self.parser.clone_box(),
+            model: self.model,
+            strict_addressing: self.strict_addressing,
+            zero_opcode_limit: self.zero_opcode_limit,
+            symbols: self.symbols.clone(),
+            zero_run: self.zero_run,
+            instruction_address: self.instruction_address,
+            instructions_run: self.instructions_run,
+            cycles_run: self.cycles_run,
+            profile: self.profile.clone(),
+            retirement_observers: vec![],
+        }
+    }
 }
 
 impl<M> Default for CPU<M>
@@ -37,13 +135,52 @@ where
     M: Memory,
 {
     pub fn new(ram_size_in_bytes: usize) -> Self {
+        Self::with_memory(M::new(ram_size_in_bytes))
+    }
+
+    /// Creates a CPU that uses existing memory, such as a [`RomMemory`](crate::ram::RomMemory) borrowing a program image
+    pub fn with_memory(memory: M) -> Self {
         Self {
             registers: Registers::new(),
-            memory: M::new(ram_size_in_bytes),
-            parser: MachineCodeParser::default(),
+            memory,
+            parser: Box::new(MachineCodeParser::default()),
+            model: CpuModel::M68000,
+            strict_addressing: false,
+            zero_opcode_limit: None,
+            symbols: SymbolTable::new(),
+            zero_run: None,
+            instruction_address: None,
+            instructions_run: 0,
+            cycles_run: 0,
+            profile: None,
+            retirement_observers: vec![],
         }
     }
 
+    /// Creates a CPU that decodes instructions with `parser` instead of a [`MachineCodeParser`],
+    /// such as one that feeds the CPU hand-built instructions to test how they execute
+    pub fn with_parser(ram_size_in_bytes: usize, parser: impl MachineCodeSource + 'static) -> Self {
+        Self {
+            parser: Box::new(parser),
+            ..Self::new(ram_size_in_bytes)
+        }
+    }
+
+    /// Writes a binary image (such as code compiled with `-Wl,--oformat=binary`) into memory at `address`
+    pub fn load_binary(&mut self, address: u32, binary: Vec<u8>) -> Result<(), EmulationError> {
+        self.memory.write_bytes(Address(address), binary)
+    }
+
+    /// Adds an observer to be told about every instruction after it runs
+    pub fn observe_retirement(&mut self, observer: Box<dyn RetirementObserver>) {
+        self.retirement_observers.push(observer);
+    }
+
+    /// Borrows the registers and memory separately, so both can be used at once
+    pub fn split_mut(&mut self) -> (&mut Registers, &mut M) {
+        (&mut self.registers, &mut self.memory)
+    }
+
     /// Runs one cycle of the CPU:
     ///
     /// - Fetch the instruction
@@ -55,29 +192,1144 @@ where
         // Fetch
         let pc = self.registers.get(Register::ProgramCounter);
 
-        let binary = self.memory.read_bytes(pc, 8)?;
+        let binary = self.fetch(pc)?;
+        if let Some(limit) = self.zero_opcode_limit {
+            self.zero_run = match self.zero_run {
+                _ if !binary.starts_with(&[0, 0]) => None,
+                Some((start, count)) if count >= limit => {
+                    return Err(EmulationError::RanIntoZeros(Address(start)))
+                }
+                Some((start, count)) => Some((start, count + 1)),
+                None => Some((pc, 1)),
+            };
+        }
 
         // Decode
         let (instruction, size, bytes_taken) = self.parser.parse(binary)?;
 
         // Execute
+        #[cfg(feature = "debug-trace")]
         println!("{}: {:?}", "Execute".green().bold(), instruction);
-        instruction.execute(self, size)?;
-
-        // Increment PC only if the instruction didn't alter it itself
-        if pc == self.registers.get(Register::ProgramCounter) {
-            self.registers
-                .set(Register::ProgramCounter, pc + bytes_taken as u32);
+        // The PC moves on to the next instruction first, so control-flow instructions just overwrite it
+        self.instruction_address = Some(pc);
+        self.registers
+            .set(Register::ProgramCounter, pc + bytes_taken as u32);
+        let result = instruction.execute(self, size);
+        self.instruction_address = None;
+        if let Err(err) = result {
+            // Leave the PC at the instruction that failed
+            self.registers.set(Register::ProgramCounter, pc);
+            return Err(err);
+        }
+        let cycles = instruction.cycles(size);
+        self.instructions_run += 1;
+        self.cycles_run += cycles as u64;
+        if let Some(profile) = &mut self.profile {
+            *profile.entry(pc).or_default() += cycles as u64;
+        }
+        for observer in &mut self.retirement_observers {
+            observer.on_retire(pc, &instruction, cycles);
         }
         Ok(())
     }
+
+    /// Gets the address of the instruction being run, which PC-relative operands and branches are based on.
+    ///
+    /// This is the PC, except while [`CPU::run_one_cycle`] is executing an instruction,
+    /// when the PC already points to the next one.
+    pub(crate) fn instruction_address(&self) -> u32 {
+        self.instruction_address
+            .unwrap_or_else(|| self.registers.get(Register::ProgramCounter))
+    }
+
+    /// Gets the number of instructions that have been run
+    pub fn instruction_count(&self) -> u64 {
+        self.instructions_run
+    }
+
+    /// Gets the (estimated) number of clock cycles the instructions that have been run would take on a 68000;
+    /// see [`Instruction::cycles`]
+    pub fn total_cycles(&self) -> u64 {
+        self.cycles_run
+    }
+
+    /// Starts counting how many cycles are spent on the instruction at each address; see [`CPU::profile_report`].
+    ///
+    /// Profiling is off by default, since it slows down every instruction.
+    pub fn enable_profiling(&mut self) {
+        self.profile.get_or_insert_with(HashMap::new);
+    }
+
+    /// Gets the address of every instruction that has run since profiling was enabled,
+    /// along with the cycles spent on it, with the most expensive first.
+    ///
+    /// Empty unless [`CPU::enable_profiling`] has been called.
+    pub fn profile_report(&self) -> Vec<(u32, u64)> {
+        let mut report: Vec<(u32, u64)> = match &self.profile {
+            Some(profile) => profile.iter().map(|(&pc, &cycles)| (pc, cycles)).collect(),
+            None => vec![],
+        };
+        report.sort_by(|(a_pc, a_cycles), (b_pc, b_cycles)| {
+            b_cycles.cmp(a_cycles).then(a_pc.cmp(b_pc))
+        });
+        report
+    }
+
+    /// Exports the profile as CSV, with a header row and then one `pc,cycles` row per instruction
+    pub fn profile_csv(&self) -> String {
+        let mut csv = String::from("pc,cycles\n");
+        for (pc, cycles) in self.profile_report() {
+            csv.push_str(&format!("{:08X},{}\n", pc, cycles));
+        }
+        csv
+    }
+
+    /// Exports the profile in the folded-stack format flame graph tools read: one `frame cycles` line per frame.
+    ///
+    /// Each instruction is attributed to the closest symbol in [`CPU::symbols`] at or before it;
+    /// instructions without one are listed by address.
+    pub fn profile_folded(&self) -> String {
+        let mut frames: HashMap<String, u64> = HashMap::new();
+        for (pc, cycles) in self.profile_report() {
+            let frame = self
+                .symbols
+                .containing(pc)
+                .map_or_else(|| format!("{:08X}", pc), |(name, _)| name.to_string());
+            *frames.entry(frame).or_default() += cycles;
+        }
+
+        let mut frames: Vec<(String, u64)> = frames.into_iter().collect();
+        frames.sort_by(|(a_frame, a_cycles), (b_frame, b_cycles)| {
+            b_cycles.cmp(a_cycles).then(a_frame.cmp(b_frame))
+        });
+        frames
+            .into_iter()
+            .map(|(frame, cycles)| format!("{} {}\n", frame, cycles))
+            .collect()
+    }
+
+    /// Runs until the CPU halts (see [`CPU::is_halted`]) or an instruction can't be run
+    pub fn run(&mut self) -> Result<(), EmulationError> {
+        loop {
+            if self.is_halted()? {
+                return Ok(());
+            }
+            self.run_one_cycle()?;
+        }
+    }
+
+    /// Runs until the PC reaches `target`, which is handy for running a program up to a label.
+    ///
+    /// Gives up with [`EmulationError::TargetNotReached`] after running `max_cycles` instructions,
+    /// and stops early if an instruction can't be run.
+    pub fn run_until_pc(&mut self, target: u32, max_cycles: usize) -> Result<(), EmulationError> {
+        for _ in 0..max_cycles {
+            if self.registers.get(Register::ProgramCounter) == target {
+                return Ok(());
+            }
+            self.run_one_cycle()?;
+        }
+        match self.registers.get(Register::ProgramCounter) {
+            pc if pc == target => Ok(()),
+            pc => Err(EmulationError::TargetNotReached(Address(pc))),
+        }
+    }
+
+    /// Runs up to `n` instructions, returning a snapshot of the registers after each one.
+    ///
+    /// Stops early if an instruction can't be run, or if the CPU halts (see [`CPU::is_halted`]).
+    pub fn run_collecting(&mut self, n: usize) -> Vec<RegisterFile> {
+        let mut snapshots = Vec::with_capacity(n);
+        for _ in 0..n {
+            if self.is_halted().unwrap_or(false) {
+                break;
+            }
+            if self.run_one_cycle().is_err() {
+                break;
+            }
+            snapshots.push(self.registers.dump());
+        }
+        snapshots
+    }
+
+    /// Checks whether the CPU has halted, i.e. the next instruction is an unconditional jump or branch to itself
+    /// (such as `bra.s *`), which is how most programs end.
+    ///
+    /// Loops that only branch to themselves while a condition holds (such as `dbra d0, *`) don't count,
+    /// since they end on their own.
+    pub fn is_halted(&self) -> Result<bool, EmulationError> {
+        let pc = self.registers.get(Register::ProgramCounter);
+        let binary = self.fetch(pc)?;
+        let (instruction, _, _) = self.parser.clone_box().parse(binary)?;
+
+        match instruction {
+            Instruction::Branch { .. } | Instruction::JumpTo { .. } => {
+                Ok(instruction.branch_target(self)? == Some(pc))
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Predicts what the program counter will be after the next instruction runs, without running it.
+    ///
+    /// Jumps and returns are followed; other instructions fall through to the instruction after them.
+    pub fn predict_next_pc(&self) -> Result<u32, EmulationError> {
+        let pc = self.registers.get(Register::ProgramCounter);
+        let binary = self.fetch(pc)?;
+        let (instruction, _, bytes_taken) = self.parser.clone_box().parse(binary)?;
+
+        match instruction.branch_target(self)? {
+            Some(target) => Ok(target),
+            None => Ok(pc + bytes_taken),
+        }
+    }
+
+    /// Reads the bytes the instruction at `address` could take up, to be decoded.
+    ///
+    /// That's usually [`MAX_FETCH`] bytes, but an instruction can end right at the end of memory,
+    /// so the read stops early there; it only fails if there's nothing at `address` at all.
+    fn fetch(&self, address: u32) -> Result<Vec<u8>, EmulationError> {
+        let mut binary = Vec::with_capacity(MAX_FETCH as usize);
+        for offset in 0..MAX_FETCH {
+            match self.memory.read_byte(Address(address) + offset) {
+                Ok(byte) => binary.push(byte),
+                Err(error) if binary.is_empty() => return Err(error),
+                Err(_) => break,
+            }
+        }
+        Ok(binary)
+    }
+
+    /// Decodes up to `count` instructions starting at `address`, without running them or touching the PC.
+    ///
+    /// Returns each instruction's address, the instruction, and its size.
+    /// Words that can't be decoded are returned as errors (with a size of [`OperandSize::Word`]) and skipped over,
+    /// and disassembly stops early at the end of memory.
+    pub fn disassemble_at(
+        &self,
+        address: u32,
+        count: usize,
+    ) -> Vec<(u32, Result<Instruction, ParseError>, OperandSize)> {
+        let mut instructions = Vec::with_capacity(count);
+        let mut address = address;
+        for _ in 0..count {
+            let binary = match self.fetch(address) {
+                Ok(binary) => binary,
+                Err(_) => break,
+            };
+            match self.parser.clone_box().parse(binary) {
+                Ok((instruction, size, bytes_taken)) => {
+                    instructions.push((address, Ok(instruction), size));
+                    address += bytes_taken;
+                }
+                Err(error) => {
+                    instructions.push((address, Err(error), OperandSize::Word));
+                    address += 2;
+                }
+            }
+        }
+        instructions
+    }
 }
 
 impl<M> Display for CPU<M>
 where
     M: Memory,
 {
+    /// Formatting flags (such as `{:#}` to label A7 as SP) are passed through to the registers
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}", self.registers)
+        Display::fmt(&self.registers, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cpu::{addressing::AddressMode, isa_68000::Condition},
+        parsers::Parser,
+        ram::{BankedMemory, RomMemory, VecBackedMemory},
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    static START: u32 = 0x100;
+
+    fn cpu_with_program(program: Vec<u8>) -> CPU<VecBackedMemory> {
+        let mut cpu = CPU::<VecBackedMemory>::new(1024);
+        cpu.memory.write_bytes(Address(START), program).unwrap();
+        cpu.registers.set(Register::ProgramCounter, START);
+        cpu
+    }
+
+    #[test]
+    fn reset_state() {
+        let cpu = CPU::<VecBackedMemory>::new(1024);
+        assert_eq!(cpu.registers.get_status_register(), 0x2700);
+        assert!(cpu.registers.is_supervisor());
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), 0);
+    }
+
+    #[test]
+    fn run_from_rom() {
+        // moveq #5, d0; moveq #-1, d1; nop; nop; nop; nop
+        let image = [
+            0x70, 0x05, 0x72, 0xFF, 0x4E, 0x71, 0x4E, 0x71, 0x4E, 0x71, 0x4E, 0x71,
+        ];
+        let mut cpu = CPU::with_memory(RomMemory::from_slice(&image));
+        for _ in 0..3 {
+            cpu.run_one_cycle().unwrap();
+        }
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 5);
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D1),
+            0xFFFF_FFFF
+        );
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), 6);
+    }
+
+    #[test]
+    fn run_to_end_of_rom() {
+        // No padding after the last instruction, so fetching 8 bytes there would run off the end
+        let image = [
+            0x70, 0x05, // moveq #5, d0
+            0x06, 0x40, 0x00, 0x01, // addi.w #1, d0
+        ];
+        let mut cpu = CPU::with_memory(RomMemory::from_slice(&image));
+        assert_eq!(cpu.disassemble_at(0, 3).len(), 2);
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(cpu.predict_next_pc().unwrap(), 6);
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 6);
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), 6);
+        assert!(matches!(
+            cpu.run_one_cycle(),
+            Err(EmulationError::MemoryOutOfBoundsAccess(Address(6)))
+        ));
+    }
+
+    #[test]
+    fn retirement_observer() {
+        #[derive(Default)]
+        struct Profile {
+            pcs: Vec<u32>,
+            cycles: u64,
+        }
+        struct Profiler(Rc<RefCell<Profile>>);
+        impl RetirementObserver for Profiler {
+            fn on_retire(&mut self, pc: u32, _instruction: &Instruction, cycles: u32) {
+                let mut profile = self.0.borrow_mut();
+                profile.pcs.push(pc);
+                profile.cycles += cycles as u64;
+            }
+        }
+
+        let mut cpu = cpu_with_program(vec![
+            0x70, 0x01, // moveq #1, d0
+            0x60, 0x02, // bra.s +2
+            0x4E, 0x71, // nop (skipped)
+            0x4E, 0x71, // nop
+            0xA0, 0x00, // not an instruction
+        ]);
+        let profile = Rc::new(RefCell::new(Profile::default()));
+        cpu.observe_retirement(Box::new(Profiler(Rc::clone(&profile))));
+        for _ in 0..3 {
+            cpu.run_one_cycle().unwrap();
+        }
+        // Instructions that fail aren't retired
+        assert!(cpu.run_one_cycle().is_err());
+
+        let profile = profile.borrow();
+        assert_eq!(profile.pcs, vec![START, START + 2, START + 6]);
+        assert_eq!(profile.cycles, cpu.total_cycles());
+        assert_eq!(profile.cycles, 4 + 10 + 4);
+    }
+
+    #[test]
+    fn custom_parser() {
+        /// Hands out a fixed list of two-byte instructions, ignoring what's in memory
+        #[derive(Clone)]
+        struct Script {
+            instructions: Vec<Instruction>,
+            next: usize,
+        }
+        impl Parser<Vec<u8>> for Script {
+            fn parse(
+                &mut self,
+                _source: Vec<u8>,
+            ) -> Result<(Instruction, OperandSize, u32), ParseError> {
+                let instruction = self
+                    .instructions
+                    .get(self.next)
+                    .cloned()
+                    .ok_or_else(|| ParseError::NoInstruction("end of script".to_string()))?;
+                self.next += 1;
+                Ok((instruction, OperandSize::Long, 2))
+            }
+        }
+
+        let mut cpu = CPU::<VecBackedMemory>::with_parser(
+            1024,
+            Script {
+                instructions: vec![
+                    Instruction::Move {
+                        src: AddressMode::Immediate { value: 40 },
+                        dest: AddressMode::data(DataRegister::D0),
+                    },
+                    Instruction::Add {
+                        src: AddressMode::Immediate { value: 2 },
+                        dest: AddressMode::data(DataRegister::D0),
+                    },
+                ],
+                next: 0,
+            },
+        );
+        cpu.registers.set(Register::ProgramCounter, START);
+
+        // Looking ahead doesn't use up instructions
+        assert_eq!(cpu.predict_next_pc().unwrap(), START + 2);
+        cpu.run_one_cycle().unwrap();
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 42);
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), START + 4);
+        assert!(matches!(
+            cpu.run_one_cycle(),
+            Err(EmulationError::Parsing(ParseError::NoInstruction(_)))
+        ));
+    }
+
+    #[test]
+    fn profile() {
+        let program = vec![
+            0x70, 0x00, // start: moveq #0, d0
+            0xD0, 0xBC, 0x00, 0x00, 0x00, 0x01, // loop: add.l #1, d0
+            0xB0, 0xBC, 0x00, 0x00, 0x00, 0x0A, // cmp.l #10, d0
+            0x66, 0xF2, // bne.s loop
+            0x60, 0xFE, // done: bra.s done
+        ];
+        let mut cpu = cpu_with_program(program.clone());
+        cpu.run().unwrap();
+        // Profiling is opt-in
+        assert!(cpu.profile_report().is_empty());
+
+        let mut cpu = cpu_with_program(program);
+        cpu.enable_profiling();
+        cpu.run().unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 10);
+
+        // The loop runs 10 times, so it dominates the single moveq
+        let report = cpu.profile_report();
+        let mut hot: Vec<u32> = report[..3].iter().map(|(pc, _)| *pc).collect();
+        hot.sort_unstable();
+        assert_eq!(hot, vec![START + 2, START + 8, START + 14]);
+        assert_eq!(report[3..], [(START, 4)]);
+        assert_eq!(
+            report.iter().map(|(_, cycles)| cycles).sum::<u64>(),
+            cpu.total_cycles()
+        );
+
+        let csv = cpu.profile_csv();
+        assert_eq!(csv.lines().next(), Some("pc,cycles"));
+        assert_eq!(csv.lines().last(), Some("00000100,4"));
+        assert_eq!(csv.lines().count(), 5);
+
+        // Without symbols, instructions are listed by address
+        assert_eq!(cpu.profile_folded().lines().last(), Some("00000100 4"));
+
+        cpu.symbols.insert(START, "start");
+        cpu.symbols.insert(START + 2, "loop");
+        cpu.symbols.insert(START + 16, "done");
+        assert_eq!(
+            cpu.profile_folded(),
+            format!("loop {}\nstart 4\n", cpu.total_cycles() - 4)
+        );
+    }
+
+    #[test]
+    fn predict_next_pc_falls_through() {
+        // nop
+        let mut cpu = cpu_with_program(vec![0x4E, 0x71]);
+        assert_eq!(cpu.predict_next_pc().unwrap(), START + 2);
+
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), START + 2);
+    }
+
+    #[test]
+    fn disassemble_at() {
+        let cpu = cpu_with_program(vec![
+            0x4E, 0x71, // nop
+            0x4E, 0xF9, 0x00, 0x00, 0x02, 0x00, // jmp ($0200).l
+            0x41, 0xFA, 0x00, 0x06, // lea (6, pc), a0
+        ]);
+
+        let disassembly = cpu.disassemble_at(START + 2, 2);
+        let addresses: Vec<u32> = disassembly.iter().map(|(address, ..)| *address).collect();
+        assert_eq!(addresses, vec![START + 2, START + 8]);
+        assert_eq!(
+            disassembly[0].1.as_ref().unwrap(),
+            &Instruction::JumpTo {
+                address: AddressMode::Absolute { address: 0x200 }
+            }
+        );
+        assert_eq!(
+            disassembly[1].1.as_ref().unwrap(),
+            &Instruction::LoadEffectiveAddress {
+                src: AddressMode::ProgramCounterIndirectWithDisplacement { displacement: 6 },
+                dest: AddressMode::RegisterDirect {
+                    register: Register::Address(AddressRegister::A0)
+                },
+            }
+        );
+        // the PC isn't moved
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), START);
+    }
+
+    #[test]
+    fn predict_next_pc_follows_jump() {
+        // jmp ($0200).l
+        let mut cpu = cpu_with_program(vec![0x4E, 0xF9, 0x00, 0x00, 0x02, 0x00]);
+        assert_eq!(cpu.predict_next_pc().unwrap(), 0x200);
+        // no side effects
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), START);
+
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), 0x200);
+    }
+
+    #[test]
+    fn decode_pc_relative_lea_and_pea() {
+        let mut cpu = cpu_with_program(vec![
+            0x41, 0xFA, 0x00, 0x06, // lea (6, pc), a0
+            0x48, 0x7A, 0x00, 0x02, // pea (2, pc)
+        ]);
+        cpu.registers
+            .set_address_register(AddressRegister::A7, 0x200_u32);
+
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A0),
+            START + 8
+        );
+
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A7),
+            0x1FC
+        );
+        assert_eq!(cpu.memory.read_long(Address(0x1FC)).unwrap(), START + 8);
+    }
+
+    #[test]
+    fn decode_bit_field() {
+        let mut cpu = cpu_with_program(vec![
+            0xE9, 0xC0, 0x10, 0xC5, // bfextu d0{3:5}, d1
+            0xEF, 0xC0, 0x10, 0xC5, // bfins d1, d0{3:5}
+        ]);
+        cpu.model = CpuModel::M68020;
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0xB5C3_1234_u32);
+
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D1), 0b10101);
+
+        cpu.registers
+            .set_data_register(DataRegister::D1, 0b01010_u32);
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0),
+            0xAAC3_1234
+        );
+    }
+
+    #[test]
+    fn decode_compare_memory() {
+        let mut cpu = cpu_with_program(vec![
+            0xB3, 0x08, // cmpm.b (a0)+, (a1)+
+        ]);
+        cpu.memory.write_byte(Address(0x10), 7).unwrap();
+        cpu.memory.write_byte(Address(0x20), 7).unwrap();
+        cpu.registers
+            .set_address_register(AddressRegister::A0, 0x10_u32);
+        cpu.registers
+            .set_address_register(AddressRegister::A1, 0x20_u32);
+
+        cpu.run_one_cycle().unwrap();
+        assert!(cpu.registers.get_flags().zero);
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A0),
+            0x11
+        );
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A1),
+            0x21
+        );
+    }
+
+    #[test]
+    fn decode_trap() {
+        for (opcode, vector) in [(0x4E40_u16, 0), (0x4E47, 7), (0x4E4F, 15)] {
+            let mut program = opcode.to_be_bytes().to_vec();
+            program.extend([0; 6]);
+            let (instruction, _, bytes_used) = MachineCodeParser::default().parse(program).unwrap();
+            assert_eq!(instruction, Instruction::Trap { vector });
+            assert_eq!(bytes_used, 2);
+        }
+
+        // Exceptions aren't emulated yet
+        let mut cpu = cpu_with_program(vec![0x4E, 0x47]); // trap #7
+        assert!(matches!(
+            cpu.run_one_cycle(),
+            Err(EmulationError::UnimplementedInstruction(_))
+        ));
+    }
+
+    #[test]
+    fn decode_rotate_with_extend() {
+        let mut cpu = cpu_with_program(vec![
+            0xE3, 0x10, // roxl.b #1, d0
+            0xE3, 0x18, // rol.b #1, d0
+        ]);
+        let d0 = AddressMode::RegisterDirect {
+            register: Register::Data(DataRegister::D0),
+        };
+        let count = AddressMode::Immediate { value: 1 };
+        let disassembly = cpu.disassemble_at(START, 2);
+        assert_eq!(
+            disassembly[0].1.as_ref().unwrap(),
+            &Instruction::RotateLeftExtended {
+                to_rotate: d0.clone(),
+                rotate_amount: count.clone(),
+            }
+        );
+        assert_eq!(
+            disassembly[1].1.as_ref().unwrap(),
+            &Instruction::RotateLeft {
+                to_rotate: d0,
+                rotate_amount: count,
+            }
+        );
+
+        // ROXL rotates the extend flag in, and ROL doesn't
+        cpu.registers.set_extend_flag(true);
+        cpu.registers.set_data_register(DataRegister::D0, 0x40_u32);
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0x81);
+        assert!(!cpu.registers.get_extend_flag());
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0x03);
+    }
+
+    #[test]
+    fn instruction_length() {
+        for program in [
+            vec![0x4E, 0x71],                         // nop
+            vec![0x4E, 0xF9, 0x00, 0x00, 0x02, 0x00], // jmp ($0200).l
+            vec![0x41, 0xFA, 0x00, 0x06],             // lea (6, pc), a0
+            vec![0x01, 0xC9, 0x00, 0x01],             // movep.l d0, (1, a1)
+        ] {
+            let parser = MachineCodeParser::default();
+            let (_, _, bytes_used) = parser.clone().parse(program.clone()).unwrap();
+            assert_eq!(parser.instruction_length(&program).unwrap(), bytes_used);
+        }
+
+        // Instructions the emulator can't run yet still have a length
+        let rte = vec![0x4E, 0x73];
+        assert!(matches!(
+            MachineCodeParser::default().parse(rte.clone()),
+            Err(ParseError::UnknownInstruction(_))
+        ));
+        assert_eq!(
+            MachineCodeParser::default()
+                .instruction_length(&rte)
+                .unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn default_size() {
+        for default_size in [OperandSize::Long, OperandSize::Word] {
+            let mut cpu = cpu_with_program(vec![0x4E, 0x71]); // nop
+            cpu.parser = Box::new(MachineCodeParser { default_size });
+            let disassembly = cpu.disassemble_at(START, 1);
+            assert_eq!(disassembly[0].2, default_size);
+        }
+    }
+
+    #[test]
+    fn decode_malformed_word() {
+        // Line A opcodes aren't instructions
+        let result = MachineCodeParser::default().parse(vec![0xA0, 0x00, 0, 0, 0, 0, 0, 0]);
+        assert!(
+            matches!(result, Err(ParseError::OpcodeParsingError(_))),
+            "{:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn shift_memory() {
+        let mut cpu = cpu_with_program(vec![
+            0xE3, 0xD0, // lsl (a0)
+        ]);
+        // With no count, memory is shifted by one bit
+        cpu.memory.write_word(Address(0x200), 0xC001).unwrap();
+        cpu.registers
+            .set_address_register(AddressRegister::A0, 0x200_u32);
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(cpu.memory.read_word(Address(0x200)).unwrap(), 0x8002);
+        assert!(cpu.registers.get_flags().carry);
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), START + 2);
+    }
+
+    #[test]
+    fn decode_move_peripheral() {
+        let mut cpu = cpu_with_program(vec![
+            0x01, 0xC9, 0x00, 0x01, // movep.l d0, (1, a1)
+        ]);
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0xAABB_CCDD_u32);
+        cpu.registers
+            .set_address_register(AddressRegister::A1, 0x20_u32);
+
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(
+            cpu.memory.read_bytes(Address(0x21), 7).unwrap(),
+            vec![0xAA, 0, 0xBB, 0, 0xCC, 0, 0xDD]
+        );
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), START + 4);
+    }
+
+    /// A device that records the value of D0 whenever it's written to
+    struct D0RecordingDevice {
+        ram: VecBackedMemory,
+        recorded: Vec<(Address, u8, u32)>,
+    }
+
+    impl D0RecordingDevice {
+        fn handle_write(
+            &mut self,
+            registers: &Registers,
+            address: Address,
+            value: u8,
+        ) -> Result<(), EmulationError> {
+            self.recorded.push((
+                address,
+                value,
+                registers.get_data_register(DataRegister::D0),
+            ));
+            self.write_byte(address, value)
+        }
+    }
+
+    impl Display for D0RecordingDevice {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", self.ram)
+        }
+    }
+
+    impl Memory for D0RecordingDevice {
+        fn new(size_in_bytes: usize) -> Self {
+            Self {
+                ram: VecBackedMemory::new(size_in_bytes),
+                recorded: vec![],
+            }
+        }
+
+        fn clear(&mut self) {
+            self.ram.clear();
+            self.recorded.clear();
+        }
+
+        fn read_byte(&self, address: Address) -> Result<u8, EmulationError> {
+            self.ram.read_byte(address)
+        }
+
+        fn write_byte(&mut self, address: Address, value: u8) -> Result<(), EmulationError> {
+            self.ram.write_byte(address, value)
+        }
+    }
+
+    #[test]
+    fn device_handler_reads_registers() {
+        let mut cpu = CPU::<D0RecordingDevice>::new(1024);
+        cpu.registers.set_data_register(DataRegister::D0, 42_u32);
+
+        let (registers, memory) = cpu.split_mut();
+        memory.handle_write(registers, Address(0x10), 7).unwrap();
+        registers.set_data_register(DataRegister::D0, 43_u32);
+        memory.handle_write(registers, Address(0x11), 8).unwrap();
+
+        assert_eq!(
+            cpu.memory.recorded,
+            vec![(Address(0x10), 7, 42), (Address(0x11), 8, 43)]
+        );
+        assert_eq!(cpu.memory.read_word(Address(0x10)).unwrap(), 0x0708);
+    }
+
+    #[test]
+    fn run_collecting() {
+        let mut cpu = cpu_with_program(vec![
+            0x20, 0x3C, 0x00, 0x00, 0x00, 0x01, // move.l #1, d0
+            0xD2, 0x80, // add.l d0, d1
+            0xD2, 0x80, // add.l d0, d1
+            0x4E, 0xF9, 0x00, 0x00, 0x01, 0x0A, // jmp ($010A).l (halt)
+        ]);
+
+        let snapshots = cpu.run_collecting(10);
+        assert_eq!(
+            snapshots
+                .iter()
+                .map(|registers| (registers.pc, registers.data[0], registers.data[1]))
+                .collect::<Vec<_>>(),
+            vec![(0x106, 1, 0), (0x108, 1, 1), (0x10A, 1, 2)]
+        );
+        assert_eq!(*snapshots.last().unwrap(), cpu.registers.dump());
+
+        // stops after n instructions
+        let mut cpu = cpu_with_program(vec![0x4E, 0x71, 0x4E, 0x71, 0x4E, 0x71]);
+        assert_eq!(cpu.run_collecting(2).len(), 2);
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), START + 4);
+    }
+
+    #[test]
+    fn run_until_halt() {
+        let mut cpu = cpu_with_program(vec![
+            0xD2, 0x80, // add.l d0, d1
+            0x4E, 0xF9, 0x00, 0x00, 0x01, 0x02, // jmp ($0102).l (halt)
+        ]);
+        cpu.registers.set_data_register(DataRegister::D0, 3_u32);
+        cpu.run().unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D1), 3);
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), START + 2);
+    }
+
+    #[test]
+    fn run_loop() {
+        let mut cpu = cpu_with_program(vec![
+            0x70, 0x05, // moveq #5, d0
+            0x74, 0x01, // moveq #1, d2
+            0xD2, 0x80, // loop: add.l d0, d1
+            0x90, 0x82, // sub.l d2, d0
+            0x66, 0xFA, // bne.s loop
+            0x60, 0xFE, // bra.s * (halt)
+        ]);
+        cpu.run().unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D1), 15);
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), START + 10);
+    }
+
+    #[test]
+    fn branch_to_itself() {
+        let mut cpu = cpu_with_program(vec![
+            0x60, 0xFE, // bra.s *
+        ]);
+        // The branch lands back where it started, which mustn't be mistaken for not having branched
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), START);
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), START);
+    }
+
+    #[test]
+    fn address_above_16_megabytes() {
+        let program = vec![
+            0x20, 0x28, 0x00, 0x20, // move.l ($20, a0), d0
+        ];
+        let mut memory = BankedMemory::with_banks(
+            1024,
+            Address(0x0100_0000),
+            vec![vec![0x12, 0x34, 0x56, 0x78]],
+            Address(0x3FF),
+        )
+        .unwrap();
+        memory.write_bytes(Address(START), program).unwrap();
+        memory.write_long(Address(0), 0xDEAD_BEEF).unwrap();
+
+        // The 68000 only has 24 address lines, so $01000000 wraps around to 0
+        let mut cpu = CPU::with_memory(memory.clone());
+        cpu.registers.set(Register::ProgramCounter, START);
+        cpu.registers
+            .set_address_register(AddressRegister::A0, 0x00FF_FFE0_u32);
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0),
+            0xDEAD_BEEF
+        );
+
+        // The 68020 can reach it
+        let mut cpu = CPU::with_memory(memory);
+        cpu.model = CpuModel::M68020;
+        cpu.registers.set(Register::ProgramCounter, START);
+        cpu.registers
+            .set_address_register(AddressRegister::A0, 0x00FF_FFE0_u32);
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0),
+            0x1234_5678
+        );
+    }
+
+    #[test]
+    fn decode_word_index() {
+        let mut cpu = cpu_with_program(vec![
+            0x20, 0x30, 0x10, 0x00, // move.l (0, a0, d1.w), d0
+        ]);
+        // A word index is sign-extended, so $FFFC is -4
+        cpu.memory.write_long(Address(0x200), 0x1234_5678).unwrap();
+        cpu.registers
+            .set_address_register(AddressRegister::A0, 0x204_u32);
+        cpu.registers
+            .set_data_register(DataRegister::D1, 0xFFFC_u32);
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0),
+            0x1234_5678
+        );
+        assert!(matches!(
+            cpu.disassemble_at(START, 1)[0].1,
+            Ok(Instruction::Move {
+                src: AddressMode::RegisterIndirectIndexed {
+                    index_size: OperandSize::Word,
+                    ..
+                },
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn run_decrement_branch_to_itself() {
+        let mut cpu = cpu_with_program(vec![
+            0x51, 0xC8, 0xFF, 0xFE, // loop: dbra d0, loop
+            0x60, 0xFE, // bra.s * (halt)
+        ]);
+        // Branching to itself doesn't halt a loop that's still counting down
+        cpu.registers.set_data_register(DataRegister::D0, 5_u32);
+        assert!(!cpu.is_halted().unwrap());
+        cpu.run().unwrap();
+        assert_eq!(cpu.instruction_count(), 6);
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 0xFFFF);
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), START + 4);
+        assert!(cpu.is_halted().unwrap());
+    }
+
+    #[test]
+    fn run_decrement_branch_loop() {
+        let mut cpu = cpu_with_program(vec![
+            0x74, 0x01, // moveq #1, d2
+            0xD2, 0x82, // loop: add.l d2, d1
+            0x51, 0xC8, 0xFF, 0xFC, // dbf d0, loop
+            0x60, 0xFE, // bra.s * (halt)
+        ]);
+        // The loop runs once more than the counter, and stops when it wraps around to $FFFF
+        cpu.registers
+            .set_data_register(DataRegister::D0, 0xABCD_0003_u32);
+        cpu.run().unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D1), 4);
+        assert_eq!(
+            cpu.registers.get_data_register(DataRegister::D0),
+            0xABCD_FFFF
+        );
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), START + 8);
+        assert_eq!(
+            cpu.disassemble_at(START + 4, 1)[0].1.as_ref().unwrap(),
+            &Instruction::DecrementBranch {
+                condition: Condition::False,
+                register: DataRegister::D0,
+                displacement: -4,
+            }
+        );
+    }
+
+    #[test]
+    fn run_until_pc() {
+        let program = vec![
+            0x70, 0x05, // moveq #5, d0
+            0x74, 0x01, // moveq #1, d2
+            0xD2, 0x80, // loop: add.l d0, d1
+            0x90, 0x82, // sub.l d2, d0
+            0x66, 0xFA, // bne.s loop
+            0xA0, 0x00, // exit: not an instruction
+        ];
+        let exit = START + 10;
+
+        let mut cpu = cpu_with_program(program.clone());
+        cpu.run_until_pc(exit, 17).unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D1), 15);
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), exit);
+        assert_eq!(cpu.instruction_count(), 17);
+        // Already there
+        cpu.run_until_pc(exit, 0).unwrap();
+
+        // One instruction short, it's partway through the last time around the loop
+        let mut cpu = cpu_with_program(program);
+        assert!(matches!(
+            cpu.run_until_pc(exit, 16),
+            Err(EmulationError::TargetNotReached(Address(pc))) if pc == START + 8
+        ));
+        // Running past the target stops at the instruction that can't be run
+        assert!(matches!(
+            cpu.run_until_pc(exit + 2, 100),
+            Err(EmulationError::Parsing(_))
+        ));
+    }
+
+    #[test]
+    fn run_subroutine() {
+        let mut cpu = cpu_with_program(vec![
+            0x61, 0x02, // bsr.s sub
+            0x60, 0xFE, // bra.s * (halt)
+            0x70, 0x07, // sub: moveq #7, d0
+            0x4E, 0x75, // rts
+        ]);
+        cpu.registers
+            .set_address_register(AddressRegister::A7, 0x200_u32);
+        cpu.run().unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 7);
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), START + 2);
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A7),
+            0x200
+        );
+        assert_eq!(cpu.memory.read_long(Address(0x1FC)).unwrap(), START + 2);
+    }
+
+    #[test]
+    fn jump_to_subroutine_and_return() {
+        let mut cpu = cpu_with_program(vec![
+            0x4E, 0xB9, 0x00, 0x00, 0x01, 0x0A, // jsr ($010A).l
+            0x60, 0xFE, // bra.s * (halt)
+            0x4E, 0x71, // nop
+            0x70, 0x07, // moveq #7, d0
+            0x4E, 0x75, // rts
+        ]);
+        cpu.registers
+            .set_address_register(AddressRegister::A7, 0x200_u32);
+
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), START + 10);
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A7),
+            0x1FC
+        );
+        assert_eq!(cpu.memory.read_long(Address(0x1FC)).unwrap(), START + 6);
+
+        cpu.run().unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 7);
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), START + 6);
+        assert_eq!(
+            cpu.registers.get_address_register(AddressRegister::A7),
+            0x200
+        );
+    }
+
+    #[test]
+    fn jump_to_subroutine_with_short_address() {
+        let mut cpu = cpu_with_program(vec![
+            0x4E, 0xB8, 0x01, 0x10, // jsr ($0110).w
+            0x60, 0xFE, // bra.s * (halt)
+            0x4E, 0x71, 0x4E, 0x71, 0x4E, 0x71, 0x4E, 0x71, 0x4E, 0x71, // nop (x5)
+            0x70, 0x07, // $0110: moveq #7, d0
+            0x4E, 0x75, // rts
+        ]);
+        cpu.registers
+            .set_address_register(AddressRegister::A7, 0x200_u32);
+
+        // The return address is right after the word-sized address
+        cpu.run_one_cycle().unwrap();
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), 0x110);
+        assert_eq!(cpu.memory.read_long(Address(0x1FC)).unwrap(), START + 4);
+
+        cpu.run().unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 7);
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), START + 4);
+    }
+
+    #[test]
+    fn clone_mid_execution() {
+        let mut cpu = cpu_with_program(vec![
+            0x70, 0x05, // moveq #5, d0
+            0x74, 0x01, // moveq #1, d2
+            0xD2, 0x80, // loop: add.l d0, d1
+            0x90, 0x82, // sub.l d2, d0
+            0x66, 0xFA, // bne.s loop
+            0x60, 0xFE, // bra.s * (halt)
+        ]);
+        for _ in 0..4 {
+            cpu.run_one_cycle().unwrap();
+        }
+
+        let mut clone = cpu.clone();
+        clone
+            .memory
+            .write_long(Address(START + 0x100), 0xDEAD_BEEF)
+            .unwrap();
+        clone.run().unwrap();
+        assert_eq!(clone.registers.get_data_register(DataRegister::D1), 15);
+        assert_eq!(clone.instruction_count(), 17);
+
+        // the original is still partway through the first pass of the loop
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 4);
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D1), 5);
+        assert_eq!(cpu.registers.get(Register::ProgramCounter), START + 8);
+        assert_eq!(cpu.instruction_count(), 4);
+        assert_eq!(cpu.memory.read_long(Address(START + 0x100)).unwrap(), 0);
+
+        // and it runs to the same place on its own
+        cpu.run().unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D1), 15);
+    }
+
+    #[test]
+    fn counts_instructions_and_cycles() {
+        let mut cpu = cpu_with_program(vec![
+            0x70, 0x01, // moveq #1, d0 (4 cycles)
+            0xD2, 0x80, // add.l d0, d1 (8 cycles)
+            0x32, 0x10, // move.w (a0), d1 (8 cycles)
+            0x4E, 0x71, // nop (4 cycles)
+            0x4E, 0xF9, 0x00, 0x00, 0x01, 0x08, // jmp ($0108).l (halt)
+        ]);
+        assert_eq!((cpu.instruction_count(), cpu.total_cycles()), (0, 0));
+
+        cpu.run().unwrap();
+        assert_eq!(cpu.instruction_count(), 4);
+        assert_eq!(cpu.total_cycles(), 24);
+
+        // instructions that fail aren't counted
+        cpu.registers.set(Register::ProgramCounter, 0x00FF_FFF0_u32);
+        assert!(cpu.run_one_cycle().is_err());
+        assert_eq!(cpu.instruction_count(), 4);
+    }
+
+    #[test]
+    fn ran_into_zeros() {
+        // a couple of NOPs, then the rest of memory is zeroed
+        let mut cpu = cpu_with_program(vec![0x4E, 0x71, 0x4E, 0x71]);
+        cpu.zero_opcode_limit = Some(8);
+        match cpu.run() {
+            Err(EmulationError::RanIntoZeros(address)) => assert_eq!(address, Address(START + 4)),
+            other => panic!("expected RanIntoZeros, got {:?}", other),
+        }
+
+        // zeros that are interrupted by another instruction don't count as a run
+        let mut program = vec![0; 8 * 4];
+        program.extend([0x4E, 0x71]);
+        program.extend(vec![0; 8 * 4]);
+        let mut cpu = cpu_with_program(program);
+        cpu.zero_opcode_limit = Some(8);
+        for _ in 0..17 {
+            cpu.run_one_cycle().unwrap();
+        }
+        assert!(matches!(
+            cpu.run_one_cycle(),
+            Err(EmulationError::RanIntoZeros(address)) if address == Address(START + 8 * 4 + 2)
+        ));
+
+        // without a limit, zeros are run like any other instruction
+        let mut cpu = cpu_with_program(vec![]);
+        for _ in 0..32 {
+            cpu.run_one_cycle().unwrap();
+        }
     }
 }
@@ -2,12 +2,76 @@
 //!
 //! There are 7 data registers, 7 address registers, a program counter, and the status register.
 
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
-use crate::{hex_format_long, hex_format_word};
+use crate::{hex_format_long, hex_format_word, parsers::ParseError, Flags};
 
 pub type RegisterValue = u32;
 
+// Bits of the condition code register (the low byte of the status register)
+const CARRY: u16 = 1 << 0;
+const OVERFLOW: u16 = 1 << 1;
+const ZERO: u16 = 1 << 2;
+const NEGATIVE: u16 = 1 << 3;
+const EXTEND: u16 = 1 << 4;
+
+/// A set of condition codes, as a mask of CCR bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlagMask(pub u8);
+
+impl FlagMask {
+    pub const NONE: FlagMask = FlagMask(0);
+    pub const CARRY: FlagMask = FlagMask(CARRY as u8);
+    pub const OVERFLOW: FlagMask = FlagMask(OVERFLOW as u8);
+    pub const ZERO: FlagMask = FlagMask(ZERO as u8);
+    pub const NEGATIVE: FlagMask = FlagMask(NEGATIVE as u8);
+    pub const EXTEND: FlagMask = FlagMask(EXTEND as u8);
+    /// Every condition code except the extend flag
+    pub const NZVC: FlagMask = FlagMask((NEGATIVE | ZERO | OVERFLOW | CARRY) as u8);
+    /// Every condition code
+    pub const XNZVC: FlagMask = FlagMask((EXTEND | NEGATIVE | ZERO | OVERFLOW | CARRY) as u8);
+}
+
+/// A single condition code in the CCR
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CcrFlag {
+    Carry,
+    Overflow,
+    Zero,
+    Negative,
+    Extend,
+}
+
+impl CcrFlag {
+    /// Every condition code, from the lowest bit to the highest
+    pub const ALL: [CcrFlag; 5] = [
+        CcrFlag::Carry,
+        CcrFlag::Overflow,
+        CcrFlag::Zero,
+        CcrFlag::Negative,
+        CcrFlag::Extend,
+    ];
+}
+
+impl From<CcrFlag> for FlagMask {
+    fn from(flag: CcrFlag) -> Self {
+        match flag {
+            CcrFlag::Carry => FlagMask::CARRY,
+            CcrFlag::Overflow => FlagMask::OVERFLOW,
+            CcrFlag::Zero => FlagMask::ZERO,
+            CcrFlag::Negative => FlagMask::NEGATIVE,
+            CcrFlag::Extend => FlagMask::EXTEND,
+        }
+    }
+}
+
+// Bits of the system byte (the high byte of the status register)
+const INTERRUPT_MASK: u16 = 0b111 << 8;
+const SUPERVISOR: u16 = 1 << 13;
+
+/// The status register after a reset: supervisor mode, all interrupts masked, and the CCR clear
+pub const RESET_STATUS: u16 = SUPERVISOR | INTERRUPT_MASK;
+
 /// The status register is smaller and has its own methods
 #[derive(Debug, Clone, Copy, PartialEq, Eq)] // remove if perf issue
 pub enum Register {
@@ -16,6 +80,51 @@ pub enum Register {
     ProgramCounter,
 }
 
+impl Display for Register {
+    /// Formats a register the way it's written in assembly.
+    ///
+    /// A7 is written as `a7`, or as `sp` with the alternate flag (`{:#}`).
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Register::Data(reg) => write!(f, "d{}", *reg as u8),
+            Register::Address(AddressRegister::A7) if f.alternate() => write!(f, "sp"),
+            Register::Address(reg) => write!(f, "a{}", *reg as u8),
+            Register::ProgramCounter => write!(f, "pc"),
+        }
+    }
+}
+
+impl FromStr for Register {
+    type Err = ParseError;
+
+    /// Parses a (lowercase) register name, such as `d0`, `a7`/`sp`, or `pc`
+    fn from_str(register: &str) -> Result<Self, Self::Err> {
+        match register {
+            "d0" => Ok(Register::Data(DataRegister::D0)),
+            "d1" => Ok(Register::Data(DataRegister::D1)),
+            "d2" => Ok(Register::Data(DataRegister::D2)),
+            "d3" => Ok(Register::Data(DataRegister::D3)),
+            "d4" => Ok(Register::Data(DataRegister::D4)),
+            "d5" => Ok(Register::Data(DataRegister::D5)),
+            "d6" => Ok(Register::Data(DataRegister::D6)),
+            "d7" => Ok(Register::Data(DataRegister::D7)),
+
+            "a0" => Ok(Register::Address(AddressRegister::A0)),
+            "a1" => Ok(Register::Address(AddressRegister::A1)),
+            "a2" => Ok(Register::Address(AddressRegister::A2)),
+            "a3" => Ok(Register::Address(AddressRegister::A3)),
+            "a4" => Ok(Register::Address(AddressRegister::A4)),
+            "a5" => Ok(Register::Address(AddressRegister::A5)),
+            "a6" => Ok(Register::Address(AddressRegister::A6)),
+            "a7" | "sp" => Ok(Register::Address(AddressRegister::A7)),
+
+            "pc" => Ok(Register::ProgramCounter),
+
+            _ => Err(ParseError::UnknownRegister(register.to_string())),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)] // remove if perf issue
 pub enum DataRegister {
     D0,
@@ -71,7 +180,12 @@ impl From<m68kdecode::AddressRegister> for AddressRegister {
     }
 }
 
-#[derive(Default)]
+/// The CPU's registers.
+///
+/// New registers (from [`Registers::new`] or [`Registers::default`]) are in the 68000's reset state:
+/// the data and address registers and the program counter are zero, and the status register is [`RESET_STATUS`]
+/// (supervisor mode, with all interrupts masked).
+#[derive(Clone)]
 pub struct Registers {
     // Data registers
     d0: RegisterValue,
@@ -100,11 +214,121 @@ pub struct Registers {
     status: u16,
 }
 
+/// A snapshot of every register's value, for inspecting CPU state between instructions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RegisterFile {
+    /// D0-D7
+    pub data: [RegisterValue; 8],
+    /// A0-A7
+    pub address: [RegisterValue; 8],
+    pub pc: RegisterValue,
+    pub status: u16,
+}
+
+/// Lists every register and condition code that differs between two snapshots, with its value in `a` and then in `b`,
+/// such as `D0: 0x00000001 → 0x00000002, Z: 0 → 1`.
+///
+/// Condition codes are listed individually; the whole status register is only listed if its system byte changed.
+/// Returns an empty string if the snapshots are the same.
+pub fn diff_state(a: &RegisterFile, b: &RegisterFile) -> String {
+    let mut differences = vec![];
+    let mut compare = |name: String, old: RegisterValue, new: RegisterValue| {
+        if old != new {
+            differences.push(format!(
+                "{}: 0x{} → 0x{}",
+                name,
+                hex_format_long(old),
+                hex_format_long(new)
+            ));
+        }
+    };
+    for (i, (&old, &new)) in a.data.iter().zip(&b.data).enumerate() {
+        compare(format!("D{}", i), old, new);
+    }
+    for (i, (&old, &new)) in a.address.iter().zip(&b.address).enumerate() {
+        compare(format!("A{}", i), old, new);
+    }
+    compare("PC".to_string(), a.pc, b.pc);
+
+    for (name, bit) in [
+        ("X", EXTEND),
+        ("N", NEGATIVE),
+        ("Z", ZERO),
+        ("V", OVERFLOW),
+        ("C", CARRY),
+    ] {
+        let (old, new) = (a.status & bit != 0, b.status & bit != 0);
+        if old != new {
+            differences.push(format!("{}: {} → {}", name, old as u8, new as u8));
+        }
+    }
+    if a.status >> 8 != b.status >> 8 {
+        differences.push(format!(
+            "SR: 0x{} → 0x{}",
+            hex_format_word(a.status),
+            hex_format_word(b.status)
+        ));
+    }
+    differences.join(", ")
+}
+
+impl Default for Registers {
+    fn default() -> Self {
+        Self {
+            d0: 0,
+            d1: 0,
+            d2: 0,
+            d3: 0,
+            d4: 0,
+            d5: 0,
+            d6: 0,
+            d7: 0,
+            a0: 0,
+            a1: 0,
+            a2: 0,
+            a3: 0,
+            a4: 0,
+            a5: 0,
+            a6: 0,
+            a7: 0,
+            pc: 0,
+            status: RESET_STATUS,
+        }
+    }
+}
+
 impl Registers {
+    /// Creates a set of zeroed registers, with the status register in its reset state ([`RESET_STATUS`])
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Takes a snapshot of all the registers
+    pub fn dump(&self) -> RegisterFile {
+        RegisterFile {
+            data: [
+                self.d0, self.d1, self.d2, self.d3, self.d4, self.d5, self.d6, self.d7,
+            ],
+            address: [
+                self.a0, self.a1, self.a2, self.a3, self.a4, self.a5, self.a6, self.a7,
+            ],
+            pc: self.pc,
+            status: self.status,
+        }
+    }
+
+    /// Sets every register from a snapshot taken with [`Registers::dump`]
+    pub fn restore(&mut self, snapshot: &RegisterFile) {
+        [
+            self.d0, self.d1, self.d2, self.d3, self.d4, self.d5, self.d6, self.d7,
+        ] = snapshot.data;
+        [
+            self.a0, self.a1, self.a2, self.a3, self.a4, self.a5, self.a6, self.a7,
+        ] = snapshot.address;
+        self.pc = snapshot.pc;
+        self.status = snapshot.status;
+    }
+
     pub fn get(&self, register: impl Into<Register>) -> RegisterValue {
         match register.into() {
             Register::Data(reg) => self.get_data_register(reg),
@@ -184,6 +408,60 @@ impl Registers {
         }
     }
 
+    /// Gets the register numbered `index` (0-7) among the address registers if `is_address` is set,
+    /// or among the data registers otherwise
+    fn register_by_index(is_address: bool, index: u8) -> Option<Register> {
+        let index = index as usize;
+        if is_address {
+            [
+                AddressRegister::A0,
+                AddressRegister::A1,
+                AddressRegister::A2,
+                AddressRegister::A3,
+                AddressRegister::A4,
+                AddressRegister::A5,
+                AddressRegister::A6,
+                AddressRegister::A7,
+            ]
+            .get(index)
+            .map(|&register| Register::Address(register))
+        } else {
+            [
+                DataRegister::D0,
+                DataRegister::D1,
+                DataRegister::D2,
+                DataRegister::D3,
+                DataRegister::D4,
+                DataRegister::D5,
+                DataRegister::D6,
+                DataRegister::D7,
+            ]
+            .get(index)
+            .map(|&register| Register::Data(register))
+        }
+    }
+
+    /// Gets a data or address register by its number, like MOVEM's register lists do
+    ///
+    /// Returns `None` if `index` is greater than 7.
+    pub fn get_by_index(&self, is_address: bool, index: u8) -> Option<RegisterValue> {
+        Self::register_by_index(is_address, index).map(|register| self.get(register))
+    }
+
+    /// Sets a data or address register by its number
+    ///
+    /// Returns `None`, leaving the registers alone, if `index` is greater than 7.
+    pub fn set_by_index(
+        &mut self,
+        is_address: bool,
+        index: u8,
+        new_value: impl Into<RegisterValue> + Copy,
+    ) -> Option<()> {
+        let register = Self::register_by_index(is_address, index)?;
+        self.set(register, new_value);
+        Some(())
+    }
+
     pub fn get_status_register(&self) -> u16 {
         self.status
     }
@@ -191,13 +469,74 @@ impl Registers {
     pub fn set_status_register(&mut self, new_value: u16) {
         self.status = new_value;
     }
+
+    /// Clears every condition code (including the extend flag), leaving the system byte alone
+    pub fn clear_ccr(&mut self) {
+        self.status &= 0xFF00;
+    }
+
+    /// Returns true if every condition code in `flag` is set
+    pub fn get_ccr_flag(&self, flag: FlagMask) -> bool {
+        let bits = flag.0 as u16;
+        self.status & bits == bits
+    }
+
+    /// Sets or clears every condition code in `flag`, leaving the rest of the status register alone
+    pub fn set_ccr_flag(&mut self, flag: FlagMask, value: bool) {
+        if value {
+            self.status |= flag.0 as u16;
+        } else {
+            self.status &= !(flag.0 as u16);
+        }
+    }
+
+    pub fn get_ccr(&self, flag: CcrFlag) -> bool {
+        self.get_ccr_flag(flag.into())
+    }
+
+    pub fn set_ccr(&mut self, flag: CcrFlag, value: bool) {
+        self.set_ccr_flag(flag.into(), value);
+    }
+
+    /// Gets the carry, overflow, zero, and negative flags from the CCR
+    pub fn get_flags(&self) -> Flags {
+        Flags {
+            carry: self.get_ccr(CcrFlag::Carry),
+            overflow: self.get_ccr(CcrFlag::Overflow),
+            zero: self.get_ccr(CcrFlag::Zero),
+            negative: self.get_ccr(CcrFlag::Negative),
+        }
+    }
+
+    /// Sets the carry, overflow, zero, and negative flags in the CCR, leaving the extend flag alone
+    pub fn set_flags(&mut self, flags: Flags) {
+        self.set_ccr(CcrFlag::Carry, flags.carry);
+        self.set_ccr(CcrFlag::Overflow, flags.overflow);
+        self.set_ccr(CcrFlag::Zero, flags.zero);
+        self.set_ccr(CcrFlag::Negative, flags.negative);
+    }
+
+    /// Returns true if the CPU is in supervisor mode (rather than user mode)
+    pub fn is_supervisor(&self) -> bool {
+        self.status & SUPERVISOR != 0
+    }
+
+    pub fn get_extend_flag(&self) -> bool {
+        self.get_ccr(CcrFlag::Extend)
+    }
+
+    pub fn set_extend_flag(&mut self, extend: bool) {
+        self.set_ccr(CcrFlag::Extend, extend);
+    }
 }
 
 impl Display for Registers {
+    /// With the alternate flag (`{:#}`), A7 is labeled `SP` instead of `A7`.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let stack_pointer_name = if f.alternate() { "SP" } else { "A7" };
         writeln!(
             f,
-            "A0: 0x{} | A1: 0x{} | A2: 0x{} | A3: 0x{} | A4: 0x{} | A5: 0x{} | A6: 0x{} | A7: 0x{}",
+            "A0: 0x{} | A1: 0x{} | A2: 0x{} | A3: 0x{} | A4: 0x{} | A5: 0x{} | A6: 0x{} | {}: 0x{}",
             hex_format_long(self.a0),
             hex_format_long(self.a1),
             hex_format_long(self.a2),
@@ -205,6 +544,7 @@ impl Display for Registers {
             hex_format_long(self.a4),
             hex_format_long(self.a5),
             hex_format_long(self.a6),
+            stack_pointer_name,
             hex_format_long(self.a7)
         )?;
         writeln!(
@@ -252,4 +592,198 @@ mod tests {
             assert_eq!(registers.get(*register), val);
         }
     }
+
+    #[test]
+    fn dump() {
+        let mut registers = Registers::new();
+        registers.set_data_register(D2, 0xABCD_u32);
+        registers.set_address_register(A7, 0x1000_u32);
+        registers.set(Register::ProgramCounter, 0x42_u32);
+        registers.set_status_register(0x2700);
+
+        let snapshot = registers.dump();
+        assert_eq!(snapshot.data, [0, 0, 0xABCD, 0, 0, 0, 0, 0]);
+        assert_eq!(snapshot.address, [0, 0, 0, 0, 0, 0, 0, 0x1000]);
+        assert_eq!(snapshot.pc, 0x42);
+        assert_eq!(snapshot.status, 0x2700);
+
+        let mut restored = Registers::new();
+        restored.restore(&snapshot);
+        assert_eq!(restored.dump(), snapshot);
+        assert_eq!(restored.get_data_register(D2), 0xABCD);
+    }
+
+    #[test]
+    fn diff() {
+        let mut registers = Registers::new();
+        registers.set_data_register(D0, 1_u32);
+        registers.set_address_register(A7, 0x1000_u32);
+        let before = registers.dump();
+        assert_eq!(diff_state(&before, &before), "");
+
+        registers.set_data_register(D0, 2_u32);
+        registers.set(Register::ProgramCounter, 0x102_u32);
+        registers.set_ccr_flag(FlagMask::ZERO, true);
+        registers.set_ccr_flag(FlagMask::CARRY, true);
+        let after = registers.dump();
+        assert_eq!(
+            diff_state(&before, &after),
+            "D0: 0x00000001 → 0x00000002, PC: 0x00000000 → 0x00000102, Z: 0 → 1, C: 0 → 1"
+        );
+        assert_eq!(
+            diff_state(&after, &before),
+            "D0: 0x00000002 → 0x00000001, PC: 0x00000102 → 0x00000000, Z: 1 → 0, C: 1 → 0"
+        );
+
+        // Changes outside the CCR show the whole status register
+        registers.set_status_register(CARRY | ZERO);
+        assert_eq!(diff_state(&after, &registers.dump()), "SR: 0x2705 → 0x0005");
+    }
+
+    #[test]
+    fn flags() {
+        let mut registers = Registers::new();
+        registers.set_status_register(0x2700 | EXTEND);
+
+        let flags = Flags {
+            carry: true,
+            negative: true,
+            ..Flags::default()
+        };
+        registers.set_flags(flags);
+        assert_eq!(registers.get_flags(), flags);
+        // The system byte and extend flag are untouched
+        assert_eq!(
+            registers.get_status_register(),
+            0x2700 | EXTEND | CARRY | NEGATIVE
+        );
+
+        registers.set_flags(Flags::default());
+        registers.set_extend_flag(false);
+        assert_eq!(registers.get_status_register(), 0x2700);
+    }
+
+    #[test]
+    fn ccr_flags() {
+        let mut registers = Registers::new();
+        registers.set_ccr_flag(FlagMask::ZERO, true);
+        registers.set_ccr_flag(FlagMask::EXTEND, true);
+        assert!(registers.get_ccr_flag(FlagMask::ZERO));
+        assert!(registers.get_ccr_flag(FlagMask::EXTEND));
+        assert!(!registers.get_ccr_flag(FlagMask::CARRY));
+        assert_eq!(registers.get_status_register(), 0x2700 | ZERO | EXTEND);
+
+        // A mask of several flags is only set if all of them are
+        assert!(!registers.get_ccr_flag(FlagMask::XNZVC));
+        registers.set_ccr_flag(FlagMask::XNZVC, true);
+        assert!(registers.get_ccr_flag(FlagMask::XNZVC));
+        registers.set_ccr_flag(FlagMask::NZVC, false);
+        assert_eq!(registers.get_status_register(), 0x2700 | EXTEND);
+    }
+
+    #[test]
+    fn ccr_flags_are_independent() {
+        for (bit, flag) in CcrFlag::ALL.into_iter().enumerate() {
+            for system in [0x0000, 0x2700, 0xFF00] {
+                let mut registers = Registers::new();
+                registers.set_status_register(system);
+                registers.set_ccr(flag, true);
+                assert_eq!(registers.get_status_register(), system | 1 << bit);
+                for other in CcrFlag::ALL {
+                    assert_eq!(registers.get_ccr(other), other == flag, "{:?}", other);
+                }
+
+                // Clearing a flag leaves the others (and the system byte) set
+                registers.set_status_register(system | 0x1F);
+                registers.set_ccr(flag, false);
+                assert_eq!(
+                    registers.get_status_register(),
+                    system | (0x1F & !(1 << bit))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn reset_status() {
+        assert_eq!(Registers::default().get_status_register(), 0x2700);
+        let mut registers = Registers::new();
+        assert_eq!(registers.get_status_register(), 0x2700);
+        assert!(registers.is_supervisor());
+        assert_eq!(registers.get_flags(), Flags::default());
+        assert!(!registers.get_extend_flag());
+
+        registers.set_status_register(0x2000 | EXTEND | ZERO | CARRY);
+        registers.clear_ccr();
+        assert_eq!(registers.get_status_register(), 0x2000);
+
+        registers.set_status_register(0x0700);
+        assert!(!registers.is_supervisor());
+    }
+
+    #[test]
+    fn registers_by_index() {
+        let mut registers = Registers::new();
+        for index in 0..8 {
+            registers
+                .set_by_index(false, index, 0x100 + index as u32)
+                .unwrap();
+            registers
+                .set_by_index(true, index, 0x200 + index as u32)
+                .unwrap();
+        }
+
+        let address_registers = [A0, A1, A2, A3, A4, A5, A6, A7];
+        let data_registers = [D0, D1, D2, D3, D4, D5, D6, D7];
+        for index in 0..8 {
+            let data = registers.get_data_register(data_registers[index as usize]);
+            let address = registers.get_address_register(address_registers[index as usize]);
+            assert_eq!(data, 0x100 + index as u32);
+            assert_eq!(address, 0x200 + index as u32);
+            assert_eq!(registers.get_by_index(false, index), Some(data));
+            assert_eq!(registers.get_by_index(true, index), Some(address));
+        }
+    }
+
+    #[test]
+    fn register_index_out_of_range() {
+        let mut registers = Registers::new();
+        assert_eq!(registers.get_by_index(true, 8), None);
+        assert_eq!(registers.get_by_index(false, 255), None);
+        assert_eq!(registers.set_by_index(false, 8, 1_u32), None);
+        assert_eq!(registers.get_data_register(D0), 0);
+    }
+
+    #[test]
+    fn register_names_round_trip() {
+        let address_registers = [A0, A1, A2, A3, A4, A5, A6, A7].map(Register::Address);
+        let data_registers = [D0, D1, D2, D3, D4, D5, D6, D7].map(Register::Data);
+        for register in address_registers
+            .iter()
+            .chain(data_registers.iter())
+            .chain([Register::ProgramCounter].iter())
+        {
+            assert_eq!(register.to_string().parse::<Register>().unwrap(), *register);
+        }
+
+        assert_eq!(Register::Address(A7).to_string(), "a7");
+        assert_eq!("sp".parse::<Register>().unwrap(), Register::Address(A7));
+        assert_eq!(Register::Data(D3).to_string(), "d3");
+        assert!("d8".parse::<Register>().is_err());
+    }
+
+    #[test]
+    fn stack_pointer_rendering() {
+        let stack_pointer = Register::Address(A7);
+        assert_eq!(format!("{}", stack_pointer), "a7");
+        assert_eq!(format!("{:#}", stack_pointer), "sp");
+        assert_eq!(format!("{:#}", Register::Address(A6)), "a6");
+        assert_eq!(format!("{:#}", "sp".parse::<Register>().unwrap()), "sp");
+
+        let mut registers = Registers::new();
+        registers.set(stack_pointer, 0x1234_u32);
+        assert!(format!("{}", registers).contains("A7: 0x00001234"));
+        assert!(format!("{:#}", registers).contains("SP: 0x00001234"));
+        assert!(!format!("{:#}", registers).contains("A7"));
+    }
 }
@@ -0,0 +1,141 @@
+//! Names for addresses, such as the labels in an assembled program or the functions in a linked one,
+//! so disassembly and profiles can show `strcpy` instead of `$1234`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::parsers::ParseError;
+
+/// Maps addresses to names
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolTable {
+    names: BTreeMap<u32, String>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Names `address`, replacing any name it already had
+    pub fn insert(&mut self, address: u32, name: impl Into<String>) {
+        self.names.insert(address, name.into());
+    }
+
+    /// Gets the name of `address`, if it has one
+    pub fn name(&self, address: u32) -> Option<&str> {
+        self.names.get(&address).map(String::as_str)
+    }
+
+    /// Finds the closest symbol at or before `address` (i.e. the function or label it's part of),
+    /// along with how far past the symbol `address` is
+    pub fn containing(&self, address: u32) -> Option<(&str, u32)> {
+        self.names
+            .range(..=address)
+            .next_back()
+            .map(|(symbol_address, name)| (name.as_str(), address - symbol_address))
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Parses a symbol file in the format `nm` prints, with a hex address and a name on each line,
+    /// optionally separated by a symbol type (`00001234 T strcpy`).
+    ///
+    /// Blank lines are ignored.
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let mut table = Self::new();
+        for (line_number, line) in text.lines().enumerate() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (address, name) = match fields[..] {
+                [] => continue,
+                [address, name] | [address, _, name] => (address, name),
+                _ => {
+                    return Err(ParseError::AtLine {
+                        line: line_number + 1,
+                        source: Box::new(ParseError::InvalidSymbol(line.to_string())),
+                    })
+                }
+            };
+            let address =
+                u32::from_str_radix(address.trim_start_matches("0x"), 16).map_err(|error| {
+                    ParseError::AtLine {
+                        line: line_number + 1,
+                        source: Box::new(ParseError::InvalidNumber {
+                            number: address.to_string(),
+                            error,
+                        }),
+                    }
+                })?;
+            table.insert(address, name);
+        }
+        Ok(table)
+    }
+}
+
+/// Makes a symbol table from labels, such as [`AssemblyInterpreter::symbols`](crate::parsers::assembly::AssemblyInterpreter::symbols).
+///
+/// If several labels are at the same address, the one that comes first alphabetically is used.
+impl From<&HashMap<String, u32>> for SymbolTable {
+    fn from(labels: &HashMap<String, u32>) -> Self {
+        let mut table = Self::new();
+        for (name, &address) in labels {
+            if !matches!(table.name(address), Some(existing) if existing < name.as_str()) {
+                table.insert(address, name.clone());
+            }
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn containing() {
+        let mut symbols = SymbolTable::new();
+        symbols.insert(0x100, "main");
+        symbols.insert(0x200, "strcpy");
+
+        assert_eq!(symbols.name(0x200), Some("strcpy"));
+        assert_eq!(symbols.name(0x204), None);
+        assert_eq!(symbols.containing(0xFF), None);
+        assert_eq!(symbols.containing(0x100), Some(("main", 0)));
+        assert_eq!(symbols.containing(0x1FE), Some(("main", 0xFE)));
+        assert_eq!(symbols.containing(0x204), Some(("strcpy", 4)));
+    }
+
+    #[test]
+    fn parse_symbol_file() {
+        let symbols = SymbolTable::parse("00000100 T main\n\n0x200 strcpy\n").unwrap();
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols.name(0x100), Some("main"));
+        assert_eq!(symbols.name(0x200), Some("strcpy"));
+
+        assert!(matches!(
+            SymbolTable::parse("100 main\nmain"),
+            Err(ParseError::AtLine { line: 2, .. })
+        ));
+        assert!(matches!(
+            SymbolTable::parse("xyz T main"),
+            Err(ParseError::AtLine { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn from_labels() {
+        let labels = HashMap::from([
+            ("loop".to_string(), 0x102),
+            ("start".to_string(), 0x100),
+            ("begin".to_string(), 0x100),
+        ]);
+        let symbols = SymbolTable::from(&labels);
+        assert_eq!(symbols.name(0x100), Some("begin"));
+        assert_eq!(symbols.name(0x102), Some("loop"));
+    }
+}
@@ -0,0 +1,417 @@
+//! A minimal server for GDB's remote serial protocol, so programs running on the emulator can be debugged with a real debugger
+//!
+//! Only the packets needed for basic debugging are supported: reading and writing registers (`g`/`G`) and memory (`m`/`M`),
+//! stepping (`s`), continuing (`c`), and software breakpoints (`Z0`/`z0`).
+//! Anything else gets an empty reply, which tells GDB it isn't supported.
+//!
+//! To debug a program, serve a connection (see [`GdbStub::serve`]) and run `target remote HOST:PORT` in `m68k-elf-gdb`.
+//! Interrupting a running program isn't supported, so set a breakpoint before continuing past code that might loop forever.
+
+use std::{
+    collections::BTreeSet,
+    io::{self, Read, Write},
+};
+
+use crate::{
+    cpu::{registers::Register, CPU},
+    ram::Memory,
+    Address, EmulationError,
+};
+
+/// GDB's m68k registers, in the order they appear in a `g` packet: D0-D7, A0-A7, the status register, and the PC.
+///
+/// GDB also has floating-point registers after these, but the 68000 doesn't.
+const REGISTER_COUNT: usize = 18;
+
+// Signals reported to GDB when the CPU stops
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+const SIGSEGV: u8 = 11;
+
+/// The reply to a packet that couldn't be understood
+const BAD_PACKET: &str = "E01";
+/// The reply to a memory access that failed
+const BAD_ACCESS: &str = "E02";
+
+/// Answers GDB's packets by controlling a [`CPU`]
+pub struct GdbStub<M: Memory> {
+    pub cpu: CPU<M>,
+    breakpoints: BTreeSet<u32>,
+}
+
+/// Gets the signal GDB should be told about when an instruction fails with `error`
+fn signal_for(error: &EmulationError) -> u8 {
+    match error {
+        EmulationError::MemoryOutOfBoundsAccess(_)
+        | EmulationError::AddressWrapped { .. }
+        | EmulationError::WriteToReadOnly(_)
+        | EmulationError::RanIntoZeros(_) => SIGSEGV,
+        _ => SIGILL,
+    }
+}
+
+fn stop_reply(signal: u8) -> String {
+    format!("S{:02x}", signal)
+}
+
+/// Parses an `ADDR,LENGTH` pair, as used by the memory and breakpoint packets
+fn parse_address_and_length(args: &str) -> Option<(u32, u32)> {
+    let (address, length) = args.split_once(',')?;
+    Some((
+        u32::from_str_radix(address, 16).ok()?,
+        u32::from_str_radix(length, 16).ok()?,
+    ))
+}
+
+fn parse_hex_bytes(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [_, _] => u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Every packet ends with the sum of its bytes, modulo 256
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, byte| sum.wrapping_add(*byte))
+}
+
+fn read_byte(connection: &mut impl Read) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    match connection.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+impl<M: Memory> GdbStub<M> {
+    pub fn new(cpu: CPU<M>) -> Self {
+        Self {
+            cpu,
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    /// Handles one packet (without the `$` and checksum), returning the reply to send back
+    pub fn handle_packet(&mut self, packet: &str) -> String {
+        let (command, args) = match packet.chars().next() {
+            // `serve` replaces bytes that aren't UTF-8, so the command can be more than one byte long
+            Some(command) => (command, packet.split_at(command.len_utf8()).1),
+            None => return String::new(),
+        };
+        let reply = match command {
+            '?' => Some(stop_reply(SIGTRAP)),
+            'g' => self.read_registers(),
+            'G' => self.write_registers(args),
+            'm' => self.read_memory(args),
+            'M' => self.write_memory(args),
+            's' => self.set_pc(args).map(|_| self.step()),
+            'c' => self.set_pc(args).map(|_| self.resume()),
+            'Z' | 'z' => match args.strip_prefix("0,") {
+                Some(args) => self.breakpoint(command == 'Z', args),
+                // Hardware breakpoints and watchpoints aren't supported
+                None => Some(String::new()),
+            },
+            _ => Some(String::new()),
+        };
+        reply.unwrap_or_else(|| BAD_PACKET.to_string())
+    }
+
+    fn read_registers(&self) -> Option<String> {
+        let registers = &self.cpu.registers;
+        let mut reply = String::with_capacity(REGISTER_COUNT * 8);
+        for is_address in [false, true] {
+            for index in 0..8 {
+                reply += &format!("{:08x}", registers.get_by_index(is_address, index)?);
+            }
+        }
+        reply += &format!("{:08x}", registers.get_status_register());
+        reply += &format!("{:08x}", registers.get(Register::ProgramCounter));
+        Some(reply)
+    }
+
+    fn write_registers(&mut self, args: &str) -> Option<String> {
+        let values = parse_hex_bytes(args)?;
+        if values.len() < REGISTER_COUNT * 4 {
+            return None;
+        }
+        let mut values = values
+            .chunks(4)
+            .map(|long| u32::from_be_bytes([long[0], long[1], long[2], long[3]]));
+
+        let registers = &mut self.cpu.registers;
+        for is_address in [false, true] {
+            for index in 0..8 {
+                registers.set_by_index(is_address, index, values.next()?)?;
+            }
+        }
+        registers.set_status_register(values.next()? as u16);
+        registers.set(Register::ProgramCounter, values.next()?);
+        Some("OK".to_string())
+    }
+
+    fn read_memory(&self, args: &str) -> Option<String> {
+        let (address, length) = parse_address_and_length(args)?;
+        Some(match self.cpu.memory.read_bytes(Address(address), length) {
+            Ok(bytes) => bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            Err(_) => BAD_ACCESS.to_string(),
+        })
+    }
+
+    fn write_memory(&mut self, args: &str) -> Option<String> {
+        let (location, data) = args.split_once(':')?;
+        let (address, length) = parse_address_and_length(location)?;
+        let data = parse_hex_bytes(data)?;
+        if data.len() != length as usize {
+            return None;
+        }
+        Some(match self.cpu.memory.write_bytes(Address(address), data) {
+            Ok(()) => "OK".to_string(),
+            Err(_) => BAD_ACCESS.to_string(),
+        })
+    }
+
+    /// `s` and `c` can give an address to resume at
+    fn set_pc(&mut self, args: &str) -> Option<()> {
+        if !args.is_empty() {
+            let pc = u32::from_str_radix(args, 16).ok()?;
+            self.cpu.registers.set(Register::ProgramCounter, pc);
+        }
+        Some(())
+    }
+
+    fn step(&mut self) -> String {
+        match self.cpu.run_one_cycle() {
+            Ok(()) => stop_reply(SIGTRAP),
+            Err(error) => stop_reply(signal_for(&error)),
+        }
+    }
+
+    /// Runs until a breakpoint is reached, an instruction fails, or the program halts (which GDB sees as it exiting)
+    ///
+    /// The connection isn't read while the program runs, so GDB's interrupt (Ctrl-C, sent as `0x03`) isn't seen:
+    /// a program stuck in a loop that [`CPU::is_halted`] doesn't recognize can't be stopped without disconnecting.
+    fn resume(&mut self) -> String {
+        loop {
+            if self.cpu.is_halted().unwrap_or(false) {
+                return "W00".to_string();
+            }
+            if let Err(error) = self.cpu.run_one_cycle() {
+                return stop_reply(signal_for(&error));
+            }
+            if self
+                .breakpoints
+                .contains(&self.cpu.registers.get(Register::ProgramCounter))
+            {
+                return stop_reply(SIGTRAP);
+            }
+        }
+    }
+
+    /// Breakpoints are kept to the side instead of being written into memory, so they can't be overwritten or read back
+    fn breakpoint(&mut self, insert: bool, args: &str) -> Option<String> {
+        let (address, _kind) = parse_address_and_length(args)?;
+        if insert {
+            self.breakpoints.insert(address);
+        } else {
+            self.breakpoints.remove(&address);
+        }
+        Some("OK".to_string())
+    }
+
+    /// Answers packets from GDB over `connection` (usually a [`TcpStream`](std::net::TcpStream))
+    /// until GDB kills the program (`k`) or disconnects
+    pub fn serve(&mut self, mut connection: impl Read + Write) -> io::Result<()> {
+        loop {
+            // Skip acknowledgements and interrupts until a packet starts
+            match read_byte(&mut connection)? {
+                Some(b'$') => {}
+                Some(_) => continue,
+                None => return Ok(()),
+            }
+
+            let mut packet = Vec::new();
+            loop {
+                match read_byte(&mut connection)? {
+                    Some(b'#') => break,
+                    Some(byte) => packet.push(byte),
+                    None => return Ok(()),
+                }
+            }
+            let mut sent_checksum = [0; 2];
+            connection.read_exact(&mut sent_checksum)?;
+            let valid = std::str::from_utf8(&sent_checksum)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                == Some(checksum(&packet));
+            if !valid {
+                connection.write_all(b"-")?;
+                continue;
+            }
+            connection.write_all(b"+")?;
+
+            let packet = String::from_utf8_lossy(&packet);
+            if packet == "k" {
+                return Ok(());
+            }
+            let reply = self.handle_packet(&packet);
+            write!(connection, "${}#{:02x}", reply, checksum(reply.as_bytes()))?;
+            connection.flush()?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cpu::registers::{AddressRegister, DataRegister},
+        ram::VecBackedMemory,
+    };
+    use std::io::Cursor;
+
+    fn stub() -> GdbStub<VecBackedMemory> {
+        GdbStub::new(CPU::new(1024))
+    }
+
+    #[test]
+    fn read_and_write_registers() {
+        let mut stub = stub();
+        stub.cpu
+            .registers
+            .set_data_register(DataRegister::D1, 0x1234_5678_u32);
+        stub.cpu
+            .registers
+            .set_address_register(AddressRegister::A7, 0x400_u32);
+        stub.cpu.registers.set(Register::ProgramCounter, 0x100_u32);
+
+        let registers = stub.handle_packet("g");
+        assert_eq!(registers.len(), REGISTER_COUNT * 8);
+        assert_eq!(&registers[8..16], "12345678");
+        assert_eq!(&registers[15 * 8..16 * 8], "00000400");
+        assert_eq!(&registers[16 * 8..17 * 8], "00002700");
+        assert_eq!(&registers[17 * 8..], "00000100");
+
+        let mut changed = registers.clone();
+        changed.replace_range(0..8, "deadbeef");
+        changed.replace_range(17 * 8.., "00000200");
+        assert_eq!(stub.handle_packet(&format!("G{}", changed)), "OK");
+        assert_eq!(
+            stub.cpu.registers.get_data_register(DataRegister::D0),
+            0xDEAD_BEEF
+        );
+        assert_eq!(
+            stub.cpu.registers.get_data_register(DataRegister::D1),
+            0x1234_5678
+        );
+        assert_eq!(stub.cpu.registers.get(Register::ProgramCounter), 0x200);
+
+        assert_eq!(stub.handle_packet("G1234"), BAD_PACKET);
+    }
+
+    #[test]
+    fn read_and_write_memory() {
+        let mut stub = stub();
+        assert_eq!(stub.handle_packet("M100,4:4e714e75"), "OK");
+        assert_eq!(
+            stub.cpu.memory.read_long(Address(0x100)).unwrap(),
+            0x4E71_4E75
+        );
+        assert_eq!(stub.handle_packet("m100,4"), "4e714e75");
+        assert_eq!(stub.handle_packet("m102,2"), "4e75");
+
+        assert_eq!(stub.handle_packet("m100000,4"), BAD_ACCESS);
+        assert_eq!(stub.handle_packet("M100,4:4e71"), BAD_PACKET);
+        assert_eq!(stub.handle_packet("m100"), BAD_PACKET);
+    }
+
+    #[test]
+    fn step_and_continue() {
+        let mut stub = stub();
+        // nop; nop; moveq #5, d0; jmp ($FFFFF0).l
+        stub.cpu
+            .load_binary(
+                0,
+                vec![
+                    0x4E, 0x71, 0x4E, 0x71, 0x70, 0x05, 0x4E, 0xF9, 0x00, 0xFF, 0xFF, 0xF0,
+                ],
+            )
+            .unwrap();
+
+        assert_eq!(stub.handle_packet("s"), "S05");
+        assert_eq!(stub.cpu.registers.get(Register::ProgramCounter), 2);
+
+        assert_eq!(stub.handle_packet("Z0,6,2"), "OK");
+        assert_eq!(stub.handle_packet("c"), "S05");
+        assert_eq!(stub.cpu.registers.get(Register::ProgramCounter), 6);
+        assert_eq!(stub.cpu.registers.get_data_register(DataRegister::D0), 5);
+
+        // Continuing from a breakpoint doesn't stop at it again
+        assert_eq!(stub.handle_packet("z0,6,2"), "OK");
+        assert_eq!(stub.handle_packet("c"), "S0b");
+
+        // Stepping can start somewhere else
+        assert_eq!(stub.handle_packet("s0"), "S05");
+        assert_eq!(stub.cpu.registers.get(Register::ProgramCounter), 2);
+    }
+
+    #[test]
+    fn unsupported_packets() {
+        let mut stub = stub();
+        assert_eq!(stub.handle_packet("qSupported:multiprocess+"), "");
+        assert_eq!(stub.handle_packet("Z2,100,4"), "");
+        assert_eq!(stub.handle_packet(""), "");
+    }
+
+    /// Reads GDB's side of a conversation from `input`, and collects the stub's side
+    struct Connection {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Connection {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Connection {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn serve_packets() {
+        let mut stub = stub();
+        stub.cpu.memory.write_long(Address(0x10), 0xCAFE).unwrap();
+        let mut connection = Connection {
+            input: Cursor::new(b"+$m10,4#2e+$m10,4#00$k#6b".to_vec()),
+            output: Vec::new(),
+        };
+        stub.serve(&mut connection).unwrap();
+        // The second packet has a bad checksum, so it's rejected
+        assert_eq!(
+            String::from_utf8(connection.output).unwrap(),
+            "+$0000cafe#4f-+"
+        );
+    }
+
+    #[test]
+    fn serve_non_ascii_packet() {
+        let mut stub = stub();
+        let mut connection = Connection {
+            input: Cursor::new(b"$\xff#ff$k#6b".to_vec()),
+            output: Vec::new(),
+        };
+        stub.serve(&mut connection).unwrap();
+        // An unknown command gets an empty reply
+        assert_eq!(String::from_utf8(connection.output).unwrap(), "+$#00+");
+    }
+}
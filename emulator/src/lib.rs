@@ -1,17 +1,62 @@
 #![feature(slice_pattern)]
 //! Motorola 68k CPU emulation library.
 
+use std::{
+    fmt::Display,
+    ops::{Add, Sub},
+};
+
+use cpu::{addressing::AddressMode, CpuModel};
 use parsers::ParseError;
 
 #[derive(Debug)]
 pub enum EmulationError {
-    MemoryOutOfBoundsAccess(u32),
+    MemoryOutOfBoundsAccess(Address),
+    /// A displacement took an address past either end of the address space while [`CPU::strict_addressing`] was set
+    ///
+    /// [`CPU::strict_addressing`]: cpu::CPU::strict_addressing
+    AddressWrapped {
+        base: Address,
+        displacement: i32,
+    },
+    /// The CPU ran too many `0x0000` opcodes in a row, starting at this address; see [`CPU::zero_opcode_limit`]
+    ///
+    /// [`CPU::zero_opcode_limit`]: cpu::CPU::zero_opcode_limit
+    RanIntoZeros(Address),
+    /// [`CPU::run_until_pc`] ran as many instructions as it was allowed to without reaching its target;
+    /// this is the address it stopped at
+    ///
+    /// [`CPU::run_until_pc`]: cpu::CPU::run_until_pc
+    TargetNotReached(Address),
+    /// A word or long was pushed onto or popped off the stack while the stack pointer (A7) held this odd address
+    OddStackPointer(Address),
     WriteToReadOnly(String),
     WrongSizeInteger(M68kInteger),
     InvalidOperandSize(i32),
     Parsing(ParseError),
     SizeMismatch,
     ReadMultipleRegisters,
+    UnimplementedInstruction(String),
+    NoEffectiveAddress(AddressMode),
+    /// A DIVU or DIVS divided by zero; the hardware would raise a zero divide exception, which isn't emulated yet
+    DivideByZero(String),
+    /// [`BankedMemory::with_banks`] was given a bank whose size differs from the first bank's
+    ///
+    /// [`BankedMemory::with_banks`]: ram::BankedMemory::with_banks
+    BankSizeMismatch {
+        bank: usize,
+        size: usize,
+        expected: usize,
+    },
+    /// A CHK found its value out of bounds; the hardware would raise a CHK exception, which isn't emulated yet
+    BoundsCheckFailed(String),
+    /// A privileged instruction was run in user mode
+    PrivilegeViolation(String),
+    /// The instruction isn't supported by the CPU model being emulated
+    UnsupportedOnModel {
+        instruction: String,
+        model: CpuModel,
+    },
 }
 impl From<ParseError> for EmulationError {
     fn from(err: ParseError) -> Self {
@@ -20,6 +65,8 @@ impl From<ParseError> for EmulationError {
 }
 
 pub mod cpu;
+#[cfg(feature = "gdb")]
+pub mod gdb;
 pub mod parsers;
 pub mod ram;
 
@@ -36,10 +83,10 @@ pub mod ram;
 /// in an addressing mode (as specified in an assembler or machine code instruction).
 /// For example, to read 16 bits from memory at the address 0xABC, use the following addressing:
 /// ```
-/// # use emulator::{EmulationError, cpu::{CPU, addressing::*}, ram::{Memory, VecBackedMemory}, OperandSize, M68kInteger};
+/// # use emulator::{Address, EmulationError, cpu::{CPU, addressing::*}, ram::{Memory, VecBackedMemory}, OperandSize, M68kInteger};
 /// # fn test() -> Result<(), EmulationError> {
 ///     let mut cpu = CPU::<VecBackedMemory>::new(1024);
-///     cpu.memory.write_word(0xABC, 0xBEEF);
+///     cpu.memory.write_word(Address(0xABC), 0xBEEF);
 ///
 ///     let address = AddressMode::Absolute { address: 0xABC };
 ///     assert_eq!(address.get_value(&mut cpu, OperandSize::Word)?, M68kInteger::Word(0xBEEF));
@@ -72,6 +119,66 @@ impl OperandSize {
             _ => Err(EmulationError::InvalidOperandSize(size)),
         }
     }
+
+    /// Gets the suffix that gives this size in assembly (`.b`, `.w`, or `.l`)
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            OperandSize::Byte => ".b",
+            OperandSize::Word => ".w",
+            OperandSize::Long => ".l",
+        }
+    }
+}
+
+/// Condition codes produced by an arithmetic operation, which instructions write into the CCR
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Flags {
+    pub carry: bool,
+    pub overflow: bool,
+    pub zero: bool,
+    pub negative: bool,
+}
+
+/// An address in memory.
+///
+/// Addresses and data values are both 32 bits wide; this type keeps one from being used as the other by mistake.
+/// Address arithmetic wraps around at 32 bits; effective addresses are also cut down to the
+/// width of the emulated model's address bus (see [`CpuModel::address_mask`]).
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Default)]
+pub struct Address(pub u32);
+
+impl From<u32> for Address {
+    fn from(address: u32) -> Self {
+        Address(address)
+    }
+}
+
+impl From<Address> for u32 {
+    fn from(address: Address) -> Self {
+        address.0
+    }
+}
+
+impl Add<u32> for Address {
+    type Output = Address;
+
+    fn add(self, offset: u32) -> Address {
+        Address(self.0.wrapping_add(offset))
+    }
+}
+
+impl Sub<u32> for Address {
+    type Output = Address;
+
+    fn sub(self, offset: u32) -> Address {
+        Address(self.0.wrapping_sub(offset))
+    }
+}
+
+impl Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "0x{}", hex_format_long(self.0))
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)] // remove if perf issue
@@ -128,13 +235,169 @@ impl M68kInteger {
         }
     }
 
+    /// Truncates `value` to `size`
+    pub fn from_u32(value: u32, size: OperandSize) -> Self {
+        match size {
+            OperandSize::Byte => M68kInteger::Byte(value as u8),
+            OperandSize::Word => M68kInteger::Word(value as u16),
+            OperandSize::Long => M68kInteger::Long(value),
+        }
+    }
+
     operation_impl!(wrapping_add, |a, b| a.wrapping_add(b));
     operation_impl!(wrapping_sub, |a, b| a.wrapping_sub(b));
     operation_impl!(wrapping_mul, |a, b| a.wrapping_mul(b));
-    operation_impl!(rotate_left, |a, b| a.rotate_left(b.into()));
     operation_impl!(and, |a, b| a & b);
     operation_impl!(or, |a, b| a | b);
     operation_impl!(xor, |a, b| a ^ b);
+
+    /// Rotates left by `count` bits, within this integer's width.
+    ///
+    /// `count` must be the same size; it's taken modulo the width, so rotating a byte by 10 is the same as by 2.
+    pub fn rotate_left(&self, count: M68kInteger) -> M68kInteger {
+        if count.size() != self.size() {
+            panic!("Mismatched operand sizes");
+        }
+        let count = u32::from(count) % (self.size().size_in_bytes() * 8);
+        match *self {
+            M68kInteger::Byte(b) => M68kInteger::Byte(b.rotate_left(count)),
+            M68kInteger::Word(w) => M68kInteger::Word(w.rotate_left(count)),
+            M68kInteger::Long(l) => M68kInteger::Long(l.rotate_left(count)),
+        }
+    }
+
+    /// Rotates right by `count` bits, within this integer's width; the opposite of [`M68kInteger::rotate_left`]
+    pub fn rotate_right(&self, count: M68kInteger) -> M68kInteger {
+        if count.size() != self.size() {
+            panic!("Mismatched operand sizes");
+        }
+        let count = u32::from(count) % (self.size().size_in_bytes() * 8);
+        match *self {
+            M68kInteger::Byte(b) => M68kInteger::Byte(b.rotate_right(count)),
+            M68kInteger::Word(w) => M68kInteger::Word(w.rotate_right(count)),
+            M68kInteger::Long(l) => M68kInteger::Long(l.rotate_right(count)),
+        }
+    }
+
+    /// Rotates left by `count` bits through the extend bit, which sits just above the most significant bit;
+    /// so a byte is rotated in a 9-bit window, a word in 17 bits, and a long in 33 bits.
+    ///
+    /// Returns the result and the new extend bit, which is the last bit rotated out (or `extend` if `count` is 0).
+    pub fn rotate_left_extended(&self, count: u32, extend: bool) -> (M68kInteger, bool) {
+        let bits = self.size().size_in_bytes() * 8;
+        let window = bits + 1;
+        let value = u32::from(*self) as u64 | (extend as u64) << bits;
+        let count = count % window;
+        let rotated = ((value << count) | (value >> (window - count))) & ((1 << window) - 1);
+        (
+            M68kInteger::from_u32(rotated as u32, self.size()),
+            (rotated >> bits) & 1 == 1,
+        )
+    }
+
+    /// Rotates right by `count` bits through the extend bit; the opposite of [`M68kInteger::rotate_left_extended`]
+    pub fn rotate_right_extended(&self, count: u32, extend: bool) -> (M68kInteger, bool) {
+        let window = self.size().size_in_bytes() * 8 + 1;
+        self.rotate_left_extended(window - count % window, extend)
+    }
+
+    /// Shifts left by `count` bits, filling with zeroes.
+    ///
+    /// Also returns the last bit shifted out, which is `false` if `count` is 0.
+    pub fn shift_left(&self, count: u32) -> (M68kInteger, bool) {
+        let bits = self.size().size_in_bytes() * 8;
+        let shifted = (u32::from(*self) as u64).checked_shl(count).unwrap_or(0);
+        let carry = (shifted >> bits) & 1 == 1;
+        let result = match self {
+            M68kInteger::Byte(_) => M68kInteger::Byte(shifted as u8),
+            M68kInteger::Word(_) => M68kInteger::Word(shifted as u16),
+            M68kInteger::Long(_) => M68kInteger::Long(shifted as u32),
+        };
+        (result, carry)
+    }
+
+    /// Shifts right by `count` bits, filling with copies of the sign bit.
+    ///
+    /// Also returns the last bit shifted out, which is `false` if `count` is 0.
+    pub fn shift_right_arithmetic(&self, count: u32) -> (M68kInteger, bool) {
+        let value = self.sign_extended() as i64;
+        // Once `count` passes the width, every bit shifted out is a copy of the sign bit
+        let count = count.min(63);
+        let carry = count != 0 && (value >> (count - 1)) & 1 == 1;
+        (
+            M68kInteger::from_u32((value >> count) as u32, self.size()),
+            carry,
+        )
+    }
+
+    /// Does shifting left by `count` bits change the sign bit at any point?
+    ///
+    /// This is ASL's overflow condition, so it's only false if the sign bit and the `count` bits below it all match.
+    pub fn shift_left_overflows(&self, count: u32) -> bool {
+        let value = self.sign_extended() as i64;
+        if count >= self.size().size_in_bytes() * 8 {
+            // Zeroes shift all the way through, so any set bit changes the sign bit on its way out
+            return value != 0;
+        }
+        let shifted = value << count;
+        M68kInteger::from_u32(shifted as u32, self.size()).sign_extended() as i64 != shifted
+    }
+
+    /// Interprets this integer as signed, extending it to 32 bits
+    pub fn sign_extended(&self) -> i32 {
+        match *self {
+            M68kInteger::Byte(b) => b as i8 as i32,
+            M68kInteger::Word(w) => w as i16 as i32,
+            M68kInteger::Long(l) => l as i32,
+        }
+    }
+
+    /// Returns true if the most significant bit (for this size) is set
+    pub fn is_negative(&self) -> bool {
+        match *self {
+            M68kInteger::Byte(b) => b & 0x80 != 0,
+            M68kInteger::Word(w) => w & 0x8000 != 0,
+            M68kInteger::Long(l) => l & 0x8000_0000 != 0,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        u32::from(*self) == 0
+    }
+
+    /// Adds `other` to `self`, also returning the resulting condition codes
+    pub fn add_with_flags(&self, other: M68kInteger) -> (M68kInteger, Flags) {
+        let result = self.wrapping_add(other);
+        let (dest, src, res) = (
+            self.is_negative(),
+            other.is_negative(),
+            result.is_negative(),
+        );
+        let flags = Flags {
+            carry: (src && dest) || (!res && (src || dest)),
+            overflow: src == dest && res != dest,
+            zero: result.is_zero(),
+            negative: res,
+        };
+        (result, flags)
+    }
+
+    /// Subtracts `other` from `self`, also returning the resulting condition codes
+    pub fn sub_with_flags(&self, other: M68kInteger) -> (M68kInteger, Flags) {
+        let result = self.wrapping_sub(other);
+        let (dest, src, res) = (
+            self.is_negative(),
+            other.is_negative(),
+            result.is_negative(),
+        );
+        let flags = Flags {
+            carry: (src && !dest) || (res && (src || !dest)),
+            overflow: src != dest && res != dest,
+            zero: result.is_zero(),
+            negative: res,
+        };
+        (result, flags)
+    }
 }
 
 pub fn hex_format_byte(byte: u8) -> String {
@@ -148,3 +411,254 @@ pub fn hex_format_word(word: u16) -> String {
 pub fn hex_format_long(long: u32) -> String {
     format!("{:08X}", long)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn address_arithmetic() {
+        assert_eq!(Address(0x100) + 4, Address(0x104));
+        assert_eq!(Address(0x100) - 4, Address(0xFC));
+        // Models with a 24-bit address bus drop the top byte when an effective address is computed instead
+        assert_eq!(Address(0x00FF_FFFE) + 4, Address(0x0100_0002));
+        assert_eq!(Address(0xFFFF_FFFE) + 4, Address(2));
+        assert_eq!(Address(2) - 4, Address(0xFFFF_FFFE));
+
+        assert_eq!(Address::from(0xABC), Address(0xABC));
+        assert_eq!(u32::from(Address(0xABC)), 0xABC);
+        assert_eq!(Address(0xABC).to_string(), "0x00000ABC");
+    }
+
+    #[test]
+    fn add_with_flags() {
+        assert_eq!(
+            M68kInteger::Byte(0xFF).add_with_flags(M68kInteger::Byte(1)),
+            (
+                M68kInteger::Byte(0),
+                Flags {
+                    carry: true,
+                    zero: true,
+                    ..Flags::default()
+                }
+            )
+        );
+        assert_eq!(
+            M68kInteger::Byte(0x7F).add_with_flags(M68kInteger::Byte(1)),
+            (
+                M68kInteger::Byte(0x80),
+                Flags {
+                    overflow: true,
+                    negative: true,
+                    ..Flags::default()
+                }
+            )
+        );
+        // The same values don't carry or overflow as words
+        assert_eq!(
+            M68kInteger::Word(0xFF).add_with_flags(M68kInteger::Word(1)),
+            (M68kInteger::Word(0x100), Flags::default())
+        );
+        assert_eq!(
+            M68kInteger::Long(0x8000_0000).add_with_flags(M68kInteger::Long(0x8000_0000)),
+            (
+                M68kInteger::Long(0),
+                Flags {
+                    carry: true,
+                    overflow: true,
+                    zero: true,
+                    negative: false,
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn shift_left() {
+        assert_eq!(
+            M68kInteger::Byte(0b1100_0001).shift_left(1),
+            (M68kInteger::Byte(0b1000_0010), true)
+        );
+        assert_eq!(
+            M68kInteger::Byte(0b1100_0001).shift_left(3),
+            (M68kInteger::Byte(0b0000_1000), false)
+        );
+        assert_eq!(
+            M68kInteger::Word(0x8001).shift_left(0),
+            (M68kInteger::Word(0x8001), false)
+        );
+        assert_eq!(
+            M68kInteger::Word(0x8001).shift_left(16),
+            (M68kInteger::Word(0), true)
+        );
+        assert_eq!(
+            M68kInteger::Long(u32::MAX).shift_left(33),
+            (M68kInteger::Long(0), false)
+        );
+        assert_eq!(
+            M68kInteger::Long(1).shift_left(63),
+            (M68kInteger::Long(0), false)
+        );
+    }
+
+    #[test]
+    fn rotate_right() {
+        assert_eq!(
+            M68kInteger::Byte(0x01).rotate_right(M68kInteger::Byte(9)),
+            M68kInteger::Byte(0x80)
+        );
+        assert_eq!(
+            M68kInteger::Word(0x8001).rotate_right(M68kInteger::Word(4)),
+            M68kInteger::Word(0x1800)
+        );
+        assert_eq!(
+            M68kInteger::Long(0x8000_0001).rotate_right(M68kInteger::Long(32)),
+            M68kInteger::Long(0x8000_0001)
+        );
+    }
+
+    #[test]
+    fn rotate_extended() {
+        // The extend bit is rotated in, and the most significant bit is rotated out into it
+        assert_eq!(
+            M68kInteger::Byte(0x80).rotate_left_extended(1, true),
+            (M68kInteger::Byte(0x01), true)
+        );
+        assert_eq!(
+            M68kInteger::Byte(0x40).rotate_left_extended(2, false),
+            (M68kInteger::Byte(0x00), true)
+        );
+        assert_eq!(
+            M68kInteger::Byte(0x01).rotate_right_extended(1, false),
+            (M68kInteger::Byte(0x00), true)
+        );
+        assert_eq!(
+            M68kInteger::Byte(0x00).rotate_right_extended(1, true),
+            (M68kInteger::Byte(0x80), false)
+        );
+        assert_eq!(
+            M68kInteger::Word(0x1234).rotate_left_extended(0, true),
+            (M68kInteger::Word(0x1234), true)
+        );
+
+        // Rotating by the width plus one goes all the way around the window: 9 bits, 17 bits, and 33 bits
+        for (value, count) in [
+            (M68kInteger::Byte(0xA5), 9),
+            (M68kInteger::Word(0x8001), 17),
+            (M68kInteger::Long(0xDEAD_BEEF), 33),
+        ] {
+            for extend in [false, true] {
+                assert_eq!(value.rotate_left_extended(count, extend), (value, extend));
+                assert_eq!(value.rotate_right_extended(count, extend), (value, extend));
+            }
+        }
+        // ...so rotating left by the width is the same as rotating right by one
+        assert_eq!(
+            M68kInteger::Word(0x8001).rotate_left_extended(16, false),
+            (M68kInteger::Word(0x4000), true)
+        );
+        assert_eq!(
+            M68kInteger::Long(0x8000_0000).rotate_left_extended(32, true),
+            (M68kInteger::Long(0xC000_0000), false)
+        );
+        assert_eq!(
+            M68kInteger::Long(1).rotate_right_extended(63, false),
+            M68kInteger::Long(1).rotate_right_extended(63 % 33, false)
+        );
+    }
+
+    #[test]
+    fn shift_right_arithmetic() {
+        assert_eq!(
+            M68kInteger::Byte(0b1000_0011).shift_right_arithmetic(1),
+            (M68kInteger::Byte(0b1100_0001), true)
+        );
+        assert_eq!(
+            M68kInteger::Word(0x4002).shift_right_arithmetic(2),
+            (M68kInteger::Word(0x1000), true)
+        );
+        assert_eq!(
+            M68kInteger::Word(0x8001).shift_right_arithmetic(0),
+            (M68kInteger::Word(0x8001), false)
+        );
+        // Past the width, the sign bit fills the result and is the last bit shifted out
+        assert_eq!(
+            M68kInteger::Byte(0x80).shift_right_arithmetic(20),
+            (M68kInteger::Byte(0xFF), true)
+        );
+        assert_eq!(
+            M68kInteger::Long(0x7FFF_FFFF).shift_right_arithmetic(63),
+            (M68kInteger::Long(0), false)
+        );
+    }
+
+    #[test]
+    fn shift_left_overflows() {
+        assert!(!M68kInteger::Byte(0b1110_0000).shift_left_overflows(2));
+        assert!(M68kInteger::Byte(0b1110_0000).shift_left_overflows(3));
+        assert!(!M68kInteger::Word(0x0FFF).shift_left_overflows(3));
+        assert!(M68kInteger::Word(0x0FFF).shift_left_overflows(4));
+        assert!(!M68kInteger::Long(0x8000_0000).shift_left_overflows(0));
+        assert!(M68kInteger::Long(u32::MAX).shift_left_overflows(32));
+        assert!(!M68kInteger::Long(0).shift_left_overflows(63));
+    }
+
+    #[test]
+    fn rotate_left() {
+        assert_eq!(
+            M68kInteger::Byte(0x01).rotate_left(M68kInteger::Byte(9)),
+            M68kInteger::Byte(0x02)
+        );
+        assert_eq!(
+            M68kInteger::Byte(0x81).rotate_left(M68kInteger::Byte(10)),
+            M68kInteger::Byte(0x06)
+        );
+        assert_eq!(
+            M68kInteger::Word(0x8001).rotate_left(M68kInteger::Word(17)),
+            M68kInteger::Word(0x0003)
+        );
+        assert_eq!(
+            M68kInteger::Long(0x8000_0001).rotate_left(M68kInteger::Long(32)),
+            M68kInteger::Long(0x8000_0001)
+        );
+        assert_eq!(
+            M68kInteger::Long(0x8000_0000).rotate_left(M68kInteger::Long(u32::MAX)),
+            M68kInteger::Long(0x4000_0000)
+        );
+    }
+
+    #[test]
+    fn sub_with_flags() {
+        assert_eq!(
+            M68kInteger::Byte(0).sub_with_flags(M68kInteger::Byte(1)),
+            (
+                M68kInteger::Byte(0xFF),
+                Flags {
+                    carry: true,
+                    negative: true,
+                    ..Flags::default()
+                }
+            )
+        );
+        assert_eq!(
+            M68kInteger::Byte(0x80).sub_with_flags(M68kInteger::Byte(1)),
+            (
+                M68kInteger::Byte(0x7F),
+                Flags {
+                    overflow: true,
+                    ..Flags::default()
+                }
+            )
+        );
+        assert_eq!(
+            M68kInteger::Word(0x1234).sub_with_flags(M68kInteger::Word(0x1234)),
+            (
+                M68kInteger::Word(0),
+                Flags {
+                    zero: true,
+                    ..Flags::default()
+                }
+            )
+        );
+    }
+}
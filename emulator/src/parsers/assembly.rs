@@ -1,10 +1,12 @@
 //! Parses assembly code
 
-use super::{ParseError, Parser};
+use std::collections::HashMap;
+
+use super::{encoder::encode, ParseError, Parser};
 use crate::cpu::{
-    addressing::AddressMode,
+    addressing::{AddressMode, IndexScale},
     isa_68000::*,
-    registers::{AddressRegister, DataRegister, Register},
+    registers::Register,
 };
 use crate::OperandSize;
 
@@ -15,47 +17,249 @@ fn to_u16(int: u32) -> Result<u16, ParseError> {
     }
 }
 
+/// Gets the number of bytes an instruction takes up in memory
+fn instruction_length(instruction: &Instruction, size: OperandSize) -> u32 {
+    match instruction {
+        // MOVEQ's data is stored in the opcode itself
+        Instruction::MoveQuick { .. } => return 2,
+        // So is a byte branch displacement, but a word displacement takes up an extension word
+        Instruction::BranchConditional { .. }
+        | Instruction::Branch { .. }
+        | Instruction::BranchToSubroutine { .. } => {
+            return if size == OperandSize::Byte { 2 } else { 4 }
+        }
+        // DBcc's register is in the opcode, and its displacement is always a word
+        Instruction::DecrementBranch { .. } => return 4,
+        // A bit number is a byte, but an immediate one still takes up a whole extension word
+        Instruction::TestBit { bit_number, dest }
+        | Instruction::SetBit { bit_number, dest }
+        | Instruction::ClearBit { bit_number, dest }
+        | Instruction::ChangeBit { bit_number, dest } => {
+            return 2 + bit_number.extension_size(OperandSize::Byte) + dest.extension_size(size)
+        }
+        _ => {}
+    }
+
+    2 + instruction
+        .operands()
+        .iter()
+        .map(|operand| operand.extension_size(size))
+        .sum::<u32>()
+}
+
+/// Alternate mnemonics used by other assemblers, and the canonical mnemonics they stand for
+const MNEMONIC_ALIASES: &[(&str, &str)] = &[
+    ("dbra", "dbf"),
+    ("bhs", "bcc"),
+    ("blo", "bcs"),
+    ("bt", "bra"),
+];
+
+/// Resolves a (lowercase) mnemonic alias to its canonical mnemonic
+fn canonical_mnemonic(mnemonic: &str) -> &str {
+    MNEMONIC_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == mnemonic)
+        .map_or(mnemonic, |(_, canonical)| canonical)
+}
+
+/// Finds the `;` that starts a comment, ignoring any inside a quoted string
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (index, c) in line.char_indices() {
+        match (c, quote) {
+            ('\'' | '"', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            (';', None) => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Splits the values of a `dc` directive on commas, except for commas inside a quoted string
+fn split_values(values: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut quote = None;
+    let mut start = 0;
+    for (index, c) in values.char_indices() {
+        match (c, quote) {
+            ('\'' | '"', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            (',', None) => {
+                parts.push(values[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(values[start..].trim());
+    parts
+}
+
+/// Gets the size of a `dc` (define constant) directive and the values after it,
+/// or `None` if `line` isn't one. `dc` without a suffix defines words.
+fn data_directive(line: &str) -> Option<(OperandSize, &str)> {
+    let (directive, values) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let size = match directive.to_lowercase().as_str() {
+        "dc.b" => OperandSize::Byte,
+        "dc" | "dc.w" => OperandSize::Word,
+        "dc.l" => OperandSize::Long,
+        _ => return None,
+    };
+    Some((size, values.trim()))
+}
+
+/// An instruction produced by [`AssemblyInterpreter::assemble`], along with the address it will be loaded at
+#[derive(Debug, PartialEq)]
+pub struct AssembledInstruction {
+    pub address: u32,
+    pub instruction: Instruction,
+    pub size: OperandSize,
+}
+
+/// What a line of a program assembles to
+enum AssembledLine {
+    Instruction(AssembledInstruction),
+    /// Bytes from a `dc` directive
+    Data {
+        address: u32,
+        bytes: Vec<u8>,
+    },
+}
+
+impl AssembledLine {
+    fn address(&self) -> u32 {
+        match self {
+            AssembledLine::Instruction(instruction) => instruction.address,
+            AssembledLine::Data { address, .. } => *address,
+        }
+    }
+
+    /// Gets the bytes this line puts in memory
+    fn bytes(&self) -> Result<Vec<u8>, ParseError> {
+        match self {
+            AssembledLine::Instruction(instruction) => {
+                encode(&instruction.instruction, instruction.size)
+            }
+            AssembledLine::Data { bytes, .. } => Ok(bytes.clone()),
+        }
+    }
+}
+
+/// An operand parsed from assembly, along with the size given by its suffix (like the `.w` in `(a0).w`), if any
+#[derive(Debug, Clone, PartialEq)]
+struct ParsedOperand {
+    mode: AddressMode,
+    size: Option<OperandSize>,
+}
+
+impl ParsedOperand {
+    fn new(mode: AddressMode, size: Option<OperandSize>) -> Self {
+        Self { mode, size }
+    }
+
+    /// Gives the operand a size from a suffix outside its parentheses, which must agree with any size inside them
+    fn with_size(self, size: OperandSize) -> Option<Self> {
+        match self.size {
+            Some(own_size) if own_size != size => None,
+            _ => Some(Self::new(self.mode, Some(size))),
+        }
+    }
+
+    /// Works out the size of an instruction from its source (`self`) and destination operands.
+    ///
+    /// Either operand can give the size, but if both do, they must agree.
+    fn common_size(
+        &self,
+        dest: &ParsedOperand,
+        instruction: &str,
+    ) -> Result<Option<OperandSize>, ParseError> {
+        match (self.size, dest.size) {
+            (Some(source_size), Some(dest_size)) if source_size != dest_size => {
+                Err(ParseError::OperandSizeMismatch {
+                    instruction: instruction.to_string(),
+                    source_size,
+                    dest_size,
+                })
+            }
+            (Some(size), _) | (None, Some(size)) => Ok(Some(size)),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
 #[derive(Default)]
-pub struct AssemblyInterpreter {}
+pub struct AssemblyInterpreter {
+    /// Addresses of labels
+    symbols: HashMap<String, u32>,
+    /// Address of the instruction being parsed; used for PC-relative operands
+    location: u32,
+    /// Set during the first pass of [`AssemblyInterpreter::assemble`]
+    allow_undefined_symbols: bool,
+    /// When set, a warning is given if an immediate source has more significant bits than the operation's size.
+    ///
+    /// The hardware only uses the low bits (`add.w #$10001, d0` adds 1), so the instruction is still assembled.
+    /// Get the warnings with [`AssemblyInterpreter::take_warnings`].
+    pub warn_on_truncated_immediates: bool,
+    /// Warnings found since [`AssemblyInterpreter::take_warnings`] was last called
+    warnings: Vec<String>,
+}
 
 impl AssemblyInterpreter {
     pub fn new() -> Self {
         Self::default()
     }
 
-    /// Parses an operand to an address
+    /// Parses an operand to an address, along with its size suffix
     ///
     /// TODO: figure out how different operand sizes are represented & handle accordingly in unit tests
     fn parse_to_operand(
+        &self,
         op_string: &str,
         instruction: &str,
-    ) -> Result<(AddressMode, Option<OperandSize>), ParseError> {
+    ) -> Result<ParsedOperand, ParseError> {
         let mut chars = op_string.chars();
         let first = chars.next();
         match first {
-            // Register Direct
-            Some('d' | 'a' | 's') => {
+            // Register Direct (GNU as writes registers with a `%` prefix)
+            Some('d' | 'a' | 's' | '%') => {
                 let (register, size) = Self::parse_to_register(op_string)?;
-                Ok((AddressMode::RegisterDirect { register }, size))
+                Ok(ParsedOperand::new(
+                    AddressMode::RegisterDirect { register },
+                    size,
+                ))
             }
             // Immediate
-            Some('#') => Ok((
+            Some('#') => Ok(ParsedOperand::new(
                 AddressMode::Immediate {
-                    value: Self::parse_to_number(&op_string[1..])?,
+                    value: self.parse_to_number(&op_string[1..])?,
                 },
                 None,
             )),
             // Indirect Stuff + Absolute
             Some('(' | '-') => {
+                // A size can follow the parentheses too (`(a0).w`), and must agree with one inside them
+                if let (inner, Some(size)) = Self::parse_size_suffix(op_string)? {
+                    if inner.ends_with(')') || inner.ends_with('+') {
+                        return self
+                            .parse_to_operand(inner, instruction)?
+                            .with_size(size)
+                            .ok_or_else(|| ParseError::InvalidOperand {
+                                operand: op_string.to_string(),
+                                instruction: instruction.to_string(),
+                            });
+                    }
+                }
+
                 // Absolute
                 if !op_string.contains(',') {
                     // if it includes a comma, it's not an absolute address
                     if let Some('$' | '0'..='9') = chars.next() {
                         let (address_asm, size) = Self::parse_size_suffix(op_string)?;
-                        let address = Self::parse_to_number(
-                            &address_asm.replace(|c| c == '(' || c == ')', ""),
-                        )?;
-                        return Ok((AddressMode::Absolute { address }, size));
+                        let address = self
+                            .parse_to_number(&address_asm.replace(|c| c == '(' || c == ')', ""))?;
+                        return Ok(ParsedOperand::new(AddressMode::Absolute { address }, size));
                     }
                 }
 
@@ -87,11 +291,20 @@ impl AssemblyInterpreter {
 
                         let (register, size) = match Self::parse_to_register(register)? {
                             (Register::Address(reg), size) => (reg, size),
+                            // Data registers and the PC can't be used as pointers this way
                             _ => {
-                                return Err(ParseError::InvalidOperand {
-                                    operand: op_string.to_string(),
+                                let reason = if is_postincr {
+                                    "Postincrement requires an address register, not a data register or the PC"
+                                } else if is_predecr {
+                                    "Predecrement requires an address register, not a data register or the PC"
+                                } else {
+                                    "Indirect addressing requires an address register, not a data register or the PC"
+                                };
+                                return Err(ParseError::InvalidRegister {
+                                    register: register.to_string(),
                                     instruction: instruction.to_string(),
-                                })
+                                    reason: String::from(reason),
+                                });
                             }
                         };
 
@@ -101,32 +314,38 @@ impl AssemblyInterpreter {
                                 instruction: instruction.to_string(),
                             })
                         } else if is_postincr {
-                            Ok((
+                            Ok(ParsedOperand::new(
                                 AddressMode::RegisterIndirectPostIncrement { register },
                                 size,
                             ))
                         } else if is_predecr {
-                            Ok((AddressMode::RegisterIndirectPreDecrement { register }, size))
+                            Ok(ParsedOperand::new(
+                                AddressMode::RegisterIndirectPreDecrement { register },
+                                size,
+                            ))
                         } else {
-                            Ok((AddressMode::RegisterIndirect { register }, size))
+                            Ok(ParsedOperand::new(
+                                AddressMode::RegisterIndirect { register },
+                                size,
+                            ))
                         }
                     }
                     // Displacement
                     2 => {
                         let (displacement, register) = (parts[0].trim(), parts[1].trim());
-                        let displacement = to_u16(Self::parse_to_number(displacement)?)?;
 
                         match Self::parse_to_register(register)? {
-                            (Register::Address(reg), size) => Ok((
+                            (Register::Address(reg), size) => Ok(ParsedOperand::new(
                                 AddressMode::RegisterIndirectWithDisplacement {
-                                    displacement,
+                                    displacement: to_u16(self.parse_to_number(displacement)?)?,
                                     register: reg,
                                 },
                                 size,
                             )),
-                            (Register::ProgramCounter, size) => Ok((
+                            (Register::ProgramCounter, size) => Ok(ParsedOperand::new(
                                 AddressMode::ProgramCounterIndirectWithDisplacement {
-                                    displacement,
+                                    displacement: self
+                                        .parse_pc_displacement(displacement, instruction)?,
                                 },
                                 size,
                             )),
@@ -138,25 +357,30 @@ impl AssemblyInterpreter {
                     }
                     // Register/PC indirect with index
                     3 if !parts[0].starts_with('[') => {
-                        let displacement = to_u16(Self::parse_to_number(parts[0].trim())?)?;
-                        let address_register = Self::parse_to_register_no_size(parts[1].trim())?;
-                        let (index_register, size) = Self::parse_to_register(parts[2].trim())?;
+                        let displacement = to_u16(self.parse_to_number(parts[0].trim())?)?;
+                        let address_register = Self::parse_register_name(parts[1].trim())?;
+                        let (index_register, index_size, index_scale) =
+                            Self::parse_index(parts[2].trim(), instruction)?;
 
                         match address_register {
-                            Register::Address(reg) => Ok((
+                            Register::Address(reg) => Ok(ParsedOperand::new(
                                 AddressMode::RegisterIndirectIndexed {
                                     displacement,
                                     address_register: reg,
                                     index_register,
+                                    index_size,
+                                    index_scale,
                                 },
-                                size,
+                                None,
                             )),
-                            Register::ProgramCounter => Ok((
+                            Register::ProgramCounter => Ok(ParsedOperand::new(
                                 AddressMode::ProgramCounterIndirectIndexed {
                                     displacement,
                                     index_register,
+                                    index_size,
+                                    index_scale,
                                 },
-                                size,
+                                None,
                             )),
                             _ => Err(ParseError::InvalidRegister {
                                 register: parts[1].to_string(),
@@ -189,7 +413,7 @@ impl AssemblyInterpreter {
                         }
 
                         let base_displacement: u16 =
-                            to_u16(Self::parse_to_number(for_ia[0].trim_start_matches('['))?)?;
+                            to_u16(self.parse_to_number(for_ia[0].trim_start_matches('['))?)?;
                         let address_register: Register =
                             match Self::parse_to_register(for_ia[1].trim()) {
                                 Ok((reg, _)) => reg,
@@ -215,8 +439,8 @@ impl AssemblyInterpreter {
                             }
                         };
 
-                        let (index_register, size) =
-                            match Self::parse_to_register(idxreg_asm.trim()) {
+                        let (index_register, index_size, index_scale) =
+                            match Self::parse_index(idxreg_asm.trim(), instruction) {
                                 Ok(reg) => reg,
                                 _ => {
                                     return Err(ParseError::InvalidOperand {
@@ -227,28 +451,32 @@ impl AssemblyInterpreter {
                             };
                         let outer_displacement = match parts.pop() {
                             Some(displacement) => {
-                                to_u16(Self::parse_to_number(displacement.trim())?)?
+                                to_u16(self.parse_to_number(displacement.trim())?)?
                             }
                             None => 0,
                         };
                         if is_preindexed {
                             match address_register {
-                                Register::Address(reg) => Ok((
+                                Register::Address(reg) => Ok(ParsedOperand::new(
                                     AddressMode::MemoryPreIndexed {
                                         base_displacement,
                                         address_register: reg,
                                         index_register,
+                                        index_size,
+                                        index_scale,
                                         outer_displacement,
                                     },
-                                    size,
+                                    None,
                                 )),
-                                Register::ProgramCounter => Ok((
+                                Register::ProgramCounter => Ok(ParsedOperand::new(
                                     AddressMode::ProgramCounterMemoryIndirectPreIndexed {
                                         base_displacement,
                                         index_register,
+                                        index_size,
+                                        index_scale,
                                         outer_displacement,
                                     },
-                                    size,
+                                    None,
                                 )),
                                 _ => Err(ParseError::InvalidOperand {
                                     operand: op_string.to_string(),
@@ -257,22 +485,26 @@ impl AssemblyInterpreter {
                             }
                         } else {
                             match address_register {
-                                Register::Address(reg) => Ok((
+                                Register::Address(reg) => Ok(ParsedOperand::new(
                                     AddressMode::MemoryPostIndexed {
                                         base_displacement,
                                         address_register: reg,
                                         index_register,
+                                        index_size,
+                                        index_scale,
                                         outer_displacement,
                                     },
-                                    size,
+                                    None,
                                 )),
-                                Register::ProgramCounter => Ok((
+                                Register::ProgramCounter => Ok(ParsedOperand::new(
                                     AddressMode::ProgramCounterMemoryIndirectPostIndexed {
                                         base_displacement,
                                         index_register,
+                                        index_size,
+                                        index_scale,
                                         outer_displacement,
                                     },
-                                    size,
+                                    None,
                                 )),
                                 _ => Err(ParseError::InvalidOperand {
                                     operand: op_string.to_string(),
@@ -294,8 +526,276 @@ impl AssemblyInterpreter {
         }
     }
 
-    /// Parses a number
-    fn parse_to_number(num: &str) -> Result<u32, ParseError> {
+    /// Parses the target of a jump.
+    ///
+    /// Some assembler dialects write absolute targets as a bare number or label (`jmp $1000`),
+    /// without the parentheses that other operands need; anything else is parsed like a normal operand.
+    /// Only operands that refer to an address can be jumped to, so registers, `(An)+`, `-(An)`, and immediates are rejected.
+    fn parse_jump_target(
+        &self,
+        operand: &str,
+        instruction: &str,
+    ) -> Result<AddressMode, ParseError> {
+        let (address_asm, _) = Self::parse_size_suffix(operand)?;
+        let is_bare_address = match address_asm.chars().next() {
+            Some('$' | '0'..='9') => true,
+            // Registers look like labels, but can't be jumped to directly
+            Some(_) => Self::is_symbol(address_asm) && address_asm.parse::<Register>().is_err(),
+            None => false,
+        };
+
+        if is_bare_address {
+            Ok(AddressMode::Absolute {
+                address: self.parse_to_number(address_asm)?,
+            })
+        } else {
+            let invalid = |reason: &str| ParseError::InvalidRegister {
+                register: address_asm.to_string(),
+                instruction: instruction.to_string(),
+                reason: String::from(reason),
+            };
+            match self.parse_to_operand(operand, instruction)?.mode {
+                AddressMode::RegisterDirect { .. } | AddressMode::RegisterDirectList { .. } => {
+                    Err(invalid(
+                        "A jump target must be an address, not a register (use (An) to jump to the address in An)",
+                    ))
+                }
+                AddressMode::RegisterIndirectPostIncrement { .. }
+                | AddressMode::RegisterIndirectPreDecrement { .. } => Err(invalid(
+                    "A jump target can't use postincrement or predecrement",
+                )),
+                // `#` is for immediates; an absolute target is written without it
+                AddressMode::Immediate { .. } => Err(ParseError::InvalidOperand {
+                    operand: operand.to_string(),
+                    instruction: instruction.to_string(),
+                }),
+                address => Ok(address),
+            }
+        }
+    }
+
+    /// Gets the condition a branch mnemonic (like `beq`) tests,
+    /// along with its size if it has the `.s` suffix for a short (byte) displacement.
+    ///
+    /// Like in the encoding, `bra` is [`Condition::True`] and `bsr` takes the place of [`Condition::False`].
+    /// Returns `None` if `mnemonic` isn't a branch.
+    fn parse_branch_mnemonic(mnemonic: &str) -> Option<(Condition, Option<OperandSize>)> {
+        let (mnemonic, size) = match mnemonic.strip_suffix(".s") {
+            Some(mnemonic) => (mnemonic, Some(OperandSize::Byte)),
+            None => (mnemonic, None),
+        };
+        let condition = match canonical_mnemonic(mnemonic) {
+            "bra" => Condition::True,
+            "bsr" => Condition::False,
+            // A branch that's never taken would be encoded as BSR
+            "bf" => return None,
+            mnemonic => mnemonic.strip_prefix('b')?.parse().ok()?,
+        };
+        Some((condition, size))
+    }
+
+    /// Parses a branch to `target` (a label or address), working out the displacement from the branch's location
+    fn parse_branch(
+        &self,
+        condition: Condition,
+        size: Option<OperandSize>,
+        target: &str,
+        source: &str,
+    ) -> Result<(Instruction, OperandSize, u32), ParseError> {
+        let is_forward_reference = Self::is_symbol(target)
+            && !matches!(self.symbols.get(target), Some(&address) if address <= self.location);
+        let target = self.parse_to_number(target)?;
+        let displacement = target as i64 - (self.location as i64 + 2);
+        // Without a size, use the short form if the displacement fits in a byte.
+        // Labels defined later on aren't known until the second pass, so they always get a word displacement;
+        // otherwise the instruction could shrink between passes and move every label after it.
+        let size = size.unwrap_or(
+            if !is_forward_reference && displacement != 0 && i8::try_from(displacement).is_ok() {
+                OperandSize::Byte
+            } else {
+                OperandSize::Word
+            },
+        );
+        let in_range = match size {
+            // A byte displacement of 0 means a word displacement follows the opcode
+            OperandSize::Byte => displacement != 0 && i8::try_from(displacement).is_ok(),
+            OperandSize::Word => i16::try_from(displacement).is_ok(),
+            OperandSize::Long => return Err(ParseError::InvalidOperandSize(4)),
+        };
+        if !in_range {
+            return Err(ParseError::DisplacementOutOfRange {
+                displacement,
+                instruction: source.to_string(),
+            });
+        }
+        let displacement = displacement as i32;
+        let instruction = match condition {
+            Condition::True => Instruction::Branch { displacement },
+            Condition::False => Instruction::BranchToSubroutine { displacement },
+            condition => Instruction::BranchConditional {
+                condition,
+                displacement,
+            },
+        };
+        Ok((instruction, size, 0))
+    }
+
+    /// Parses DBcc's operands: a data register to count down, and a branch target like [`Self::parse_branch`]'s.
+    ///
+    /// The displacement is always a word, so the only size suffix allowed is `.w`.
+    fn parse_decrement_branch(
+        &self,
+        condition: Condition,
+        mnemonic_size: Option<OperandSize>,
+        operands: &str,
+        source: &str,
+    ) -> Result<(Instruction, OperandSize, u32), ParseError> {
+        if let Some(size) = mnemonic_size.filter(|&size| size != OperandSize::Word) {
+            return Err(ParseError::InvalidOperandSize(size.size_in_bytes() as i32));
+        }
+        let (register, target) = operands
+            .split_once(',')
+            .ok_or_else(|| ParseError::MissingOperand(source.to_string()))?;
+        let register = match Self::parse_register_name(register.trim())? {
+            Register::Data(register) => register,
+            _ => {
+                return Err(ParseError::InvalidRegister {
+                    register: register.trim().to_string(),
+                    instruction: source.to_string(),
+                    reason: String::from("The loop counter must be a data register"),
+                })
+            }
+        };
+
+        let displacement = self.parse_to_number(target.trim())? as i64 - (self.location as i64 + 2);
+        if i16::try_from(displacement).is_err() {
+            return Err(ParseError::DisplacementOutOfRange {
+                displacement,
+                instruction: source.to_string(),
+            });
+        }
+        Ok((
+            Instruction::DecrementBranch {
+                condition,
+                register,
+                displacement: displacement as i32,
+            },
+            OperandSize::Word,
+            0,
+        ))
+    }
+
+    /// Parses an instruction that has a single operand.
+    ///
+    /// Returns `None` if `mnemonic` isn't a single-operand instruction.
+    fn parse_single_operand(
+        &self,
+        mnemonic: &str,
+        mnemonic_size: Option<OperandSize>,
+        operand: &str,
+        source: &str,
+    ) -> Option<Result<(Instruction, OperandSize, u32), ParseError>> {
+        if let Some((condition, short_size)) = Self::parse_branch_mnemonic(mnemonic) {
+            return Some(self.parse_branch(
+                condition,
+                short_size.or(mnemonic_size),
+                operand,
+                source,
+            ));
+        }
+        match mnemonic {
+            "jmp" => Some(
+                self.parse_jump_target(operand, source)
+                    .map(|address| (Instruction::JumpTo { address }, OperandSize::Long, 0)),
+            ),
+            "jsr" => Some(self.parse_jump_target(operand, source).map(|address| {
+                (
+                    Instruction::JumpToSubroutine { address },
+                    OperandSize::Long,
+                    0,
+                )
+            })),
+            // Like ABCD and SBCD, NBCD only works on bytes
+            "nbcd" => Some(self.parse_to_operand(operand, source).and_then(
+                |ParsedOperand {
+                     mode: operand,
+                     size: operand_size,
+                 }| {
+                    let size = Self::resolve_size(mnemonic_size, operand_size, source)?;
+                    if (mnemonic_size.is_some() || operand_size.is_some())
+                        && size != OperandSize::Byte
+                    {
+                        return Err(ParseError::InvalidOperandSize(size.size_in_bytes() as i32));
+                    }
+                    Ok((
+                        Instruction::NegateBCD { dest: operand },
+                        OperandSize::Byte,
+                        0,
+                    ))
+                },
+            )),
+            "neg" | "negx" | "clr" | "not" | "tst" => {
+                Some(self.parse_to_operand(operand, source).and_then(
+                    |ParsedOperand {
+                         mode: operand,
+                         size: operand_size,
+                     }| {
+                        let size = Self::resolve_size(mnemonic_size, operand_size, source)?;
+                        let instruction = match mnemonic {
+                            "neg" => Instruction::Negate { dest: operand },
+                            "negx" => Instruction::NegateWithExtend { dest: operand },
+                            "clr" => Instruction::Clear { dest: operand },
+                            "not" => Instruction::Not { dest: operand },
+                            _ => Instruction::Test { src: operand },
+                        };
+                        Ok((instruction, size, 0))
+                    },
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// Works out an instruction's size from its mnemonic's size suffix and its operands' sizes, which must agree.
+    ///
+    /// Defaults to a long if neither has a size.
+    fn resolve_size(
+        mnemonic_size: Option<OperandSize>,
+        operand_size: Option<OperandSize>,
+        source: &str,
+    ) -> Result<OperandSize, ParseError> {
+        match (mnemonic_size, operand_size) {
+            (Some(mnemonic_size), Some(operand_size)) if mnemonic_size != operand_size => {
+                Err(ParseError::MnemonicSizeMismatch {
+                    instruction: source.to_string(),
+                    mnemonic_size,
+                    operand_size,
+                })
+            }
+            (Some(size), _) | (None, Some(size)) => Ok(size),
+            (None, None) => Ok(OperandSize::Long),
+        }
+    }
+
+    /// Checks whether an immediate `value` fits in `size` without losing significant bits.
+    ///
+    /// Negative numbers fit if they sign-extend back to `value`, so `#-1` fits in a byte.
+    fn immediate_fits(value: u32, size: OperandSize) -> bool {
+        let bits = size.size_in_bytes() * 8;
+        bits == 32 || value >> bits == 0 || (value as i32) >> (bits - 1) == -1
+    }
+
+    /// Parses a number (or the address of a label)
+    fn parse_to_number(&self, num: &str) -> Result<u32, ParseError> {
+        // Negative numbers are stored in two's complement
+        if let Some(num) = num.strip_prefix('-') {
+            return Ok(self.parse_to_number(num)?.wrapping_neg());
+        }
+
+        if Self::is_symbol(num) {
+            return self.resolve_symbol(num);
+        }
+
         let parse_result = if let Some(hex_num) = num.strip_prefix('$') {
             // Hex
             u32::from_str_radix(hex_num, 16)
@@ -311,10 +811,365 @@ impl AssemblyInterpreter {
         }
     }
 
+    /// Parses the displacement of a PC-relative operand.
+    ///
+    /// Numbers are used as-is, but labels (as in `(label, pc)`) are converted to a displacement
+    /// from the extension word, which directly follows the opcode.
+    fn parse_pc_displacement(
+        &self,
+        displacement: &str,
+        instruction: &str,
+    ) -> Result<u16, ParseError> {
+        if !Self::is_symbol(displacement) {
+            return to_u16(self.parse_to_number(displacement)?);
+        }
+
+        let target = self.parse_to_number(displacement)? as i64;
+        let displacement = target - (self.location as i64 + 2);
+        match i16::try_from(displacement) {
+            Ok(displacement) => Ok(displacement as u16),
+            Err(_) => Err(ParseError::DisplacementOutOfRange {
+                displacement,
+                instruction: instruction.to_string(),
+            }),
+        }
+    }
+
+    /// Is this token a label rather than a number?
+    fn is_symbol(token: &str) -> bool {
+        matches!(token.chars().next(), Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '.')
+    }
+
+    /// Gets the address of a label
+    fn resolve_symbol(&self, symbol: &str) -> Result<u32, ParseError> {
+        match self.symbols.get(symbol) {
+            Some(address) => Ok(*address),
+            // The first pass can't know about labels that are defined later on,
+            // so we use a placeholder; it only needs to produce an instruction of the right length.
+            None if self.allow_undefined_symbols => Ok(self.location),
+            None => Err(ParseError::UnknownSymbol(symbol.to_string())),
+        }
+    }
+
+    /// Assembles a multi-line program that will be loaded at `origin`.
+    ///
+    /// Lines may start with a label (`label:`), and `;` begins a comment.
+    /// This is a two-pass assembler: the first pass finds the address of every label,
+    /// and the second pass parses each instruction with all labels known.
+    ///
+    /// Data from `dc` directives takes up space, but isn't returned; use [`AssemblyInterpreter::assemble_binary`] to get it.
+    pub fn assemble(
+        &mut self,
+        source: &str,
+        origin: u32,
+    ) -> Result<Vec<AssembledInstruction>, ParseError> {
+        Ok(self
+            .assemble_lines(source, origin)?
+            .into_iter()
+            .filter_map(|(_, line)| match line {
+                AssembledLine::Instruction(instruction) => Some(instruction),
+                AssembledLine::Data { .. } => None,
+            })
+            .collect())
+    }
+
+    /// Assembles a program into the bytes to load at `origin`, including data from `dc` directives.
+    ///
+    /// `dc.b`, `dc.w`, and `dc.l` (or just `dc`, for words) define a list of big-endian constants,
+    /// which can be numbers, labels, or quoted strings (`dc.b 'Hi', 0`), where each character is a constant.
+    /// Like instructions, words and longs start at an even address, so a byte of padding
+    /// follows an odd number of bytes.
+    pub fn assemble_binary(&mut self, source: &str, origin: u32) -> Result<Vec<u8>, ParseError> {
+        let mut binary = vec![];
+        for (line_index, line) in self.assemble_lines(source, origin)? {
+            let bytes = line.bytes().map_err(|error| ParseError::AtLine {
+                line: line_index + 1,
+                source: Box::new(error),
+            })?;
+            binary.resize((line.address() - origin) as usize, 0);
+            binary.extend(bytes);
+        }
+        Ok(binary)
+    }
+
+    /// Assembles a program like [`AssemblyInterpreter::assemble`], along with a listing of it
+    /// like the `.lst` file a traditional assembler writes.
+    ///
+    /// Each line of the listing is a line of the source, preceded by the address and encoded bytes
+    /// of the instruction on it, if there is one:
+    ///
+    /// ```text
+    /// 00000100: 70 05                          moveq #5, d0
+    ///                                          ; done
+    /// ```
+    pub fn assemble_with_listing(
+        &mut self,
+        source: &str,
+        origin: u32,
+    ) -> Result<(Vec<AssembledInstruction>, String), ParseError> {
+        let assembled = self.assemble_lines(source, origin)?;
+        let mut lines = assembled.iter().peekable();
+        let mut listing = String::new();
+        for (line_index, source_line) in source.lines().enumerate() {
+            let code = match lines.next_if(|(index, _)| *index == line_index) {
+                Some((_, assembled)) => {
+                    let bytes = assembled.bytes().map_err(|error| ParseError::AtLine {
+                        line: line_index + 1,
+                        source: Box::new(error),
+                    })?;
+                    let bytes: Vec<String> =
+                        bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+                    format!("{:08X}: {}", assembled.address(), bytes.join(" "))
+                }
+                None => String::new(),
+            };
+            // The longest instruction is 10 bytes: "00000000: " plus 10 * "XX " (data can run longer)
+            let line = format!("{:<40} {}", code, source_line);
+            listing.push_str(line.trim_end());
+            listing.push('\n');
+        }
+
+        let instructions = assembled
+            .into_iter()
+            .filter_map(|(_, line)| match line {
+                AssembledLine::Instruction(instruction) => Some(instruction),
+                AssembledLine::Data { .. } => None,
+            })
+            .collect();
+        Ok((instructions, listing))
+    }
+
+    /// Runs both passes of the assembler, returning what each line assembled to along with its (0-based) index
+    fn assemble_lines(
+        &mut self,
+        source: &str,
+        origin: u32,
+    ) -> Result<Vec<(usize, AssembledLine)>, ParseError> {
+        self.symbols.clear();
+
+        // First pass: find labels
+        self.allow_undefined_symbols = true;
+        let result = self.assemble_pass(source, origin);
+        self.allow_undefined_symbols = false;
+        result?;
+
+        // Second pass: parse instructions with all labels resolved
+        self.assemble_pass(source, origin)
+    }
+
+    /// Assembles a single instruction straight to machine code, which is handy for building test programs
+    pub fn assemble_one(&mut self, line: &str) -> Result<Vec<u8>, ParseError> {
+        let (instruction, size, _) = self.parse(line.to_string())?;
+        encode(&instruction, size)
+    }
+
+    /// Gets the address of every label in the program that was last assembled
+    pub fn symbols(&self) -> &HashMap<String, u32> {
+        &self.symbols
+    }
+
+    /// Takes the warnings found since this was last called, such as immediates that were truncated
+    /// (if [`AssemblyInterpreter::warn_on_truncated_immediates`] is set)
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    fn assemble_pass(
+        &mut self,
+        source: &str,
+        origin: u32,
+    ) -> Result<Vec<(usize, AssembledLine)>, ParseError> {
+        let mut lines = vec![];
+        // Labels on lines of their own, which label whatever comes next
+        let mut pending_labels: Vec<(usize, String)> = vec![];
+        self.location = origin;
+
+        for (line_number, line) in source.lines().enumerate() {
+            // Line numbers start at 1, like in an editor
+            let at_line = |error| ParseError::AtLine {
+                line: line_number + 1,
+                source: Box::new(error),
+            };
+            let mut line = strip_comment(line).trim();
+
+            match line.split_once(':') {
+                // A colon in a string isn't a label
+                Some((label, rest)) if !label.contains(['\'', '"']) => {
+                    pending_labels.push((line_number, label.trim().to_lowercase()));
+                    line = rest.trim();
+                }
+                _ => {}
+            }
+
+            if line.is_empty() {
+                continue;
+            }
+
+            // Words and longs, including every instruction, have to be at an even address
+            let data = data_directive(line);
+            if !matches!(data, Some((OperandSize::Byte, _))) {
+                self.location += self.location & 1;
+            }
+            for (label_line, label) in pending_labels.drain(..) {
+                self.define_label(label)
+                    .map_err(|error| ParseError::AtLine {
+                        line: label_line + 1,
+                        source: Box::new(error),
+                    })?;
+            }
+
+            let address = self.location;
+            let assembled = match data {
+                Some((size, values)) => {
+                    let bytes = self.parse_data(size, values, line).map_err(at_line)?;
+                    self.location += bytes.len() as u32;
+                    AssembledLine::Data { address, bytes }
+                }
+                None => {
+                    let (instruction, size, _) = self.parse(line.to_string()).map_err(at_line)?;
+                    self.location += instruction_length(&instruction, size);
+                    AssembledLine::Instruction(AssembledInstruction {
+                        address,
+                        instruction,
+                        size,
+                    })
+                }
+            };
+            lines.push((line_number, assembled));
+        }
+
+        // Labels at the end of the program point just past it
+        for (label_line, label) in pending_labels {
+            self.define_label(label)
+                .map_err(|error| ParseError::AtLine {
+                    line: label_line + 1,
+                    source: Box::new(error),
+                })?;
+        }
+        Ok(lines)
+    }
+
+    /// Records that `label` is at the current location; only done in the first pass
+    fn define_label(&mut self, label: String) -> Result<(), ParseError> {
+        if self.allow_undefined_symbols
+            && self.symbols.insert(label.clone(), self.location).is_some()
+        {
+            return Err(ParseError::DuplicateLabel(label));
+        }
+        Ok(())
+    }
+
+    /// Parses the values of a `dc` directive of the given size into big-endian bytes
+    fn parse_data(
+        &self,
+        size: OperandSize,
+        values: &str,
+        instruction: &str,
+    ) -> Result<Vec<u8>, ParseError> {
+        if values.is_empty() {
+            return Err(ParseError::MissingOperand(instruction.to_string()));
+        }
+
+        let mut constants = vec![];
+        for value in split_values(values) {
+            let quote = value.chars().next().filter(|c| matches!(c, '\'' | '"'));
+            match quote {
+                Some(quote) if value.len() >= 2 && value.ends_with(quote) => {
+                    let text = &value[1..value.len() - 1];
+                    if !text.is_ascii() {
+                        return Err(ParseError::InvalidOperand {
+                            operand: value.to_string(),
+                            instruction: instruction.to_string(),
+                        });
+                    }
+                    constants.extend(text.bytes().map(u32::from));
+                }
+                Some(_) => {
+                    return Err(ParseError::UnknownOperandFormat {
+                        operand: value.to_string(),
+                        instruction: instruction.to_string(),
+                    })
+                }
+                None => {
+                    let constant = self.parse_to_number(&value.to_lowercase())?;
+                    if !Self::immediate_fits(constant, size) {
+                        return Err(ParseError::ImmediateOutOfRange {
+                            value: constant as i32 as i64,
+                            instruction: instruction.to_string(),
+                        });
+                    }
+                    constants.push(constant);
+                }
+            }
+        }
+
+        let width = size.size_in_bytes();
+        Ok(constants
+            .into_iter()
+            .flat_map(|constant| (0..width).rev().map(move |i| (constant >> (8 * i)) as u8))
+            .collect())
+    }
+
     /// Parses a string to a register and size
     fn parse_to_register(register: &str) -> Result<(Register, Option<OperandSize>), ParseError> {
         let (reg, size) = Self::parse_size_suffix(register)?;
-        Ok((Self::parse_to_register_no_size(reg)?, size))
+        Ok((Self::parse_register_name(reg)?, size))
+    }
+
+    /// Parses a register name, with or without the `%` prefix GNU as uses (`%d0`, `%sp`)
+    fn parse_register_name(register: &str) -> Result<Register, ParseError> {
+        register.strip_prefix('%').unwrap_or(register).parse()
+    }
+
+    /// Checks the count of a shift or rotate, which is either an immediate from 1 to 8 or a data register
+    fn check_shift_count(count: AddressMode, instruction: &str) -> Result<AddressMode, ParseError> {
+        match count {
+            AddressMode::Immediate { value: 1..=8 }
+            | AddressMode::RegisterDirect {
+                register: Register::Data(_),
+            } => Ok(count),
+            AddressMode::Immediate { value } => Err(ParseError::ImmediateOutOfRange {
+                value: value as i32 as i64,
+                instruction: instruction.to_string(),
+            }),
+            _ => Err(ParseError::InvalidOperand {
+                operand: format!("{:?}", count),
+                instruction: instruction.to_string(),
+            }),
+        }
+    }
+
+    /// Parses an index register with an optional size and scale, like `d3.l*4`
+    ///
+    /// Index registers are word-sized and unscaled unless otherwise specified.
+    fn parse_index(
+        index: &str,
+        instruction: &str,
+    ) -> Result<(Register, OperandSize, IndexScale), ParseError> {
+        let (register_asm, scale_asm) = match index.split_once('*') {
+            Some((register_asm, scale_asm)) => (register_asm.trim(), Some(scale_asm.trim())),
+            None => (index, None),
+        };
+        let invalid = |reason: &str| ParseError::InvalidRegister {
+            register: index.to_string(),
+            instruction: instruction.to_string(),
+            reason: String::from(reason),
+        };
+
+        let (index_register, size) = Self::parse_to_register(register_asm)?;
+        let index_size = match size {
+            None | Some(OperandSize::Word) => OperandSize::Word,
+            Some(OperandSize::Long) => OperandSize::Long,
+            Some(OperandSize::Byte) => return Err(invalid("Index registers must be word or long")),
+        };
+        let index_scale = match scale_asm {
+            None | Some("1") => IndexScale::One,
+            Some("2") => IndexScale::Two,
+            Some("4") => IndexScale::Four,
+            Some("8") => IndexScale::Eight,
+            Some(_) => return Err(invalid("Index scale must be 1, 2, 4, or 8")),
+        };
+        Ok((index_register, index_size, index_scale))
     }
 
     /// Gets a size suffix
@@ -330,35 +1185,9 @@ impl AssemblyInterpreter {
         }
     }
 
-    /// Parses a string to a register
-    fn parse_to_register_no_size(register: &str) -> Result<Register, ParseError> {
-        match register {
-            "d0" => Ok(Register::Data(DataRegister::D0)),
-            "d1" => Ok(Register::Data(DataRegister::D1)),
-            "d2" => Ok(Register::Data(DataRegister::D2)),
-            "d3" => Ok(Register::Data(DataRegister::D3)),
-            "d4" => Ok(Register::Data(DataRegister::D4)),
-            "d5" => Ok(Register::Data(DataRegister::D5)),
-            "d6" => Ok(Register::Data(DataRegister::D6)),
-            "d7" => Ok(Register::Data(DataRegister::D7)),
-
-            "a0" => Ok(Register::Address(AddressRegister::A0)),
-            "a1" => Ok(Register::Address(AddressRegister::A1)),
-            "a2" => Ok(Register::Address(AddressRegister::A2)),
-            "a3" => Ok(Register::Address(AddressRegister::A3)),
-            "a4" => Ok(Register::Address(AddressRegister::A4)),
-            "a5" => Ok(Register::Address(AddressRegister::A5)),
-            "a6" => Ok(Register::Address(AddressRegister::A6)),
-            "a7" | "sp" => Ok(Register::Address(AddressRegister::A7)),
-
-            "pc" => Ok(Register::ProgramCounter),
-
-            _ => Err(ParseError::UnknownRegister(register.to_string())),
-        }
-    }
-
     /// Parses source and destination operands
     fn parse_source_dest(
+        &self,
         op_string: &str,
         instruction: String,
     ) -> Result<(AddressMode, AddressMode, Option<OperandSize>), ParseError> {
@@ -376,28 +1205,13 @@ impl AssemblyInterpreter {
                 // they are part of the assembly representation of certain addressing modes.
                 ',' if paren_level == 0 => {
                     let (source_asm, dest_asm) = op_string.split_at(idx);
-                    let (source_mode, source_size) =
-                        Self::parse_to_operand(source_asm.trim(), &instruction)?;
-                    let (dest_mode, dest_size) = Self::parse_to_operand(
+                    let source = self.parse_to_operand(source_asm.trim(), &instruction)?;
+                    let dest = self.parse_to_operand(
                         dest_asm.trim_start_matches(|c| c == ' ' || c == ','),
                         &instruction,
                     )?;
-
-                    // Please, Rust, stabilize multiple `if let`s in one statement :(
-                    if source_size.is_some() && dest_size.is_some() && source_size != dest_size {
-                        return Err(ParseError::OperandSizeMismatch {
-                            instruction,
-                            source_size: source_size.unwrap(),
-                            dest_size: dest_size.unwrap(),
-                        });
-                    }
-
-                    let size = match source_size {
-                        Some(size) => Some(size),
-                        None => dest_size,
-                    };
-
-                    return Ok((source_mode, dest_mode, size));
+                    let size = source.common_size(&dest, &instruction)?;
+                    return Ok((source.mode, dest.mode, size));
                 }
                 _ => {}
             }
@@ -410,28 +1224,248 @@ impl AssemblyInterpreter {
 impl Parser<String> for AssemblyInterpreter {
     fn parse(&mut self, source: String) -> Result<(Instruction, OperandSize, u32), ParseError> {
         let lowercase_source = source.to_lowercase();
-        let (instruction_token, rest) = match lowercase_source.trim().split_once(' ') {
-            Some(s) => s,
-            None => return Err(ParseError::NoInstruction(source)),
+        let trimmed_source = lowercase_source.trim();
+        let (instruction_token, rest) = trimmed_source
+            .split_once(' ')
+            .unwrap_or((trimmed_source, ""));
+        // The mnemonic can have a size suffix too (`move.w d0, d1`), which must agree with the operands'
+        let (instruction_token, mnemonic_size) = Self::parse_size_suffix(instruction_token)?;
+        let instruction_token = canonical_mnemonic(instruction_token);
+
+        // Instructions without operands
+        match instruction_token {
+            "nop" => return Ok((Instruction::NoOp, OperandSize::Long, 0)),
+            "rts" => return Ok((Instruction::ReturnFromSubroutine, OperandSize::Long, 0)),
+            _ => {}
+        }
+        if rest.trim().is_empty() {
+            return Err(ParseError::NoInstruction(source));
+        }
+        if let Some(condition) = instruction_token
+            .strip_prefix("db")
+            .and_then(|condition| condition.parse().ok())
+        {
+            return self.parse_decrement_branch(condition, mnemonic_size, rest.trim(), &source);
+        }
+        if let Some(result) =
+            self.parse_single_operand(instruction_token, mnemonic_size, rest.trim(), &source)
+        {
+            return result;
+        }
+
+        let (src, dest, operand_size) = self.parse_source_dest(rest, source.clone())?;
+        let size = match (instruction_token, mnemonic_size, operand_size) {
+            // MULU multiplies words on the 68000, so it's word-sized unless a size is given
+            ("mulu", None, None) => OperandSize::Word,
+            _ => Self::resolve_size(mnemonic_size, operand_size, &source)?,
         };
-        let (src, dest, size) = Self::parse_source_dest(rest, source)?;
-        let size = size.unwrap_or(OperandSize::Long);
+        match src {
+            // The first pass parses every line too, so only the final pass warns
+            AddressMode::Immediate { value }
+                if self.warn_on_truncated_immediates
+                    && !self.allow_undefined_symbols
+                    && !Self::immediate_fits(value, size) =>
+            {
+                self.warnings.push(format!(
+                    "#{:#X} is too big for a {:?} operation, so only its low bits are used: {}",
+                    value,
+                    size,
+                    source.trim()
+                ));
+            }
+            _ => {}
+        }
+        // The immediate forms of the logical instructions are the same instructions, but only take an immediate source
+        if matches!(instruction_token, "andi" | "ori" | "eori" | "cmpi")
+            && !matches!(src, AddressMode::Immediate { .. })
+        {
+            return Err(ParseError::InvalidOperand {
+                operand: format!("{:?}", src),
+                instruction: source,
+            });
+        }
         match instruction_token {
             "add" => Ok((Instruction::Add { src, dest }, size, 0)),
             "sub" => Ok((Instruction::Subtract { src, dest }, size, 0)),
+            "cmp" | "cmpa" | "cmpi" => Ok((Instruction::Compare { src, dest }, size, 0)),
+            "addx" | "subx" | "abcd" | "sbcd" => match (&src, &dest) {
+                // BCD instructions only work on bytes
+                _ if matches!(instruction_token, "abcd" | "sbcd")
+                    && (mnemonic_size.is_some() || operand_size.is_some())
+                    && size != OperandSize::Byte =>
+                {
+                    Err(ParseError::InvalidOperandSize(size.size_in_bytes() as i32))
+                }
+                (
+                    AddressMode::RegisterDirect {
+                        register: Register::Data(_),
+                    },
+                    AddressMode::RegisterDirect {
+                        register: Register::Data(_),
+                    },
+                )
+                | (
+                    AddressMode::RegisterIndirectPreDecrement { .. },
+                    AddressMode::RegisterIndirectPreDecrement { .. },
+                ) => match instruction_token {
+                    "addx" => Ok((Instruction::AddWithExtend { src, dest }, size, 0)),
+                    "subx" => Ok((Instruction::SubtractWithExtend { src, dest }, size, 0)),
+                    "abcd" => Ok((Instruction::AddBCD { src, dest }, OperandSize::Byte, 0)),
+                    _ => Ok((Instruction::SubtractBCD { src, dest }, OperandSize::Byte, 0)),
+                },
+                _ => Err(ParseError::InvalidOperand {
+                    operand: format!("{:?}, {:?}", src, dest),
+                    instruction: source,
+                }),
+            },
+            "btst" | "bset" | "bclr" | "bchg" => {
+                // Bit instructions work on a whole data register, or a single byte of memory
+                let bit_size = match dest {
+                    AddressMode::RegisterDirect {
+                        register: Register::Data(_),
+                    } => OperandSize::Long,
+                    _ => OperandSize::Byte,
+                };
+                if (mnemonic_size.is_some() || operand_size.is_some()) && size != bit_size {
+                    return Err(ParseError::InvalidOperandSize(size.size_in_bytes() as i32));
+                }
+                match (&src, &dest) {
+                    (
+                        AddressMode::Immediate { .. }
+                        | AddressMode::RegisterDirect {
+                            register: Register::Data(_),
+                        },
+                        _,
+                    ) if !matches!(
+                        dest,
+                        AddressMode::RegisterDirect {
+                            register: Register::Address(_)
+                        }
+                    ) =>
+                    {
+                        let (bit_number, dest) = (src, dest);
+                        let instruction = match instruction_token {
+                            "btst" => Instruction::TestBit { bit_number, dest },
+                            "bset" => Instruction::SetBit { bit_number, dest },
+                            "bclr" => Instruction::ClearBit { bit_number, dest },
+                            _ => Instruction::ChangeBit { bit_number, dest },
+                        };
+                        Ok((instruction, bit_size, 0))
+                    }
+                    _ => Err(ParseError::InvalidOperand {
+                        operand: format!("{:?}, {:?}", src, dest),
+                        instruction: source,
+                    }),
+                }
+            }
             "mulu" => Ok((Instruction::MultiplyUnsigned { src, dest }, size, 0)),
+            "divu" => Ok((Instruction::DivideUnsigned { src, dest }, size, 0)),
+            "divs" => Ok((Instruction::DivideSigned { src, dest }, size, 0)),
             "move" => Ok((Instruction::Move { src, dest }, size, 0)),
-            "roxl" => Ok((
-                Instruction::RotateLeft {
-                    to_rotate: src,
-                    rotate_amount: dest,
-                },
-                size,
-                0,
-            )),
-            "eor" => Ok((Instruction::ExclusiveOr { src, dest }, size, 0)),
-            "or" => Ok((Instruction::InclusiveOr { src, dest }, size, 0)),
-            "nop" => Ok((Instruction::NoOp, size, 0)),
+            "movep" => match (&src, &dest) {
+                _ if size == OperandSize::Byte => Err(ParseError::InvalidOperandSize(1)),
+                (
+                    AddressMode::RegisterDirect {
+                        register: Register::Data(_),
+                    },
+                    AddressMode::RegisterIndirectWithDisplacement { .. },
+                )
+                | (
+                    AddressMode::RegisterIndirectWithDisplacement { .. },
+                    AddressMode::RegisterDirect {
+                        register: Register::Data(_),
+                    },
+                ) => Ok((Instruction::MovePeripheral { src, dest }, size, 0)),
+                (
+                    AddressMode::RegisterDirect {
+                        register: Register::Data(_),
+                    },
+                    operand,
+                )
+                | (operand, _) => Err(ParseError::InvalidOperand {
+                    operand: format!("{:?}", operand),
+                    instruction: source,
+                }),
+            },
+            "moveq" => match src {
+                // MOVEQ's data is a signed byte
+                AddressMode::Immediate { value } if (-128..=127).contains(&(value as i32)) => {
+                    Ok((Instruction::MoveQuick { src, dest }, OperandSize::Long, 0))
+                }
+                AddressMode::Immediate { value } => Err(ParseError::ImmediateOutOfRange {
+                    value: value as i32 as i64,
+                    instruction: source,
+                }),
+                _ => Err(ParseError::InvalidOperand {
+                    operand: format!("{:?}", src),
+                    instruction: source,
+                }),
+            },
+            "rol" => Ok((
+                Instruction::RotateLeft {
+                    to_rotate: dest,
+                    rotate_amount: Self::check_shift_count(src, &source)?,
+                },
+                size,
+                0,
+            )),
+            "ror" => Ok((
+                Instruction::RotateRight {
+                    to_rotate: dest,
+                    rotate_amount: Self::check_shift_count(src, &source)?,
+                },
+                size,
+                0,
+            )),
+            "roxl" => Ok((
+                Instruction::RotateLeftExtended {
+                    to_rotate: dest,
+                    rotate_amount: Self::check_shift_count(src, &source)?,
+                },
+                size,
+                0,
+            )),
+            "roxr" => Ok((
+                Instruction::RotateRightExtended {
+                    to_rotate: dest,
+                    rotate_amount: Self::check_shift_count(src, &source)?,
+                },
+                size,
+                0,
+            )),
+            "lsl" => Ok((
+                Instruction::LogicalShiftLeft {
+                    to_shift: dest,
+                    shift_amount: Self::check_shift_count(src, &source)?,
+                },
+                size,
+                0,
+            )),
+            "moves" => Ok((Instruction::MoveAlternateSpace { src, dest }, size, 0)),
+            "asl" => Ok((
+                Instruction::ArithmeticShiftLeft {
+                    count: Self::check_shift_count(src, &source)?,
+                    dest,
+                },
+                size,
+                0,
+            )),
+            "asr" => Ok((
+                Instruction::ArithmeticShiftRight {
+                    count: Self::check_shift_count(src, &source)?,
+                    dest,
+                },
+                size,
+                0,
+            )),
+            "lea" => Ok((
+                Instruction::LoadEffectiveAddress { src, dest },
+                OperandSize::Long,
+                0,
+            )),
+            "eor" | "eori" => Ok((Instruction::ExclusiveOr { src, dest }, size, 0)),
+            "or" | "ori" => Ok((Instruction::InclusiveOr { src, dest }, size, 0)),
+            "and" | "andi" => Ok((Instruction::And { src, dest }, size, 0)),
             _ => Err(ParseError::UnknownInstruction(
                 instruction_token.to_string(),
             )),
@@ -443,7 +1477,11 @@ impl Parser<String> for AssemblyInterpreter {
 mod tests {
     use super::*;
     use crate::{
-        cpu::registers::{AddressRegister, DataRegister, Register::*},
+        cpu::{
+            registers::{AddressRegister, DataRegister, Register::*},
+            CPU,
+        },
+        ram::{Memory, VecBackedMemory},
         OperandSize::*,
     };
     use lazy_static::lazy_static;
@@ -511,6 +1549,11 @@ mod tests {
         test_source_dest_instruction("SUB", |src, dest| Instruction::Subtract { src, dest });
     }
 
+    #[test]
+    fn parse_and() {
+        test_source_dest_instruction("AND", |src, dest| Instruction::And { src, dest });
+    }
+
     #[test]
     fn parse_unsigned_multiplication() {
         test_source_dest_instruction("MULU", |src, dest| Instruction::MultiplyUnsigned {
@@ -519,6 +1562,817 @@ mod tests {
         });
     }
 
+    #[test]
+    fn parse_division() {
+        test_source_dest_instruction("DIVU", |src, dest| Instruction::DivideUnsigned {
+            src,
+            dest,
+        });
+        test_source_dest_instruction("DIVS", |src, dest| Instruction::DivideSigned { src, dest });
+    }
+
+    #[test]
+    fn parse_negative_immediate() {
+        assert_eq!(
+            AssemblyInterpreter::new()
+                .parse_to_operand("#-1", &DUMMY_INSTRUCTION)
+                .unwrap(),
+            ParsedOperand::new(AddressMode::Immediate { value: 0xFFFFFFFF }, None)
+        );
+        assert_eq!(
+            AssemblyInterpreter::new()
+                .parse_to_operand("#-$80", &DUMMY_INSTRUCTION)
+                .unwrap(),
+            ParsedOperand::new(AddressMode::Immediate { value: 0xFFFFFF80 }, None)
+        );
+    }
+
+    #[test]
+    fn parse_move_quick() {
+        let mut interpreter = AssemblyInterpreter::new();
+        assert_eq!(
+            interpreter.parse("moveq #-128, d0".to_string()).unwrap(),
+            (
+                Instruction::MoveQuick {
+                    src: AddressMode::Immediate { value: 0xFFFFFF80 },
+                    dest: AddressMode::RegisterDirect {
+                        register: Data(DataRegister::D0)
+                    },
+                },
+                Long,
+                0
+            )
+        );
+        assert!(interpreter.parse("moveq #127, d0".to_string()).is_ok());
+
+        for asm in ["moveq #200, d0", "moveq #128, d0", "moveq #-129, d0"] {
+            assert!(
+                matches!(
+                    interpreter.parse(asm.to_string()),
+                    Err(ParseError::ImmediateOutOfRange { .. })
+                ),
+                "{}",
+                asm
+            );
+        }
+    }
+
+    #[test]
+    fn parse_shift() {
+        let mut interpreter = AssemblyInterpreter::new();
+        let d0 = AddressMode::RegisterDirect {
+            register: Data(DataRegister::D0),
+        };
+        assert_eq!(
+            interpreter.parse("lsl d1, d0".to_string()).unwrap(),
+            (
+                Instruction::LogicalShiftLeft {
+                    to_shift: d0.clone(),
+                    shift_amount: AddressMode::RegisterDirect {
+                        register: Data(DataRegister::D1)
+                    },
+                },
+                Long,
+                0
+            )
+        );
+        assert_eq!(
+            interpreter.parse("lsl #3, d0".to_string()).unwrap(),
+            (
+                Instruction::LogicalShiftLeft {
+                    to_shift: d0.clone(),
+                    shift_amount: AddressMode::Immediate { value: 3 },
+                },
+                Long,
+                0
+            )
+        );
+        assert_eq!(
+            interpreter.parse("roxl #8, d0".to_string()).unwrap(),
+            (
+                Instruction::RotateLeftExtended {
+                    to_rotate: d0,
+                    rotate_amount: AddressMode::Immediate { value: 8 },
+                },
+                Long,
+                0
+            )
+        );
+
+        for asm in ["lsl #0, d0", "lsl #9, d0", "roxl #9, d0"] {
+            assert!(
+                matches!(
+                    interpreter.parse(asm.to_string()),
+                    Err(ParseError::ImmediateOutOfRange { .. })
+                ),
+                "{}",
+                asm
+            );
+        }
+        assert!(matches!(
+            interpreter.parse("lsl a1, d0".to_string()),
+            Err(ParseError::InvalidOperand { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_mnemonic_size_suffix() {
+        for (asm, size) in [
+            ("move d0, d1", Long),
+            ("move.w d0, d1", Word),
+            ("move.b d0.b, d1", Byte),
+            ("move.l d0, a0.l", Long),
+            ("move d0.w, d1", Word),
+        ] {
+            let (_, parsed_size, _) = AssemblyInterpreter::new().parse(asm.to_string()).unwrap();
+            assert_eq!(parsed_size, size, "{}", asm);
+        }
+    }
+
+    #[test]
+    fn parse_mnemonic_size_suffix_instructions() {
+        let mut interpreter = AssemblyInterpreter::new();
+        assert_eq!(
+            interpreter.parse("move.l a0, a1".to_string()).unwrap(),
+            (
+                Instruction::Move {
+                    src: AddressMode::RegisterDirect {
+                        register: Address(AddressRegister::A0)
+                    },
+                    dest: AddressMode::RegisterDirect {
+                        register: Address(AddressRegister::A1)
+                    },
+                },
+                Long,
+                0
+            )
+        );
+        assert_eq!(
+            interpreter.parse("ADD.W #1, D0".to_string()).unwrap(),
+            (
+                Instruction::Add {
+                    src: AddressMode::Immediate { value: 1 },
+                    dest: AddressMode::RegisterDirect {
+                        register: Data(DataRegister::D0)
+                    },
+                },
+                Word,
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn parse_mnemonic_size_mismatch() {
+        for (asm, mnemonic, operand) in [
+            ("move.l d0, a0.w", Long, Word),
+            ("move.b d0.w, d1.w", Byte, Word),
+            ("add.w ($1000).l, d0", Word, Long),
+        ] {
+            match AssemblyInterpreter::new().parse(asm.to_string()) {
+                Err(ParseError::MnemonicSizeMismatch {
+                    mnemonic_size,
+                    operand_size,
+                    ..
+                }) => {
+                    assert_eq!(mnemonic_size, mnemonic, "{}", asm);
+                    assert_eq!(operand_size, operand, "{}", asm);
+                }
+                other => panic!("expected a size mismatch for {}, got {:?}", asm, other),
+            }
+        }
+
+        // operands that disagree with each other are still caught
+        assert!(matches!(
+            AssemblyInterpreter::new().parse("move.l d0.l, d1.w".to_string()),
+            Err(ParseError::OperandSizeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn truncated_immediates() {
+        for (value, size, fits) in [
+            (0xFF, Byte, true),
+            (0x100, Byte, false),
+            (-128_i32 as u32, Byte, true),
+            (-129_i32 as u32, Byte, false),
+            (0xFFFF, Word, true),
+            (0x1_0001, Word, false),
+            (u32::MAX, Word, true),
+            (0x1234_5678, Long, true),
+        ] {
+            assert_eq!(
+                AssemblyInterpreter::immediate_fits(value, size),
+                fits,
+                "{:#X} in a {:?}",
+                value,
+                size
+            );
+        }
+
+        // Warning about an immediate doesn't stop it from being assembled, and only the low word is added
+        let mut interpreter = AssemblyInterpreter {
+            warn_on_truncated_immediates: true,
+            ..AssemblyInterpreter::default()
+        };
+        let (instruction, size, _) = interpreter.parse("add.w #$10001, d0".to_string()).unwrap();
+        assert_eq!(
+            instruction,
+            Instruction::Add {
+                src: AddressMode::Immediate { value: 0x1_0001 },
+                dest: AddressMode::RegisterDirect {
+                    register: Data(DataRegister::D0)
+                },
+            }
+        );
+        let mut cpu = CPU::<VecBackedMemory>::new(1024);
+        cpu.registers.set_data_register(DataRegister::D0, 5_u32);
+        instruction.execute(&mut cpu, size).unwrap();
+        assert_eq!(cpu.registers.get_data_register(DataRegister::D0), 6);
+        assert_eq!(interpreter.take_warnings().len(), 1);
+
+        // Assembling a program warns once per line, not once per pass
+        let source = "add.w #$10001, d0\nmove.b #$FF, d1\nmove.b #$100, d2";
+        interpreter.assemble(source, 0x100).unwrap();
+        let warnings = interpreter.take_warnings();
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert!(warnings[1].ends_with("move.b #$100, d2"), "{}", warnings[1]);
+        assert!(interpreter.take_warnings().is_empty());
+
+        let mut interpreter = AssemblyInterpreter::new();
+        interpreter.assemble(source, 0x100).unwrap();
+        assert!(interpreter.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn assemble_one() {
+        let mut interpreter = AssemblyInterpreter::new();
+        assert_eq!(interpreter.assemble_one("nop").unwrap(), vec![0x4E, 0x71]);
+        assert_eq!(
+            interpreter.assemble_one("move.w d0, d1").unwrap(),
+            vec![0x32, 0x00]
+        );
+        assert!(interpreter.assemble_one("bogus d0").is_err());
+    }
+
+    #[test]
+    fn assemble_with_listing() {
+        let source = "start: moveq #5, d0 ; five\n\n    ; loop forever\nloop:\n    move.w d0, d1\n    bra loop";
+        let (instructions, listing) = AssemblyInterpreter::new()
+            .assemble_with_listing(source, 0x100)
+            .unwrap();
+        assert_eq!(
+            instructions,
+            AssemblyInterpreter::new().assemble(source, 0x100).unwrap()
+        );
+
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(lines.len(), source.lines().count());
+        assert_eq!(
+            lines[0],
+            "00000100: 70 05                          start: moveq #5, d0 ; five"
+        );
+        assert_eq!(lines[1], "");
+        assert_eq!(
+            lines[2],
+            "                                             ; loop forever"
+        );
+        assert_eq!(lines[3].trim(), "loop:");
+        assert!(lines[4].starts_with("00000102: 32 00 "));
+        assert!(lines[5].starts_with("00000104: 60 FC "));
+        assert!(lines[5].ends_with("    bra loop"));
+    }
+
+    #[test]
+    fn assemble_data() {
+        let source = "\
+    lea (table, pc), a0
+    rts
+table:
+    dc.w $1234, $5678
+    dc.b 'A','B', \"a;b\", -1 ; a comment
+    dc.l $DEADBEEF, table
+bytes: dc.b 1, 2, 3
+after:
+    nop";
+        let mut interpreter = AssemblyInterpreter::new();
+        let binary = interpreter.assemble_binary(source, 0x100).unwrap();
+        let mut cpu = CPU::<VecBackedMemory>::new(1024);
+        cpu.load_binary(0x100, binary).unwrap();
+
+        // Words and longs are big-endian
+        let table = interpreter.symbols()["table"];
+        assert_eq!(table, 0x106);
+        assert_eq!(
+            cpu.memory.read_bytes(crate::Address(table), 4).unwrap(),
+            vec![0x12, 0x34, 0x56, 0x78]
+        );
+        // Each character of a string is a byte
+        assert_eq!(
+            cpu.memory.read_bytes(crate::Address(0x10A), 6).unwrap(),
+            vec![b'A', b'B', b'a', b';', b'b', 0xFF]
+        );
+        assert_eq!(
+            cpu.memory.read_bytes(crate::Address(0x110), 8).unwrap(),
+            vec![0xDE, 0xAD, 0xBE, 0xEF, 0x00, 0x00, 0x01, 0x06]
+        );
+        // After an odd number of bytes, the next instruction is padded to an even address
+        assert_eq!(interpreter.symbols()["bytes"], 0x118);
+        assert_eq!(interpreter.symbols()["after"], 0x11C);
+        assert_eq!(cpu.memory.read_word(crate::Address(0x11C)).unwrap(), 0x4E71);
+
+        // Only instructions are returned by assemble, but data takes up space
+        let instructions = interpreter.assemble(source, 0x100).unwrap();
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(instructions[2].address, 0x11C);
+    }
+
+    #[test]
+    fn assemble_invalid_data() {
+        for source in [
+            "dc.b 256",
+            "dc.w $10000",
+            "dc.b 'unterminated",
+            "dc.l",
+            "dc.b nowhere",
+        ] {
+            assert!(
+                matches!(
+                    AssemblyInterpreter::new().assemble_binary(source, 0),
+                    Err(ParseError::AtLine { line: 1, .. })
+                ),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn parse_bcd() {
+        let mut interpreter = AssemblyInterpreter::new();
+        let d0 = AddressMode::RegisterDirect {
+            register: Data(DataRegister::D0),
+        };
+        let d1 = AddressMode::RegisterDirect {
+            register: Data(DataRegister::D1),
+        };
+        let a0 = AddressMode::RegisterIndirectPreDecrement {
+            register: AddressRegister::A0,
+        };
+        let a1 = AddressMode::RegisterIndirectPreDecrement {
+            register: AddressRegister::A1,
+        };
+        for (asm, expected) in [
+            (
+                "abcd d0, d1",
+                Instruction::AddBCD {
+                    src: d0.clone(),
+                    dest: d1.clone(),
+                },
+            ),
+            (
+                "abcd.b -(a0), -(a1)",
+                Instruction::AddBCD {
+                    src: a0.clone(),
+                    dest: a1.clone(),
+                },
+            ),
+            (
+                "sbcd d1, d0",
+                Instruction::SubtractBCD {
+                    src: d1.clone(),
+                    dest: d0.clone(),
+                },
+            ),
+            (
+                "sbcd -(a1), -(a0)",
+                Instruction::SubtractBCD {
+                    src: a1.clone(),
+                    dest: a0.clone(),
+                },
+            ),
+            ("nbcd d1", Instruction::NegateBCD { dest: d1.clone() }),
+            ("nbcd.b -(a0)", Instruction::NegateBCD { dest: a0.clone() }),
+        ] {
+            assert_eq!(
+                interpreter.parse(asm.to_string()).unwrap(),
+                (expected, OperandSize::Byte, 0),
+                "{}",
+                asm
+            );
+        }
+
+        for asm in [
+            "abcd d0, -(a1)",
+            "sbcd (a0), (a1)",
+            "abcd #1, d0",
+            "abcd.w d0, d1",
+            "sbcd.l -(a0), -(a1)",
+            "nbcd.w d0",
+        ] {
+            assert!(interpreter.parse(asm.to_string()).is_err(), "{}", asm);
+        }
+    }
+
+    #[test]
+    fn parse_bit_instructions() {
+        let mut interpreter = AssemblyInterpreter::new();
+        let d0 = AddressMode::RegisterDirect {
+            register: Register::Data(DataRegister::D0),
+        };
+        let d1 = AddressMode::RegisterDirect {
+            register: Register::Data(DataRegister::D1),
+        };
+        let a0 = AddressMode::RegisterIndirect {
+            register: AddressRegister::A0,
+        };
+        let three = AddressMode::Immediate { value: 3 };
+        // Data registers are accessed as longs, and memory as bytes
+        for (asm, instruction, size) in [
+            (
+                "btst #3, d0",
+                Instruction::TestBit {
+                    bit_number: three.clone(),
+                    dest: d0.clone(),
+                },
+                Long,
+            ),
+            (
+                "bset.l d1, d0",
+                Instruction::SetBit {
+                    bit_number: d1.clone(),
+                    dest: d0.clone(),
+                },
+                Long,
+            ),
+            (
+                "bclr #3, (a0)",
+                Instruction::ClearBit {
+                    bit_number: three,
+                    dest: a0.clone(),
+                },
+                Byte,
+            ),
+            (
+                "bchg.b d1, (a0)",
+                Instruction::ChangeBit {
+                    bit_number: d1,
+                    dest: a0,
+                },
+                Byte,
+            ),
+        ] {
+            assert_eq!(
+                interpreter.parse(asm.to_string()).unwrap(),
+                (instruction, size, 0),
+                "{}",
+                asm
+            );
+        }
+
+        let mut parse = |asm: &str| interpreter.parse(asm.to_string());
+        assert!(matches!(
+            parse("btst.b #3, d0"),
+            Err(ParseError::InvalidOperandSize(1))
+        ));
+        assert!(matches!(
+            parse("bset.w #3, (a0)"),
+            Err(ParseError::InvalidOperandSize(2))
+        ));
+        assert!(matches!(
+            parse("bclr a0, d0"),
+            Err(ParseError::InvalidOperand { .. })
+        ));
+        assert!(matches!(
+            parse("bchg #1, a0"),
+            Err(ParseError::InvalidOperand { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_memory_to_memory_size_mismatch() {
+        let mut interpreter = AssemblyInterpreter::new();
+        for asm in [
+            "move.b (a0), (a1).w",
+            "move.l (a0).w, (a1)",
+            "move.w ($100), ($200).b",
+        ] {
+            assert!(
+                matches!(
+                    interpreter.parse(asm.to_string()),
+                    Err(ParseError::MnemonicSizeMismatch { .. })
+                ),
+                "{}",
+                asm
+            );
+        }
+        for asm in [
+            "move (a0).b, (a1).w",
+            "move (a0)+.l, -(a1).w",
+            "move.b (a0).w, (a1).b",
+        ] {
+            assert!(
+                matches!(
+                    interpreter.parse(asm.to_string()),
+                    Err(ParseError::OperandSizeMismatch { .. })
+                ),
+                "{}",
+                asm
+            );
+        }
+        assert!(matches!(
+            interpreter.parse("move (a0.b).w, (a1)".to_string()),
+            Err(ParseError::InvalidOperand { .. })
+        ));
+
+        // sizes that agree are fine
+        assert_eq!(
+            interpreter
+                .parse("move.w (a0).w, (a1)".to_string())
+                .unwrap(),
+            (
+                Instruction::Move {
+                    src: AddressMode::RegisterIndirect {
+                        register: AddressRegister::A0
+                    },
+                    dest: AddressMode::RegisterIndirect {
+                        register: AddressRegister::A1
+                    },
+                },
+                Word,
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn mnemonic_aliases() {
+        for (alias, canonical) in [
+            ("dbra", "dbf"),
+            ("bhs", "bcc"),
+            ("blo", "bcs"),
+            ("bt", "bra"),
+        ] {
+            assert_eq!(canonical_mnemonic(alias), canonical);
+            assert_eq!(canonical_mnemonic(canonical), canonical);
+        }
+        assert_eq!(canonical_mnemonic("move"), "move");
+
+        // An alias assembles to the same machine code as what it stands for
+        let mut interpreter = AssemblyInterpreter::new();
+        let mut assemble = |branch: &str| {
+            let source = format!("target: nop\n{}\n", branch);
+            interpreter.assemble_binary(&source, 0x100).unwrap()
+        };
+        for (alias, canonical) in [
+            ("DBRA d0, target", "dbf d0, target"),
+            ("bhs target", "bcc target"),
+            ("blo target", "bcs target"),
+            ("bt target", "bra target"),
+        ] {
+            assert_eq!(assemble(alias), assemble(canonical), "{}", alias);
+        }
+    }
+
+    #[test]
+    fn parse_jump() {
+        let mut interpreter = AssemblyInterpreter::new();
+        for (asm, address) in [
+            ("jmp $1000", AddressMode::Absolute { address: 0x1000 }),
+            ("jmp 4096", AddressMode::Absolute { address: 0x1000 }),
+            ("jmp ($1000)", AddressMode::Absolute { address: 0x1000 }),
+            (
+                "jmp (a0)",
+                AddressMode::RegisterIndirect {
+                    register: AddressRegister::A0,
+                },
+            ),
+        ] {
+            assert_eq!(
+                interpreter.parse(asm.to_string()).unwrap(),
+                (Instruction::JumpTo { address }, Long, 0),
+                "{}",
+                asm
+            );
+        }
+        for asm in ["jmp a0", "jsr d1", "jmp %a6", "jsr (a0)+", "jmp -(a1)"] {
+            assert!(
+                matches!(
+                    interpreter.parse(asm.to_string()),
+                    Err(ParseError::InvalidRegister { .. })
+                ),
+                "{}",
+                asm
+            );
+        }
+        for asm in ["jmp #$1000", "jsr #4"] {
+            assert!(
+                matches!(
+                    interpreter.parse(asm.to_string()),
+                    Err(ParseError::InvalidOperand { .. })
+                ),
+                "{}",
+                asm
+            );
+        }
+        assert_eq!(
+            interpreter.parse("jsr $2000".to_string()).unwrap(),
+            (
+                Instruction::JumpToSubroutine {
+                    address: AddressMode::Absolute { address: 0x2000 }
+                },
+                Long,
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn parse_unary() {
+        let mut interpreter = AssemblyInterpreter::new();
+        let d3 = AddressMode::RegisterDirect {
+            register: Data(DataRegister::D3),
+        };
+        for (suffix, size) in [("b", Byte), ("w", Word), ("l", Long)] {
+            for (mnemonic, instruction) in [
+                ("neg", Instruction::Negate { dest: d3.clone() }),
+                ("negx", Instruction::NegateWithExtend { dest: d3.clone() }),
+                ("clr", Instruction::Clear { dest: d3.clone() }),
+                ("not", Instruction::Not { dest: d3.clone() }),
+                ("tst", Instruction::Test { src: d3.clone() }),
+            ] {
+                let asm = format!("{}.{} d3", mnemonic, suffix);
+                assert_eq!(
+                    interpreter.parse(asm.clone()).unwrap(),
+                    (instruction, size, 0),
+                    "{}",
+                    asm
+                );
+            }
+        }
+
+        assert_eq!(
+            interpreter.parse("clr (a0)".to_string()).unwrap(),
+            (
+                Instruction::Clear {
+                    dest: AddressMode::RegisterIndirect {
+                        register: AddressRegister::A0
+                    }
+                },
+                Long,
+                0
+            )
+        );
+        assert!(matches!(
+            interpreter.parse("tst.w d0.b".to_string()),
+            Err(ParseError::MnemonicSizeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_decrement_branch() {
+        let mut interpreter = AssemblyInterpreter::new();
+        interpreter.location = 0x100;
+        for (asm, condition, register, displacement) in [
+            ("dbf d0, $F0", Condition::False, DataRegister::D0, -0x12),
+            ("dbra d1, $100", Condition::False, DataRegister::D1, -2),
+            ("dbeq.w %d7, $200", Condition::Equal, DataRegister::D7, 0xFE),
+            ("dblo d2, $102", Condition::CarrySet, DataRegister::D2, 0),
+        ] {
+            assert_eq!(
+                interpreter.parse(asm.to_string()).unwrap(),
+                (
+                    Instruction::DecrementBranch {
+                        condition,
+                        register,
+                        displacement
+                    },
+                    Word,
+                    0
+                ),
+                "{}",
+                asm
+            );
+        }
+
+        let mut parse = |asm: &str| interpreter.parse(asm.to_string());
+        assert!(matches!(
+            parse("dbf a0, $100"),
+            Err(ParseError::InvalidRegister { .. })
+        ));
+        assert!(matches!(
+            parse("dbf.l d0, $100"),
+            Err(ParseError::InvalidOperandSize(4))
+        ));
+        assert!(matches!(
+            parse("dbf d0"),
+            Err(ParseError::MissingOperand(_))
+        ));
+        assert!(matches!(
+            parse("dbf d0, $10000"),
+            Err(ParseError::DisplacementOutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn assemble_jump_to_label() {
+        let program = AssemblyInterpreter::new()
+            .assemble("start: nop\njmp start", 0x400)
+            .unwrap();
+        assert_eq!(
+            program[1].instruction,
+            Instruction::JumpTo {
+                address: AddressMode::Absolute { address: 0x400 }
+            }
+        );
+    }
+
+    #[test]
+    fn parse_without_operands() {
+        let mut interpreter = AssemblyInterpreter::new();
+        assert_eq!(
+            interpreter.parse("nop".to_string()).unwrap(),
+            (Instruction::NoOp, Long, 0)
+        );
+        assert_eq!(
+            interpreter.parse("  RTS ".to_string()).unwrap(),
+            (Instruction::ReturnFromSubroutine, Long, 0)
+        );
+        assert!(matches!(
+            interpreter.parse("add".to_string()),
+            Err(ParseError::NoInstruction(_))
+        ));
+    }
+
+    #[test]
+    fn parse_operand_sizes() {
+        let interpreter = AssemblyInterpreter::new();
+        let a0 = AddressRegister::A0;
+        for (operand, expected) in [
+            (
+                "#5",
+                ParsedOperand::new(AddressMode::Immediate { value: 5 }, None),
+            ),
+            (
+                "d0.b",
+                ParsedOperand::new(
+                    AddressMode::RegisterDirect {
+                        register: Data(DataRegister::D0),
+                    },
+                    Some(Byte),
+                ),
+            ),
+            (
+                "(a0).w",
+                ParsedOperand::new(AddressMode::RegisterIndirect { register: a0 }, Some(Word)),
+            ),
+            (
+                "(a0.w)+.w",
+                ParsedOperand::new(
+                    AddressMode::RegisterIndirectPostIncrement { register: a0 },
+                    Some(Word),
+                ),
+            ),
+            (
+                "($1000).l",
+                ParsedOperand::new(AddressMode::Absolute { address: 0x1000 }, Some(Long)),
+            ),
+        ] {
+            assert_eq!(
+                interpreter
+                    .parse_to_operand(operand, &DUMMY_INSTRUCTION)
+                    .unwrap(),
+                expected,
+                "{}",
+                operand
+            );
+        }
+        // Sizes inside and outside the parentheses must agree
+        assert!(interpreter
+            .parse_to_operand("(a0.w).l", &DUMMY_INSTRUCTION)
+            .is_err());
+
+        let byte = ParsedOperand::new(AddressMode::Immediate { value: 1 }, Some(Byte));
+        let no_size = ParsedOperand::new(AddressMode::Immediate { value: 1 }, None);
+        assert_eq!(no_size.common_size(&no_size, "").unwrap(), None);
+        assert_eq!(byte.common_size(&no_size, "").unwrap(), Some(Byte));
+        assert_eq!(no_size.common_size(&byte, "").unwrap(), Some(Byte));
+        assert!(matches!(
+            byte.common_size(&byte.clone().with_size(Byte).unwrap(), "")
+                .unwrap(),
+            Some(Byte)
+        ));
+        assert_eq!(byte.clone().with_size(Word), None);
+        assert!(matches!(
+            byte.common_size(&no_size.with_size(Long).unwrap(), "move"),
+            Err(ParseError::OperandSizeMismatch {
+                source_size: Byte,
+                dest_size: Long,
+                ..
+            })
+        ));
+    }
+
     #[test]
     fn parse_to_operand_register_direct() {
         for (operand, register) in [
@@ -528,8 +2382,10 @@ mod tests {
             ("sp", Address(AddressRegister::A7)),
         ] {
             assert_eq!(
-                AssemblyInterpreter::parse_to_operand(operand, &DUMMY_INSTRUCTION).unwrap(),
-                (AddressMode::RegisterDirect { register }, None)
+                AssemblyInterpreter::new()
+                    .parse_to_operand(operand, &DUMMY_INSTRUCTION)
+                    .unwrap(),
+                ParsedOperand::new(AddressMode::RegisterDirect { register }, None)
             );
         }
     }
@@ -542,8 +2398,10 @@ mod tests {
             ("(sp)", AddressRegister::A7),
         ] {
             assert_eq!(
-                AssemblyInterpreter::parse_to_operand(operand, &DUMMY_INSTRUCTION).unwrap(),
-                (AddressMode::RegisterIndirect { register }, None)
+                AssemblyInterpreter::new()
+                    .parse_to_operand(operand, &DUMMY_INSTRUCTION)
+                    .unwrap(),
+                ParsedOperand::new(AddressMode::RegisterIndirect { register }, None)
             );
         }
     }
@@ -556,8 +2414,10 @@ mod tests {
             ("(sp)+", AddressRegister::A7),
         ] {
             assert_eq!(
-                AssemblyInterpreter::parse_to_operand(operand, &DUMMY_INSTRUCTION).unwrap(),
-                (
+                AssemblyInterpreter::new()
+                    .parse_to_operand(operand, &DUMMY_INSTRUCTION)
+                    .unwrap(),
+                ParsedOperand::new(
                     AddressMode::RegisterIndirectPostIncrement { register },
                     None
                 )
@@ -573,12 +2433,109 @@ mod tests {
             ("-(sp)", AddressRegister::A7),
         ] {
             assert_eq!(
-                AssemblyInterpreter::parse_to_operand(operand, &DUMMY_INSTRUCTION).unwrap(),
-                (AddressMode::RegisterIndirectPreDecrement { register }, None)
+                AssemblyInterpreter::new()
+                    .parse_to_operand(operand, &DUMMY_INSTRUCTION)
+                    .unwrap(),
+                ParsedOperand::new(AddressMode::RegisterIndirectPreDecrement { register }, None)
             );
         }
     }
 
+    #[test]
+    fn parse_to_operand_indirect_needs_address_register() {
+        for (operand, register, mode) in [
+            ("(d0)+", "d0", "Postincrement"),
+            ("(pc)+", "pc", "Postincrement"),
+            ("-(pc)", "pc", "Predecrement"),
+            ("-(d3)", "d3", "Predecrement"),
+            ("(d1)", "d1", "Indirect addressing"),
+        ] {
+            match AssemblyInterpreter::new().parse_to_operand(operand, &DUMMY_INSTRUCTION) {
+                Err(ParseError::InvalidRegister {
+                    register: invalid,
+                    reason,
+                    ..
+                }) => {
+                    assert_eq!(invalid, register, "{}", operand);
+                    assert!(reason.starts_with(mode), "{}: {}", operand, reason);
+                    assert!(reason.contains("address register"), "{}", operand);
+                }
+                other => panic!("expected InvalidRegister for {}, got {:?}", operand, other),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_gnu_register_prefix() {
+        let (instruction, size, _) = AssemblyInterpreter::new()
+            .parse("move.l %d0, %d1".to_string())
+            .unwrap();
+        assert_eq!(
+            (instruction, size),
+            (
+                Instruction::Move {
+                    src: AddressMode::RegisterDirect {
+                        register: Data(DataRegister::D0),
+                    },
+                    dest: AddressMode::RegisterDirect {
+                        register: Data(DataRegister::D1),
+                    },
+                },
+                OperandSize::Long
+            )
+        );
+
+        for (operand, mode) in [
+            (
+                "%sp",
+                AddressMode::RegisterDirect {
+                    register: Address(AddressRegister::A7),
+                },
+            ),
+            (
+                "-(%sp)",
+                AddressMode::RegisterIndirectPreDecrement {
+                    register: AddressRegister::A7,
+                },
+            ),
+            (
+                "(%a5)+",
+                AddressMode::RegisterIndirectPostIncrement {
+                    register: AddressRegister::A5,
+                },
+            ),
+            (
+                "(8, %a6)",
+                AddressMode::RegisterIndirectWithDisplacement {
+                    displacement: 8,
+                    register: AddressRegister::A6,
+                },
+            ),
+            (
+                "(2, %pc, %d3.l)",
+                AddressMode::ProgramCounterIndirectIndexed {
+                    displacement: 2,
+                    index_register: Data(DataRegister::D3),
+                    index_size: OperandSize::Long,
+                    index_scale: IndexScale::One,
+                },
+            ),
+        ] {
+            assert_eq!(
+                AssemblyInterpreter::new()
+                    .parse_to_operand(operand, &DUMMY_INSTRUCTION)
+                    .unwrap()
+                    .mode,
+                mode,
+                "{}",
+                operand
+            );
+        }
+        assert!(AssemblyInterpreter::new()
+            .parse_to_operand("%d8", &DUMMY_INSTRUCTION)
+            .is_err());
+    }
+
     #[test]
     fn parse_to_operand_register_indirect_displacement() {
         for (operand, displacement, register) in [
@@ -587,8 +2544,10 @@ mod tests {
             ("(952, sp)", 952, AddressRegister::A7),
         ] {
             assert_eq!(
-                AssemblyInterpreter::parse_to_operand(operand, &DUMMY_INSTRUCTION).unwrap(),
-                (
+                AssemblyInterpreter::new()
+                    .parse_to_operand(operand, &DUMMY_INSTRUCTION)
+                    .unwrap(),
+                ParsedOperand::new(
                     AddressMode::RegisterIndirectWithDisplacement {
                         register,
                         displacement
@@ -601,43 +2560,59 @@ mod tests {
 
     #[test]
     fn parse_to_operand_register_indirect_indexed() {
-        for (operand, displacement, address_register, index_register, size) in [
+        for (operand, displacement, address_register, index_register, index_size, index_scale) in [
             (
-                "(1, a1, d3.b)",
+                "(1, a1, d3.w*2)",
                 1,
                 AddressRegister::A1,
                 Data(DataRegister::D3),
-                Byte,
+                Word,
+                IndexScale::Two,
             ),
             (
-                "(8, a7, a4.w)",
+                "(8, a7, a4)",
                 8,
                 AddressRegister::A7,
                 Address(AddressRegister::A4),
                 Word,
+                IndexScale::One,
             ),
             (
-                "(952, sp, d5.l)",
+                "(952, sp, d5.l * 8)",
                 952,
                 AddressRegister::A7,
                 Data(DataRegister::D5),
                 Long,
+                IndexScale::Eight,
             ),
         ] {
             assert_eq!(
-                AssemblyInterpreter::parse_to_operand(operand, &DUMMY_INSTRUCTION).unwrap(),
-                (
+                AssemblyInterpreter::new()
+                    .parse_to_operand(operand, &DUMMY_INSTRUCTION)
+                    .unwrap(),
+                ParsedOperand::new(
                     AddressMode::RegisterIndirectIndexed {
                         address_register,
                         index_register,
+                        index_size,
+                        index_scale,
                         displacement,
                     },
-                    Some(size)
+                    None
                 )
             );
         }
     }
 
+    #[test]
+    fn parse_to_operand_invalid_index() {
+        for operand in ["(1, a1, d3.b)", "(1, a1, d3.w*3)", "([1,a1,d3*16], 2)"] {
+            assert!(AssemblyInterpreter::new()
+                .parse_to_operand(operand, &DUMMY_INSTRUCTION)
+                .is_err());
+        }
+    }
+
     #[test]
     fn parse_to_operand_memory_postindexed() {
         for (
@@ -646,15 +2621,17 @@ mod tests {
             outer_displacement,
             address_register,
             index_register,
-            size,
+            index_size,
+            index_scale,
         ) in [
             (
-                "([1,a1], d3.b, 2)", // TODO: does this need + signs
+                "([1,a1], d3.w*2, 2)", // TODO: does this need + signs
                 1,
                 2,
                 AddressRegister::A1,
                 Data(DataRegister::D3),
-                Byte,
+                Word,
+                IndexScale::Two,
             ),
             (
                 "([8,a7], a4.w, 952)",
@@ -663,26 +2640,32 @@ mod tests {
                 AddressRegister::A7,
                 Address(AddressRegister::A4),
                 Word,
+                IndexScale::One,
             ),
             (
-                "([952,sp], d5.l, 1)",
+                "([952,sp], d5.l*4, 1)",
                 952,
                 1,
                 AddressRegister::A7,
                 Data(DataRegister::D5),
                 Long,
+                IndexScale::Four,
             ),
         ] {
             assert_eq!(
-                AssemblyInterpreter::parse_to_operand(operand, &DUMMY_INSTRUCTION).unwrap(),
-                (
+                AssemblyInterpreter::new()
+                    .parse_to_operand(operand, &DUMMY_INSTRUCTION)
+                    .unwrap(),
+                ParsedOperand::new(
                     AddressMode::MemoryPostIndexed {
                         address_register,
                         index_register,
+                        index_size,
+                        index_scale,
                         base_displacement,
                         outer_displacement,
                     },
-                    Some(size)
+                    None
                 )
             );
         }
@@ -696,15 +2679,17 @@ mod tests {
             outer_displacement,
             address_register,
             index_register,
-            size,
+            index_size,
+            index_scale,
         ) in [
             (
-                "([1,a1,d3.b], 2)",
+                "([1,a1,d3.w*2], 2)",
                 1,
                 2,
                 AddressRegister::A1,
                 Data(DataRegister::D3),
-                Byte,
+                Word,
+                IndexScale::Two,
             ),
             (
                 "([8,a7, a4.w],952)",
@@ -713,26 +2698,32 @@ mod tests {
                 AddressRegister::A7,
                 Address(AddressRegister::A4),
                 Word,
+                IndexScale::One,
             ),
             (
-                "([952, sp, d5.l], 1)",
+                "([952, sp, d5.l*4], 1)",
                 952,
                 1,
                 AddressRegister::A7,
                 Data(DataRegister::D5),
                 Long,
+                IndexScale::Four,
             ),
         ] {
             assert_eq!(
-                AssemblyInterpreter::parse_to_operand(operand, &DUMMY_INSTRUCTION).unwrap(),
-                (
+                AssemblyInterpreter::new()
+                    .parse_to_operand(operand, &DUMMY_INSTRUCTION)
+                    .unwrap(),
+                ParsedOperand::new(
                     AddressMode::MemoryPreIndexed {
                         address_register,
                         index_register,
+                        index_size,
+                        index_scale,
                         base_displacement,
                         outer_displacement,
                     },
-                    Some(size)
+                    None
                 )
             );
         }
@@ -742,8 +2733,10 @@ mod tests {
     fn parse_to_operand_pc_indirect_with_displacement() {
         for (operand, displacement) in [("(1, pc)", 1), ("(8, pc)", 8), ("(952, pc)", 952)] {
             assert_eq!(
-                AssemblyInterpreter::parse_to_operand(operand, &DUMMY_INSTRUCTION).unwrap(),
-                (
+                AssemblyInterpreter::new()
+                    .parse_to_operand(operand, &DUMMY_INSTRUCTION)
+                    .unwrap(),
+                ParsedOperand::new(
                     AddressMode::ProgramCounterIndirectWithDisplacement { displacement },
                     None
                 )
@@ -753,19 +2746,41 @@ mod tests {
 
     #[test]
     fn parse_to_operand_pc_indirect_indexed() {
-        for (operand, displacement, index_register, size) in [
-            ("(1, pc, d3.b)", 1, Data(DataRegister::D3), Byte),
-            ("(8, pc, a4.w)", 8, Address(AddressRegister::A4), Word),
-            ("(952, pc, d5.l)", 952, Data(DataRegister::D5), Long),
+        for (operand, displacement, index_register, index_size, index_scale) in [
+            (
+                "(1, pc, d3.w*2)",
+                1,
+                Data(DataRegister::D3),
+                Word,
+                IndexScale::Two,
+            ),
+            (
+                "(8, pc, a4.w)",
+                8,
+                Address(AddressRegister::A4),
+                Word,
+                IndexScale::One,
+            ),
+            (
+                "(952, pc, d5.l)",
+                952,
+                Data(DataRegister::D5),
+                Long,
+                IndexScale::One,
+            ),
         ] {
             assert_eq!(
-                AssemblyInterpreter::parse_to_operand(operand, &DUMMY_INSTRUCTION).unwrap(),
-                (
+                AssemblyInterpreter::new()
+                    .parse_to_operand(operand, &DUMMY_INSTRUCTION)
+                    .unwrap(),
+                ParsedOperand::new(
                     AddressMode::ProgramCounterIndirectIndexed {
                         index_register,
+                        index_size,
+                        index_scale,
                         displacement,
                     },
-                    Some(size)
+                    None
                 )
             );
         }
@@ -773,26 +2788,52 @@ mod tests {
 
     #[test]
     fn parse_to_operand_pc_indirect_postindexed() {
-        for (operand, base_displacement, outer_displacement, index_register, size) in [
-            ("([1,pc], d3.b, 2)", 1, 2, Data(DataRegister::D3), Byte),
+        for (
+            operand,
+            base_displacement,
+            outer_displacement,
+            index_register,
+            index_size,
+            index_scale,
+        ) in [
+            (
+                "([1,pc], d3.w*2, 2)",
+                1,
+                2,
+                Data(DataRegister::D3),
+                Word,
+                IndexScale::Two,
+            ),
             (
                 "([8, pc], a4.w, 952)",
                 8,
                 952,
                 Address(AddressRegister::A4),
                 Word,
+                IndexScale::One,
+            ),
+            (
+                "([952,pc], d5.l,1)",
+                952,
+                1,
+                Data(DataRegister::D5),
+                Long,
+                IndexScale::One,
             ),
-            ("([952,pc], d5.l,1)", 952, 1, Data(DataRegister::D5), Long),
         ] {
             assert_eq!(
-                AssemblyInterpreter::parse_to_operand(operand, &DUMMY_INSTRUCTION).unwrap(),
-                (
+                AssemblyInterpreter::new()
+                    .parse_to_operand(operand, &DUMMY_INSTRUCTION)
+                    .unwrap(),
+                ParsedOperand::new(
                     AddressMode::ProgramCounterMemoryIndirectPostIndexed {
                         index_register,
+                        index_size,
+                        index_scale,
                         base_displacement,
                         outer_displacement,
                     },
-                    Some(size)
+                    None
                 )
             );
         }
@@ -800,26 +2841,52 @@ mod tests {
 
     #[test]
     fn parse_to_operand_pc_preindexed() {
-        for (operand, base_displacement, outer_displacement, index_register, size) in [
-            ("([1,pc,d3.b], 2)", 1, 2, Data(DataRegister::D3), Byte),
+        for (
+            operand,
+            base_displacement,
+            outer_displacement,
+            index_register,
+            index_size,
+            index_scale,
+        ) in [
+            (
+                "([1,pc,d3.w*2], 2)",
+                1,
+                2,
+                Data(DataRegister::D3),
+                Word,
+                IndexScale::Two,
+            ),
             (
                 "([8,pc, a4.w],952)",
                 8,
                 952,
                 Address(AddressRegister::A4),
                 Word,
+                IndexScale::One,
+            ),
+            (
+                "([952, pc, d5.l], 1)",
+                952,
+                1,
+                Data(DataRegister::D5),
+                Long,
+                IndexScale::One,
             ),
-            ("([952, pc, d5.l], 1)", 952, 1, Data(DataRegister::D5), Long),
         ] {
             assert_eq!(
-                AssemblyInterpreter::parse_to_operand(operand, &DUMMY_INSTRUCTION).unwrap(),
-                (
+                AssemblyInterpreter::new()
+                    .parse_to_operand(operand, &DUMMY_INSTRUCTION)
+                    .unwrap(),
+                ParsedOperand::new(
                     AddressMode::ProgramCounterMemoryIndirectPreIndexed {
                         index_register,
+                        index_size,
+                        index_scale,
                         base_displacement,
                         outer_displacement,
                     },
-                    Some(size)
+                    None
                 )
             );
         }
@@ -829,19 +2896,268 @@ mod tests {
     fn parse_to_operand_absolute() {
         for (operand, address, size) in [("($400).w", 0x400, Word), ("($b4a).l", 0xB4A, Long)] {
             assert_eq!(
-                AssemblyInterpreter::parse_to_operand(operand, &DUMMY_INSTRUCTION).unwrap(),
-                (AddressMode::Absolute { address }, Some(size))
+                AssemblyInterpreter::new()
+                    .parse_to_operand(operand, &DUMMY_INSTRUCTION)
+                    .unwrap(),
+                ParsedOperand::new(AddressMode::Absolute { address }, Some(size))
             );
         }
     }
 
+    #[test]
+    fn assemble_pc_relative_labels() {
+        let mut interpreter = AssemblyInterpreter::new();
+        let program = interpreter
+            .assemble(
+                "start:  move d1, d2         ; 2 bytes
+                         move (msg, pc), d0  ; 4 bytes
+                         move (start, pc), d3
+                         lea (msg, pc), a0
+                 msg:    move d0, d1",
+                0x1000,
+            )
+            .unwrap();
+
+        assert_eq!(
+            program.iter().map(|i| i.address).collect::<Vec<_>>(),
+            vec![0x1000, 0x1002, 0x1006, 0x100A, 0x100E]
+        );
+        // displacements are relative to the extension word, which comes right after the opcode
+        assert_eq!(
+            program[1].instruction,
+            Instruction::Move {
+                src: AddressMode::ProgramCounterIndirectWithDisplacement {
+                    displacement: 0x100E - 0x1004
+                },
+                dest: AddressMode::RegisterDirect {
+                    register: Data(DataRegister::D0)
+                },
+            }
+        );
+        assert_eq!(
+            program[2].instruction,
+            Instruction::Move {
+                src: AddressMode::ProgramCounterIndirectWithDisplacement {
+                    displacement: (-8i16) as u16
+                },
+                dest: AddressMode::RegisterDirect {
+                    register: Data(DataRegister::D3)
+                },
+            }
+        );
+        assert_eq!(
+            program[3].instruction,
+            Instruction::LoadEffectiveAddress {
+                src: AddressMode::ProgramCounterIndirectWithDisplacement {
+                    displacement: 0x100E - 0x100C
+                },
+                dest: AddressMode::RegisterDirect {
+                    register: Address(AddressRegister::A0)
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn assemble_branches() {
+        let mut interpreter = AssemblyInterpreter::new();
+        let program = interpreter
+            .assemble(
+                "        moveq #3, d0    ; 2 bytes
+                 loop:   sub #1, d0      ; 6 bytes
+                         bne.s loop      ; 2 bytes
+                         bhs end         ; 4 bytes
+                         bra.b loop
+                 end:    nop",
+                0x1000,
+            )
+            .unwrap();
+
+        assert_eq!(
+            program.iter().map(|i| i.address).collect::<Vec<_>>(),
+            vec![0x1000, 0x1002, 0x1008, 0x100A, 0x100E, 0x1010]
+        );
+        let branches = program[2..5]
+            .iter()
+            .map(|i| (&i.instruction, i.size))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            branches,
+            vec![
+                (
+                    &Instruction::BranchConditional {
+                        condition: Condition::NotEqual,
+                        displacement: 0x1002 - 0x100A,
+                    },
+                    Byte
+                ),
+                (
+                    &Instruction::BranchConditional {
+                        condition: Condition::CarryClear,
+                        displacement: 0x1010 - 0x100C,
+                    },
+                    Word
+                ),
+                (
+                    &Instruction::Branch {
+                        displacement: 0x1002 - 0x1010,
+                    },
+                    Byte
+                ),
+            ]
+        );
+
+        // A byte displacement can't be 0, since that means a word displacement follows
+        assert!(matches!(
+            interpreter.assemble("beq.s next\nnext: nop", 0),
+            Err(ParseError::AtLine { line: 1, .. })
+        ));
+        assert!(matches!(
+            AssemblyInterpreter::new().parse("beq.l $100".to_string()),
+            Err(ParseError::InvalidOperandSize(4))
+        ));
+        assert_eq!(
+            AssemblyInterpreter::new()
+                .parse("bsr.s $20".to_string())
+                .unwrap(),
+            (
+                Instruction::BranchToSubroutine { displacement: 0x1E },
+                Byte,
+                0
+            )
+        );
+        // bf would never branch; its encoding is BSR's
+        assert!(AssemblyInterpreter::new()
+            .parse("bf $100".to_string())
+            .is_err());
+    }
+
+    #[test]
+    fn assemble_branch_to_absolute_address() {
+        let mut interpreter = AssemblyInterpreter::new();
+        let program = interpreter
+            .assemble(
+                "start: bra $1040     ; forward, fits in a byte
+                        bne $1000     ; backward, fits in a byte
+                        bsr $1200     ; forward, needs a word
+                        bra start     ; earlier label, fits in a byte
+                        beq $100C     ; displacement of 0, so needs a word
+                        bra $800      ; backward, needs a word
+                        bra end       ; later label, always a word
+                 end:   nop",
+                0x1000,
+            )
+            .unwrap();
+
+        let branches = program[..7]
+            .iter()
+            .map(|i| (i.address, &i.instruction, i.size))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            branches,
+            vec![
+                (0x1000, &Instruction::Branch { displacement: 0x3E }, Byte),
+                (
+                    0x1002,
+                    &Instruction::BranchConditional {
+                        condition: Condition::NotEqual,
+                        displacement: -4,
+                    },
+                    Byte
+                ),
+                (
+                    0x1004,
+                    &Instruction::BranchToSubroutine {
+                        displacement: 0x1FA
+                    },
+                    Word
+                ),
+                (0x1008, &Instruction::Branch { displacement: -10 }, Byte),
+                (
+                    0x100A,
+                    &Instruction::BranchConditional {
+                        condition: Condition::Equal,
+                        displacement: 0,
+                    },
+                    Word
+                ),
+                (
+                    0x100E,
+                    &Instruction::Branch {
+                        displacement: -0x810
+                    },
+                    Word
+                ),
+                (0x1012, &Instruction::Branch { displacement: 2 }, Word),
+            ]
+        );
+
+        // A target too far away for a word displacement is an error
+        assert!(matches!(
+            AssemblyInterpreter::new().parse("bra $10000".to_string()),
+            Err(ParseError::DisplacementOutOfRange {
+                displacement: 0xFFFE,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn assemble_pc_relative_label_out_of_range() {
+        let source = format!(
+            "move (msg, pc), d0\n{}msg: move d0, d1",
+            "move d0, d1\n".repeat(20_000)
+        );
+        match AssemblyInterpreter::new().assemble(&source, 0) {
+            Err(ParseError::AtLine { line: 1, source }) => {
+                assert!(matches!(*source, ParseError::DisplacementOutOfRange { .. }))
+            }
+            other => panic!("expected an out-of-range displacement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn assemble_unknown_label() {
+        match AssemblyInterpreter::new().assemble("move (nowhere, pc), d0", 0) {
+            Err(ParseError::AtLine { line: 1, source }) => {
+                assert!(matches!(*source, ParseError::UnknownSymbol(_)))
+            }
+            other => panic!("expected an unknown symbol, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn assemble_reports_error_line() {
+        let source = "start: move d0, d1
+                      ; a comment takes up a line too
+                      move d1, dq
+                      jmp start";
+        match AssemblyInterpreter::new().assemble(source, 0) {
+            Err(ParseError::AtLine { line, source }) => {
+                assert_eq!(line, 3);
+                assert!(matches!(*source, ParseError::UnknownRegister(_)));
+            }
+            other => panic!("expected an error on line 3, got {:?}", other),
+        }
+
+        match AssemblyInterpreter::new().assemble("a: nop\nb: nop\na: nop", 0) {
+            Err(ParseError::AtLine { line, source }) => {
+                assert_eq!(line, 3);
+                assert!(matches!(*source, ParseError::DuplicateLabel(_)));
+            }
+            other => panic!("expected a duplicate label on line 3, got {:?}", other),
+        }
+    }
+
     #[test]
     fn parse_to_operand_immediate() {
         // TODO: should this be a byte, word, or long?
         for (operand, value) in [("#$400", 0x400)] {
             assert_eq!(
-                AssemblyInterpreter::parse_to_operand(operand, &DUMMY_INSTRUCTION).unwrap(),
-                (AddressMode::Immediate { value }, None)
+                AssemblyInterpreter::new()
+                    .parse_to_operand(operand, &DUMMY_INSTRUCTION)
+                    .unwrap(),
+                ParsedOperand::new(AddressMode::Immediate { value }, None)
             );
         }
     }
@@ -2,98 +2,614 @@
 
 use super::{ParseError, Parser};
 use crate::{
-    cpu::{addressing::AddressMode, isa_68000::Instruction},
+    cpu::{
+        addressing::AddressMode,
+        isa_68000::{Condition, Instruction},
+        registers::Register,
+    },
     EmulationError, OperandSize,
 };
-use colored::Colorize;
 
-use m68kdecode::Operation;
-#[derive(Default)]
-pub struct MachineCodeParser;
+use m68kdecode::{Indexer, InstructionExtra, MemoryIndirection, Operand, Operation};
+
+/// Unwraps an operand that an instruction needs, but that m68kdecode might not have given
+fn require_operand(
+    operand: Option<AddressMode>,
+    name: &str,
+    mnemonic: &str,
+) -> Result<AddressMode, ParseError> {
+    operand.ok_or_else(|| ParseError::MissingOperand(format!("{} (no {} operand)", mnemonic, name)))
+}
+
+/// Gets the operand of an instruction that only has one.
+///
+/// m68kdecode can put a lone operand in either position, so this takes whichever one is there.
+fn only_operand(
+    src: Option<AddressMode>,
+    dest: Option<AddressMode>,
+    mnemonic: &str,
+) -> Result<AddressMode, ParseError> {
+    match (src, dest) {
+        (Some(operand), None) | (None, Some(operand)) => Ok(operand),
+        (None, None) => Err(ParseError::MissingOperand(format!(
+            "{} (no operand)",
+            mnemonic
+        ))),
+        (Some(_), Some(operand)) => Err(ParseError::InvalidOperand {
+            operand: format!("{:?}", operand),
+            instruction: mnemonic.to_string(),
+        }),
+    }
+}
+
+/// Gets the count and target of a shift or rotate.
+///
+/// The memory forms always move by one bit, and m68kdecode only gives them the memory operand.
+fn shift_operands(
+    src: Option<AddressMode>,
+    dest: Option<AddressMode>,
+    mnemonic: &str,
+) -> Result<(AddressMode, AddressMode), ParseError> {
+    match (src, dest) {
+        (Some(count), Some(target)) => Ok((count, target)),
+        (Some(target), None) | (None, Some(target)) => {
+            Ok((AddressMode::Immediate { value: 1 }, target))
+        }
+        (None, None) => Err(ParseError::MissingOperand(format!(
+            "{} (no operand)",
+            mnemonic
+        ))),
+    }
+}
+
+/// Gets the number of bytes of extension words between the opcode of `instruction` and those of its operands,
+/// like the register mask of MOVEM or the second word of a 68020 long multiply
+fn instruction_extension_size(instruction: &m68kdecode::Instruction) -> usize {
+    match instruction.operation {
+        Operation::MOVEM
+        | Operation::MOVES
+        | Operation::CHK2
+        | Operation::CMP2
+        | Operation::DIVSL
+        | Operation::DIVUL => 2,
+        Operation::MULU | Operation::MULS | Operation::DIVU | Operation::DIVS
+            if instruction.size == 4 =>
+        {
+            2
+        }
+        _ if matches!(instruction.extra, InstructionExtra::Bitfield(..)) => 2,
+        _ => 0,
+    }
+}
+
+/// Gets the number of bytes of extension words that `operand` takes up, if they start at `offset` in `code`
+fn operand_extension_size(operand: &Operand, code: &[u8], offset: usize) -> usize {
+    let (indexer, indirection) = match operand {
+        Operand::IMM8(_) | Operand::IMM16(_) | Operand::ABS16(_) => return 2,
+        Operand::IMM32(_) | Operand::ABS32(_) => return 4,
+        Operand::ARDISP(_, displacement)
+        | Operand::PCDISP(_, displacement)
+        | Operand::DISP(displacement) => (&displacement.indexer, &displacement.indirection),
+        _ => return 0,
+    };
+    if matches!(indexer, Indexer::NoIndexer)
+        && matches!(indirection, MemoryIndirection::NoIndirection)
+    {
+        return 2;
+    }
+    let word = extension_word(code, offset);
+    // A brief extension word has bit 8 clear; a full one is followed by displacements whose sizes it gives
+    if word & 0x0100 == 0 {
+        return 2;
+    }
+    let displacement_size = |bits: u16| [0, 0, 2, 4][bits as usize & 3];
+    2 + displacement_size(word >> 4) + displacement_size(word)
+}
+
+/// Reads the big-endian word at `offset` in `code`, or 0 if it's past the end
+fn extension_word(code: &[u8], offset: usize) -> u16 {
+    match code.get(offset..offset + 2) {
+        Some(&[high, low]) => u16::from_be_bytes([high, low]),
+        _ => 0,
+    }
+}
+
+/// Gets the size of each operand's index register from the W/L bit (bit 11) of its extension word,
+/// since m68kdecode doesn't give it.
+///
+/// Operands without an index register get [`OperandSize::Long`], which goes unused.
+fn index_sizes(instruction: &m68kdecode::Instruction, code: &[u8]) -> [OperandSize; 2] {
+    let mut offset = 2 + instruction_extension_size(instruction);
+    let mut sizes = [OperandSize::Long; 2];
+    for (operand, size) in instruction.operands.iter().zip(sizes.iter_mut()) {
+        let indexed = match operand {
+            Operand::ARDISP(_, displacement)
+            | Operand::PCDISP(_, displacement)
+            | Operand::DISP(displacement) => !matches!(displacement.indexer, Indexer::NoIndexer),
+            _ => false,
+        };
+        if indexed && extension_word(code, offset) & 0x0800 == 0 {
+            *size = OperandSize::Word;
+        }
+        offset += operand_extension_size(operand, code, offset);
+    }
+    sizes
+}
+
+/// Gets the displacement of a branch, which m68kdecode gives as a PC-relative operand
+fn branch_displacement(operand: Option<AddressMode>, mnemonic: &str) -> Result<i32, ParseError> {
+    match require_operand(operand, "displacement", mnemonic)? {
+        AddressMode::ProgramCounterIndirectWithDisplacement { displacement } => {
+            Ok(displacement as i16 as i32)
+        }
+        operand => Err(ParseError::InvalidOperand {
+            operand: format!("{:?}", operand),
+            instruction: mnemonic.to_string(),
+        }),
+    }
+}
+
+/// Gets the condition a branch (Bcc or DBcc) tests
+fn branch_condition(operation: Operation) -> Condition {
+    match operation {
+        Operation::DBT => Condition::True,
+        Operation::DBF => Condition::False,
+        Operation::BHI | Operation::DBHI => Condition::Higher,
+        Operation::BLS | Operation::DBLS => Condition::LowerOrSame,
+        Operation::BCC | Operation::DBCC => Condition::CarryClear,
+        Operation::BCS | Operation::DBCS => Condition::CarrySet,
+        Operation::BNE | Operation::DBNE => Condition::NotEqual,
+        Operation::BEQ | Operation::DBEQ => Condition::Equal,
+        Operation::BVC | Operation::DBVC => Condition::OverflowClear,
+        Operation::BVS | Operation::DBVS => Condition::OverflowSet,
+        Operation::BPL | Operation::DBPL => Condition::Plus,
+        Operation::BMI | Operation::DBMI => Condition::Minus,
+        Operation::BGE | Operation::DBGE => Condition::GreaterOrEqual,
+        Operation::BLT | Operation::DBLT => Condition::LessThan,
+        Operation::BGT | Operation::DBGT => Condition::GreaterThan,
+        Operation::BLE | Operation::DBLE => Condition::LessOrEqual,
+        _ => unreachable!("{:?} isn't a branch", operation),
+    }
+}
+
+#[derive(Clone)]
+pub struct MachineCodeParser {
+    /// The size used for instructions that m68kdecode doesn't give a size for, and whose operands don't imply one
+    pub default_size: OperandSize,
+}
+
+impl Default for MachineCodeParser {
+    fn default() -> Self {
+        Self {
+            default_size: OperandSize::Long,
+        }
+    }
+}
+
+impl MachineCodeParser {
+    /// Gets the length in bytes of the instruction at the start of `code`, for when that's all that's needed
+    /// (e.g. to step over an instruction or place a breakpoint after it).
+    ///
+    /// m68kdecode can't measure an instruction without decoding it, but this skips converting the
+    /// decoded instruction into an [`Instruction`], so it also works for instructions the emulator doesn't support yet.
+    pub fn instruction_length(&self, code: &[u8]) -> Result<u32, ParseError> {
+        Ok(m68kdecode::decode_instruction(code)?.bytes_used)
+    }
+}
 
 impl Parser<Vec<u8>> for MachineCodeParser {
     fn parse(&mut self, source: Vec<u8>) -> Result<(Instruction, OperandSize, u32), ParseError> {
         let decoded = m68kdecode::decode_instruction(source.as_slice())?;
+        let mnemonic = format!("{:?}", decoded.instruction.operation);
         let (src, dest, size_override) = AddressMode::from_m68kdecode(
             decoded.instruction.operands[0].clone(),
             decoded.instruction.operands[1].clone(),
+            index_sizes(&decoded.instruction, &source),
         )
-        .unwrap();
+        .map_err(|_| ParseError::InvalidOperand {
+            operand: format!("{:?}", decoded.instruction.operands),
+            instruction: mnemonic.clone(),
+        })?;
 
         let size = if decoded.instruction.size == 0 {
-            size_override.unwrap_or_else(|| {
-                eprintln!(
-                    "Warning: no size override and instruction size is 0. Defaulting to Long."
-                );
-                OperandSize::Long
-            })
+            match size_override {
+                Some(size) => size,
+                None => {
+                    #[cfg(feature = "debug-trace")]
+                    eprintln!(
+                        "Warning: no size override and instruction size is 0. Defaulting to {:?}.",
+                        self.default_size
+                    );
+                    self.default_size
+                }
+            }
         } else {
             match OperandSize::from_size_in_bytes(decoded.instruction.size) {
                 Ok(s) => s,
-                Err(e) => match e {
-                    EmulationError::InvalidOperandSize(s) => {
-                        return Err(ParseError::InvalidOperandSize(s))
-                    }
-                    _ => panic!("Unexpected error while parsing operand size: {:?}", e),
-                },
+                Err(EmulationError::InvalidOperandSize(s)) => {
+                    return Err(ParseError::InvalidOperandSize(s))
+                }
+                Err(_) => return Err(ParseError::InvalidOperandSize(decoded.instruction.size)),
             }
         };
 
         let parsed = match decoded.instruction.operation {
             Operation::ADD | Operation::ADDI | Operation::ADDA | Operation::ADDQ => {
                 Instruction::Add {
-                    src: src.unwrap(),
-                    dest: dest.unwrap(),
+                    src: require_operand(src, "source", &mnemonic)?,
+                    dest: require_operand(dest, "destination", &mnemonic)?,
                 }
             }
             Operation::SUB | Operation::SUBI | Operation::SUBA => Instruction::Subtract {
-                src: src.unwrap(),
-                dest: dest.unwrap(),
+                src: require_operand(src, "source", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
+            },
+            Operation::ADDX => Instruction::AddWithExtend {
+                src: require_operand(src, "source", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
+            },
+            Operation::SUBX => Instruction::SubtractWithExtend {
+                src: require_operand(src, "source", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
+            },
+            Operation::ABCD => Instruction::AddBCD {
+                src: require_operand(src, "source", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
+            },
+            Operation::SBCD => Instruction::SubtractBCD {
+                src: require_operand(src, "source", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
+            },
+            Operation::NBCD => Instruction::NegateBCD {
+                dest: only_operand(src, dest, &mnemonic)?,
+            },
+            Operation::BTST => Instruction::TestBit {
+                bit_number: require_operand(src, "bit number", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
+            },
+            Operation::BSET => Instruction::SetBit {
+                bit_number: require_operand(src, "bit number", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
+            },
+            Operation::BCLR => Instruction::ClearBit {
+                bit_number: require_operand(src, "bit number", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
+            },
+            Operation::BCHG => Instruction::ChangeBit {
+                bit_number: require_operand(src, "bit number", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
             },
             Operation::MULU => Instruction::MultiplyUnsigned {
-                src: src.unwrap(),
-                dest: dest.unwrap(),
+                src: require_operand(src, "source", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
+            },
+            Operation::DIVU => Instruction::DivideUnsigned {
+                src: require_operand(src, "source", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
+            },
+            Operation::DIVS => Instruction::DivideSigned {
+                src: require_operand(src, "source", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
             },
             // TODO: should movea alter the address mode to be indirect?
             // TODO: support reading from multiple registers to a pre/postdecrement register
             // Necessary for things like `movem %a5/%a6, (%sp)-` which is used in Macintosh ROM calling conventions
             Operation::MOVE | Operation::MOVEA | Operation::MOVEM => Instruction::Move {
-                src: src.unwrap(),
-                dest: dest.unwrap(),
+                src: require_operand(src, "source", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
+            },
+            Operation::MOVEP => Instruction::MovePeripheral {
+                src: require_operand(src, "source", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
+            },
+            Operation::MOVEQ => Instruction::MoveQuick {
+                src: require_operand(src, "source", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
             },
             Operation::EOR | Operation::EORI => Instruction::ExclusiveOr {
-                src: src.unwrap(),
-                dest: dest.unwrap(),
+                src: require_operand(src, "source", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
             },
             Operation::OR | Operation::ORI => Instruction::InclusiveOr {
-                src: src.unwrap(),
-                dest: dest.unwrap(),
+                src: require_operand(src, "source", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
             },
             Operation::AND | Operation::ANDI => Instruction::And {
-                src: src.unwrap(),
-                dest: dest.unwrap(),
+                src: require_operand(src, "source", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
             },
-            // TODO: figure out what ROL means and how it is different from ROXL
-            Operation::ROXL | Operation::ROL => Instruction::RotateLeft {
-                to_rotate: dest.unwrap(),
-                rotate_amount: src.unwrap(),
+            Operation::ROL => {
+                let (count, target) = shift_operands(src, dest, &mnemonic)?;
+                Instruction::RotateLeft {
+                    to_rotate: target,
+                    rotate_amount: count,
+                }
+            }
+            Operation::ROR => {
+                let (count, target) = shift_operands(src, dest, &mnemonic)?;
+                Instruction::RotateRight {
+                    to_rotate: target,
+                    rotate_amount: count,
+                }
+            }
+            Operation::ROXL => {
+                let (count, target) = shift_operands(src, dest, &mnemonic)?;
+                Instruction::RotateLeftExtended {
+                    to_rotate: target,
+                    rotate_amount: count,
+                }
+            }
+            Operation::ROXR => {
+                let (count, target) = shift_operands(src, dest, &mnemonic)?;
+                Instruction::RotateRightExtended {
+                    to_rotate: target,
+                    rotate_amount: count,
+                }
+            }
+            Operation::LSL => {
+                let (count, target) = shift_operands(src, dest, &mnemonic)?;
+                Instruction::LogicalShiftLeft {
+                    to_shift: target,
+                    shift_amount: count,
+                }
+            }
+            Operation::MOVES => Instruction::MoveAlternateSpace {
+                src: require_operand(src, "source", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
             },
+            Operation::ASL => {
+                let (count, dest) = shift_operands(src, dest, &mnemonic)?;
+                Instruction::ArithmeticShiftLeft { count, dest }
+            }
+            Operation::ASR => {
+                let (count, dest) = shift_operands(src, dest, &mnemonic)?;
+                Instruction::ArithmeticShiftRight { count, dest }
+            }
             Operation::JMP => Instruction::JumpTo {
-                address: src.unwrap(),
+                address: only_operand(src, dest, &mnemonic)?,
+            },
+            Operation::JSR => Instruction::JumpToSubroutine {
+                address: only_operand(src, dest, &mnemonic)?,
+            },
+            Operation::BRA => Instruction::Branch {
+                displacement: branch_displacement(src, &mnemonic)?,
+            },
+            Operation::BSR => Instruction::BranchToSubroutine {
+                displacement: branch_displacement(src, &mnemonic)?,
+            },
+            Operation::BHI
+            | Operation::BLS
+            | Operation::BCC
+            | Operation::BCS
+            | Operation::BNE
+            | Operation::BEQ
+            | Operation::BVC
+            | Operation::BVS
+            | Operation::BPL
+            | Operation::BMI
+            | Operation::BGE
+            | Operation::BLT
+            | Operation::BGT
+            | Operation::BLE => Instruction::BranchConditional {
+                condition: branch_condition(decoded.instruction.operation),
+                displacement: branch_displacement(src, &mnemonic)?,
+            },
+            Operation::DBT
+            | Operation::DBF
+            | Operation::DBHI
+            | Operation::DBLS
+            | Operation::DBCC
+            | Operation::DBCS
+            | Operation::DBNE
+            | Operation::DBEQ
+            | Operation::DBVC
+            | Operation::DBVS
+            | Operation::DBPL
+            | Operation::DBMI
+            | Operation::DBGE
+            | Operation::DBLT
+            | Operation::DBGT
+            | Operation::DBLE => Instruction::DecrementBranch {
+                condition: branch_condition(decoded.instruction.operation),
+                register: match require_operand(src, "counter", &mnemonic)? {
+                    AddressMode::RegisterDirect {
+                        register: Register::Data(register),
+                    } => register,
+                    operand => {
+                        return Err(ParseError::InvalidOperand {
+                            operand: format!("{:?}", operand),
+                            instruction: mnemonic,
+                        })
+                    }
+                },
+                displacement: branch_displacement(dest, &mnemonic)?,
+            },
+            // The vector is the low 4 bits of the opcode, which m68kdecode gives as an immediate
+            Operation::TRAP => match require_operand(src, "vector", &mnemonic)? {
+                AddressMode::Immediate { value } if value < 16 => Instruction::Trap {
+                    vector: value as u8,
+                },
+                operand => {
+                    return Err(ParseError::InvalidOperand {
+                        operand: format!("{:?}", operand),
+                        instruction: mnemonic,
+                    })
+                }
+            },
+            Operation::LEA => Instruction::LoadEffectiveAddress {
+                src: require_operand(src, "source", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
+            },
+            Operation::PEA => Instruction::PushEffectiveAddress {
+                src: only_operand(src, dest, &mnemonic)?,
+            },
+            Operation::NEG => Instruction::Negate {
+                dest: only_operand(src, dest, &mnemonic)?,
+            },
+            Operation::NEGX => Instruction::NegateWithExtend {
+                dest: only_operand(src, dest, &mnemonic)?,
+            },
+            Operation::CLR => Instruction::Clear {
+                dest: only_operand(src, dest, &mnemonic)?,
+            },
+            Operation::NOT => Instruction::Not {
+                dest: only_operand(src, dest, &mnemonic)?,
+            },
+            Operation::TST => Instruction::Test {
+                src: only_operand(src, dest, &mnemonic)?,
             },
             Operation::CHK => Instruction::BoundsCheck {
-                value: dest.unwrap(),
-                bound: src.unwrap(),
+                value: require_operand(dest, "destination", &mnemonic)?,
+                bound: require_operand(src, "source", &mnemonic)?,
+            },
+            Operation::BFEXTU | Operation::BFEXTS | Operation::BFINS => {
+                let (offset, width) = match decoded.instruction.extra {
+                    InstructionExtra::Bitfield(offset, width) => (offset.into(), width.into()),
+                    _ => {
+                        return Err(ParseError::InvalidOperand {
+                            operand: format!("{:?}", decoded.instruction.extra),
+                            instruction: format!("{:?}", decoded.instruction),
+                        })
+                    }
+                };
+                match decoded.instruction.operation {
+                    Operation::BFEXTU => Instruction::BitFieldExtractUnsigned {
+                        base: require_operand(src, "source", &mnemonic)?,
+                        offset,
+                        width,
+                        dest: require_operand(dest, "destination", &mnemonic)?,
+                    },
+                    Operation::BFEXTS => Instruction::BitFieldExtractSigned {
+                        base: require_operand(src, "source", &mnemonic)?,
+                        offset,
+                        width,
+                        dest: require_operand(dest, "destination", &mnemonic)?,
+                    },
+                    _ => Instruction::BitFieldInsert {
+                        src: require_operand(src, "source", &mnemonic)?,
+                        base: require_operand(dest, "destination", &mnemonic)?,
+                        offset,
+                        width,
+                    },
+                }
+            }
+            Operation::CMP | Operation::CMPI | Operation::CMPA => Instruction::Compare {
+                src: require_operand(src, "source", &mnemonic)?,
+                dest: require_operand(dest, "destination", &mnemonic)?,
+            },
+            Operation::CMPM => match (
+                require_operand(src, "source", &mnemonic)?,
+                require_operand(dest, "destination", &mnemonic)?,
+            ) {
+                (
+                    AddressMode::RegisterIndirectPostIncrement { register: src },
+                    AddressMode::RegisterIndirectPostIncrement { register: dest },
+                ) => Instruction::CompareMemory { src, dest },
+                (src, dest) => {
+                    return Err(ParseError::InvalidOperand {
+                        operand: format!("{:?}, {:?}", src, dest),
+                        instruction: format!("{:?}", decoded.instruction),
+                    })
+                }
             },
             Operation::NOP => Instruction::NoOp,
             Operation::RTS => Instruction::ReturnFromSubroutine,
-            _ => unimplemented!(
-                "{} for instruction {:?}",
-                format!("{:?}", decoded.instruction.operation).cyan().bold(),
-                decoded
-            ),
+            _ => {
+                return Err(ParseError::UnknownInstruction(format!(
+                    "{} (not supported yet)",
+                    mnemonic
+                )))
+            }
         };
 
         Ok((parsed, size, decoded.bytes_used))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::registers::{DataRegister, Register};
+
+    #[test]
+    fn missing_operand() {
+        let d0 = AddressMode::RegisterDirect {
+            register: Register::Data(DataRegister::D0),
+        };
+        assert_eq!(
+            require_operand(Some(d0.clone()), "source", "MULU").unwrap(),
+            d0
+        );
+        match require_operand(None, "source", "MULU") {
+            Err(ParseError::MissingOperand(message)) => {
+                assert_eq!(message, "MULU (no source operand)")
+            }
+            other => panic!("expected MissingOperand, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn only_operand_in_either_position() {
+        let d0 = AddressMode::RegisterDirect {
+            register: Register::Data(DataRegister::D0),
+        };
+        assert_eq!(only_operand(Some(d0.clone()), None, "NEG").unwrap(), d0);
+        assert_eq!(only_operand(None, Some(d0.clone()), "NEG").unwrap(), d0);
+        assert!(matches!(
+            only_operand(None, None, "NEG"),
+            Err(ParseError::MissingOperand(_))
+        ));
+        assert!(matches!(
+            only_operand(Some(d0.clone()), Some(d0), "NEG"),
+            Err(ParseError::InvalidOperand { .. })
+        ));
+    }
+
+    #[test]
+    fn index_size_from_extension_word() {
+        // move.l (0, a0, d1.w), (4, a1, d2.l)
+        let code = vec![0x23, 0xB0, 0x10, 0x00, 0x28, 0x04, 0, 0];
+        let (instruction, _, bytes_taken) = MachineCodeParser::default().parse(code).unwrap();
+        assert_eq!(bytes_taken, 6);
+        match instruction {
+            Instruction::Move {
+                src:
+                    AddressMode::RegisterIndirectIndexed {
+                        index_size: src, ..
+                    },
+                dest:
+                    AddressMode::RegisterIndirectIndexed {
+                        index_size: dest, ..
+                    },
+            } => {
+                assert_eq!(src, OperandSize::Word);
+                assert_eq!(dest, OperandSize::Long);
+            }
+            other => panic!("expected indexed operands, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn shift_memory_by_one() {
+        let d0 = AddressMode::RegisterDirect {
+            register: Register::Data(DataRegister::D0),
+        };
+        let one = AddressMode::Immediate { value: 1 };
+        assert_eq!(
+            shift_operands(Some(one.clone()), Some(d0.clone()), "LSL").unwrap(),
+            (one.clone(), d0.clone())
+        );
+        assert_eq!(
+            shift_operands(Some(d0.clone()), None, "LSL").unwrap(),
+            (one.clone(), d0.clone())
+        );
+        assert_eq!(
+            shift_operands(None, Some(d0.clone()), "LSL").unwrap(),
+            (one, d0)
+        );
+        assert!(matches!(
+            shift_operands(None, None, "LSL"),
+            Err(ParseError::MissingOperand(_))
+        ));
+    }
+}
@@ -0,0 +1,1314 @@
+//! Encodes instructions into 68k machine code
+//!
+//! This is the inverse of the [`MachineCodeParser`](super::binary::MachineCodeParser).
+//! Refer to https://www.nxp.com/docs/en/reference-manual/M68000PRM.pdf for the opcode formats.
+//!
+//! Absolute addresses are always encoded in their long form.
+
+use super::ParseError;
+use crate::{
+    cpu::{
+        addressing::{AddressMode, IndexScale},
+        isa_68000::{BitFieldParameter, Condition, Instruction},
+        registers::Register,
+    },
+    OperandSize,
+};
+
+/// Builds the error returned when an instruction can't be encoded
+fn cannot_encode(instruction: &Instruction, reason: &str) -> ParseError {
+    ParseError::CannotEncode {
+        instruction: format!("{:?}", instruction),
+        reason: reason.to_string(),
+    }
+}
+
+/// Gets the 2-bit size field used by most instructions (`00` = byte, `01` = word, `10` = long)
+fn size_bits(size: OperandSize) -> u16 {
+    match size {
+        OperandSize::Byte => 0b00,
+        OperandSize::Word => 0b01,
+        OperandSize::Long => 0b10,
+    }
+}
+
+/// Gets the size field used by MOVE, which is ordered differently (`01` = byte, `11` = word, `10` = long)
+fn move_size_bits(size: OperandSize) -> u16 {
+    match size {
+        OperandSize::Byte => 0b01,
+        OperandSize::Word => 0b11,
+        OperandSize::Long => 0b10,
+    }
+}
+
+/// Gets the `D/A | register | W/L | scale` fields of an index, as used in the top bits of extension words
+fn index_bits(register: Register, size: OperandSize, scale: IndexScale) -> Option<u16> {
+    let register_bits = match register {
+        Register::Data(reg) => reg as u16,
+        Register::Address(reg) => 0b1000 | reg as u16,
+        Register::ProgramCounter => return None,
+    };
+    let size_bit = match size {
+        OperandSize::Word => 0,
+        OperandSize::Long => 1,
+        OperandSize::Byte => return None,
+    };
+    let scale_bits = match scale {
+        IndexScale::One => 0b00,
+        IndexScale::Two => 0b01,
+        IndexScale::Four => 0b10,
+        IndexScale::Eight => 0b11,
+    };
+    Some((register_bits << 12) | (size_bit << 11) | (scale_bits << 9))
+}
+
+/// Encodes a brief extension word: `D/A | register | W/L | scale | 0 | 8-bit displacement`
+fn brief_extension_word(index: u16, displacement: u16) -> Option<u16> {
+    let displacement = i8::try_from(displacement as i16).ok()?;
+    Some(index | (displacement as u8 as u16))
+}
+
+/// Encodes a full extension word (68020+) for memory indirect modes, followed by its displacements
+///
+/// Null displacements are omitted, mirroring [`AddressMode::extension_size`].
+fn full_extension_words(
+    index: u16,
+    base_displacement: u16,
+    outer_displacement: u16,
+    post_indexed: bool,
+) -> Option<Vec<u8>> {
+    let base_displacement_size = if base_displacement == 0 { 0b01 } else { 0b10 };
+    let outer_displacement_size = if outer_displacement == 0 { 0b01 } else { 0b10 };
+    let indirection = if post_indexed {
+        0b100 | outer_displacement_size
+    } else {
+        outer_displacement_size
+    };
+
+    let word = index | (1 << 8) | (base_displacement_size << 4) | indirection;
+
+    let mut bytes = word.to_be_bytes().to_vec();
+    if base_displacement != 0 {
+        bytes.extend(base_displacement.to_be_bytes());
+    }
+    if outer_displacement != 0 {
+        bytes.extend(outer_displacement.to_be_bytes());
+    }
+    Some(bytes)
+}
+
+/// Encodes an operand as a 6-bit effective address field (`mode << 3 | register`) and its extension words
+///
+/// Returns `None` if the operand can't be represented as an effective address.
+fn effective_address(operand: &AddressMode, size: OperandSize) -> Option<(u16, Vec<u8>)> {
+    match *operand {
+        AddressMode::RegisterDirect { register } => match register {
+            Register::Data(reg) => Some((reg as u16, vec![])),
+            Register::Address(reg) => Some(((0b001 << 3) | reg as u16, vec![])),
+            Register::ProgramCounter => None,
+        },
+        AddressMode::RegisterDirectList { .. } => None,
+        AddressMode::RegisterIndirect { register } => {
+            Some(((0b010 << 3) | register as u16, vec![]))
+        }
+        AddressMode::RegisterIndirectPostIncrement { register } => {
+            Some(((0b011 << 3) | register as u16, vec![]))
+        }
+        AddressMode::RegisterIndirectPreDecrement { register } => {
+            Some(((0b100 << 3) | register as u16, vec![]))
+        }
+        AddressMode::RegisterIndirectWithDisplacement {
+            register,
+            displacement,
+        } => Some((
+            (0b101 << 3) | register as u16,
+            displacement.to_be_bytes().to_vec(),
+        )),
+        AddressMode::RegisterIndirectIndexed {
+            displacement,
+            address_register,
+            index_register,
+            index_size,
+            index_scale,
+        } => Some((
+            (0b110 << 3) | address_register as u16,
+            brief_extension_word(
+                index_bits(index_register, index_size, index_scale)?,
+                displacement,
+            )?
+            .to_be_bytes()
+            .to_vec(),
+        )),
+        AddressMode::MemoryPostIndexed {
+            base_displacement,
+            outer_displacement,
+            address_register,
+            index_register,
+            index_size,
+            index_scale,
+        } => Some((
+            (0b110 << 3) | address_register as u16,
+            full_extension_words(
+                index_bits(index_register, index_size, index_scale)?,
+                base_displacement,
+                outer_displacement,
+                true,
+            )?,
+        )),
+        AddressMode::MemoryPreIndexed {
+            base_displacement,
+            outer_displacement,
+            address_register,
+            index_register,
+            index_size,
+            index_scale,
+        } => Some((
+            (0b110 << 3) | address_register as u16,
+            full_extension_words(
+                index_bits(index_register, index_size, index_scale)?,
+                base_displacement,
+                outer_displacement,
+                false,
+            )?,
+        )),
+        AddressMode::ProgramCounterIndirectWithDisplacement { displacement } => {
+            Some((0b111_010, displacement.to_be_bytes().to_vec()))
+        }
+        AddressMode::ProgramCounterIndirectIndexed {
+            displacement,
+            index_register,
+            index_size,
+            index_scale,
+        } => Some((
+            0b111_011,
+            brief_extension_word(
+                index_bits(index_register, index_size, index_scale)?,
+                displacement,
+            )?
+            .to_be_bytes()
+            .to_vec(),
+        )),
+        AddressMode::ProgramCounterMemoryIndirectPostIndexed {
+            base_displacement,
+            outer_displacement,
+            index_register,
+            index_size,
+            index_scale,
+        } => Some((
+            0b111_011,
+            full_extension_words(
+                index_bits(index_register, index_size, index_scale)?,
+                base_displacement,
+                outer_displacement,
+                true,
+            )?,
+        )),
+        AddressMode::ProgramCounterMemoryIndirectPreIndexed {
+            base_displacement,
+            outer_displacement,
+            index_register,
+            index_size,
+            index_scale,
+        } => Some((
+            0b111_011,
+            full_extension_words(
+                index_bits(index_register, index_size, index_scale)?,
+                base_displacement,
+                outer_displacement,
+                false,
+            )?,
+        )),
+        AddressMode::Absolute { address } => Some((0b111_001, address.to_be_bytes().to_vec())),
+        AddressMode::Immediate { value } => Some((
+            0b111_100,
+            match size {
+                // Byte immediates still take up a whole extension word
+                OperandSize::Byte | OperandSize::Word => (value as u16).to_be_bytes().to_vec(),
+                OperandSize::Long => value.to_be_bytes().to_vec(),
+            },
+        )),
+    }
+}
+
+/// Encodes Bcc, BRA, and BSR, which share a format; `condition` is the 4-bit condition field
+fn encode_branch(
+    instruction: &Instruction,
+    size: OperandSize,
+    condition: u16,
+    displacement: i32,
+) -> Result<Vec<u8>, ParseError> {
+    let opword = 0x6000 | (condition << 8);
+    let extension = match size {
+        // A byte displacement of 0 means a word displacement follows the opcode
+        OperandSize::Byte => match i8::try_from(displacement) {
+            Ok(displacement) if displacement != 0 => {
+                return Ok((opword | displacement as u8 as u16).to_be_bytes().to_vec())
+            }
+            _ => {
+                return Err(cannot_encode(
+                    instruction,
+                    "byte displacement must be from -128 to 127, and not 0",
+                ))
+            }
+        },
+        OperandSize::Word => match i16::try_from(displacement) {
+            Ok(displacement) => displacement.to_be_bytes(),
+            Err(_) => {
+                return Err(cannot_encode(
+                    instruction,
+                    "word displacement must be from -32768 to 32767",
+                ))
+            }
+        },
+        OperandSize::Long => {
+            return Err(cannot_encode(
+                instruction,
+                "long displacements need a 68020",
+            ))
+        }
+    };
+
+    let mut bytes = opword.to_be_bytes().to_vec();
+    bytes.extend(extension);
+    Ok(bytes)
+}
+
+/// Encodes ADD, SUB, AND, and OR, which share a format.
+///
+/// `opcode` is the top 4 bits of the register form, and `immediate_opcode` is the top byte of the immediate form.
+/// `address_dest` controls whether the address register destination form (e.g. ADDA) exists.
+fn encode_arithmetic(
+    instruction: &Instruction,
+    size: OperandSize,
+    src: &AddressMode,
+    dest: &AddressMode,
+    opcode: u16,
+    immediate_opcode: u16,
+    address_dest: bool,
+) -> Result<Vec<u8>, ParseError> {
+    let invalid_operand = || cannot_encode(instruction, "invalid operand");
+    let (src_ea, src_ext) = effective_address(src, size).ok_or_else(invalid_operand)?;
+    let (dest_ea, dest_ext) = effective_address(dest, size).ok_or_else(invalid_operand)?;
+
+    let (opword, extension) = match (src, dest) {
+        // <ea>, An
+        (
+            _,
+            AddressMode::RegisterDirect {
+                register: Register::Address(reg),
+            },
+        ) => {
+            if !address_dest {
+                return Err(cannot_encode(
+                    instruction,
+                    "destination can't be an address register",
+                ));
+            }
+            let long = match size {
+                OperandSize::Byte => {
+                    return Err(cannot_encode(
+                        instruction,
+                        "address registers can't be used with byte operands",
+                    ))
+                }
+                OperandSize::Word => 0,
+                OperandSize::Long => 1,
+            };
+            (
+                (opcode << 12) | ((*reg as u16) << 9) | (long << 8) | (0b11 << 6) | src_ea,
+                src_ext,
+            )
+        }
+        // #<data>, <ea>
+        (AddressMode::Immediate { .. }, _) => (
+            (immediate_opcode << 8) | (size_bits(size) << 6) | dest_ea,
+            [src_ext, dest_ext].concat(),
+        ),
+        // <ea>, Dn
+        (
+            _,
+            AddressMode::RegisterDirect {
+                register: Register::Data(reg),
+            },
+        ) => (
+            (opcode << 12) | ((*reg as u16) << 9) | (size_bits(size) << 6) | src_ea,
+            src_ext,
+        ),
+        // Dn, <ea>
+        (
+            AddressMode::RegisterDirect {
+                register: Register::Data(reg),
+            },
+            _,
+        ) => (
+            (opcode << 12) | ((*reg as u16) << 9) | (1 << 8) | (size_bits(size) << 6) | dest_ea,
+            dest_ext,
+        ),
+        _ => {
+            return Err(cannot_encode(
+                instruction,
+                "one operand must be a data register or an immediate value",
+            ))
+        }
+    };
+
+    Ok([opword.to_be_bytes().to_vec(), extension].concat())
+}
+
+/// Encodes ABCD, SBCD, ADDX, or SUBX, which either work on two data registers or two predecremented address registers
+fn encode_extended(
+    instruction: &Instruction,
+    src: &AddressMode,
+    dest: &AddressMode,
+    opword: u16,
+) -> Result<Vec<u8>, ParseError> {
+    let opword = match (src, dest) {
+        (
+            AddressMode::RegisterDirect {
+                register: Register::Data(src),
+            },
+            AddressMode::RegisterDirect {
+                register: Register::Data(dest),
+            },
+        ) => opword | ((*dest as u16) << 9) | *src as u16,
+        (
+            AddressMode::RegisterIndirectPreDecrement { register: src },
+            AddressMode::RegisterIndirectPreDecrement { register: dest },
+        ) => opword | ((*dest as u16) << 9) | (1 << 3) | *src as u16,
+        _ => {
+            return Err(cannot_encode(
+                instruction,
+                "operands must both be data registers or both be predecrement",
+            ))
+        }
+    };
+    Ok(opword.to_be_bytes().to_vec())
+}
+
+/// Encodes a shift or rotate, where `kind` is 0b00 for ASx, 0b01 for LSx, 0b10 for ROXx, or 0b11 for ROx
+fn encode_shift(
+    instruction: &Instruction,
+    size: OperandSize,
+    operand: &AddressMode,
+    count: &AddressMode,
+    kind: u16,
+    left: bool,
+) -> Result<Vec<u8>, ParseError> {
+    let direction = (left as u16) << 8;
+    let (opword, extension) = match operand {
+        AddressMode::RegisterDirect {
+            register: Register::Data(reg),
+        } => {
+            // The i/r bit is set when the count is in a register
+            let (count, register_count) = match count {
+                // Counts of 1-8 are stored in the opcode, with 8 represented as 0
+                AddressMode::Immediate {
+                    value: count @ 1..=8,
+                } => ((*count as u16) & 0b111, 0),
+                AddressMode::RegisterDirect {
+                    register: Register::Data(count),
+                } => (*count as u16, 1),
+                _ => {
+                    return Err(cannot_encode(
+                        instruction,
+                        "count must be a data register or an immediate value from 1 to 8",
+                    ))
+                }
+            };
+            (
+                (0b1110 << 12)
+                    | (count << 9)
+                    | direction
+                    | (size_bits(size) << 6)
+                    | (register_count << 5)
+                    | (kind << 3)
+                    | *reg as u16,
+                vec![],
+            )
+        }
+        // Memory is always shifted by one bit, on a word
+        _ => match count {
+            AddressMode::Immediate { value: 1 } => {
+                let (ea, ext) = effective_address(operand, OperandSize::Word)
+                    .ok_or_else(|| cannot_encode(instruction, "invalid operand"))?;
+                (0xE0C0 | (kind << 9) | direction | ea, ext)
+            }
+            _ => {
+                return Err(cannot_encode(
+                    instruction,
+                    "memory can only be shifted by one bit",
+                ))
+            }
+        },
+    };
+
+    Ok([opword.to_be_bytes().to_vec(), extension].concat())
+}
+
+/// Encodes the extension word of a bit-field instruction.
+///
+/// Immediate offsets must be from 0 to 31; immediate widths are from 1 to 32, with 32 encoded as 0.
+fn bit_field_extension_word(
+    instruction: &Instruction,
+    register: &AddressMode,
+    offset: BitFieldParameter,
+    width: BitFieldParameter,
+) -> Result<u16, ParseError> {
+    let register = match register {
+        AddressMode::RegisterDirect {
+            register: Register::Data(reg),
+        } => *reg as u16,
+        _ => {
+            return Err(cannot_encode(
+                instruction,
+                "operand must be a data register",
+            ))
+        }
+    };
+    let offset = match offset {
+        BitFieldParameter::Immediate(offset @ 0..=31) => (offset as u16) << 6,
+        BitFieldParameter::Immediate(_) => {
+            return Err(cannot_encode(instruction, "offset must be from 0 to 31"))
+        }
+        BitFieldParameter::Register(reg) => (1 << 11) | ((reg as u16) << 6),
+    };
+    let width = match width {
+        BitFieldParameter::Immediate(width @ 1..=32) => (width as u16) & 0b11111,
+        BitFieldParameter::Immediate(_) => {
+            return Err(cannot_encode(instruction, "width must be from 1 to 32"))
+        }
+        BitFieldParameter::Register(reg) => (1 << 5) | reg as u16,
+    };
+    Ok((register << 12) | offset | width)
+}
+
+/// Encodes an instruction to machine code
+pub fn encode(instruction: &Instruction, size: OperandSize) -> Result<Vec<u8>, ParseError> {
+    let invalid_operand = || cannot_encode(instruction, "invalid operand");
+
+    let (opword, extension) = match instruction {
+        Instruction::Add { src, dest } => {
+            return encode_arithmetic(instruction, size, src, dest, 0b1101, 0b0000_0110, true)
+        }
+        Instruction::Subtract { src, dest } => {
+            return encode_arithmetic(instruction, size, src, dest, 0b1001, 0b0000_0100, true)
+        }
+        Instruction::Compare { src, dest } => {
+            // CMP's `Dn, <ea>` encoding is EOR's, so comparing to memory needs an immediate source (CMPI)
+            if !matches!(
+                (src, dest),
+                (AddressMode::Immediate { .. }, _) | (_, AddressMode::RegisterDirect { .. })
+            ) {
+                return Err(cannot_encode(
+                    instruction,
+                    "destination must be a register unless the source is immediate",
+                ));
+            }
+            return encode_arithmetic(instruction, size, src, dest, 0b1011, 0b0000_1100, true);
+        }
+        Instruction::And { src, dest } => {
+            return encode_arithmetic(instruction, size, src, dest, 0b1100, 0b0000_0010, false)
+        }
+        Instruction::InclusiveOr { src, dest } => {
+            return encode_arithmetic(instruction, size, src, dest, 0b1000, 0b0000_0000, false)
+        }
+        Instruction::ExclusiveOr { src, dest } => {
+            let (dest_ea, dest_ext) = effective_address(dest, size).ok_or_else(invalid_operand)?;
+            match src {
+                AddressMode::RegisterDirect {
+                    register: Register::Data(reg),
+                } => (
+                    (0b1011 << 12)
+                        | ((*reg as u16) << 9)
+                        | (1 << 8)
+                        | (size_bits(size) << 6)
+                        | dest_ea,
+                    dest_ext,
+                ),
+                AddressMode::Immediate { .. } => {
+                    let (_, src_ext) = effective_address(src, size).ok_or_else(invalid_operand)?;
+                    (
+                        (0b0000_1010 << 8) | (size_bits(size) << 6) | dest_ea,
+                        [src_ext, dest_ext].concat(),
+                    )
+                }
+                _ => {
+                    return Err(cannot_encode(
+                        instruction,
+                        "source must be a data register or an immediate value",
+                    ))
+                }
+            }
+        }
+        Instruction::Move { src, dest } => {
+            if let (
+                OperandSize::Byte,
+                AddressMode::RegisterDirect {
+                    register: Register::Address(_),
+                },
+            ) = (size, dest)
+            {
+                return Err(cannot_encode(
+                    instruction,
+                    "address registers can't be used with byte operands",
+                ));
+            }
+            let (src_ea, src_ext) = effective_address(src, size).ok_or_else(invalid_operand)?;
+            let (dest_ea, dest_ext) = effective_address(dest, size).ok_or_else(invalid_operand)?;
+            // The destination's mode and register fields are swapped relative to a normal effective address
+            let dest_field = ((dest_ea & 0b111) << 3) | (dest_ea >> 3);
+            (
+                (move_size_bits(size) << 12) | (dest_field << 6) | src_ea,
+                [src_ext, dest_ext].concat(),
+            )
+        }
+        Instruction::MoveQuick { src, dest } => match (src, dest) {
+            (
+                AddressMode::Immediate { value },
+                AddressMode::RegisterDirect {
+                    register: Register::Data(reg),
+                },
+            ) => {
+                let data = i8::try_from(*value as i32).map_err(|_| {
+                    cannot_encode(instruction, "data must be in the range -128 to 127")
+                })?;
+                (
+                    (0b0111 << 12) | ((*reg as u16) << 9) | (data as u8 as u16),
+                    vec![],
+                )
+            }
+            _ => {
+                return Err(cannot_encode(
+                    instruction,
+                    "source must be immediate and destination must be a data register",
+                ))
+            }
+        },
+        Instruction::MultiplyUnsigned { src, dest }
+        | Instruction::DivideUnsigned { src, dest }
+        | Instruction::DivideSigned { src, dest } => match dest {
+            AddressMode::RegisterDirect {
+                register: Register::Data(reg),
+            } => {
+                let (line, opmode) = match instruction {
+                    Instruction::MultiplyUnsigned { .. } => (0b1100, 0b011),
+                    Instruction::DivideUnsigned { .. } => (0b1000, 0b011),
+                    _ => (0b1000, 0b111),
+                };
+                // MULU, DIVU, and DIVS only have word forms on the 68000
+                if matches!(instruction, Instruction::MultiplyUnsigned { .. })
+                    && size != OperandSize::Word
+                {
+                    return Err(cannot_encode(
+                        instruction,
+                        "the 68000 only has a word-sized MULU",
+                    ));
+                }
+                let (src_ea, src_ext) =
+                    effective_address(src, OperandSize::Word).ok_or_else(invalid_operand)?;
+                (
+                    (line << 12) | ((*reg as u16) << 9) | (opmode << 6) | src_ea,
+                    src_ext,
+                )
+            }
+            _ => {
+                return Err(cannot_encode(
+                    instruction,
+                    "destination must be a data register",
+                ))
+            }
+        },
+        Instruction::AddBCD { src, dest } => {
+            return encode_extended(instruction, src, dest, (0b1100 << 12) | (1 << 8))
+        }
+        Instruction::SubtractBCD { src, dest } => {
+            return encode_extended(instruction, src, dest, (0b1000 << 12) | (1 << 8))
+        }
+        Instruction::AddWithExtend { src, dest } => {
+            return encode_extended(
+                instruction,
+                src,
+                dest,
+                (0b1101 << 12) | (1 << 8) | (size_bits(size) << 6),
+            )
+        }
+        Instruction::SubtractWithExtend { src, dest } => {
+            return encode_extended(
+                instruction,
+                src,
+                dest,
+                (0b1001 << 12) | (1 << 8) | (size_bits(size) << 6),
+            )
+        }
+        Instruction::RotateLeft {
+            to_rotate,
+            rotate_amount,
+        } => return encode_shift(instruction, size, to_rotate, rotate_amount, 0b11, true),
+        Instruction::RotateRight {
+            to_rotate,
+            rotate_amount,
+        } => return encode_shift(instruction, size, to_rotate, rotate_amount, 0b11, false),
+        Instruction::RotateLeftExtended {
+            to_rotate,
+            rotate_amount,
+        } => return encode_shift(instruction, size, to_rotate, rotate_amount, 0b10, true),
+        Instruction::RotateRightExtended {
+            to_rotate,
+            rotate_amount,
+        } => return encode_shift(instruction, size, to_rotate, rotate_amount, 0b10, false),
+        Instruction::LogicalShiftLeft {
+            to_shift,
+            shift_amount,
+        } => return encode_shift(instruction, size, to_shift, shift_amount, 0b01, true),
+        Instruction::ArithmeticShiftLeft { count, dest } => {
+            return encode_shift(instruction, size, dest, count, 0b00, true)
+        }
+        Instruction::ArithmeticShiftRight { count, dest } => {
+            return encode_shift(instruction, size, dest, count, 0b00, false)
+        }
+        Instruction::JumpTo { address } | Instruction::JumpToSubroutine { address } => {
+            let opword = match instruction {
+                Instruction::JumpTo { .. } => 0x4EC0,
+                _ => 0x4E80,
+            };
+            if let AddressMode::Immediate { .. } = address {
+                return Err(cannot_encode(
+                    instruction,
+                    "a jump target must be an address, not an immediate",
+                ));
+            }
+            let (ea, ext) = effective_address(address, size).ok_or_else(invalid_operand)?;
+            (opword | ea, ext)
+        }
+        Instruction::BranchConditional {
+            condition,
+            displacement,
+        } => {
+            if *condition == Condition::False {
+                return Err(cannot_encode(
+                    instruction,
+                    "a branch can't be never taken (that encoding is BSR's)",
+                ));
+            }
+            return encode_branch(instruction, size, *condition as u16, *displacement);
+        }
+        Instruction::Branch { displacement } => {
+            return encode_branch(instruction, size, Condition::True as u16, *displacement);
+        }
+        Instruction::BranchToSubroutine { displacement } => {
+            // BSR is encoded where a branch that's never taken would be
+            return encode_branch(instruction, size, Condition::False as u16, *displacement);
+        }
+        Instruction::DecrementBranch {
+            condition,
+            register,
+            displacement,
+        } => match i16::try_from(*displacement) {
+            Ok(displacement) => (
+                0x50C8 | ((*condition as u16) << 8) | *register as u16,
+                displacement.to_be_bytes().to_vec(),
+            ),
+            Err(_) => {
+                return Err(cannot_encode(
+                    instruction,
+                    "displacement must be from -32768 to 32767",
+                ))
+            }
+        },
+        Instruction::LoadEffectiveAddress { src, dest } => match dest {
+            AddressMode::RegisterDirect {
+                register: Register::Address(reg),
+            } => {
+                let (ea, ext) = effective_address(src, size).ok_or_else(invalid_operand)?;
+                (
+                    (0b0100 << 12) | ((*reg as u16) << 9) | (0b111 << 6) | ea,
+                    ext,
+                )
+            }
+            _ => {
+                return Err(cannot_encode(
+                    instruction,
+                    "destination must be an address register",
+                ))
+            }
+        },
+        Instruction::PushEffectiveAddress { src } => {
+            let (ea, ext) = effective_address(src, size).ok_or_else(invalid_operand)?;
+            (0x4840 | ea, ext)
+        }
+        Instruction::NegateBCD { dest } => {
+            let (ea, ext) =
+                effective_address(dest, OperandSize::Byte).ok_or_else(invalid_operand)?;
+            (0x4800 | ea, ext)
+        }
+        Instruction::Negate { dest: operand }
+        | Instruction::NegateWithExtend { dest: operand }
+        | Instruction::Clear { dest: operand }
+        | Instruction::Not { dest: operand }
+        | Instruction::Test { src: operand } => {
+            let opword = match instruction {
+                Instruction::NegateWithExtend { .. } => 0x4000,
+                Instruction::Clear { .. } => 0x4200,
+                Instruction::Negate { .. } => 0x4400,
+                Instruction::Not { .. } => 0x4600,
+                _ => 0x4A00,
+            };
+            let (ea, ext) = effective_address(operand, size).ok_or_else(invalid_operand)?;
+            (opword | (size_bits(size) << 6) | ea, ext)
+        }
+        Instruction::TestBit { bit_number, dest }
+        | Instruction::ChangeBit { bit_number, dest }
+        | Instruction::ClearBit { bit_number, dest }
+        | Instruction::SetBit { bit_number, dest } => {
+            let kind = match instruction {
+                Instruction::TestBit { .. } => 0b00,
+                Instruction::ChangeBit { .. } => 0b01,
+                Instruction::ClearBit { .. } => 0b10,
+                _ => 0b11,
+            };
+            // The mode that would be An is MOVEP's
+            if matches!(
+                dest,
+                AddressMode::RegisterDirect {
+                    register: Register::Address(_)
+                }
+            ) {
+                return Err(invalid_operand());
+            }
+            let size = instruction.intrinsic_size().unwrap_or(size);
+            let (ea, ext) = effective_address(dest, size).ok_or_else(invalid_operand)?;
+            match *bit_number {
+                AddressMode::RegisterDirect {
+                    register: Register::Data(reg),
+                } => (0x0100 | ((reg as u16) << 9) | (kind << 6) | ea, ext),
+                // An immediate bit number goes in an extension word before the destination's
+                AddressMode::Immediate { value } if value < 256 => (
+                    0x0800 | (kind << 6) | ea,
+                    [(value as u16).to_be_bytes().to_vec(), ext].concat(),
+                ),
+                AddressMode::Immediate { .. } => {
+                    return Err(cannot_encode(
+                        instruction,
+                        "bit number must be from 0 to 255",
+                    ))
+                }
+                _ => return Err(invalid_operand()),
+            }
+        }
+        Instruction::BoundsCheck { bound, value } => match value {
+            AddressMode::RegisterDirect {
+                register: Register::Data(reg),
+            } => {
+                let size_field = match size {
+                    OperandSize::Byte => {
+                        return Err(cannot_encode(instruction, "CHK has no byte form"))
+                    }
+                    OperandSize::Word => 0b110,
+                    OperandSize::Long => 0b100,
+                };
+                let (ea, ext) = effective_address(bound, size).ok_or_else(invalid_operand)?;
+                (
+                    (0b0100 << 12) | ((*reg as u16) << 9) | (size_field << 6) | ea,
+                    ext,
+                )
+            }
+            _ => return Err(cannot_encode(instruction, "value must be a data register")),
+        },
+        Instruction::BitFieldExtractUnsigned {
+            base,
+            offset,
+            width,
+            dest: register,
+        }
+        | Instruction::BitFieldExtractSigned {
+            base,
+            offset,
+            width,
+            dest: register,
+        }
+        | Instruction::BitFieldInsert {
+            src: register,
+            base,
+            offset,
+            width,
+        } => {
+            let opword = match instruction {
+                Instruction::BitFieldExtractUnsigned { .. } => 0xE9C0,
+                Instruction::BitFieldExtractSigned { .. } => 0xEBC0,
+                _ => 0xEFC0,
+            };
+            let extension = bit_field_extension_word(instruction, register, *offset, *width)?;
+            let (ea, ext) =
+                effective_address(base, OperandSize::Long).ok_or_else(invalid_operand)?;
+            (
+                opword | ea,
+                [extension.to_be_bytes().to_vec(), ext].concat(),
+            )
+        }
+        Instruction::CompareMemory { src, dest } => (
+            0xB108 | ((*dest as u16) << 9) | (size_bits(size) << 6) | *src as u16,
+            vec![],
+        ),
+        Instruction::MovePeripheral { src, dest } => {
+            // The opmode's high bit is always set; the middle bit is the direction, and the low bit is the size
+            let (register, address_register, displacement, to_memory) = match (src, dest) {
+                (
+                    AddressMode::RegisterDirect {
+                        register: Register::Data(reg),
+                    },
+                    AddressMode::RegisterIndirectWithDisplacement {
+                        register,
+                        displacement,
+                    },
+                ) => (reg, register, displacement, 1),
+                (
+                    AddressMode::RegisterIndirectWithDisplacement {
+                        register,
+                        displacement,
+                    },
+                    AddressMode::RegisterDirect {
+                        register: Register::Data(reg),
+                    },
+                ) => (reg, register, displacement, 0),
+                _ => return Err(cannot_encode(
+                    instruction,
+                    "operands must be a data register and an address register with displacement",
+                )),
+            };
+            let long = match size {
+                OperandSize::Word => 0,
+                OperandSize::Long => 1,
+                OperandSize::Byte => {
+                    return Err(cannot_encode(instruction, "MOVEP can't move a byte"))
+                }
+            };
+            (
+                ((*register as u16) << 9)
+                    | (1 << 8)
+                    | (to_memory << 7)
+                    | (long << 6)
+                    | (1 << 3)
+                    | *address_register as u16,
+                displacement.to_be_bytes().to_vec(),
+            )
+        }
+        Instruction::MoveAlternateSpace { src, dest } => {
+            // The register goes in the extension word, along with whether it's being written to memory
+            let (register, memory, to_memory) = match (src, dest) {
+                (AddressMode::RegisterDirect { register }, memory)
+                    if !matches!(memory, AddressMode::RegisterDirect { .. }) =>
+                {
+                    (register, memory, 1)
+                }
+                (memory, AddressMode::RegisterDirect { register })
+                    if !matches!(memory, AddressMode::RegisterDirect { .. }) =>
+                {
+                    (register, memory, 0)
+                }
+                _ => {
+                    return Err(cannot_encode(
+                        instruction,
+                        "operands must be a register and memory",
+                    ))
+                }
+            };
+            let register_bits = match register {
+                Register::Data(reg) => *reg as u16,
+                Register::Address(reg) => 0b1000 | *reg as u16,
+                Register::ProgramCounter => return Err(invalid_operand()),
+            };
+            let (ea, ext) = effective_address(memory, size).ok_or_else(invalid_operand)?;
+            let extension = (register_bits << 12) | (to_memory << 11);
+            (
+                0x0E00 | (size_bits(size) << 6) | ea,
+                [extension.to_be_bytes().to_vec(), ext].concat(),
+            )
+        }
+        Instruction::ReturnFromSubroutine => (0x4E75, vec![]),
+        Instruction::Trap { vector } if *vector < 16 => (0x4E40 | *vector as u16, vec![]),
+        Instruction::Trap { .. } => {
+            return Err(cannot_encode(
+                instruction,
+                "trap vector must be from 0 to 15",
+            ))
+        }
+        Instruction::NoOp => (0x4E71, vec![]),
+    };
+
+    Ok([opword.to_be_bytes().to_vec(), extension].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cpu::registers::{AddressRegister, DataRegister, Register::*},
+        parsers::{assembly::AssemblyInterpreter, binary::MachineCodeParser, Parser},
+        OperandSize::*,
+    };
+
+    // Expected bytes are from a reference assembler/disassembler
+    #[test]
+    fn encode_assembly() {
+        for (asm, expected) in [
+            // MOVE
+            ("move d0, d1", vec![0x22, 0x00]),
+            ("move d0.w, d1.w", vec![0x32, 0x00]),
+            ("move d0.b, (a1)", vec![0x12, 0x80]),
+            ("move (a0)+, -(a1)", vec![0x23, 0x18]),
+            ("move (4, a0), d1", vec![0x22, 0x28, 0x00, 0x04]),
+            ("move d2, a3", vec![0x26, 0x42]),
+            (
+                "move #$12345678, d0",
+                vec![0x20, 0x3C, 0x12, 0x34, 0x56, 0x78],
+            ),
+            (
+                "move ($1234), ($5678)",
+                vec![0x23, 0xF9, 0x00, 0x00, 0x12, 0x34, 0x00, 0x00, 0x56, 0x78],
+            ),
+            ("move (8, pc), d0", vec![0x20, 0x3A, 0x00, 0x08]),
+            ("move (2, a0, d1), d0", vec![0x20, 0x30, 0x10, 0x02]),
+            ("move (2, a0, d1.l*4), d0", vec![0x20, 0x30, 0x1C, 0x02]),
+            ("move (1, a1, d3.w*2), d0", vec![0x20, 0x31, 0x32, 0x01]),
+            (
+                "move ([4,a0], d1, 8), d0",
+                vec![0x20, 0x30, 0x11, 0x26, 0x00, 0x04, 0x00, 0x08],
+            ),
+            // ADD/ADDA/ADDI
+            ("add d1, d2", vec![0xD4, 0x81]),
+            ("add d1.w, (a0)", vec![0xD3, 0x50]),
+            ("add d0, a1", vec![0xD3, 0xC0]),
+            ("add #100, d3", vec![0x06, 0x83, 0x00, 0x00, 0x00, 0x64]),
+            // SUB/SUBA/SUBI
+            ("sub d1, d2", vec![0x94, 0x81]),
+            ("sub (a0), d7", vec![0x9E, 0x90]),
+            ("sub d0.w, a1.w", vec![0x92, 0xC0]),
+            // AND
+            ("and d0, d1", vec![0xC2, 0x80]),
+            // OR/ORI
+            ("or d1, d2", vec![0x84, 0x81]),
+            ("or #$F0, d0.b", vec![0x00, 0x00, 0x00, 0xF0]),
+            // EOR/EORI
+            ("eor d1, d2", vec![0xB3, 0x82]),
+            ("eor #$FF, (a0)", vec![0x0A, 0x90, 0x00, 0x00, 0x00, 0xFF]),
+            // CMP/CMPA/CMPI
+            ("cmp d1, d2", vec![0xB4, 0x81]),
+            ("cmp.w (a0), d0", vec![0xB0, 0x50]),
+            ("cmpa d0, a1", vec![0xB3, 0xC0]),
+            ("cmpi.b #5, (a0)", vec![0x0C, 0x10, 0x00, 0x05]),
+            // Bcc/BRA/BSR
+            ("beq.s $10", vec![0x67, 0x0E]),
+            ("bne $100", vec![0x66, 0x00, 0x00, 0xFE]),
+            ("bra.b 0", vec![0x60, 0xFE]),
+            ("ble.w $8000", vec![0x6F, 0x00, 0x7F, 0xFE]),
+            ("bsr.s $10", vec![0x61, 0x0E]),
+            ("bsr $100", vec![0x61, 0x00, 0x00, 0xFE]),
+            // DBcc
+            ("dbf d0, 0", vec![0x51, 0xC8, 0xFF, 0xFE]),
+            ("dbra d3, $100", vec![0x51, 0xCB, 0x00, 0xFE]),
+            ("dbeq.w d1, $10", vec![0x57, 0xC9, 0x00, 0x0E]),
+            // JMP/JSR/RTS
+            ("jmp (a0)", vec![0x4E, 0xD0]),
+            ("jmp ($1000)", vec![0x4E, 0xF9, 0x00, 0x00, 0x10, 0x00]),
+            ("jsr $1000", vec![0x4E, 0xB9, 0x00, 0x00, 0x10, 0x00]),
+            ("jsr (4, a1)", vec![0x4E, 0xA9, 0x00, 0x04]),
+            ("rts", vec![0x4E, 0x75]),
+            // NOP
+            ("nop", vec![0x4E, 0x71]),
+            // MOVEQ
+            ("moveq #-128, d0", vec![0x70, 0x80]),
+            ("moveq #1, d7", vec![0x7E, 0x01]),
+            // MULU
+            ("mulu d1, d2", vec![0xC4, 0xC1]),
+            ("mulu (a0), d0", vec![0xC0, 0xD0]),
+            // DIVU/DIVS
+            ("divu d1, d2", vec![0x84, 0xC1]),
+            ("divs #3, d0", vec![0x81, 0xFC, 0x00, 0x03]),
+            // LEA
+            ("lea (8, pc), a0", vec![0x41, 0xFA, 0x00, 0x08]),
+            ("lea ($1000), a1", vec![0x43, 0xF9, 0x00, 0x00, 0x10, 0x00]),
+            // MOVEP
+            ("movep.l d0, (8, a1)", vec![0x01, 0xC9, 0x00, 0x08]),
+            ("movep.w (6, a0), d3", vec![0x07, 0x08, 0x00, 0x06]),
+            // LSL
+            ("lsl d1, d0", vec![0xE3, 0xA8]),
+            ("lsl #3, d0", vec![0xE7, 0x88]),
+            ("lsl.w #1, (a0)", vec![0xE3, 0xD0]),
+            // MOVES
+            ("moves.l d0, (a0)", vec![0x0E, 0x90, 0x08, 0x00]),
+            (
+                "moves.b ($1000), a3",
+                vec![0x0E, 0x39, 0xB0, 0x00, 0x00, 0x00, 0x10, 0x00],
+            ),
+            // ROL/ROR/ROXL/ROXR
+            ("rol.b #1, d0", vec![0xE3, 0x18]),
+            ("rol #1, d0", vec![0xE3, 0x98]),
+            ("rol.w d1, d0", vec![0xE3, 0x78]),
+            ("ror.w d2, d1", vec![0xE4, 0x79]),
+            ("roxl.l #8, d3", vec![0xE1, 0x93]),
+            ("roxr.w d0, d7", vec![0xE0, 0x77]),
+            // ASL/ASR
+            ("asl.b #1, d2", vec![0xE3, 0x02]),
+            ("asr.w d1, d0", vec![0xE2, 0x60]),
+            ("asr.l #8, d7", vec![0xE0, 0x87]),
+            // NEG/NEGX/CLR/NOT/TST
+            ("neg.l d0", vec![0x44, 0x80]),
+            ("neg.w d3", vec![0x44, 0x43]),
+            ("negx.l d1", vec![0x40, 0x81]),
+            ("clr.w d0", vec![0x42, 0x40]),
+            ("not.w (a0)", vec![0x46, 0x50]),
+            ("tst.b (a0)+", vec![0x4A, 0x18]),
+            ("tst.b ($ABCD)", vec![0x4A, 0x39, 0x00, 0x00, 0xAB, 0xCD]),
+            // ADDX/SUBX
+            ("addx d0, d1", vec![0xD3, 0x80]),
+            ("addx.w -(a0), -(a1)", vec![0xD3, 0x48]),
+            ("subx.b d2, d0", vec![0x91, 0x02]),
+            // ABCD/SBCD/NBCD
+            ("abcd d0, d1", vec![0xC3, 0x00]),
+            ("abcd -(a0), -(a1)", vec![0xC3, 0x08]),
+            ("sbcd -(a0), -(a1)", vec![0x83, 0x08]),
+            ("nbcd d1", vec![0x48, 0x01]),
+            ("nbcd -(a0)", vec![0x48, 0x20]),
+            // BTST/BCHG/BCLR/BSET
+            ("btst #3, d0", vec![0x08, 0x00, 0x00, 0x03]),
+            ("btst d1, (a0)", vec![0x03, 0x10]),
+            (
+                "bchg.b #7, ($1000)",
+                vec![0x08, 0x79, 0x00, 0x07, 0x00, 0x00, 0x10, 0x00],
+            ),
+            ("bclr.l d2, d3", vec![0x05, 0x83]),
+            ("bset #31, d7", vec![0x08, 0xC7, 0x00, 0x1F]),
+            ("bset d0, (4, a1)", vec![0x01, 0xE9, 0x00, 0x04]),
+        ] {
+            let (instruction, size, _) = AssemblyInterpreter::new().parse(asm.to_string()).unwrap();
+            assert_eq!(encode(&instruction, size).unwrap(), expected, "{}", asm);
+        }
+    }
+
+    // Instructions that the assembly parser doesn't support yet: CHK, PEA, the bit field instructions, and CMPM
+    #[test]
+    fn encode_instructions() {
+        let d0 = AddressMode::RegisterDirect {
+            register: Data(DataRegister::D0),
+        };
+        let d1 = AddressMode::RegisterDirect {
+            register: Data(DataRegister::D1),
+        };
+
+        for (instruction, size, expected) in [
+            (
+                Instruction::BoundsCheck {
+                    bound: d1.clone(),
+                    value: d0.clone(),
+                },
+                Word,
+                vec![0x41, 0x81],
+            ),
+            (
+                Instruction::PushEffectiveAddress {
+                    src: AddressMode::ProgramCounterIndirectWithDisplacement { displacement: 8 },
+                },
+                Long,
+                vec![0x48, 0x7A, 0x00, 0x08],
+            ),
+            (
+                Instruction::BitFieldExtractUnsigned {
+                    base: d0.clone(),
+                    offset: BitFieldParameter::Immediate(3),
+                    width: BitFieldParameter::Immediate(5),
+                    dest: d1.clone(),
+                },
+                Long,
+                vec![0xE9, 0xC0, 0x10, 0xC5],
+            ),
+            (
+                Instruction::BitFieldExtractSigned {
+                    base: AddressMode::RegisterIndirect {
+                        register: AddressRegister::A0,
+                    },
+                    offset: BitFieldParameter::Immediate(3),
+                    width: BitFieldParameter::Immediate(32),
+                    dest: d1.clone(),
+                },
+                Long,
+                vec![0xEB, 0xD0, 0x10, 0xC0],
+            ),
+            (
+                Instruction::BitFieldInsert {
+                    src: d1.clone(),
+                    base: d0.clone(),
+                    offset: BitFieldParameter::Register(DataRegister::D2),
+                    width: BitFieldParameter::Register(DataRegister::D3),
+                },
+                Long,
+                vec![0xEF, 0xC0, 0x18, 0xA3],
+            ),
+            (
+                Instruction::CompareMemory {
+                    src: AddressRegister::A0,
+                    dest: AddressRegister::A1,
+                },
+                Byte,
+                vec![0xB3, 0x08],
+            ),
+            (
+                Instruction::CompareMemory {
+                    src: AddressRegister::A2,
+                    dest: AddressRegister::A7,
+                },
+                Long,
+                vec![0xBF, 0x8A],
+            ),
+        ] {
+            assert_eq!(
+                encode(&instruction, size).unwrap(),
+                expected,
+                "{:?}",
+                instruction
+            );
+        }
+    }
+
+    /// Assembling an instruction should mean the same thing as decoding its machine code
+    #[test]
+    fn assembly_and_machine_code_agree() {
+        for asm in [
+            // MOVE
+            "move d0, d1",
+            "move.w (a0)+, -(a1)",
+            "move.b d0, (4, a0)",
+            "move ($1234), d0",
+            "move #$12345678, d0",
+            // ADD/ADDA/ADDI
+            "add d1, d2",
+            "add.w d1, (a0)",
+            "add d0, a1",
+            "add #100, d3",
+            // SUB
+            "sub d1, d2",
+            "sub.w (a0), d3",
+            // AND/OR/EOR
+            "and d1, d2",
+            "and.b #$0F, d0",
+            "or.w d1, (a0)",
+            "or (8, a2), d4",
+            "eor d1, d2",
+            "eor.w d3, ($2000)",
+            // CMP/CMPA/CMPI
+            "cmp d1, d2",
+            "cmp.w (a0), d3",
+            "cmpa.w (a0)+, a2",
+            "cmpi.b #5, (a0)",
+            // Bcc/BRA/BSR
+            "bhi.s $20",
+            "bvs $1000",
+            "bra.w $200",
+            "bsr.s $40",
+            "bsr $400",
+            // DBcc
+            "dbne d2, $40",
+            "dbt d7, $1000",
+            // JSR/RTS
+            "jsr ($2000)",
+            "jsr (a3)",
+            "rts",
+            // ADDX/SUBX
+            "addx.w -(a0), -(a1)",
+            "subx d2, d0",
+            // MULU
+            "mulu d1, d2",
+            "mulu.w (a0), d0",
+            // DIVU/DIVS
+            "divu.w d1, d2",
+            "divs.w #3, d0",
+            // ABCD/SBCD/NBCD
+            "abcd d0, d1",
+            "sbcd -(a0), -(a1)",
+            "nbcd (a2)",
+            // BTST/BCHG/BCLR/BSET
+            "btst #3, d0",
+            "btst d1, (a0)",
+            "bchg #7, ($1000)",
+            "bclr d2, d3",
+            "bset #1, -(a0)",
+        ] {
+            let (assembled, size, _) = AssemblyInterpreter::new().parse(asm.to_string()).unwrap();
+            let machine_code = encode(&assembled, size).unwrap();
+            let (decoded, decoded_size, length) = MachineCodeParser::default()
+                .parse(machine_code.clone())
+                .unwrap();
+
+            assert_eq!(decoded, assembled, "{}", asm);
+            assert_eq!(decoded_size, size, "{}", asm);
+            assert_eq!(length as usize, machine_code.len(), "{}", asm);
+        }
+    }
+
+    #[test]
+    fn encode_long_multiply() {
+        let instruction = Instruction::MultiplyUnsigned {
+            src: AddressMode::RegisterDirect {
+                register: Data(DataRegister::D1),
+            },
+            dest: AddressMode::RegisterDirect {
+                register: Data(DataRegister::D2),
+            },
+        };
+        assert_eq!(encode(&instruction, Word).unwrap(), vec![0xC4, 0xC1]);
+        assert!(matches!(
+            encode(&instruction, Long),
+            Err(ParseError::CannotEncode { .. })
+        ));
+    }
+
+    #[test]
+    fn encode_jump_to_immediate() {
+        let instruction = Instruction::JumpTo {
+            address: AddressMode::Immediate { value: 0x1000 },
+        };
+        assert!(matches!(
+            encode(&instruction, Long),
+            Err(ParseError::CannotEncode { .. })
+        ));
+    }
+
+    #[test]
+    fn encode_move_quick_out_of_range() {
+        let instruction = Instruction::MoveQuick {
+            src: AddressMode::Immediate { value: 200 },
+            dest: AddressMode::RegisterDirect {
+                register: Data(DataRegister::D0),
+            },
+        };
+        assert!(matches!(
+            encode(&instruction, Long),
+            Err(ParseError::CannotEncode { .. })
+        ));
+    }
+
+    #[test]
+    fn encode_invalid_operands() {
+        let instruction = Instruction::MultiplyUnsigned {
+            src: AddressMode::RegisterDirect {
+                register: Data(DataRegister::D0),
+            },
+            dest: AddressMode::Absolute { address: 0x1000 },
+        };
+        assert!(matches!(
+            encode(&instruction, Word),
+            Err(ParseError::CannotEncode { .. })
+        ));
+
+        // That encoding of CMP would be EOR
+        let instruction = Instruction::Compare {
+            src: AddressMode::RegisterDirect {
+                register: Data(DataRegister::D0),
+            },
+            dest: AddressMode::Absolute { address: 0x1000 },
+        };
+        assert!(matches!(
+            encode(&instruction, Word),
+            Err(ParseError::CannotEncode { .. })
+        ));
+    }
+}
@@ -8,12 +8,14 @@ use std::num::{ParseIntError, TryFromIntError};
 use crate::{cpu::isa_68000::Instruction, OperandSize};
 pub mod assembly;
 pub mod binary;
+pub mod encoder;
 
 #[derive(Debug)]
 pub enum ParseError {
     NoInstruction(String),
     UnknownInstruction(String),
     UnknownRegister(String),
+    UnknownCondition(String),
     InvalidRegister {
         register: String,
         instruction: String,
@@ -41,9 +43,35 @@ pub enum ParseError {
         source_size: OperandSize,
         dest_size: OperandSize,
     },
+    MnemonicSizeMismatch {
+        instruction: String,
+        mnemonic_size: OperandSize,
+        operand_size: OperandSize,
+    },
     NumberTooLarge(TryFromIntError),
+    DisplacementOutOfRange {
+        displacement: i64,
+        instruction: String,
+    },
+    ImmediateOutOfRange {
+        value: i64,
+        instruction: String,
+    },
+    UnknownSymbol(String),
+    DuplicateLabel(String),
+    CannotEncode {
+        instruction: String,
+        reason: String,
+    },
     OpcodeParsingError(m68kdecode::DecodingError),
+    /// An error in a multi-line program, along with the (1-based) line it's on
+    AtLine {
+        line: usize,
+        source: Box<ParseError>,
+    },
     InvalidOperandSize(i32),
+    /// A line of a symbol file that isn't an address followed by a name
+    InvalidSymbol(String),
 }
 
 impl From<m68kdecode::DecodingError> for ParseError {
@@ -57,3 +85,20 @@ pub trait Parser<T> {
     /// u8 is the number of bytes used for the opcode
     fn parse(&mut self, source: T) -> Result<(Instruction, OperandSize, u32), ParseError>;
 }
+
+/// A parser that a [`CPU`](crate::cpu::CPU) can decode machine code with.
+///
+/// The CPU needs to copy its parser to look ahead at instructions without disturbing it,
+/// so any [`Parser`] for machine code that can be cloned will do.
+pub trait MachineCodeSource: Parser<Vec<u8>> {
+    fn clone_box(&self) -> Box<dyn MachineCodeSource>;
+}
+
+impl<P> MachineCodeSource for P
+where
+    P: Parser<Vec<u8>> + Clone + 'static,
+{
+    fn clone_box(&self) -> Box<dyn MachineCodeSource> {
+        Box::new(self.clone())
+    }
+}
@@ -1,15 +1,17 @@
 use std::fmt::Display;
 
-use crate::{hex_format_byte, EmulationError, M68kInteger, OperandSize};
+use crate::{hex_format_byte, Address, EmulationError, M68kInteger, OperandSize};
 
 // Emulation of Random-Access Memory (RAM)
 
 pub trait Memory: Display {
     fn new(size_in_bytes: usize) -> Self;
-    fn read_byte(&self, address: u32) -> Result<u8, EmulationError>;
-    fn write_byte(&mut self, address: u32, value: u8) -> Result<(), EmulationError>;
+    fn read_byte(&self, address: Address) -> Result<u8, EmulationError>;
+    fn write_byte(&mut self, address: Address, value: u8) -> Result<(), EmulationError>;
+    /// Zeroes all of memory (and drops anything watching it), as if it had just been created
+    fn clear(&mut self);
 
-    fn read(&self, address: u32, size: OperandSize) -> Result<M68kInteger, EmulationError> {
+    fn read(&self, address: Address, size: OperandSize) -> Result<M68kInteger, EmulationError> {
         match size {
             OperandSize::Byte => Ok(M68kInteger::Byte(self.read_byte(address)?)),
             OperandSize::Word => Ok(M68kInteger::Word(self.read_word(address)?)),
@@ -17,7 +19,7 @@ pub trait Memory: Display {
         }
     }
 
-    fn read_bytes(&self, address: u32, len: u32) -> Result<Vec<u8>, EmulationError> {
+    fn read_bytes(&self, address: Address, len: u32) -> Result<Vec<u8>, EmulationError> {
         let mut bytes = Vec::with_capacity(len as usize);
         for i in 0..len {
             bytes.push(self.read_byte(address + i)?);
@@ -25,33 +27,33 @@ pub trait Memory: Display {
         Ok(bytes)
     }
 
-    fn read_word(&self, address: u32) -> Result<u16, EmulationError> {
+    fn read_word(&self, address: Address) -> Result<u16, EmulationError> {
         let high_byte = self.read_byte(address)?;
         let low_byte = self.read_byte(address + 1)?;
         Ok(((high_byte as u16) << 8) + low_byte as u16)
     }
 
-    fn read_long(&self, address: u32) -> Result<u32, EmulationError> {
+    fn read_long(&self, address: Address) -> Result<u32, EmulationError> {
         let high_word = self.read_word(address)?;
         let low_word = self.read_word(address + 2)?;
         Ok(((high_word as u32) << 16) + low_word as u32)
     }
 
-    fn write(&mut self, address: u32, value: M68kInteger) -> Result<(), EmulationError> {
+    fn write(&mut self, address: Address, value: M68kInteger) -> Result<(), EmulationError> {
         match value {
             M68kInteger::Byte(value) => self.write_byte(address, value),
             M68kInteger::Word(value) => self.write_word(address, value),
             M68kInteger::Long(value) => self.write_long(address, value),
         }
     }
-    fn write_bytes(&mut self, address: u32, value: Vec<u8>) -> Result<(), EmulationError> {
+    fn write_bytes(&mut self, address: Address, value: Vec<u8>) -> Result<(), EmulationError> {
         for (i, byte) in value.iter().enumerate() {
             self.write_byte(address + i as u32, *byte)?;
         }
         Ok(())
     }
 
-    fn write_word(&mut self, address: u32, value: u16) -> Result<(), EmulationError> {
+    fn write_word(&mut self, address: Address, value: u16) -> Result<(), EmulationError> {
         let low_byte = (value & 0x00FF) as u8;
         let high_byte = (value >> 8) as u8;
 
@@ -59,7 +61,7 @@ pub trait Memory: Display {
         self.write_byte(address + 1, low_byte)
     }
 
-    fn write_long(&mut self, address: u32, long: u32) -> Result<(), EmulationError> {
+    fn write_long(&mut self, address: Address, long: u32) -> Result<(), EmulationError> {
         let low_word = (long & 0x0000FFFF) as u16;
         let high_word = (long >> 16) as u16;
 
@@ -68,12 +70,65 @@ pub trait Memory: Display {
     }
 }
 
+/// Called with the address and value of each write to memory
+pub type WriteCallback = Box<dyn FnMut(Address, M68kInteger)>;
+
 /// Naive Vec<u8> implementation of RAM
 pub struct VecBackedMemory {
     random_access_buf: Vec<u8>,
+    write_callbacks: Vec<WriteCallback>,
     // TODO: implement memory mapping
 }
 
+impl VecBackedMemory {
+    /// Uses an existing buffer (such as a ROM image) as memory, without copying it.
+    ///
+    /// The memory is exactly as big as the buffer;
+    /// to make room beyond the end of an image, [`Vec::resize`] it before calling this.
+    pub fn from_vec(buf: Vec<u8>) -> Self {
+        Self {
+            random_access_buf: buf,
+            write_callbacks: vec![],
+        }
+    }
+
+    /// Registers a callback to be run after every successful write.
+    ///
+    /// Words and longs are reported as a single write; bulk writes are reported byte by byte.
+    pub fn on_write(&mut self, callback: WriteCallback) {
+        self.write_callbacks.push(callback);
+    }
+
+    /// Writes bytes to memory without running callbacks. Nothing is written if any byte is out of bounds.
+    fn write_raw(&mut self, address: Address, bytes: &[u8]) -> Result<(), EmulationError> {
+        let start = address.0 as usize;
+        match self.random_access_buf.get_mut(start..start + bytes.len()) {
+            Some(buf) => {
+                buf.copy_from_slice(bytes);
+                Ok(())
+            }
+            None => Err(EmulationError::MemoryOutOfBoundsAccess(
+                address.max(Address(self.random_access_buf.len() as u32)),
+            )),
+        }
+    }
+
+    fn notify_write(&mut self, address: Address, value: M68kInteger) {
+        for callback in &mut self.write_callbacks {
+            callback(address, value);
+        }
+    }
+}
+
+/// Copies the contents of memory.
+///
+/// Write callbacks can't be cloned, so the copy has none; register them again with [`VecBackedMemory::on_write`].
+impl Clone for VecBackedMemory {
+    fn clone(&self) -> Self {
+        Self::from_vec(self.random_access_buf.clone())
+    }
+}
+
 impl Display for VecBackedMemory {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         // remove long stretches of 0s
@@ -119,18 +174,204 @@ impl Memory for VecBackedMemory {
     fn new(size_in_bytes: usize) -> Self {
         Self {
             random_access_buf: vec![0; size_in_bytes],
+            write_callbacks: vec![],
         }
     }
 
-    fn read_byte(&self, address: u32) -> Result<u8, EmulationError> {
-        match self.random_access_buf.get(address as usize) {
+    fn clear(&mut self) {
+        self.random_access_buf.fill(0);
+        self.write_callbacks.clear();
+    }
+
+    fn read_byte(&self, address: Address) -> Result<u8, EmulationError> {
+        match self.random_access_buf.get(address.0 as usize) {
             Some(byte) => Ok(*byte),
             None => Err(EmulationError::MemoryOutOfBoundsAccess(address)),
         }
     }
 
-    fn write_byte(&mut self, address: u32, value: u8) -> Result<(), EmulationError> {
-        match self.random_access_buf.get_mut(address as usize) {
+    fn write_byte(&mut self, address: Address, value: u8) -> Result<(), EmulationError> {
+        self.write_raw(address, &[value])?;
+        self.notify_write(address, M68kInteger::Byte(value));
+        Ok(())
+    }
+
+    fn write_word(&mut self, address: Address, value: u16) -> Result<(), EmulationError> {
+        self.write_raw(address, &value.to_be_bytes())?;
+        self.notify_write(address, M68kInteger::Word(value));
+        Ok(())
+    }
+
+    fn write_long(&mut self, address: Address, long: u32) -> Result<(), EmulationError> {
+        self.write_raw(address, &long.to_be_bytes())?;
+        self.notify_write(address, M68kInteger::Long(long));
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, address: Address, value: Vec<u8>) -> Result<(), EmulationError> {
+        self.write_raw(address, &value)?;
+        for (i, byte) in value.into_iter().enumerate() {
+            self.notify_write(address + i as u32, M68kInteger::Byte(byte));
+        }
+        Ok(())
+    }
+}
+
+/// Read-only memory backed by a borrowed program image, so code can be run without copying it into RAM
+#[derive(Clone)]
+pub struct RomMemory<'a> {
+    image: &'a [u8],
+}
+
+impl<'a> RomMemory<'a> {
+    /// Uses `image` as memory starting at address 0. The memory is exactly as big as the image.
+    pub fn from_slice(image: &'a [u8]) -> Self {
+        Self { image }
+    }
+}
+
+impl Display for RomMemory<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ROM ({} bytes)", self.image.len())
+    }
+}
+
+impl Memory for RomMemory<'_> {
+    /// A ROM doesn't own its contents, so this creates an empty one; use [`RomMemory::from_slice`] instead.
+    fn new(_size_in_bytes: usize) -> Self {
+        Self { image: &[] }
+    }
+
+    /// A ROM can't be written to, so there's nothing to clear
+    fn clear(&mut self) {}
+
+    fn read_byte(&self, address: Address) -> Result<u8, EmulationError> {
+        match self.image.get(address.0 as usize) {
+            Some(byte) => Ok(*byte),
+            None => Err(EmulationError::MemoryOutOfBoundsAccess(address)),
+        }
+    }
+
+    fn write_byte(&mut self, address: Address, _value: u8) -> Result<(), EmulationError> {
+        Err(EmulationError::WriteToReadOnly(format!(
+            "can't write to ROM at {}",
+            address
+        )))
+    }
+}
+
+/// RAM with a window onto one of several read-only banks, like a bank-switched cartridge.
+///
+/// Writing a byte to the control address selects which bank (modulo the number of banks) the window shows.
+/// That write only switches banks; it doesn't reach the memory underneath.
+#[derive(Clone)]
+pub struct BankedMemory {
+    ram: Vec<u8>,
+    banks: Vec<Vec<u8>>,
+    current_bank: usize,
+    /// Where the window starts; it's as big as a bank
+    window: u32,
+    control_address: u32,
+}
+
+impl BankedMemory {
+    /// Creates `ram_size` bytes of RAM, with `banks` visible at `window`.
+    ///
+    /// The first bank is selected to start with.
+    /// The banks must all be the same size; if one isn't, this returns [`EmulationError::BankSizeMismatch`].
+    pub fn with_banks(
+        ram_size: usize,
+        window: Address,
+        banks: Vec<Vec<u8>>,
+        control_address: Address,
+    ) -> Result<Self, EmulationError> {
+        let expected = banks.first().map_or(0, Vec::len);
+        if let Some((bank, size)) = banks
+            .iter()
+            .map(Vec::len)
+            .enumerate()
+            .find(|&(_, size)| size != expected)
+        {
+            return Err(EmulationError::BankSizeMismatch {
+                bank,
+                size,
+                expected,
+            });
+        }
+        Ok(Self {
+            ram: vec![0; ram_size],
+            banks,
+            current_bank: 0,
+            window: window.0,
+            control_address: control_address.0,
+        })
+    }
+
+    /// Gets the index of the bank that the window shows
+    pub fn current_bank(&self) -> usize {
+        self.current_bank
+    }
+
+    /// Gets the offset of `address` into the window, if it's in the window
+    fn window_offset(&self, address: Address) -> Option<usize> {
+        let bank_size = self.banks.first().map_or(0, Vec::len) as u32;
+        let offset = address.0.wrapping_sub(self.window);
+        (offset < bank_size).then_some(offset as usize)
+    }
+}
+
+impl Display for BankedMemory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Banked memory ({} bytes of RAM; bank {} of {} at {})",
+            self.ram.len(),
+            self.current_bank,
+            self.banks.len(),
+            Address(self.window)
+        )
+    }
+}
+
+impl Memory for BankedMemory {
+    /// Creates plain RAM, without any banks; use [`BankedMemory::with_banks`] to add some.
+    fn new(size_in_bytes: usize) -> Self {
+        Self {
+            ram: vec![0; size_in_bytes],
+            banks: vec![],
+            current_bank: 0,
+            window: 0,
+            control_address: 0,
+        }
+    }
+
+    /// Zeroes the RAM and selects the first bank again; the banks themselves are read-only, so they're kept
+    fn clear(&mut self) {
+        self.ram.fill(0);
+        self.current_bank = 0;
+    }
+
+    fn read_byte(&self, address: Address) -> Result<u8, EmulationError> {
+        let byte = match self.window_offset(address) {
+            Some(offset) => self.banks[self.current_bank].get(offset),
+            None => self.ram.get(address.0 as usize),
+        };
+        byte.copied()
+            .ok_or(EmulationError::MemoryOutOfBoundsAccess(address))
+    }
+
+    fn write_byte(&mut self, address: Address, value: u8) -> Result<(), EmulationError> {
+        if address.0 == self.control_address && !self.banks.is_empty() {
+            self.current_bank = value as usize % self.banks.len();
+            return Ok(());
+        }
+        if self.window_offset(address).is_some() {
+            return Err(EmulationError::WriteToReadOnly(format!(
+                "can't write to bank {} at {}",
+                self.current_bank, address
+            )));
+        }
+        match self.ram.get_mut(address.0 as usize) {
             Some(byte) => {
                 *byte = value;
                 Ok(())
@@ -143,9 +384,10 @@ impl Memory for VecBackedMemory {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::{cell::RefCell, rc::Rc};
 
     static SIZE: usize = 0x400; // 1KB
-    static ADDRESS: u32 = 0x201;
+    static ADDRESS: Address = Address(0x201);
 
     #[test]
     fn byte_rw() {
@@ -192,6 +434,105 @@ mod tests {
         }
     }
 
+    #[test]
+    fn address_conversions() {
+        let mut ram = VecBackedMemory::new(SIZE);
+        ram.write_long(0x100.into(), 0xDEADBEEF).unwrap();
+        assert_eq!(ram.read_word(Address(0x100) + 2).unwrap(), 0xBEEF);
+        assert_eq!(ram.read_byte(Address(0x100) + 1).unwrap(), 0xAD);
+    }
+
+    #[test]
+    fn clear() {
+        let mut ram = VecBackedMemory::new(SIZE);
+        let writes = Rc::new(RefCell::new(0));
+        let counter = writes.clone();
+        ram.on_write(Box::new(move |_, _| *counter.borrow_mut() += 1));
+        ram.write_long(ADDRESS, 0xDEADBEEF).unwrap();
+        ram.write_byte(Address(SIZE as u32 - 1), 0xFF).unwrap();
+
+        ram.clear();
+        assert_eq!(
+            ram.read_bytes(Address(0), SIZE as u32).unwrap(),
+            vec![0; SIZE]
+        );
+        // Callbacks are dropped too
+        ram.write_byte(ADDRESS, 1).unwrap();
+        assert_eq!(*writes.borrow(), 2);
+    }
+
+    #[test]
+    fn rom() {
+        let image = [0xDE, 0xAD, 0xBE, 0xEF];
+        let mut rom = RomMemory::from_slice(&image);
+        assert_eq!(rom.read_long(Address(0)).unwrap(), 0xDEADBEEF);
+        assert_eq!(rom.read_word(Address(2)).unwrap(), 0xBEEF);
+        assert!(matches!(
+            rom.read_byte(Address(4)),
+            Err(EmulationError::MemoryOutOfBoundsAccess(Address(4)))
+        ));
+        assert!(matches!(
+            rom.write_word(Address(0), 0x1234),
+            Err(EmulationError::WriteToReadOnly(_))
+        ));
+        assert!(rom.write_bytes(Address(2), vec![0]).is_err());
+        assert_eq!(rom.read_long(Address(0)).unwrap(), 0xDEADBEEF);
+    }
+
+    #[test]
+    fn from_vec() {
+        let mut rom = vec![0xDE, 0xAD, 0xBE, 0xEF, 0x12];
+        rom.resize(SIZE, 0);
+        let ram = VecBackedMemory::from_vec(rom);
+
+        assert_eq!(ram.read_long(Address(0)).unwrap(), 0xDEADBEEF);
+        assert_eq!(ram.read_byte(Address(4)).unwrap(), 0x12);
+        assert_eq!(ram.read_byte(Address(SIZE as u32 - 1)).unwrap(), 0);
+        assert!(ram.read_byte(Address(SIZE as u32)).is_err());
+    }
+
+    #[test]
+    fn write_callback() {
+        let writes = Rc::new(RefCell::new(vec![]));
+        let mut ram = VecBackedMemory::new(SIZE);
+        let callback_writes = Rc::clone(&writes);
+        ram.on_write(Box::new(move |address, value| {
+            callback_writes.borrow_mut().push((address, value))
+        }));
+
+        ram.write_byte(ADDRESS, 0xAB).unwrap();
+        ram.write_word(ADDRESS, 0xDEAD).unwrap();
+        ram.write_long(ADDRESS, 0xDEADBEEF).unwrap();
+        ram.write(ADDRESS + 4, M68kInteger::Word(0xBEEF)).unwrap();
+        ram.write_bytes(ADDRESS, vec![0x12, 0x34]).unwrap();
+        // failed writes aren't reported
+        assert!(ram
+            .write_long(Address(SIZE as u32 - 2), 0xFFFFFFFF)
+            .is_err());
+
+        assert_eq!(
+            *writes.borrow(),
+            vec![
+                (ADDRESS, M68kInteger::Byte(0xAB)),
+                (ADDRESS, M68kInteger::Word(0xDEAD)),
+                (ADDRESS, M68kInteger::Long(0xDEADBEEF)),
+                (ADDRESS + 4, M68kInteger::Word(0xBEEF)),
+                (ADDRESS, M68kInteger::Byte(0x12)),
+                (ADDRESS + 1, M68kInteger::Byte(0x34)),
+            ]
+        );
+    }
+
+    #[test]
+    fn out_of_bounds_write_is_atomic() {
+        let mut ram = VecBackedMemory::new(SIZE);
+        assert!(matches!(
+            ram.write_long(Address(SIZE as u32 - 2), 0xFFFFFFFF),
+            Err(EmulationError::MemoryOutOfBoundsAccess(address)) if address == Address(SIZE as u32)
+        ));
+        assert_eq!(ram.read_word(Address(SIZE as u32 - 2)).unwrap(), 0);
+    }
+
     #[test]
     fn display_does_not_include_lots_of_zeroes() {
         for ram_impl in [VecBackedMemory::new(SIZE)] {
@@ -200,4 +541,68 @@ mod tests {
             assert!(zeroes < 10, "too many zeroes not filtered out: {}", zeroes);
         }
     }
+
+    #[test]
+    fn clone_copies_contents_but_not_callbacks() {
+        let writes = Rc::new(RefCell::new(0));
+        let mut original = VecBackedMemory::new(SIZE);
+        original.write_long(ADDRESS, 0xDEADBEEF).unwrap();
+        let counter = writes.clone();
+        original.on_write(Box::new(move |_, _| *counter.borrow_mut() += 1));
+
+        let mut clone = original.clone();
+        assert_eq!(clone.read_long(ADDRESS).unwrap(), 0xDEADBEEF);
+        clone.write_byte(ADDRESS, 0).unwrap();
+        assert_eq!(*writes.borrow(), 0);
+        assert_eq!(original.read_long(ADDRESS).unwrap(), 0xDEADBEEF);
+    }
+
+    #[test]
+    fn bank_switching() {
+        const WINDOW: Address = Address(0x100);
+        const CONTROL: Address = Address(0x3FF);
+        let banks = (0..3).map(|bank| vec![bank * 0x10; 0x80]).collect();
+        let mut memory = BankedMemory::with_banks(SIZE, WINDOW, banks, CONTROL).unwrap();
+        memory.write_long(Address(0x10), 0xDEADBEEF).unwrap();
+        memory.write_byte(CONTROL - 1, 0xAB).unwrap();
+
+        assert_eq!(memory.current_bank(), 0);
+        assert_eq!(memory.read_long(WINDOW).unwrap(), 0);
+        for (selection, bank) in [(2, 2), (1, 1), (5, 2)] {
+            memory.write_byte(CONTROL, selection).unwrap();
+            assert_eq!(memory.current_bank(), bank);
+            let fill = bank as u8 * 0x10;
+            assert_eq!(memory.read_byte(WINDOW).unwrap(), fill);
+            assert_eq!(memory.read_byte(WINDOW + 0x7F).unwrap(), fill);
+        }
+
+        // The control write doesn't land anywhere, and the rest of memory is untouched
+        assert_eq!(memory.read_byte(CONTROL).unwrap(), 0);
+        assert_eq!(memory.read_byte(CONTROL - 1).unwrap(), 0xAB);
+        assert_eq!(memory.read_long(Address(0x10)).unwrap(), 0xDEADBEEF);
+        // Past the end of the window is RAM again
+        assert_eq!(memory.read_byte(WINDOW + 0x80).unwrap(), 0);
+
+        // Banks are read-only
+        assert!(matches!(
+            memory.write_byte(WINDOW + 4, 0xFF),
+            Err(EmulationError::WriteToReadOnly(_))
+        ));
+        assert_eq!(memory.read_byte(WINDOW + 4).unwrap(), 0x20);
+
+        memory.clear();
+        assert_eq!(memory.current_bank(), 0);
+        assert_eq!(memory.read_long(Address(0x10)).unwrap(), 0);
+
+        // Banks that aren't all the same size are refused
+        let banks = vec![vec![0; 0x80], vec![0; 0x80], vec![0; 0x40]];
+        assert!(matches!(
+            BankedMemory::with_banks(SIZE, WINDOW, banks, CONTROL),
+            Err(EmulationError::BankSizeMismatch {
+                bank: 2,
+                size: 0x40,
+                expected: 0x80
+            })
+        ));
+    }
 }
@@ -1,10 +1,71 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use emulator::cpu::registers::Register;
+use emulator::cpu::symbols::SymbolTable;
 use emulator::ram::Memory;
-use emulator::{cpu::CPU, ram::VecBackedMemory};
+use emulator::{cpu::CPU, ram::VecBackedMemory, Address, EmulationError};
 use structopt::StructOpt;
 
+/// Parses an address, which can be decimal or hexadecimal (prefixed with `0x` or `$`)
+fn parse_address(address: &str) -> Result<u32, String> {
+    let result = match address
+        .strip_prefix("0x")
+        .or_else(|| address.strip_prefix('$'))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => address.parse(),
+    };
+    result.map_err(|e| format!("invalid address '{}': {}", address, e))
+}
+
+/// A binary image to load into memory, specified as `FILE@ADDR`
+#[derive(Debug, PartialEq)]
+struct Image {
+    path: PathBuf,
+    address: u32,
+}
+
+impl FromStr for Image {
+    type Err = String;
+
+    fn from_str(image: &str) -> Result<Self, Self::Err> {
+        // Split on the last @, since the path could contain one too
+        match image.rsplit_once('@') {
+            Some((path, address)) if !path.is_empty() => Ok(Image {
+                path: PathBuf::from(path),
+                address: parse_address(address)?,
+            }),
+            _ => Err(format!("expected FILE@ADDR, got '{}'", image)),
+        }
+    }
+}
+
+/// A region of memory to write to a file after the run, specified as `ADDR:LEN:FILE`
+#[derive(Debug, PartialEq)]
+struct MemoryDump {
+    address: u32,
+    length: u32,
+    path: PathBuf,
+}
+
+impl FromStr for MemoryDump {
+    type Err = String;
+
+    fn from_str(dump: &str) -> Result<Self, Self::Err> {
+        // Split on the first two colons, since the path could contain one too
+        match dump.splitn(3, ':').collect::<Vec<_>>()[..] {
+            [address, length, path] if !path.is_empty() => Ok(MemoryDump {
+                address: parse_address(address)?,
+                length: parse_address(length)
+                    .map_err(|_| format!("invalid length '{}'", length))?,
+                path: PathBuf::from(path),
+            }),
+            _ => Err(format!("expected ADDR:LEN:FILE, got '{}'", dump)),
+        }
+    }
+}
+
 #[derive(StructOpt)]
 #[structopt(
     author = "Annika L.",
@@ -12,27 +73,179 @@ use structopt::StructOpt;
 )]
 struct Options {
     #[structopt(
-        help = "File to run. Currently, only raw binary files (compiled with the `-Wl,--oformat=binary` gcc flags) are supported."
+        help = "File to run, loaded at address 0. Currently, only raw binary files (compiled with the `-Wl,--oformat=binary` gcc flags) are supported."
+    )]
+    file: Option<PathBuf>,
+    #[structopt(
+        long = "load",
+        value_name = "FILE@ADDR",
+        number_of_values = 1,
+        help = "Loads a raw binary file at the given address (decimal, or hex prefixed with 0x or $). Can be given multiple times."
     )]
-    file: PathBuf,
+    load: Vec<Image>,
+    #[structopt(
+        long = "entry",
+        value_name = "ADDR",
+        parse(try_from_str = parse_address),
+        help = "Address to start executing at [default: 0]"
+    )]
+    entry: Option<u32>,
     #[structopt(
         short = "v",
         long = "verbose",
         help = "Prints the CPU state after each instruction, instead of only after the file is run"
     )]
     verbose: bool,
+    #[structopt(
+        long = "skip-unknown",
+        help = "Skips over instructions that can't be decoded one word at a time, instead of stopping the run"
+    )]
+    skip_unknown: bool,
+    #[structopt(
+        long = "gdb",
+        value_name = "HOST:PORT",
+        help = "Waits for GDB to connect on the given address (e.g. localhost:1234) and lets it control the run. Requires the `gdb` feature."
+    )]
+    gdb: Option<String>,
+    #[structopt(
+        long = "dump-memory",
+        value_name = "ADDR:LEN:FILE",
+        number_of_values = 1,
+        help = "After the run, writes LEN bytes of memory starting at ADDR to FILE (both decimal, or hex prefixed with 0x or $). Can be given multiple times."
+    )]
+    dump_memory: Vec<MemoryDump>,
+    #[structopt(
+        long = "symbols",
+        value_name = "FILE",
+        help = "Loads names for addresses from FILE, in the format `nm` prints (`00001234 T strcpy`), to show in disassembly"
+    )]
+    symbols: Option<PathBuf>,
+}
+
+/// Reads each image from disk and writes it into memory at its address
+fn load_images(cpu: &mut CPU<impl Memory>, images: &[Image]) -> Result<(), String> {
+    for image in images {
+        let binary = std::fs::read(&image.path)
+            .map_err(|e| format!("could not read {}: {}", image.path.display(), e))?;
+        cpu.load_binary(image.address, binary).map_err(|e| {
+            format!(
+                "could not load {} at {:#X}: {:?}",
+                image.path.display(),
+                image.address,
+                e
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// Reads a symbol file into the CPU's symbol table
+fn load_symbols(cpu: &mut CPU<impl Memory>, path: &Path) -> Result<(), String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    cpu.symbols = SymbolTable::parse(&text)
+        .map_err(|e| format!("could not parse {}: {:?}", path.display(), e))?;
+    Ok(())
+}
+
+/// Writes each region of memory to its file
+fn dump_memory(cpu: &CPU<impl Memory>, dumps: &[MemoryDump]) -> Result<(), String> {
+    for dump in dumps {
+        let bytes = cpu
+            .memory
+            .read_bytes(Address(dump.address), dump.length)
+            .map_err(|e| {
+                format!(
+                    "could not read {} bytes at {:#X}: {:?}",
+                    dump.length, dump.address, e
+                )
+            })?;
+        std::fs::write(&dump.path, bytes)
+            .map_err(|e| format!("could not write {}: {}", dump.path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Describes an error from running the instruction at the PC, including the instruction if it can be decoded
+fn describe_fault(cpu: &CPU<impl Memory>, error: &EmulationError) -> String {
+    let pc = cpu.registers.get(Register::ProgramCounter);
+    let instruction = match cpu.disassemble_at(pc, 1).pop() {
+        Some((_, Ok(instruction), size)) => instruction.disassemble(pc, size, &cpu.symbols),
+        _ => String::from("undecodable"),
+    };
+    format!("Error at {} ({}): {:?}", Address(pc), instruction, error)
+}
+
+/// Hands the CPU over to GDB, which connects to `address`
+#[cfg(feature = "gdb")]
+fn debug_with_gdb(cpu: CPU<VecBackedMemory>, address: &str) -> CPU<VecBackedMemory> {
+    let listener = match std::net::TcpListener::bind(address) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Error: could not listen on {}: {}", address, e);
+            std::process::exit(1);
+        }
+    };
+    println!("Waiting for GDB to connect on {}...", address);
+    let mut stub = emulator::gdb::GdbStub::new(cpu);
+    let result = listener
+        .accept()
+        .and_then(|(connection, _)| stub.serve(connection));
+    if let Err(e) = result {
+        eprintln!("Error: lost connection to GDB: {}", e);
+    }
+    println!("{}", stub.cpu);
+    stub.cpu
+}
+
+#[cfg(not(feature = "gdb"))]
+fn debug_with_gdb(_cpu: CPU<VecBackedMemory>, _address: &str) -> CPU<VecBackedMemory> {
+    eprintln!("Error: --gdb needs run68 to be built with the `gdb` feature");
+    std::process::exit(1);
 }
 
 fn main() {
-    let options = Options::from_args();
-    let code = std::fs::read(&options.file).expect("Could not read file");
+    let mut options = Options::from_args();
+    if let Some(path) = options.file.take() {
+        options.load.insert(0, Image { path, address: 0 });
+    }
+    if options.load.is_empty() {
+        eprintln!("Error: nothing to run; give a file or at least one --load FILE@ADDR");
+        std::process::exit(1);
+    }
+
     let mut cpu = CPU::<VecBackedMemory>::new(8_192 * 1_024); // 8MB
-    cpu.memory
-        .write_bytes(cpu.registers.get(Register::ProgramCounter), code)
-        .unwrap();
+    if let Err(e) = load_images(&mut cpu, &options.load) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+    if let Some(path) = &options.symbols {
+        if let Err(e) = load_symbols(&mut cpu, path) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+    if let Some(entry) = options.entry {
+        cpu.registers.set(Register::ProgramCounter, entry);
+    }
+    if let Some(address) = &options.gdb {
+        cpu = debug_with_gdb(cpu, address);
+    } else {
+        run(&mut cpu, &options);
+    }
+    if let Err(e) = dump_memory(&cpu, &options.dump_memory) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Runs the program until it halts (with `bra.s *`) or fails, printing the CPU state as it goes
+fn run(cpu: &mut CPU<VecBackedMemory>, options: &Options) {
+    // Real programs don't run long stretches of `ori.b #0, d0`; it means we've crashed into empty memory
+    cpu.zero_opcode_limit = Some(16);
     println!("{}", cpu);
     let mut cycles = 1;
-    loop {
+    while !matches!(cpu.is_halted(), Ok(true)) {
         if options.verbose {
             println!("{}> Cycle #{}", "=".repeat(cycles), cycles);
         }
@@ -43,11 +256,139 @@ fn main() {
                     println!("{}", cpu);
                 }
             }
+            Err(EmulationError::UnimplementedInstruction(instruction)) => {
+                eprintln!("Error: instruction not yet supported: {}", instruction);
+                break;
+            }
+            Err(EmulationError::Parsing(e)) if options.skip_unknown => {
+                // Decoding failed, so the PC still points at the instruction we couldn't decode
+                let pc = cpu.registers.get(Register::ProgramCounter);
+                eprintln!(
+                    "Warning: skipping undecodable instruction at {}: {:?}",
+                    Address(pc),
+                    e
+                );
+                cpu.registers.set(Register::ProgramCounter, pc.wrapping_add(2));
+            }
+            Err(EmulationError::RanIntoZeros(address)) => {
+                eprintln!(
+                    "Error: ran into zeroed memory at {}; the program has probably crashed",
+                    address
+                );
+                break;
+            }
             Err(e) => {
-                eprintln!("Error: {:?}", e);
+                eprintln!("{}", describe_fault(cpu, &e));
                 break;
             }
         }
     }
     println!("{}", cpu);
+    println!(
+        "Ran {} instructions in about {} cycles",
+        cpu.instruction_count(),
+        cpu.total_cycles()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use emulator::cpu::registers::AddressRegister;
+
+    #[test]
+    fn parse_image() {
+        assert_eq!(
+            "code.bin@0x1000".parse(),
+            Ok(Image {
+                path: PathBuf::from("code.bin"),
+                address: 0x1000
+            })
+        );
+        assert_eq!(
+            "me@host/data.bin@$20".parse(),
+            Ok(Image {
+                path: PathBuf::from("me@host/data.bin"),
+                address: 0x20
+            })
+        );
+        assert_eq!(
+            "data.bin@64".parse::<Image>().map(|image| image.address),
+            Ok(64)
+        );
+        assert!("data.bin".parse::<Image>().is_err());
+        assert!("@0x1000".parse::<Image>().is_err());
+        assert!("data.bin@0xZZ".parse::<Image>().is_err());
+    }
+
+    #[test]
+    fn parse_memory_dump() {
+        assert_eq!(
+            "0x1000:256:screen.bin".parse(),
+            Ok(MemoryDump {
+                address: 0x1000,
+                length: 256,
+                path: PathBuf::from("screen.bin")
+            })
+        );
+        assert_eq!(
+            "$20:$10:C:\\out.bin"
+                .parse::<MemoryDump>()
+                .map(|dump| dump.path),
+            Ok(PathBuf::from("C:\\out.bin"))
+        );
+        assert!("0x1000:256".parse::<MemoryDump>().is_err());
+        assert!("0x1000:256:".parse::<MemoryDump>().is_err());
+        assert!("0x1000:lots:out.bin".parse::<MemoryDump>().is_err());
+    }
+
+    #[test]
+    fn describe_fault_includes_instruction() {
+        let mut cpu = CPU::<VecBackedMemory>::new(1024);
+        cpu.load_binary(0x100, vec![0x20, 0x18]).unwrap(); // move.l (a0)+, d0
+        cpu.registers.set(Register::ProgramCounter, 0x100_u32);
+        cpu.registers
+            .set_address_register(AddressRegister::A0, 0x1000_u32);
+
+        let error = cpu.run_one_cycle().unwrap_err();
+        assert_eq!(
+            describe_fault(&cpu, &error),
+            "Error at 0x00000100 (move.l (a0)+, d0): MemoryOutOfBoundsAccess(Address(4096))"
+        );
+
+        // The error is still reported if the instruction can't be decoded
+        cpu.load_binary(0x100, vec![0xA0, 0x00]).unwrap();
+        assert!(describe_fault(&cpu, &error).starts_with("Error at 0x00000100 (undecodable): "));
+    }
+
+    #[test]
+    fn load_multiple_images() {
+        let directory = std::env::temp_dir().join(format!("run68-test-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let code = directory.join("code.bin");
+        let data = directory.join("data.bin");
+        std::fs::write(&code, [0x4E, 0x71, 0x4E, 0x75]).unwrap();
+        std::fs::write(&data, [0xDE, 0xAD, 0xBE, 0xEF]).unwrap();
+
+        let mut cpu = CPU::<VecBackedMemory>::new(1024);
+        let result = load_images(
+            &mut cpu,
+            &[
+                Image {
+                    path: code,
+                    address: 0x100,
+                },
+                Image {
+                    path: data,
+                    address: 0x200,
+                },
+            ],
+        );
+        std::fs::remove_dir_all(&directory).unwrap();
+        result.unwrap();
+
+        assert_eq!(cpu.memory.read_long(Address(0x100)).unwrap(), 0x4E71_4E75);
+        assert_eq!(cpu.memory.read_long(Address(0x200)).unwrap(), 0xDEAD_BEEF);
+        assert_eq!(cpu.memory.read_long(Address(0x104)).unwrap(), 0);
+    }
 }
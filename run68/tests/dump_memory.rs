@@ -0,0 +1,45 @@
+//! Checks that `--dump-memory` writes memory to a file after the run
+
+use std::process::Command;
+
+#[test]
+fn dumps_memory_after_run() {
+    let directory = std::env::temp_dir().join(format!("run68-dump-test-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let code = directory.join("code.bin");
+    let first = directory.join("first.bin");
+    let second = directory.join("second.bin");
+    std::fs::write(
+        &code,
+        [
+            // lea ($1000).w, a0
+            0x41, 0xF8, 0x10, 0x00, // move.l #$DEADBEEF, (a0)+
+            0x20, 0xFC, 0xDE, 0xAD, 0xBE, 0xEF, // move.l #$CAFEF00D, (a0)+
+            0x20, 0xFC, 0xCA, 0xFE, 0xF0, 0x0D, // bra.s *, which ends the run
+            0x60, 0xFE,
+        ],
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_run68"))
+        .arg(&code)
+        .arg("--dump-memory")
+        .arg(format!("0x1000:8:{}", first.display()))
+        .arg("--dump-memory")
+        .arg(format!("$1002:4:{}", second.display()))
+        .output()
+        .unwrap();
+    let dumps = (std::fs::read(&first), std::fs::read(&second));
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        dumps.0.unwrap(),
+        [0xDE, 0xAD, 0xBE, 0xEF, 0xCA, 0xFE, 0xF0, 0x0D]
+    );
+    assert_eq!(dumps.1.unwrap(), [0xBE, 0xEF, 0xCA, 0xFE]);
+}
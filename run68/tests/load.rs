@@ -0,0 +1,44 @@
+//! Checks that `--load` puts several images in memory, and `--entry` starts running at the given address
+
+use std::process::Command;
+
+#[test]
+fn loads_images_and_starts_at_entry() {
+    let directory = std::env::temp_dir().join(format!("run68-load-test-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let code = directory.join("code.bin");
+    let data = directory.join("data.bin");
+    let dump = directory.join("dump.bin");
+    std::fs::write(
+        &code,
+        [
+            // move.l ($2000).w, ($3000).w
+            0x21, 0xF8, 0x20, 0x00, 0x30, 0x00, // bra.s *, which ends the run
+            0x60, 0xFE,
+        ],
+    )
+    .unwrap();
+    std::fs::write(&data, [0xDE, 0xAD, 0xBE, 0xEF]).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_run68"))
+        .arg("--load")
+        .arg(format!("{}@0x1000", code.display()))
+        .arg("--load")
+        .arg(format!("{}@$2000", data.display()))
+        .arg("--entry")
+        .arg("0x1000")
+        .arg("--dump-memory")
+        .arg(format!("0x3000:4:{}", dump.display()))
+        .output()
+        .unwrap();
+    let dumped = std::fs::read(&dump);
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(!stderr.contains("Error"), "{}", stderr);
+    // The code at the entry point copied the long from the second image
+    assert_eq!(dumped.unwrap(), [0xDE, 0xAD, 0xBE, 0xEF]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Ran 1 instructions"), "{}", stdout);
+}
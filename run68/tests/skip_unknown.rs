@@ -0,0 +1,50 @@
+//! Checks that `--skip-unknown` steps over instructions that can't be decoded
+
+use std::process::Command;
+
+/// Runs `code` with the given extra arguments, returning its stdout and stderr
+fn run(name: &str, code: &[u8], args: &[&str]) -> (String, String) {
+    let directory = std::env::temp_dir().join(format!(
+        "run68-skip-unknown-test-{}-{}",
+        name,
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("code.bin");
+    std::fs::write(&path, code).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_run68"))
+        .args(args)
+        .arg(&path)
+        .output();
+    std::fs::remove_dir_all(&directory).unwrap();
+    let output = output.unwrap();
+    (
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+// nop; a line-A opcode, which can't be decoded; moveq #5, d0; bra.s *, which ends the run
+const CODE: [u8; 8] = [0x4E, 0x71, 0xA0, 0x00, 0x70, 0x05, 0x60, 0xFE];
+
+#[test]
+fn stops_at_unknown_instruction() {
+    let (stdout, stderr) = run("stop", &CODE, &[]);
+    assert!(stderr.contains("Parsing"), "{}", stderr);
+    // Only the nop ran
+    assert!(stdout.contains("Ran 1 instructions"), "{}", stdout);
+}
+
+#[test]
+fn skips_unknown_instruction() {
+    let (stdout, stderr) = run("skip", &CODE, &["--skip-unknown"]);
+    assert!(
+        stderr.contains("skipping undecodable instruction at 0x00000002"),
+        "{}",
+        stderr
+    );
+    // The moveq after the bad opcode ran, and the run ended at the bra.s without an error
+    assert!(!stderr.contains("Error:"), "{}", stderr);
+    assert!(stdout.contains("Ran 2 instructions"), "{}", stdout);
+}
@@ -0,0 +1,26 @@
+//! Checks that the emulator doesn't print its debugging traces unless the `debug-trace` feature is enabled
+
+use std::process::Command;
+
+#[test]
+#[cfg_attr(feature = "debug-trace", ignore)]
+fn silent_by_default() {
+    let directory = std::env::temp_dir().join(format!("run68-trace-test-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let code = directory.join("code.bin");
+    // nop; bra.s *, which ends the run
+    std::fs::write(&code, [0x4E, 0x71, 0x60, 0xFE]).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_run68"))
+        .arg(&code)
+        .output();
+    std::fs::remove_dir_all(&directory).unwrap();
+    let output = output.unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stdout.contains("Execute"), "{}", stdout);
+    assert!(!stderr.contains("Jumping"), "{}", stderr);
+    // The run itself still happened
+    assert!(stdout.contains("Ran 1 instructions"), "{}", stdout);
+}